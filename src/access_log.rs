@@ -0,0 +1,172 @@
+//! JSON-Lines access log of completed exchanges, for tools outside yap.
+
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    fs::{self, File, OpenOptions},
+    io::AsyncWriteExt,
+};
+
+use crate::config::AccessLogConfig;
+
+/// Name of the file being written; rotated files get a timestamp instead.
+const FILE_NAME: &str = "access.jsonl";
+
+/// One line of the access log.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AccessRecord {
+    /// When the request arrived, RFC 3339.
+    pub timestamp: String,
+    pub method: String,
+    pub host: String,
+    pub path: String,
+    pub status: u16,
+    /// Size of the response body.
+    pub bytes: u64,
+    pub duration_ms: u64,
+    /// Address of the client that sent the request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<SocketAddr>,
+}
+
+impl AccessRecord {
+    pub fn new(
+        timestamp: DateTime<Utc>,
+        method: &str,
+        uri: &hyper::Uri,
+        status: u16,
+        bytes: u64,
+        duration: Duration,
+    ) -> Self {
+        Self {
+            timestamp: timestamp.to_rfc3339(),
+            method: method.to_string(),
+            host: uri.authority().map(|a| a.to_string()).unwrap_or_default(),
+            path: uri.path_and_query().map(|p| p.to_string()).unwrap_or_else(|| "/".to_string()),
+            status,
+            bytes,
+            duration_ms: duration.as_millis() as u64,
+            client: None,
+        }
+    }
+
+    /// The record with the address of the client that sent the request.
+    pub fn client(mut self, client: SocketAddr) -> Self {
+        self.client = Some(client);
+        self
+    }
+}
+
+/// Appends records to `access.jsonl`, rotating it by size and date.
+pub struct AccessLog {
+    dir: PathBuf,
+    config: AccessLogConfig,
+    file: Option<File>,
+    size: u64,
+    opened: NaiveDate,
+}
+
+impl AccessLog {
+    pub fn new(dir: &Path, config: AccessLogConfig) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            config,
+            file: None,
+            size: 0,
+            opened: Local::now().date_naive(),
+        }
+    }
+
+    pub async fn append(&mut self, record: &AccessRecord) -> std::io::Result<()> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+
+        let today = Local::now().date_naive();
+        if self.file.is_some() && should_rotate(&self.config, self.size, line.len() as u64, self.opened, today) {
+            self.rotate().await?;
+        }
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => {
+                let file = OpenOptions::new().create(true).append(true).open(self.dir.join(FILE_NAME)).await?;
+                self.size = file.metadata().await?.len();
+                self.opened = today;
+                self.file.insert(file)
+            }
+        };
+        file.write_all(&line).await?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Drop the open file, e.g. after the directory was deleted; the next
+    /// record starts a new one.
+    pub fn close(&mut self) {
+        self.file = None;
+        self.size = 0;
+    }
+
+    async fn rotate(&mut self) -> std::io::Result<()> {
+        if let Some(mut file) = self.file.take() {
+            file.flush().await?;
+        }
+        let rotated = format!("access-{}.jsonl", Local::now().format("%Y%m%d-%H%M%S%.3f"));
+        fs::rename(self.dir.join(FILE_NAME), self.dir.join(rotated)).await?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+/// Whether a file of `size` bytes opened on `opened` must be rotated before
+/// `incoming` more bytes are written on `today`.
+fn should_rotate(config: &AccessLogConfig, size: u64, incoming: u64, opened: NaiveDate, today: NaiveDate) -> bool {
+    size > 0 && (size + incoming > config.max_bytes || config.rotate_daily && today != opened)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_record_serializes_to_one_line() {
+        let timestamp = DateTime::parse_from_rfc3339("2026-10-17T08:00:00Z").unwrap().to_utc();
+        let uri: hyper::Uri = "http://example.com:8080/api?page=2".parse().unwrap();
+        let record = AccessRecord::new(timestamp, "GET", &uri, 200, 512, Duration::from_millis(42));
+        assert_eq!(
+            serde_json::to_string(&record).unwrap(),
+            r#"{"timestamp":"2026-10-17T08:00:00+00:00","method":"GET","host":"example.com:8080","path":"/api?page=2","status":200,"bytes":512,"duration_ms":42}"#
+        );
+        let record = record.client("[2001:db8::7]:50312".parse().unwrap());
+        assert!(serde_json::to_string(&record).unwrap().ends_with(r#""duration_ms":42,"client":"[2001:db8::7]:50312"}"#));
+    }
+
+    #[test]
+    fn test_should_rotate_by_size_and_date() {
+        let config = AccessLogConfig {
+            enabled: true,
+            max_bytes: 100,
+            rotate_daily: true,
+        };
+        let day = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+        let next_day = day.succ_opt().unwrap();
+        assert!(!should_rotate(&config, 50, 50, day, day));
+        assert!(should_rotate(&config, 60, 50, day, day));
+        assert!(should_rotate(&config, 10, 10, day, next_day));
+        // never rotate an empty file, however large the record
+        assert!(!should_rotate(&config, 0, 500, day, next_day));
+
+        let config = AccessLogConfig { rotate_daily: false, ..config };
+        assert!(!should_rotate(&config, 10, 10, day, next_day));
+    }
+}
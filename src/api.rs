@@ -0,0 +1,335 @@
+//! Optional HTTP management API, for driving yap from scripts and tests.
+//!
+//! | Route                   | Effect                                   |
+//! |-------------------------|------------------------------------------|
+//! | `GET /exchanges`        | List captured exchanges                  |
+//! | `GET /exchanges/{id}`   | One exchange, with its captured response |
+//! | `DELETE /exchanges`     | Clear the session                        |
+//! | `GET`/`PUT /capture`    | Read or set `{"enabled": bool}`          |
+//! | `GET`/`POST /rules`     | List or add a rewrite rule               |
+//! | `DELETE /rules`         | Remove every rewrite rule                |
+//! | `GET /events`           | Server-sent events for new traffic       |
+
+use std::{
+    collections::BTreeSet,
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use futures::stream;
+use http_body_util::{BodyExt, Full, StreamBody, combinators::BoxBody};
+use hyper::{
+    Method, Request, Response, StatusCode,
+    body::{Bytes, Frame, Incoming},
+    server::conn::http1,
+    service::service_fn,
+};
+use hyper_util::rt::TokioIo;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tracing::{error, info};
+
+use crate::{
+    components::proxy::{CapturedExchange, ClearLogs, HttpLog, LogReceiver},
+    framework::Updater,
+    listener,
+    rewrite::{RewriteRule, SharedRules},
+    state::SharedState,
+};
+
+/// How often an idle event stream sends a comment, so intermediaries keep it open.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+type Body = BoxBody<Bytes, Infallible>;
+
+/// What the API reads and changes, shared with the proxy.
+#[derive(Clone)]
+pub struct ApiContext {
+    pub logs: LogReceiver,
+    pub capturing: Arc<AtomicBool>,
+    pub rules: SharedRules,
+    pub state: SharedState,
+    pub updater: Updater,
+}
+
+#[derive(Debug, PartialEq)]
+enum Route {
+    ListExchanges,
+    GetExchange(u64),
+    ClearExchanges,
+    GetCapture,
+    SetCapture,
+    ListRules,
+    AddRule,
+    ClearRules,
+    Events,
+    NotFound,
+}
+
+fn route(method: &Method, path: &str) -> Route {
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    match (method, segments.as_slice()) {
+        (&Method::GET, ["exchanges"]) => Route::ListExchanges,
+        (&Method::DELETE, ["exchanges"]) => Route::ClearExchanges,
+        (&Method::GET, ["exchanges", id]) => id.parse().map_or(Route::NotFound, Route::GetExchange),
+        (&Method::GET, ["capture"]) => Route::GetCapture,
+        (&Method::PUT, ["capture"]) => Route::SetCapture,
+        (&Method::GET, ["rules"]) => Route::ListRules,
+        (&Method::POST, ["rules"]) => Route::AddRule,
+        (&Method::DELETE, ["rules"]) => Route::ClearRules,
+        (&Method::GET, ["events"]) => Route::Events,
+        _ => Route::NotFound,
+    }
+}
+
+#[derive(Deserialize)]
+struct CaptureBody {
+    enabled: bool,
+}
+
+fn summary(log: &HttpLog) -> Value {
+    json!({
+        "id": log.id,
+        "method": log.method,
+        "uri": log.uri,
+        "timestamp": log.timestamp.to_rfc3339(),
+        "status": log.status,
+        "duration_ms": log.duration.map(|d| d.as_millis() as u64),
+        "pinned": log.pinned,
+        "client": log.client.map(|client| client.to_string()),
+        "client_name": log.client_name,
+    })
+}
+
+fn respond(status: StatusCode, body: Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(body.to_string())).boxed())
+        .unwrap()
+}
+
+fn error_response(status: StatusCode, message: impl std::fmt::Display) -> Response<Body> {
+    respond(status, json!({ "error": message.to_string() }))
+}
+
+async fn read_json<T: for<'de> Deserialize<'de>>(req: Request<Incoming>) -> Result<T, Response<Body>> {
+    let body = req
+        .into_body()
+        .collect()
+        .await
+        .map_err(|e| error_response(StatusCode::BAD_REQUEST, e))?
+        .to_bytes();
+    serde_json::from_slice(&body).map_err(|e| error_response(StatusCode::BAD_REQUEST, e))
+}
+
+/// What an event stream has sent so far.
+#[derive(Debug, Default)]
+struct EventCursor {
+    /// Newest exchange announced; older ones predate the stream or were sent.
+    last_id: u64,
+    /// Announced exchanges still waiting for their response.
+    pending: BTreeSet<u64>,
+}
+
+impl EventCursor {
+    /// Start after everything in `logs`, so only new traffic is streamed.
+    fn after(logs: &[Arc<HttpLog>]) -> Self {
+        Self {
+            last_id: logs.iter().map(|log| log.id).max().unwrap_or(0),
+            pending: BTreeSet::new(),
+        }
+    }
+
+    /// `captured` events for new exchanges and `completed` events for
+    /// responses that arrived since the last call.
+    fn events(&mut self, logs: &[Arc<HttpLog>]) -> String {
+        let mut events = String::new();
+        for log in logs {
+            let name = if log.id > self.last_id {
+                if log.status.is_none() {
+                    self.pending.insert(log.id);
+                }
+                "captured"
+            } else if log.status.is_some() && self.pending.remove(&log.id) {
+                "completed"
+            } else {
+                continue;
+            };
+            events.push_str(&format!("event: {}\ndata: {}\n\n", name, summary(log)));
+        }
+        self.last_id = logs.iter().map(|log| log.id).max().unwrap_or(0).max(self.last_id);
+        // forget exchanges that were evicted or cleared before completing
+        self.pending.retain(|id| logs.iter().any(|log| log.id == *id));
+        events
+    }
+}
+
+/// Stream traffic as server-sent events until the client or the app goes away.
+fn event_stream(ctx: ApiContext) -> Response<Body> {
+    let mut logs = ctx.logs.clone();
+    let cursor = EventCursor::after(&logs.borrow_and_update());
+    let events = stream::unfold((logs, cursor, ctx.updater), |(mut logs, mut cursor, updater)| async move {
+        let chunk = tokio::select! {
+            changed = logs.changed() => {
+                changed.ok()?;
+                let snapshot = logs.borrow_and_update().clone();
+                cursor.events(&snapshot)
+            }
+            _ = tokio::time::sleep(KEEP_ALIVE) => ": keep-alive\n\n".to_string(),
+            _ = updater.cancelled() => return None,
+        };
+        Some((Ok::<_, Infallible>(Frame::data(Bytes::from(chunk))), (logs, cursor, updater)))
+    });
+    Response::builder()
+        .header(hyper::header::CONTENT_TYPE, "text/event-stream")
+        .header(hyper::header::CACHE_CONTROL, "no-cache")
+        .body(StreamBody::new(events).boxed())
+        .unwrap()
+}
+
+async fn handle(req: Request<Incoming>, ctx: ApiContext) -> Response<Body> {
+    let logs = ctx.logs.borrow().clone();
+    match route(req.method(), req.uri().path()) {
+        Route::ListExchanges => respond(StatusCode::OK, logs.iter().map(|log| summary(log)).collect()),
+        Route::GetExchange(id) => {
+            let Some(log) = logs.iter().find(|log| log.id == id) else {
+                return error_response(StatusCode::NOT_FOUND, format!("no exchange {}", id));
+            };
+            let mut body = summary(log);
+            // the capture is only written once the response has arrived
+            body["response"] = match CapturedExchange::load(&log.path).await {
+                Ok(exchange) => json!({
+                    "status": exchange.status,
+                    "headers": exchange.headers,
+                    "body": exchange.body,
+                }),
+                Err(_) => Value::Null,
+            };
+            respond(StatusCode::OK, body)
+        }
+        Route::ClearExchanges => {
+            ctx.updater.send(ClearLogs { delete_captures: false });
+            respond(StatusCode::ACCEPTED, json!({}))
+        }
+        Route::GetCapture => respond(StatusCode::OK, json!({ "enabled": ctx.capturing.load(Ordering::Relaxed) })),
+        Route::SetCapture => match read_json::<CaptureBody>(req).await {
+            Ok(CaptureBody { enabled }) => {
+                ctx.capturing.store(enabled, Ordering::Relaxed);
+                ctx.state.set_capturing(enabled);
+                respond(StatusCode::OK, json!({ "enabled": enabled }))
+            }
+            Err(response) => response,
+        },
+        Route::ListRules => respond(StatusCode::OK, json!(*ctx.rules.read().await)),
+        Route::AddRule => match read_json::<RewriteRule>(req).await {
+            Ok(rule) => {
+                info!("Adding rewrite rule {} -> {}", rule.from, rule.to);
+                ctx.rules.write().await.push(rule.clone());
+                respond(StatusCode::CREATED, json!(rule))
+            }
+            Err(response) => response,
+        },
+        Route::ClearRules => {
+            ctx.rules.write().await.clear();
+            respond(StatusCode::OK, json!([]))
+        }
+        Route::Events => event_stream(ctx),
+        Route::NotFound => error_response(StatusCode::NOT_FOUND, "no such route"),
+    }
+}
+
+/// Serve the API on `listen` until the app shuts down.
+pub async fn serve(listen: SocketAddr, ctx: ApiContext) {
+    let listener = match listener::bind(listen) {
+        Ok(listener) => {
+            info!("Management API listening on {}", listen);
+            listener
+        }
+        Err(e) => {
+            error!("Failed to bind management API to {}: {}", listen, e);
+            ctx.state.error(format!("Failed to bind API to {}: {}", listen, e));
+            return;
+        }
+    };
+
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            _ = ctx.updater.cancelled() => return,
+        };
+        let stream = match accepted {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                error!("Failed to accept API connection: {}", e);
+                continue;
+            }
+        };
+        let ctx = ctx.clone();
+        let updater = ctx.updater.clone();
+        updater.spawn(async move {
+            let shutdown = ctx.updater.clone();
+            let service = service_fn(move |req| {
+                let ctx = ctx.clone();
+                async move { Ok::<_, hyper::Error>(handle(req, ctx).await) }
+            });
+            let conn = http1::Builder::new().serve_connection(TokioIo::new(stream), service);
+            tokio::pin!(conn);
+            let result = tokio::select! {
+                result = conn.as_mut() => result,
+                _ = shutdown.cancelled() => {
+                    conn.as_mut().graceful_shutdown();
+                    conn.await
+                }
+            };
+            if let Err(e) = result {
+                error!("Error serving API connection: {:?}", e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_route() {
+        assert_eq!(route(&Method::GET, "/exchanges"), Route::ListExchanges);
+        assert_eq!(route(&Method::GET, "/exchanges/42/"), Route::GetExchange(42));
+        assert_eq!(route(&Method::GET, "/exchanges/abc"), Route::NotFound);
+        assert_eq!(route(&Method::DELETE, "/exchanges"), Route::ClearExchanges);
+        assert_eq!(route(&Method::PUT, "/capture"), Route::SetCapture);
+        assert_eq!(route(&Method::POST, "/rules"), Route::AddRule);
+        assert_eq!(route(&Method::POST, "/exchanges"), Route::NotFound);
+    }
+
+    #[test]
+    fn test_event_cursor_reports_new_and_completed_exchanges() {
+        let log = |id, status| {
+            Arc::new(HttpLog {
+                id,
+                method: "GET".to_string(),
+                uri: format!("http://example.com/{}", id),
+                status,
+                ..Default::default()
+            })
+        };
+        let mut cursor = EventCursor::after(&[log(1, Some(200))]);
+        let events = cursor.events(&[log(1, Some(200)), log(2, None)]);
+        assert_eq!(events.matches("event: captured").count(), 1);
+        assert!(events.contains("\"id\":2"));
+
+        let events = cursor.events(&[log(1, Some(200)), log(2, Some(404))]);
+        assert!(events.starts_with("event: completed\n"));
+        assert!(events.contains("\"status\":404"));
+        assert_eq!(cursor.events(&[log(1, Some(200)), log(2, Some(404))]), "");
+    }
+}
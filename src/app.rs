@@ -1,8 +1,16 @@
-use serde::{Deserialize, Serialize};
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+use serde::{Deserialize, Deserializer, Serialize, de::Error};
+use strum::{EnumString, VariantNames};
+use tracing::warn;
 
 use crate::{
-    components::{layout::Layout},
-    config::Config,
+    cli::Cli,
+    components::{layout::Layout, setup::Setup},
+    config::{self, Config, check},
     framework::Runtime,
 };
 
@@ -11,20 +19,70 @@ pub struct App {
     mode: Mode,
 }
 
-#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// Keybinding contexts. `Home` bindings are global and handled by the runtime,
+/// the others are looked up by the component that owns that context.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, EnumString, VariantNames)]
+#[strum(ascii_case_insensitive)]
 pub enum Mode {
     #[default]
     Home,
+    List,
+    Detail,
+    Diff,
+    Confirm,
+    Filter,
+    Command,
+    Timeline,
+    /// The rules, sessions, cookies and clients panels.
+    Panel,
+    Composer,
+}
+
+/// Modes are read ignoring case, since the config crate lowercases keys.
+impl<'de> Deserialize<'de> for Mode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(|_| D::Error::unknown_variant(&name, Self::VARIANTS))
+    }
 }
 
 impl App {
-    pub fn new() -> color_eyre::Result<Self> {
+    pub fn new(cli: Cli) -> color_eyre::Result<Self> {
+        let mut config = Config::new()?;
+        // command line flags take precedence over the config file
+        if cli.reverse.is_some() {
+            config.proxy.reverse = cli.reverse;
+        }
+        if cli.replay.is_some() {
+            config.replay.from = cli.replay;
+        }
+        if cli.insecure {
+            config.proxy.upstream.tls.insecure = true;
+        }
+        if cli.tee.is_some() {
+            config.capture.tee = cli.tee;
+        }
+        for problem in check::problems(&config) {
+            warn!("config: `{}`: {}", problem.key, problem.message);
+        }
         Ok(Self {
-            config: Config::new()?,
+            config,
             mode: Mode::Home,
         })
     }
 
+    /// Walk through the setup when there is no config file yet. Returns
+    /// whether to go on and start the proxy.
+    pub async fn first_run() -> color_eyre::Result<bool> {
+        if !config::config_files(&config::get_config_dir()).is_empty() {
+            return Ok(true);
+        }
+        let done = Arc::new(AtomicBool::new(false));
+        let components: Vec<Box<dyn crate::framework::Component>> = vec![Box::new(Setup::new(done.clone()))];
+        Runtime::new(components, Config::new()?, Mode::Home).run().await?;
+        Ok(done.load(Ordering::Relaxed))
+    }
+
     pub async fn run(&mut self) -> color_eyre::Result<()> {
         
         let components: Vec<Box<dyn crate::framework::Component>> = vec![
@@ -38,3 +96,65 @@ impl App {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crossterm::event::{KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    use super::*;
+    use crate::{
+        framework::testing::{self, Script},
+        tui::Event,
+    };
+
+    /// Send a request for `url` through the proxy at `proxy` once it listens.
+    async fn request_through(proxy: u16, url: String) -> String {
+        let mut stream = loop {
+            match TcpStream::connect(("127.0.0.1", proxy)).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+            }
+        };
+        let request = format!("GET {} HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n", url);
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_scripted_run_captures_a_request() {
+        let upstream = crate::demo::spawn().await.unwrap();
+        let proxy = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let mut config = testing::config();
+        config.proxy.listen = proxy;
+
+        let client = tokio::spawn(request_through(proxy.port(), format!("http://{}/json", upstream)));
+        // filter for it, then click it open once it is in the list
+        let click = MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column: 10,
+            row: 1,
+            modifiers: KeyModifiers::NONE,
+        };
+        let script = Script::new(100, 20)
+            .keys(Duration::from_millis(300), "/json<esc>")
+            .event(Duration::from_millis(800), Event::Mouse(click))
+            .quit(Duration::from_secs(1));
+        let components: Vec<Box<dyn crate::framework::Component>> = vec![Box::new(Layout::default())];
+        let mut runtime = Runtime::scripted(components, config, Mode::Home, script);
+        runtime.run().await.unwrap();
+
+        assert!(client.await.unwrap().contains("\"name\": \"Ada\""));
+        let screen = runtime.screen();
+        assert!(screen.contains("Status: 200"), "{}", screen);
+        assert!(screen.contains("\"name\": \"Ada\""), "{}", screen);
+        assert!(screen.contains("│json"), "{}", screen);
+    }
+}
@@ -0,0 +1,161 @@
+//! Expectations on the responses to matching requests, checked as they
+//! arrive, so yap flags contract breaks while the app is tried by hand.
+
+use std::sync::Arc;
+
+use hyper::{HeaderMap, Uri};
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::sync::RwLock;
+
+use crate::{highlight::status_matches, json_path::JsonPath, pac::sh_exp_match};
+
+/// A value the JSON body must hold.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JsonCheck {
+    pub path: JsonPath,
+    /// Every value at `path` must be this; without it, one just has to be there.
+    #[serde(default)]
+    pub equals: Option<Value>,
+}
+
+/// What the responses to the requests matching `host`, `path` and `method`
+/// must look like.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AssertionRule {
+    /// Host pattern with `*` wildcards.
+    #[serde(default = "any_host")]
+    pub host: String,
+    /// Only requests whose path starts with this.
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub method: Option<String>,
+    /// A status code, or a class like `2xx`.
+    #[serde(default)]
+    pub status: Option<String>,
+    /// Headers the response must have.
+    #[serde(default)]
+    pub headers: Vec<String>,
+    #[serde(default)]
+    pub json: Vec<JsonCheck>,
+}
+
+/// Rules shared between the proxy and a reloaded config.
+pub type SharedAssertions = Arc<RwLock<Vec<AssertionRule>>>;
+
+fn any_host() -> String {
+    "*".to_string()
+}
+
+/// A JSON value on one line, shortened for the list.
+fn short(value: &Value) -> String {
+    let text = value.to_string();
+    match text.char_indices().nth(40) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text,
+    }
+}
+
+impl AssertionRule {
+    fn applies(&self, method: &str, uri: &Uri) -> bool {
+        sh_exp_match(&self.host, uri.host().unwrap_or_default())
+            && self.path.as_deref().is_none_or(|prefix| uri.path().starts_with(prefix))
+            && self.method.as_deref().is_none_or(|expected| expected.eq_ignore_ascii_case(method))
+    }
+
+    fn check(&self, status: u16, headers: &HeaderMap, body: Option<&[u8]>, failures: &mut Vec<String>) {
+        if let Some(expected) = &self.status
+            && !status_matches(expected, status)
+        {
+            failures.push(format!("status {}, expected {}", status, expected));
+        }
+        for name in &self.headers {
+            if !headers.contains_key(name.as_str()) {
+                failures.push(format!("no {} header", name));
+            }
+        }
+        // bodies too large to keep in memory are not parsed
+        let Some(body) = body.filter(|_| !self.json.is_empty()) else {
+            return;
+        };
+        let Ok(document) = serde_json::from_slice::<Value>(body) else {
+            failures.push("body is not JSON".to_string());
+            return;
+        };
+        for check in &self.json {
+            let values = check.path.select(&document);
+            match (&check.equals, values.as_slice()) {
+                (_, []) => failures.push(format!("{} not found", check.path)),
+                (Some(expected), values) => {
+                    if let Some(value) = values.iter().find(|value| **value != expected) {
+                        failures.push(format!("{} is {}, expected {}", check.path, short(value), short(expected)));
+                    }
+                }
+                (None, _) => {}
+            }
+        }
+    }
+}
+
+/// What the response to `method` `uri` breaks of the rules that apply to
+/// it, empty when it passes. `body` is `None` when it wasn't kept in memory.
+pub fn check(
+    rules: &[AssertionRule],
+    method: &str,
+    uri: &Uri,
+    status: u16,
+    headers: &HeaderMap,
+    body: Option<&[u8]>,
+) -> Vec<String> {
+    let mut failures = Vec::new();
+    for rule in rules.iter().filter(|rule| rule.applies(method, uri)) {
+        rule.check(status, headers, body, &mut failures);
+    }
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let rules: Vec<AssertionRule> = json5::from_str(
+            r#"[
+                { host: "api.*", path: "/users", status: "2xx", headers: ["x-request-id"],
+                  json: [{ path: "$.id" }, { path: "$.roles[*]", equals: "user" }] },
+                { host: "*", method: "DELETE", status: "204" },
+            ]"#,
+        )
+        .unwrap();
+        let uri: Uri = "http://api.test/users/7".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("x-request-id", "abc".parse().unwrap());
+
+        let body = br#"{ "id": 7, "roles": ["user", "user"] }"#;
+        assert_eq!(check(&rules, "GET", &uri, 200, &headers, Some(body)), Vec::<String>::new());
+
+        let body = br#"{ "roles": ["user", "admin"] }"#;
+        assert_eq!(
+            check(&rules, "GET", &uri, 404, &HeaderMap::new(), Some(body)),
+            vec![
+                "status 404, expected 2xx".to_string(),
+                "no x-request-id header".to_string(),
+                "$.id not found".to_string(),
+                "$.roles[*] is \"admin\", expected \"user\"".to_string(),
+            ]
+        );
+        assert_eq!(check(&rules, "GET", &uri, 200, &headers, Some(b"<html>")), vec!["body is not JSON".to_string()]);
+        assert_eq!(check(&rules, "GET", &uri, 200, &headers, None), Vec::<String>::new());
+        assert_eq!(check(&rules, "DELETE", &uri, 200, &headers, None), vec!["status 200, expected 204".to_string()]);
+
+        let other: Uri = "http://cdn.test/users".parse().unwrap();
+        assert_eq!(check(&rules, "GET", &other, 500, &headers, None), Vec::<String>::new());
+        assert!(json5::from_str::<Vec<AssertionRule>>(r#"[{ json: [{ path: "$.a[" }] }]"#).is_err());
+    }
+}
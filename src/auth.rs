@@ -0,0 +1,88 @@
+//! Basic `Proxy-Authorization` for clients of the forward proxy, so yap can
+//! listen on a shared network without being an open proxy.
+
+use std::collections::BTreeMap;
+
+use base64::Engine;
+use hyper::{HeaderMap, header::PROXY_AUTHORIZATION};
+use serde::Deserialize;
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProxyAuth {
+    /// Passwords by user name; authentication is off while this is empty.
+    pub users: BTreeMap<String, String>,
+    /// Realm sent in the `Proxy-Authenticate` challenge.
+    pub realm: String,
+}
+
+impl Default for ProxyAuth {
+    fn default() -> Self {
+        Self {
+            users: BTreeMap::new(),
+            realm: "yap".to_string(),
+        }
+    }
+}
+
+impl ProxyAuth {
+    pub fn enabled(&self) -> bool {
+        !self.users.is_empty()
+    }
+
+    /// Whether a request with `headers` may use the proxy.
+    pub fn allows(&self, headers: &HeaderMap) -> bool {
+        if !self.enabled() {
+            return true;
+        }
+        let credentials = headers
+            .get(PROXY_AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Basic "))
+            .and_then(|encoded| base64::engine::general_purpose::STANDARD.decode(encoded.trim()).ok())
+            .and_then(|decoded| String::from_utf8(decoded).ok());
+        let Some((user, password)) = credentials.as_deref().and_then(|c| c.split_once(':')) else {
+            return false;
+        };
+        self.users.get(user).is_some_and(|expected| expected == password)
+    }
+
+    /// `Proxy-Authorization` for requests yap sends itself, as the first user.
+    pub fn credentials(&self) -> Option<String> {
+        let (user, password) = self.users.iter().next()?;
+        let encoded = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", user, password));
+        Some(format!("Basic {}", encoded))
+    }
+
+    /// Value of the `Proxy-Authenticate` header in `407` answers.
+    pub fn challenge(&self) -> String {
+        format!("Basic realm=\"{}\"", self.realm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::header::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_allows_known_users() {
+        let auth = ProxyAuth {
+            users: BTreeMap::from([("alice".to_string(), "s3:cret".to_string())]),
+            ..Default::default()
+        };
+        let with = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(PROXY_AUTHORIZATION, HeaderValue::from_str(value).unwrap());
+            headers
+        };
+        // alice:s3:cret
+        assert!(auth.allows(&with("Basic YWxpY2U6czM6Y3JldA==")));
+        // alice:wrong
+        assert!(!auth.allows(&with("Basic YWxpY2U6d3Jvbmc=")));
+        assert!(!auth.allows(&with("Bearer YWxpY2U6czM6Y3JldA==")));
+        assert!(!auth.allows(&HeaderMap::new()));
+        assert!(ProxyAuth::default().allows(&HeaderMap::new()));
+    }
+}
@@ -0,0 +1,271 @@
+//! Sending a whole set of captured requests again, e.g. those matching the
+//! filter after a backend deploy, and reporting which got another status
+//! than when they were captured.
+
+use std::{fmt::Write as _, path::PathBuf, time::Duration};
+
+use chrono::Local;
+use futures::{StreamExt, stream};
+use serde::Deserialize;
+
+use crate::{
+    client::ProxyClient,
+    components::proxy::{BodyPart, CapturedExchange, HttpLog},
+    redact::RedactConfig,
+    retry::Resend,
+    state::SharedState,
+};
+
+/// How long a single request may take.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BatchConfig {
+    /// Requests in flight at once, 1 to send them one after the other.
+    pub concurrency: usize,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self { concurrency: 1 }
+    }
+}
+
+/// The sections of a report.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Changed,
+    Failed,
+    Unchanged,
+}
+
+impl Kind {
+    fn title(self) -> &'static str {
+        match self {
+            Kind::Changed => "Changed",
+            Kind::Failed => "Failed",
+            Kind::Unchanged => "Unchanged",
+        }
+    }
+}
+
+/// How one request fared the second time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Outcome {
+    pub method: String,
+    pub uri: String,
+    /// Status in the capture, unless it never got a response.
+    pub before: Option<u16>,
+    /// The new status, or why there is none.
+    pub after: Result<u16, String>,
+}
+
+impl Outcome {
+    fn kind(&self) -> Kind {
+        match self.after {
+            Ok(after) if self.before != Some(after) => Kind::Changed,
+            Ok(_) => Kind::Unchanged,
+            Err(_) => Kind::Failed,
+        }
+    }
+
+    fn line(&self) -> String {
+        let before = self.before.map_or("-".to_string(), |status| status.to_string());
+        let after = match &self.after {
+            Ok(status) => status.to_string(),
+            Err(e) => e.clone(),
+        };
+        format!("{} {}  {} → {}", self.method, self.uri, before, after)
+    }
+}
+
+/// The outcomes of one batch, in the order the requests were listed.
+#[derive(Debug, Default, PartialEq)]
+pub struct Report {
+    pub filter: String,
+    pub concurrency: usize,
+    pub outcomes: Vec<Outcome>,
+}
+
+impl Report {
+    /// e.g. `Replayed 12: 2 changed, 1 failed, 9 unchanged`.
+    pub fn summary(&self) -> String {
+        format!(
+            "Replayed {}: {} changed, {} failed, {} unchanged",
+            self.outcomes.len(),
+            self.count(Kind::Changed),
+            self.count(Kind::Failed),
+            self.count(Kind::Unchanged)
+        )
+    }
+
+    fn count(&self, kind: Kind) -> usize {
+        self.outcomes.iter().filter(|outcome| outcome.kind() == kind).count()
+    }
+
+    /// The whole report as Markdown, status changes first.
+    pub fn render(&self) -> String {
+        let mut text = format!("# {}\n\n", self.summary());
+        let filter = if self.filter.is_empty() { "(none)" } else { &self.filter };
+        let _ = writeln!(text, "- Filter: `{}`", filter);
+        let _ = writeln!(text, "- Concurrency: {}", self.concurrency);
+        let _ = writeln!(text, "- Finished: {}", Local::now().format("%Y-%m-%d %H:%M:%S"));
+        for kind in [Kind::Changed, Kind::Failed, Kind::Unchanged] {
+            let lines: Vec<String> =
+                self.outcomes.iter().filter(|outcome| outcome.kind() == kind).map(Outcome::line).collect();
+            if lines.is_empty() {
+                continue;
+            }
+            let _ = writeln!(text, "\n## {} ({})\n", kind.title(), lines.len());
+            for line in lines {
+                let _ = writeln!(text, "- {}", line);
+            }
+        }
+        text
+    }
+}
+
+/// The request of `log` rebuilt from its own capture, to be sent again.
+async fn prepare(log: &HttpLog, redact: &RedactConfig) -> Result<Resend, String> {
+    let exchange = CapturedExchange::load(&log.path).await.map_err(|e| e.to_string())?;
+    let body = CapturedExchange::load_body(&log.path, BodyPart::Request).await.map_err(|e| e.to_string())?;
+    Resend::new(&log.method, &log.uri, &exchange.request_headers, body.into(), redact)
+}
+
+/// Send `log` again, as `prepare` rebuilt it.
+async fn replay(log: HttpLog, resend: Result<Resend, String>, client: &ProxyClient) -> Outcome {
+    let sent = async {
+        match tokio::time::timeout(TIMEOUT, client.send(resend?.request(None))).await {
+            Ok(Ok(response)) => Ok(response.status().as_u16()),
+            Ok(Err(e)) => Err(e.to_string()),
+            Err(_) => Err("timed out".to_string()),
+        }
+    };
+    Outcome {
+        after: sent.await,
+        method: log.method,
+        uri: log.uri,
+        before: log.status,
+    }
+}
+
+/// Replay `logs`, up to `concurrency` at once, with the outcomes in their order.
+async fn replay_all(logs: Vec<HttpLog>, concurrency: usize, client: &ProxyClient, redact: &RedactConfig) -> Vec<Outcome> {
+    // every capture is read before anything is sent, so none of them can
+    // change under a request that is still to go
+    let prepared: Vec<_> =
+        stream::iter(logs).then(|log| async move { (prepare(&log, redact).await, log) }).collect().await;
+    stream::iter(prepared)
+        .map(|(resend, log)| replay(log, resend, client))
+        .buffered(concurrency.max(1))
+        .collect()
+        .await
+}
+
+/// Replay `logs` and write the report to `dir`, telling how it went in the
+/// status bar.
+pub async fn run(
+    logs: Vec<HttpLog>,
+    mut report: Report,
+    client: ProxyClient,
+    redact: RedactConfig,
+    state: SharedState,
+    dir: PathBuf,
+) {
+    report.outcomes = replay_all(logs, report.concurrency, &client, &redact).await;
+    let path = dir.join(format!("replay-{}.md", Local::now().format("%Y%m%d-%H%M%S")));
+    let written = async {
+        tokio::fs::create_dir_all(&dir).await?;
+        tokio::fs::write(&path, report.render()).await
+    };
+    match written.await {
+        Ok(()) => state.notify(format!("{}, report in {}", report.summary(), path.display())),
+        Err(e) => state.error(format!("{}, but the report couldn't be written: {}", report.summary(), e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::framework::testing::{TempDir, capture};
+
+    fn outcome(uri: &str, before: Option<u16>, after: Result<u16, &str>) -> Outcome {
+        Outcome {
+            method: "GET".to_string(),
+            uri: uri.to_string(),
+            before,
+            after: after.map_err(str::to_string),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_same_url_exchanges_replay_their_own_bodies() {
+        use std::convert::Infallible;
+
+        use http_body_util::{BodyExt, Full};
+        use hyper::{Response, body::Bytes, server::conn::http1, service::service_fn};
+        use hyper_util::rt::TokioIo;
+
+        use crate::{auth::ProxyAuth, components::proxy::Proxy};
+
+        // stands in for the proxy, answering with 200 plus the length of the body
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let service = service_fn(|req: hyper::Request<hyper::body::Incoming>| async move {
+                    let body = req.into_body().collect().await.unwrap().to_bytes();
+                    let status = 200 + body.len() as u16;
+                    Ok::<_, Infallible>(Response::builder().status(status).body(Full::new(Bytes::new())).unwrap())
+                });
+                tokio::spawn(http1::Builder::new().serve_connection(TokioIo::new(stream), service));
+            }
+        });
+
+        let dir = TempDir::new();
+        let uri = "http://api.test/orders";
+        let mut logs = Vec::new();
+        for (id, body) in [(1, "first"), (2, "second!")] {
+            let path = Proxy::capture_path(dir.path(), uri, Some(id));
+            capture("POST", uri, 200)
+                .request_header("content-type", "text/plain")
+                .request_body(body)
+                .write(&path);
+            logs.push(HttpLog {
+                id,
+                method: "POST".to_string(),
+                uri: uri.to_string(),
+                status: Some(200),
+                path,
+                ..Default::default()
+            });
+        }
+
+        let client = ProxyClient::new(proxy, &ProxyAuth::default());
+        let outcomes = replay_all(logs, 2, &client, &RedactConfig::default()).await;
+        let after: Vec<_> = outcomes.into_iter().map(|outcome| outcome.after).collect();
+        assert_eq!(after, vec![Ok(205), Ok(207)]);
+    }
+
+    #[test]
+    fn test_report() {
+        let report = Report {
+            filter: "host:api".to_string(),
+            concurrency: 4,
+            outcomes: vec![
+                outcome("http://api.test/a", Some(200), Ok(200)),
+                outcome("http://api.test/b", Some(200), Ok(500)),
+                outcome("http://api.test/c", None, Ok(200)),
+                outcome("http://api.test/d", Some(204), Err("connection refused")),
+            ],
+        };
+        assert_eq!(report.summary(), "Replayed 4: 2 changed, 1 failed, 1 unchanged");
+        let text = report.render();
+        assert!(text.contains("## Changed (2)\n\n- GET http://api.test/b  200 → 500\n- GET http://api.test/c  - → 200\n"));
+        assert!(text.contains("## Failed (1)\n\n- GET http://api.test/d  204 → connection refused\n"));
+        assert!(text.contains("## Unchanged (1)\n\n- GET http://api.test/a  200 → 200\n"));
+    }
+}
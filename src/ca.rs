@@ -0,0 +1,420 @@
+//! The root CA certificate and `yap cert`.
+//!
+//! yap doesn't decrypt HTTPS yet, so nothing is signed with the CA so far; it
+//! can be generated, exported and trusted ahead of interception. The
+//! certificate is a self-signed ECDSA P-256 root, encoded by hand since only
+//! `ring` is around to sign it. It lives in `<data_dir>/ca`, next to its key.
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use base64::Engine;
+use chrono::{DateTime, Duration, Datelike, Utc};
+use color_eyre::eyre::{eyre, WrapErr};
+use ring::{
+    digest,
+    rand::{SecureRandom, SystemRandom},
+    signature::{ECDSA_P256_SHA256_ASN1_SIGNING, EcdsaKeyPair, KeyPair},
+};
+use rustls_pki_types::{CertificateDer, pem::PemObject};
+
+use crate::cli::CertCommands;
+
+/// What the certificate is issued to, and by.
+const NAME: &str = "yap root CA";
+/// How long a generated certificate is valid.
+const VALIDITY_DAYS: i64 = 3650;
+
+// DER-encoded object identifiers
+const ECDSA_WITH_SHA256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+const EC_PUBLIC_KEY: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+const PRIME256V1: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+const ORGANIZATION: &[u8] = &[0x55, 0x04, 0x0a];
+const COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+const BASIC_CONSTRAINTS: &[u8] = &[0x55, 0x1d, 0x13];
+const KEY_USAGE: &[u8] = &[0x55, 0x1d, 0x0f];
+const SUBJECT_KEY_IDENTIFIER: &[u8] = &[0x55, 0x1d, 0x0e];
+
+/// Where the CA is kept.
+pub fn dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("ca")
+}
+
+pub fn cert_path(data_dir: &Path) -> PathBuf {
+    dir(data_dir).join("yap-ca.pem")
+}
+
+fn key_path(data_dir: &Path) -> PathBuf {
+    dir(data_dir).join("yap-ca.key")
+}
+
+/// The DER of the CA certificate, or `None` if none was generated.
+pub fn load(data_dir: &Path) -> color_eyre::Result<Option<Vec<u8>>> {
+    let path = cert_path(data_dir);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let cert = CertificateDer::from_pem_file(&path)
+        .map_err(|e| eyre!("Failed to read the CA certificate {}: {}", path.display(), e))?;
+    Ok(Some(cert.to_vec()))
+}
+
+/// Create a CA certificate and key in `data_dir`, replacing any there.
+/// Returns the DER of the certificate.
+pub fn generate(data_dir: &Path, now: DateTime<Utc>) -> color_eyre::Result<Vec<u8>> {
+    let rng = SystemRandom::new();
+    let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng)
+        .map_err(|_| eyre!("Failed to generate a key"))?;
+    let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng)
+        .map_err(|e| eyre!("Failed to load the generated key: {}", e))?;
+    let cert = certificate(&key, &rng, now)?;
+
+    let dir = dir(data_dir);
+    create_private_dir(&dir).wrap_err_with(|| format!("Failed to create {}", dir.display()))?;
+    let path = key_path(data_dir);
+    write_private(&path, pem("PRIVATE KEY", pkcs8.as_ref()).as_bytes())
+        .wrap_err_with(|| format!("Failed to write {}", path.display()))?;
+    let path = cert_path(data_dir);
+    fs::write(&path, pem("CERTIFICATE", &cert)).wrap_err_with(|| format!("Failed to write {}", path.display()))?;
+    Ok(cert)
+}
+
+/// A self-signed CA certificate for `key`, valid from a day before `now` on.
+fn certificate(key: &EcdsaKeyPair, rng: &SystemRandom, now: DateTime<Utc>) -> color_eyre::Result<Vec<u8>> {
+    let mut serial = [0u8; 16];
+    rng.fill(&mut serial).map_err(|_| eyre!("Failed to generate a serial number"))?;
+    // positive and without a leading zero byte
+    serial[0] = serial[0] & 0x7f | 0x40;
+
+    let public_key = key.public_key().as_ref();
+    let algorithm = sequence(&[oid(ECDSA_WITH_SHA256)]);
+    let name = sequence(&[
+        der(0x31, &sequence(&[oid(ORGANIZATION), der(0x0c, b"yap")])),
+        der(0x31, &sequence(&[oid(COMMON_NAME), der(0x0c, NAME.as_bytes())])),
+    ]);
+    let key_id = digest::digest(&digest::SHA1_FOR_LEGACY_USE_ONLY, public_key);
+    let extensions = sequence(&[
+        // a CA, critical
+        sequence(&[oid(BASIC_CONSTRAINTS), der(0x01, &[0xff]), der(0x04, &sequence(&[der(0x01, &[0xff])]))]),
+        // keyCertSign and cRLSign, critical
+        sequence(&[oid(KEY_USAGE), der(0x01, &[0xff]), der(0x04, &der(0x03, &[0x01, 0x06]))]),
+        sequence(&[oid(SUBJECT_KEY_IDENTIFIER), der(0x04, &der(0x04, key_id.as_ref()))]),
+    ]);
+    let tbs = sequence(&[
+        // version 3
+        der(0xa0, &der(0x02, &[0x02])),
+        der(0x02, &serial),
+        algorithm.clone(),
+        name.clone(),
+        sequence(&[time(now - Duration::days(1)), time(now + Duration::days(VALIDITY_DAYS))]),
+        name,
+        sequence(&[sequence(&[oid(EC_PUBLIC_KEY), oid(PRIME256V1)]), bit_string(public_key)]),
+        der(0xa3, &extensions),
+    ]);
+    let signature = key.sign(rng, &tbs).map_err(|_| eyre!("Failed to sign the certificate"))?;
+    Ok(sequence(&[tbs, algorithm, bit_string(signature.as_ref())]))
+}
+
+/// A DER element of type `tag`.
+fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    if content.len() < 0x80 {
+        out.push(content.len() as u8);
+    } else {
+        let length = content.len().to_be_bytes();
+        let length = &length[length.iter().take_while(|byte| **byte == 0).count()..];
+        out.push(0x80 | length.len() as u8);
+        out.extend_from_slice(length);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+fn sequence(items: &[Vec<u8>]) -> Vec<u8> {
+    der(0x30, &items.concat())
+}
+
+fn oid(encoded: &[u8]) -> Vec<u8> {
+    der(0x06, encoded)
+}
+
+fn bit_string(bytes: &[u8]) -> Vec<u8> {
+    der(0x03, &[&[0], bytes].concat())
+}
+
+/// UTCTime up to 2049, GeneralizedTime after, as RFC 5280 asks.
+fn time(at: DateTime<Utc>) -> Vec<u8> {
+    if at.year() < 2050 {
+        der(0x17, at.format("%y%m%d%H%M%SZ").to_string().as_bytes())
+    } else {
+        der(0x18, at.format("%Y%m%d%H%M%SZ").to_string().as_bytes())
+    }
+}
+
+/// `der` as a PEM block with `label`.
+pub fn pem(label: &str, der: &[u8]) -> String {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(der);
+    let mut out = format!("-----BEGIN {}-----\n", label);
+    for line in encoded.as_bytes().chunks(64) {
+        out.push_str(std::str::from_utf8(line).unwrap_or_default());
+        out.push('\n');
+    }
+    out.push_str(&format!("-----END {}-----\n", label));
+    out
+}
+
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(dir)
+}
+
+/// Write a file only the current user can read. It is written next to
+/// `path` and renamed over it, so a file already there keeps neither its
+/// permissions nor, after a crash, half of the new content.
+fn write_private(path: &Path, content: &[u8]) -> io::Result<()> {
+    let (temp, mut file) = loop {
+        let temp = path.with_extension(format!("{:08x}.tmp", fastrand::u32(..)));
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        match options.open(&temp) {
+            Ok(file) => break (temp, file),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    };
+    let written = file.write_all(content).and_then(|_| file.sync_all()).and_then(|_| fs::rename(&temp, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written
+}
+
+/// Whether the system trusts the certificate `der`, as far as can be told.
+pub fn trusted(data_dir: &Path, der: &[u8]) -> bool {
+    trusted_by_system(&cert_path(data_dir), der)
+}
+
+/// CA bundles of the common distributions, generated from their trust stores.
+#[cfg(all(unix, not(target_os = "macos")))]
+const BUNDLES: [&str; 4] = [
+    "/etc/ssl/certs/ca-certificates.crt",
+    "/etc/pki/tls/certs/ca-bundle.crt",
+    "/etc/ssl/cert.pem",
+    "/etc/ssl/ca-bundle.pem",
+];
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn trusted_by_system(_path: &Path, der: &[u8]) -> bool {
+    BUNDLES.iter().any(|bundle| in_bundle(Path::new(bundle), der))
+}
+
+#[cfg(target_os = "macos")]
+fn trusted_by_system(path: &Path, _der: &[u8]) -> bool {
+    Command::new("security")
+        .arg("verify-cert")
+        .arg("-c")
+        .arg(path)
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+#[cfg(windows)]
+fn trusted_by_system(_path: &Path, der: &[u8]) -> bool {
+    Command::new("certutil")
+        .args(["-user", "-verifystore", "Root", &thumbprint(der)])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Whether the PEM bundle at `path` holds the certificate `der`.
+#[cfg(any(test, all(unix, not(target_os = "macos"))))]
+fn in_bundle(path: &Path, der: &[u8]) -> bool {
+    CertificateDer::pem_file_iter(path).is_ok_and(|mut certs| certs.any(|cert| cert.is_ok_and(|cert| *cert == *der)))
+}
+
+/// The SHA-1 fingerprint, how certificate stores name a certificate.
+fn thumbprint(der: &[u8]) -> String {
+    digest::digest(&digest::SHA1_FOR_LEGACY_USE_ONLY, der).as_ref().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Trust stores read by `update-ca-certificates` or `update-ca-trust`: the
+/// directory to put the certificate in, its file name and the command that
+/// rebuilds the bundles.
+#[cfg(all(unix, not(target_os = "macos")))]
+const ANCHORS: [(&str, &str, &str); 3] = [
+    ("/usr/local/share/ca-certificates", "yap-ca.crt", "update-ca-certificates"),
+    ("/etc/pki/ca-trust/source/anchors", "yap-ca.pem", "update-ca-trust"),
+    ("/etc/ca-certificates/trust-source/anchors", "yap-ca.crt", "update-ca-trust"),
+];
+
+/// Add the certificate at `path` to the system trust store.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn install(path: &Path) -> color_eyre::Result<()> {
+    let Some((dir, name, update)) = ANCHORS.iter().find(|(dir, _, _)| Path::new(dir).is_dir()) else {
+        return Err(eyre!("No known trust store on this system, add {} to it by hand", path.display()));
+    };
+    let target = Path::new(dir).join(name);
+    run(privileged("install").arg("-m").arg("644").arg(path).arg(&target))?;
+    run(&mut privileged(update))
+}
+
+#[cfg(target_os = "macos")]
+fn install(path: &Path) -> color_eyre::Result<()> {
+    let home = std::env::var_os("HOME").ok_or_else(|| eyre!("HOME is not set"))?;
+    let keychain = Path::new(&home).join("Library/Keychains/login.keychain-db");
+    run(Command::new("security")
+        .args(["add-trusted-cert", "-r", "trustRoot", "-k"])
+        .arg(keychain)
+        .arg(path))
+}
+
+#[cfg(windows)]
+fn install(path: &Path) -> color_eyre::Result<()> {
+    run(Command::new("certutil").args(["-user", "-addstore", "Root"]).arg(path))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn install(path: &Path) -> color_eyre::Result<()> {
+    Err(eyre!("Installing isn't supported on this system, add {} to its trust store by hand", path.display()))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn trusted_by_system(_path: &Path, _der: &[u8]) -> bool {
+    false
+}
+
+/// `program` through `sudo`, unless yap already runs as root.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn privileged(program: &str) -> Command {
+    // SAFETY: geteuid has no preconditions
+    if unsafe { libc::geteuid() } == 0 {
+        Command::new(program)
+    } else {
+        let mut command = Command::new("sudo");
+        command.arg(program);
+        command
+    }
+}
+
+fn run(command: &mut Command) -> color_eyre::Result<()> {
+    let line = std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|part| part.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ");
+    println!("Running {}", line);
+    let status = command.status().wrap_err_with(|| format!("Failed to run {}", line))?;
+    if !status.success() {
+        return Err(eyre!("{} failed with {}", line, status));
+    }
+    Ok(())
+}
+
+/// `yap cert`.
+pub fn run_command(command: CertCommands, data_dir: &Path) -> color_eyre::Result<()> {
+    match command {
+        CertCommands::Generate { force } => {
+            if !force && let Some(cert) = load(data_dir)? {
+                println!("There already is a CA certificate at {}, --force replaces it", cert_path(data_dir).display());
+                println!("Fingerprint: {}", thumbprint(&cert));
+                return Ok(());
+            }
+            let cert = generate(data_dir, Utc::now())?;
+            println!("Wrote the CA certificate to {}", cert_path(data_dir).display());
+            println!("Fingerprint: {}", thumbprint(&cert));
+            println!("`yap cert install` adds it to the system trust store");
+        }
+        CertCommands::Export { der, output, .. } => {
+            let cert = require(data_dir)?;
+            let bytes = if der { cert } else { pem("CERTIFICATE", &cert).into_bytes() };
+            match output {
+                Some(path) => {
+                    fs::write(&path, bytes).wrap_err_with(|| format!("Failed to write {}", path.display()))?;
+                    eprintln!("Wrote the CA certificate to {}", path.display());
+                }
+                None => io::stdout().write_all(&bytes)?,
+            }
+        }
+        CertCommands::Install => {
+            let cert = require(data_dir)?;
+            install(&cert_path(data_dir))?;
+            if trusted(data_dir, &cert) {
+                println!("The CA certificate is trusted now");
+            } else {
+                println!("The CA certificate was installed, but the system doesn't report it as trusted yet");
+            }
+        }
+    }
+    Ok(())
+}
+
+fn require(data_dir: &Path) -> color_eyre::Result<Vec<u8>> {
+    load(data_dir)?.ok_or_else(|| eyre!("There is no CA certificate yet, `yap cert generate` creates one"))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use rustls::RootCertStore;
+
+    use super::*;
+    use crate::framework::testing::TempDir;
+
+    #[test]
+    fn test_der_lengths() {
+        assert_eq!(der(0x04, &[1, 2]), vec![0x04, 0x02, 1, 2]);
+        assert_eq!(der(0x04, &[0; 200])[..3], [0x04, 0x81, 200]);
+        assert_eq!(der(0x04, &[0; 300])[..4], [0x04, 0x82, 0x01, 0x2c]);
+    }
+
+    #[test]
+    fn test_generated_ca() {
+        let dir = TempDir::new();
+        let data_dir = dir.path();
+        let now = DateTime::parse_from_rfc3339("2026-10-17T09:00:00Z").unwrap().with_timezone(&Utc);
+        let cert = generate(data_dir, now).unwrap();
+        assert_eq!(load(data_dir).unwrap(), Some(cert.clone()));
+
+        // accepted as a trust anchor
+        let mut roots = RootCertStore::empty();
+        roots.add(CertificateDer::from(cert.clone())).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(key_path(data_dir)).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let bundle = data_dir.join("bundle.pem");
+        fs::write(&bundle, pem("CERTIFICATE", b"another")).unwrap();
+        assert!(!in_bundle(&bundle, &cert));
+        fs::write(&bundle, [pem("CERTIFICATE", b"another"), pem("CERTIFICATE", &cert)].concat()).unwrap();
+        assert!(in_bundle(&bundle, &cert));
+
+        // a new one replaces it, and a key left readable to others isn't reused as it is
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(key_path(data_dir), fs::Permissions::from_mode(0o644)).unwrap();
+        }
+        let replaced = generate(data_dir, now).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(key_path(data_dir)).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        assert_eq!(fs::read_dir(super::dir(data_dir)).unwrap().count(), 2, "no temporary file is left");
+        assert_ne!(replaced, cert);
+        assert_eq!(load(data_dir).unwrap(), Some(replaced));
+    }
+}
@@ -1,10 +1,99 @@
-use clap::Parser;
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
 
 use crate::config::{get_config_dir, get_data_dir};
 
 #[derive(Parser, Debug)]
 #[command(author, version = version(), about)]
-pub struct Cli {}
+pub struct Cli {
+    /// Run as a reverse proxy forwarding every request to this base URL
+    #[arg(long, value_name = "URL")]
+    pub reverse: Option<String>,
+
+    /// Serve responses from this capture directory instead of the upstream
+    #[arg(long, value_name = "DIR")]
+    pub replay: Option<PathBuf>,
+
+    /// Accept any certificate from https upstreams
+    #[arg(long)]
+    pub insecure: bool,
+
+    /// Also write a line per request to stderr, or to this file or FIFO
+    #[arg(long, value_name = "PATH", num_args = 0..=1, default_missing_value = "-")]
+    pub tee: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    /// Work with the config file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+    /// Manage the root CA certificate for HTTPS interception
+    Cert {
+        #[command(subcommand)]
+        command: CertCommands,
+    },
+    /// Proxy a mock server on a local port, to try yap without a real upstream
+    Demo,
+    /// Replay a saved session against its upstreams and report latency and errors
+    Load {
+        /// Session archive saved with `:w`
+        archive: PathBuf,
+
+        /// Pace relative to the capture, 2 for twice as fast
+        #[arg(long, default_value_t = 1.0, value_parser = positive)]
+        speed: f64,
+
+        /// Send this many requests per second instead of following the capture
+        #[arg(long, conflicts_with = "speed", value_parser = positive)]
+        rps: Option<f64>,
+    },
+}
+
+fn positive(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(number) if number > 0.0 && number.is_finite() => Ok(number),
+        _ => Err(format!("`{}` is not a positive number", value)),
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommands {
+    /// Report unknown keys, invalid values and missing files in the config
+    Check,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CertCommands {
+    /// Create the CA certificate and key, unless there already is one
+    Generate {
+        /// Replace the existing CA, which then has to be installed again
+        #[arg(long)]
+        force: bool,
+    },
+    /// Print the CA certificate, or write it to a file
+    Export {
+        /// PEM encoded, the default
+        #[arg(long, conflicts_with = "der")]
+        pem: bool,
+
+        /// DER encoded
+        #[arg(long)]
+        der: bool,
+
+        /// File to write instead of printing
+        #[arg(long, short, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+    /// Add the CA certificate to the system trust store
+    Install,
+}
 
 const VERSION_MESSAGE: &str = concat!(
     env!("CARGO_PKG_VERSION"),
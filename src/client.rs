@@ -0,0 +1,57 @@
+//! Requests yap sends itself, like the ones written in the composer. They go
+//! through yap's own proxy, so rules and scripts apply and they are captured
+//! like any client's.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use http_body_util::{BodyExt, Full};
+use hyper::{
+    Request, Response,
+    body::Bytes,
+    header::{HeaderValue, PROXY_AUTHORIZATION},
+};
+use hyper_util::rt::TokioIo;
+
+use crate::auth::ProxyAuth;
+
+#[derive(Clone, Debug)]
+pub struct ProxyClient {
+    proxy: SocketAddr,
+    /// `Proxy-Authorization` when the proxy asks for credentials.
+    credentials: Option<String>,
+}
+
+impl ProxyClient {
+    /// A client of the proxy listening on `listen`.
+    pub fn new(listen: SocketAddr, auth: &ProxyAuth) -> Self {
+        Self {
+            proxy: local(listen),
+            credentials: auth.credentials(),
+        }
+    }
+
+    /// Send `request` in absolute form, like any proxy client, and read the
+    /// whole response so that it is captured complete.
+    pub async fn send(&self, mut request: Request<Full<Bytes>>) -> color_eyre::Result<Response<Bytes>> {
+        if let Some(credentials) = &self.credentials {
+            request
+                .headers_mut()
+                .insert(PROXY_AUTHORIZATION, HeaderValue::try_from(credentials)?);
+        }
+        let stream = tokio::net::TcpStream::connect(self.proxy).await?;
+        let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream)).await?;
+        tokio::spawn(connection);
+        let (parts, body) = sender.send_request(request).await?.into_parts();
+        let body = body.collect().await?.to_bytes();
+        Ok(Response::from_parts(parts, body))
+    }
+}
+
+/// An address to reach a listener bound to `listen` from this machine.
+fn local(listen: SocketAddr) -> SocketAddr {
+    match listen.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => SocketAddr::new(Ipv4Addr::LOCALHOST.into(), listen.port()),
+        IpAddr::V6(ip) if ip.is_unspecified() => SocketAddr::new(Ipv6Addr::LOCALHOST.into(), listen.port()),
+        _ => listen,
+    }
+}
@@ -0,0 +1,389 @@
+//! Telling the devices that send requests apart: the names shown for their
+//! addresses, from `clients.labels` or, when `clients.reverse_dns` is on,
+//! from the reverse DNS of the address, and the profiles that decide what
+//! each one's requests go through.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use hickory_resolver::{
+    TokioAsyncResolver,
+    config::{NameServerConfigGroup, ResolverConfig, ResolverOpts},
+};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::{
+    config::DnsConfig,
+    limits::{LimitRule, Limiter},
+    rewrite::{self, RewriteRule},
+};
+
+/// How long a reverse lookup may take before the address is left unnamed.
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClientsConfig {
+    /// Names for client addresses, e.g. `"192.168.1.23": "pixel"`.
+    pub labels: BTreeMap<IpAddr, String>,
+    /// Name the clients without a label by the reverse DNS of their address.
+    pub reverse_dns: bool,
+    /// Show the client of each request in the list.
+    pub column: bool,
+    /// Named sets of limits that clients can be throttled with.
+    pub throttles: BTreeMap<String, Vec<LimitRule>>,
+    /// Named sets of rewrite rules for the requests of some clients.
+    pub rewrites: BTreeMap<String, Vec<RewriteRule>>,
+    /// What the requests of particular clients go through, by address.
+    pub profiles: BTreeMap<IpAddr, ClientProfile>,
+}
+
+/// What the requests of one client go through, on top of the `proxy` settings.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClientProfile {
+    /// List the client's requests; they are forwarded either way.
+    pub capture: bool,
+    /// The `throttles` entry the client's requests are limited by, besides `proxy.limits`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub throttle: Option<String>,
+    /// The `rewrites` entry tried on the client's requests ahead of `proxy.rewrites`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rewrites: Option<String>,
+}
+
+impl Default for ClientProfile {
+    fn default() -> Self {
+        Self {
+            capture: true,
+            throttle: None,
+            rewrites: None,
+        }
+    }
+}
+
+/// The names of the clients, looked up once per address.
+#[derive(Default)]
+pub struct ClientNames {
+    labels: BTreeMap<IpAddr, String>,
+    /// Set when `reverse_dns` is on and a resolver could be set up.
+    resolver: Option<TokioAsyncResolver>,
+    /// Reverse DNS answers, including the addresses without a name; `None`
+    /// while the lookup is running.
+    resolved: Arc<Mutex<HashMap<IpAddr, Option<Option<String>>>>>,
+}
+
+impl ClientNames {
+    /// Names from `config`, looked up with the servers of `dns` when set.
+    pub fn new(config: &ClientsConfig, dns: &DnsConfig) -> Self {
+        let resolver = config.reverse_dns.then(|| resolver(dns)).flatten();
+        Self {
+            labels: config.labels.clone(),
+            resolver,
+            resolved: Arc::default(),
+        }
+    }
+
+    /// The label of `ip`, if it has one.
+    pub fn label(&self, ip: IpAddr) -> Option<String> {
+        self.labels.get(&ip).cloned()
+    }
+
+    /// The name of `ip` as far as it is known: its label, else its host name
+    /// once [`ClientNames::lookup`] found it.
+    pub fn name(&self, ip: IpAddr) -> Option<String> {
+        self.label(ip).or_else(|| self.resolved.lock().unwrap().get(&ip).cloned().flatten().flatten())
+    }
+
+    /// The reverse lookup of `ip`, unless it has a label or was looked up
+    /// already, so there is one lookup per address however many connections
+    /// it opens. The future resolves to the host name, which [`ClientNames::name`]
+    /// returns from then on.
+    pub fn lookup(&self, ip: IpAddr) -> Option<impl Future<Output = Option<String>> + Send + 'static> {
+        if self.labels.contains_key(&ip) {
+            return None;
+        }
+        let resolver = self.resolver.clone()?;
+        {
+            let mut resolved = self.resolved.lock().unwrap();
+            if resolved.contains_key(&ip) {
+                return None;
+            }
+            resolved.insert(ip, None);
+        }
+        let resolved = self.resolved.clone();
+        Some(async move {
+            let name = match tokio::time::timeout(LOOKUP_TIMEOUT, resolver.reverse_lookup(ip)).await {
+                Ok(Ok(lookup)) => lookup.iter().next().map(|name| name.to_utf8().trim_end_matches('.').to_string()),
+                Ok(Err(e)) => {
+                    debug!("No host name for client {}: {}", ip, e);
+                    None
+                }
+                Err(_) => {
+                    debug!("Looking up the host name of client {} timed out", ip);
+                    None
+                }
+            };
+            resolved.lock().unwrap().insert(ip, Some(name.clone()));
+            name
+        })
+    }
+}
+
+/// A client as the clients panel lists it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClientRow {
+    pub ip: IpAddr,
+    pub name: Option<String>,
+    /// Requests it sent this run.
+    pub requests: u64,
+    pub profile: ClientProfile,
+}
+
+/// A client with a profile or requests this run.
+#[derive(Default)]
+struct Client {
+    name: Option<String>,
+    requests: u64,
+    profile: ClientProfile,
+    /// Budgets of its throttle, apart from every other client's.
+    limiter: Option<Arc<Limiter>>,
+}
+
+/// The profiles of the clients, shared by the proxy and the clients panel,
+/// where they are changed while the proxy runs.
+#[derive(Default)]
+pub struct ClientProfiles {
+    throttles: Mutex<BTreeMap<String, Vec<LimitRule>>>,
+    rewrites: Mutex<BTreeMap<String, Vec<RewriteRule>>>,
+    clients: Mutex<BTreeMap<IpAddr, Client>>,
+}
+
+impl ClientProfiles {
+    pub fn new(config: &ClientsConfig) -> Self {
+        let profiles = Self::default();
+        profiles.set_config(config);
+        profiles
+    }
+
+    /// Take the throttles, rewrite sets and profiles of a reloaded config.
+    /// Clients keep their request counts, and start over on their throttle.
+    pub fn set_config(&self, config: &ClientsConfig) {
+        *self.throttles.lock().unwrap() = config.throttles.clone();
+        *self.rewrites.lock().unwrap() = config.rewrites.clone();
+        let mut clients = self.clients.lock().unwrap();
+        for client in clients.values_mut() {
+            client.profile = ClientProfile::default();
+            client.limiter = None;
+        }
+        for (ip, profile) in &config.profiles {
+            let client = clients.entry(*ip).or_default();
+            if client.name.is_none() {
+                client.name = config.labels.get(ip).cloned();
+            }
+            client.profile = profile.clone();
+        }
+    }
+
+    /// Count a request from `ip`, known as `name`.
+    pub fn request(&self, ip: IpAddr, name: Option<String>) {
+        let mut clients = self.clients.lock().unwrap();
+        let client = clients.entry(ip).or_default();
+        client.requests += 1;
+        if name.is_some() {
+            client.name = name;
+        }
+    }
+
+    /// Name `ip` once its host name was looked up.
+    pub fn set_name(&self, ip: IpAddr, name: String) {
+        self.clients.lock().unwrap().entry(ip).or_default().name = Some(name);
+    }
+
+    /// Whether the requests of `ip` are listed.
+    pub fn captures(&self, ip: IpAddr) -> bool {
+        self.clients.lock().unwrap().get(&ip).is_none_or(|client| client.profile.capture)
+    }
+
+    /// The URL after the first enabled rule of the rewrite set of `ip`
+    /// matching `url`, if any.
+    pub fn rewrite(&self, ip: IpAddr, url: &str) -> Option<String> {
+        let set = self.clients.lock().unwrap().get(&ip)?.profile.rewrites.clone()?;
+        rewrite::apply(self.rewrites.lock().unwrap().get_mut(&set)?, url)
+    }
+
+    /// The limiter of the throttle of `ip`, if it has one.
+    pub fn limiter(&self, ip: IpAddr) -> Option<Arc<Limiter>> {
+        let mut clients = self.clients.lock().unwrap();
+        let client = clients.get_mut(&ip)?;
+        let throttle = client.profile.throttle.as_ref()?;
+        if client.limiter.is_none() {
+            let rules = self.throttles.lock().unwrap().get(throttle)?.clone();
+            client.limiter = Some(Arc::new(Limiter::new(rules)));
+        }
+        client.limiter.clone()
+    }
+
+    /// Every client with a profile or requests, by address.
+    pub fn rows(&self) -> Vec<ClientRow> {
+        (self.clients.lock().unwrap().iter())
+            .map(|(ip, client)| ClientRow {
+                ip: *ip,
+                name: client.name.clone(),
+                requests: client.requests,
+                profile: client.profile.clone(),
+            })
+            .collect()
+    }
+
+    /// The profiles that differ from the default, as `clients.profiles` saves them.
+    pub fn profiles(&self) -> BTreeMap<IpAddr, ClientProfile> {
+        (self.clients.lock().unwrap().iter())
+            .filter(|(_, client)| client.profile != ClientProfile::default())
+            .map(|(ip, client)| (*ip, client.profile.clone()))
+            .collect()
+    }
+
+    pub fn toggle_capture(&self, ip: IpAddr) {
+        if let Some(client) = self.clients.lock().unwrap().get_mut(&ip) {
+            client.profile.capture = !client.profile.capture;
+        }
+    }
+
+    /// Move `ip` on to the next throttle, after the last one to none.
+    pub fn next_throttle(&self, ip: IpAddr) {
+        let names: Vec<String> = self.throttles.lock().unwrap().keys().cloned().collect();
+        if let Some(client) = self.clients.lock().unwrap().get_mut(&ip) {
+            client.profile.throttle = next(&names, client.profile.throttle.as_deref());
+            client.limiter = None;
+        }
+    }
+
+    /// Move `ip` on to the next rewrite set, after the last one to none.
+    pub fn next_rewrites(&self, ip: IpAddr) {
+        let names: Vec<String> = self.rewrites.lock().unwrap().keys().cloned().collect();
+        if let Some(client) = self.clients.lock().unwrap().get_mut(&ip) {
+            client.profile.rewrites = next(&names, client.profile.rewrites.as_deref());
+        }
+    }
+}
+
+/// The name after `current` in `names`, the first after none, and none after the last.
+fn next(names: &[String], current: Option<&str>) -> Option<String> {
+    let at = match current {
+        Some(current) => names.iter().position(|name| name == current).map_or(0, |at| at + 1),
+        None => 0,
+    };
+    names.get(at).cloned()
+}
+
+/// A resolver querying the servers of `dns`, or those of the system.
+fn resolver(dns: &DnsConfig) -> Option<TokioAsyncResolver> {
+    if !dns.servers.is_empty() {
+        let servers = NameServerConfigGroup::from_ips_clear(&dns.servers, 53, true);
+        let config = ResolverConfig::from_parts(None, Vec::new(), servers);
+        return Some(TokioAsyncResolver::tokio(config, ResolverOpts::default()));
+    }
+    match TokioAsyncResolver::tokio_from_system_conf() {
+        Ok(resolver) => Some(resolver),
+        Err(e) => {
+            warn!("Failed to set up reverse DNS of clients: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_names() {
+        let config: ClientsConfig =
+            serde_json::from_str(r#"{"labels": {"192.168.1.23": "pixel", "fd00::7": "laptop"}}"#).unwrap();
+        let names = ClientNames::new(&config, &DnsConfig::default());
+        assert_eq!(names.name("192.168.1.23".parse().unwrap()).as_deref(), Some("pixel"));
+        assert_eq!(names.name("fd00::7".parse().unwrap()).as_deref(), Some("laptop"));
+        // without reverse DNS the others stay unnamed, and nothing is looked up
+        assert_eq!(names.name("192.168.1.24".parse().unwrap()), None);
+        assert!(names.lookup("192.168.1.24".parse().unwrap()).is_none());
+
+        // answers are kept, unnamed addresses included
+        let names = ClientNames {
+            resolver: Some(TokioAsyncResolver::tokio(ResolverConfig::new(), ResolverOpts::default())),
+            ..names
+        };
+        names.resolved.lock().unwrap().insert("10.0.0.2".parse().unwrap(), Some(Some("build-box.lan".to_string())));
+        names.resolved.lock().unwrap().insert("10.0.0.3".parse().unwrap(), Some(None));
+        assert_eq!(names.name("10.0.0.2".parse().unwrap()).as_deref(), Some("build-box.lan"));
+        assert_eq!(names.name("10.0.0.3".parse().unwrap()), None);
+        assert!(names.lookup("10.0.0.2".parse().unwrap()).is_none());
+        assert!(names.lookup("192.168.1.23".parse().unwrap()).is_none(), "labels aren't looked up");
+
+        // one lookup per address, the name stays unknown until it is done
+        let ip = "10.0.0.4".parse().unwrap();
+        let lookup = names.lookup(ip).unwrap();
+        assert!(names.lookup(ip).is_none());
+        assert_eq!(names.name(ip), None);
+        // there are no servers to ask
+        assert_eq!(lookup.await, None);
+        assert_eq!(names.resolved.lock().unwrap().get(&ip), Some(&Some(None)));
+        assert!(names.lookup(ip).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_profiles() {
+        let config: ClientsConfig = json5::from_str(
+            r#"{
+                labels: { "192.168.1.23": "pixel" },
+                throttles: { "3g": [{ per_second: 1, reject: true }], "edge": [{ max_concurrent: 1 }] },
+                rewrites: { staging: [{ from: "api.test", to: "staging.api.test" }] },
+                profiles: { "192.168.1.23": { throttle: "3g", rewrites: "staging" }, "192.168.1.40": { capture: false } },
+            }"#,
+        )
+        .unwrap();
+        let profiles = ClientProfiles::new(&config);
+        let (phone, laptop, other): (IpAddr, IpAddr, IpAddr) =
+            ("192.168.1.23".parse().unwrap(), "192.168.1.40".parse().unwrap(), "10.0.0.2".parse().unwrap());
+
+        profiles.request(phone, Some("pixel".to_string()));
+        profiles.request(other, None);
+        assert_eq!((profiles.captures(phone), profiles.captures(laptop), profiles.captures(other)), (true, false, true));
+        assert_eq!(profiles.rewrite(phone, "http://api.test/users").as_deref(), Some("http://staging.api.test/users"));
+        assert_eq!(profiles.rewrite(other, "http://api.test/users"), None);
+
+        // the phone's throttle lets one request a second through, and nobody else's count
+        let limiter = profiles.limiter(phone).unwrap();
+        assert!(limiter.acquire("api.test").await.is_ok());
+        assert!(profiles.limiter(phone).unwrap().acquire("api.test").await.is_err());
+        assert!(profiles.limiter(other).is_none());
+
+        // as switched from the clients panel
+        profiles.next_throttle(phone);
+        profiles.request(phone, None);
+        assert_eq!(profiles.rows()[1].profile.throttle.as_deref(), Some("edge"));
+        profiles.next_throttle(phone);
+        profiles.next_rewrites(phone);
+        profiles.toggle_capture(laptop);
+        profiles.next_throttle(other);
+        let rows: Vec<_> = profiles.rows().into_iter().map(|row| (row.ip, row.name, row.requests)).collect();
+        assert_eq!(rows, [(other, None, 1), (phone, Some("pixel".to_string()), 2), (laptop, None, 0)]);
+        assert_eq!(
+            profiles.profiles(),
+            BTreeMap::from([(
+                other,
+                ClientProfile {
+                    throttle: Some("3g".to_string()),
+                    ..ClientProfile::default()
+                }
+            )])
+        );
+    }
+}
@@ -0,0 +1,48 @@
+//! Copying to the clipboard: the system clipboard locally, the terminal's
+//! through OSC 52 over SSH or when there is no system clipboard.
+
+use std::{io::Write, sync::Mutex};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use tracing::{debug, warn};
+
+lazy_static::lazy_static! {
+    // On X11 the copied contents are served by the owning process, so the
+    // clipboard handle has to outlive the copy call.
+    static ref CLIPBOARD: Mutex<Option<arboard::Clipboard>> = Mutex::new(None);
+}
+
+/// Copy `text` to the system clipboard.
+///
+/// Over SSH, or when no system clipboard is reachable, this falls back to the
+/// OSC 52 escape sequence, which asks the local terminal to set its clipboard.
+pub fn copy(text: &str) -> color_eyre::Result<()> {
+    let over_ssh = std::env::var_os("SSH_TTY").is_some() || std::env::var_os("SSH_CONNECTION").is_some();
+    if !over_ssh {
+        match copy_native(text) {
+            Ok(()) => return Ok(()),
+            Err(e) => warn!("System clipboard unavailable, falling back to OSC 52: {}", e),
+        }
+    }
+    copy_osc52(text)
+}
+
+fn copy_native(text: &str) -> Result<(), arboard::Error> {
+    let mut clipboard = CLIPBOARD.lock().unwrap_or_else(|e| e.into_inner());
+    if clipboard.is_none() {
+        *clipboard = Some(arboard::Clipboard::new()?);
+    }
+    if let Some(clipboard) = clipboard.as_mut() {
+        clipboard.set_text(text)?;
+    }
+    debug!("Copied {} bytes to the system clipboard", text.len());
+    Ok(())
+}
+
+fn copy_osc52(text: &str) -> color_eyre::Result<()> {
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    stdout.flush()?;
+    debug!("Copied {} bytes through OSC 52", text.len());
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use std::{collections::VecDeque, time::Duration};
+
+use ratatui::{prelude::*, symbols::Marker, widgets::*};
+use tracing::info;
+
+use super::{Component, stats::bytes};
+use crate::{
+    config::Config,
+    connections::ConnectionStats,
+    framework::{Action, Updater},
+    theme::Theme,
+};
+
+/// Seconds of throughput charted.
+const WINDOW: usize = 60;
+const SAMPLE_EVERY: Duration = Duration::from_secs(1);
+
+/// Bytes sent upstream and received back in each of the last [`WINDOW`]
+/// seconds, oldest first.
+#[derive(Debug, Default, PartialEq)]
+struct Throughput {
+    /// Counter totals at the previous sample.
+    last: Option<(u64, u64)>,
+    seconds: VecDeque<(u64, u64)>,
+}
+
+impl Throughput {
+    fn sample(&mut self, (sent, received): (u64, u64)) {
+        // the first sample only sets where counting starts
+        if let Some((last_sent, last_received)) = self.last {
+            if self.seconds.len() == WINDOW {
+                self.seconds.pop_front();
+            }
+            self.seconds.push_back((sent.saturating_sub(last_sent), received.saturating_sub(last_received)));
+        }
+        self.last = Some((sent, received));
+    }
+
+    /// One direction as chart points, the newest second at `x = 0` and
+    /// older ones towards `-WINDOW`.
+    fn points(&self, direction: impl Fn(&(u64, u64)) -> u64) -> Vec<(f64, f64)> {
+        let offset = self.seconds.len() as f64 - 1.0;
+        self.seconds
+            .iter()
+            .enumerate()
+            .map(|(second, sample)| (second as f64 - offset, direction(sample) as f64))
+            .collect()
+    }
+
+    fn peak(&self) -> u64 {
+        self.seconds.iter().map(|&(sent, received)| sent.max(received)).max().unwrap_or_default()
+    }
+}
+
+/// Chart of how fast the proxy talks to the upstream hosts, all of them
+/// together, from the byte counters of their connections.
+pub struct BandwidthGraph {
+    connections: ConnectionStats,
+    throughput: Throughput,
+    shown: bool,
+    task_handle: Option<tokio::task::JoinHandle<()>>,
+    theme: Theme,
+}
+
+impl BandwidthGraph {
+    pub fn new(connections: ConnectionStats) -> Self {
+        Self {
+            connections,
+            throughput: Throughput::default(),
+            shown: false,
+            task_handle: None,
+            theme: Theme::default(),
+        }
+    }
+}
+
+/// Sent by the sampling task.
+struct Sample;
+
+impl Component for BandwidthGraph {
+    fn component_will_mount(&mut self, config: Config) -> color_eyre::Result<()> {
+        self.theme = config.theme;
+        Ok(())
+    }
+
+    fn component_did_mount(
+        &mut self,
+        _area: ratatui::layout::Size,
+        updater: Updater,
+    ) -> color_eyre::Result<()> {
+        info!("BandwidthGraph::component_did_mount");
+        self.throughput.sample(self.connections.totals());
+        let ticker = updater.clone();
+        self.task_handle = Some(updater.spawn(async move {
+            let mut interval = tokio::time::interval(SAMPLE_EVERY);
+            interval.tick().await;
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => ticker.send(Sample),
+                    _ = ticker.cancelled() => break,
+                }
+            }
+        }));
+        Ok(())
+    }
+
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        match action {
+            Action::ConfigReloaded(reloaded) => {
+                self.component_will_mount(reloaded.config())?;
+                Ok(None)
+            }
+            Action::ToggleBandwidth => {
+                self.shown = !self.shown;
+                Ok(None)
+            }
+            // sampled while hidden too, so the chart has a past when shown
+            Action::Message(message) if message.is::<Sample>() => {
+                self.throughput.sample(self.connections.totals());
+                Ok(self.shown.then_some(Action::Render))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect) -> color_eyre::Result<()> {
+        let theme = &self.theme;
+        let (sent, received) = self.throughput.seconds.back().copied().unwrap_or_default();
+        let up = self.throughput.points(|&(sent, _)| sent);
+        let down = self.throughput.points(|&(_, received)| received);
+        let datasets = vec![
+            Dataset::default()
+                .name(format!("up {}/s", bytes(sent)))
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(theme.fg(theme.warning))
+                .data(&up),
+            Dataset::default()
+                .name(format!("down {}/s", bytes(received)))
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(theme.fg(theme.success))
+                .data(&down),
+        ];
+        // a little headroom, and some scale when idle
+        let top = (self.throughput.peak() as f64 * 1.1).max(1024.0);
+        let x_axis = Axis::default()
+            .bounds([1.0 - WINDOW as f64, 0.0])
+            .labels([format!("-{}s", WINDOW), "now".to_string()])
+            .style(theme.fg(theme.muted));
+        let y_axis = Axis::default()
+            .bounds([0.0, top])
+            .labels(["0".to_string(), format!("{}/s", bytes(top as u64))])
+            .style(theme.fg(theme.muted));
+        let chart = Chart::new(datasets)
+            .block(Block::default().title("Bandwidth").borders(Borders::ALL).border_style(theme.fg(theme.accent)))
+            .x_axis(x_axis)
+            .y_axis(y_axis)
+            .legend_position(Some(LegendPosition::TopLeft))
+            .hidden_legend_constraints((Constraint::Ratio(1, 2), Constraint::Ratio(1, 1)));
+        frame.render_widget(chart, area);
+        Ok(())
+    }
+}
+
+impl Drop for BandwidthGraph {
+    fn drop(&mut self) {
+        if let Some(handle) = self.task_handle.take() {
+            handle.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_throughput_window() {
+        let mut throughput = Throughput::default();
+        throughput.sample((100, 1000));
+        assert_eq!(throughput.points(|&(sent, _)| sent), Vec::new());
+
+        throughput.sample((150, 3000));
+        throughput.sample((150, 3500));
+        assert_eq!(throughput.points(|&(sent, _)| sent), vec![(-1.0, 50.0), (0.0, 0.0)]);
+        assert_eq!(throughput.points(|&(_, received)| received), vec![(-1.0, 2000.0), (0.0, 500.0)]);
+        assert_eq!(throughput.peak(), 2000);
+
+        for _ in 0..WINDOW {
+            throughput.sample((150, 3500));
+        }
+        assert_eq!(throughput.seconds.len(), WINDOW);
+        assert_eq!(throughput.peak(), 0);
+    }
+}
@@ -0,0 +1,250 @@
+use ratatui::prelude::*;
+
+use super::sse;
+use crate::theme::Theme;
+
+const INDENT: &str = "  ";
+
+/// Pretty-print and colorize a body for display based on its content type.
+/// Bodies that don't match a known format, or fail to parse, are returned as-is.
+pub fn format_body(theme: &Theme, content_type: Option<&str>, body: &str) -> Text<'static> {
+    let content_type = content_type.unwrap_or_default().to_lowercase();
+    let trimmed = body.trim();
+
+    let formatted = if sse::is_event_stream(Some(&content_type)) {
+        Some(sse::format_events(theme, &sse::parse(body)))
+    } else if content_type.contains("json") || looks_like_json(trimmed) {
+        format_json(theme, trimmed)
+    } else if content_type.contains("xml") {
+        Some(format_xml(theme, trimmed))
+    } else if content_type.starts_with("application/x-www-form-urlencoded") {
+        Some(format_form(theme, trimmed))
+    } else {
+        None
+    };
+
+    formatted.unwrap_or_else(|| Text::raw(body.to_string()))
+}
+
+fn looks_like_json(body: &str) -> bool {
+    (body.starts_with('{') && body.ends_with('}')) || (body.starts_with('[') && body.ends_with(']'))
+}
+
+#[derive(Debug, PartialEq)]
+enum JsonToken {
+    Open(char),
+    Close(char),
+    Colon,
+    Comma,
+    Key(String),
+    Str(String),
+    Number(String),
+    Literal(String),
+}
+
+fn tokenize_json(body: &str) -> Vec<JsonToken> {
+    let mut tokens = Vec::new();
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '[' => tokens.push(JsonToken::Open(c)),
+            '}' | ']' => tokens.push(JsonToken::Close(c)),
+            ':' => {
+                // the string before a colon is an object key
+                if let Some(JsonToken::Str(s)) = tokens.pop() {
+                    tokens.push(JsonToken::Key(s));
+                }
+                tokens.push(JsonToken::Colon);
+            }
+            ',' => tokens.push(JsonToken::Comma),
+            '"' => {
+                let mut s = String::from('"');
+                let mut escaped = false;
+                for c in chars.by_ref() {
+                    s.push(c);
+                    match c {
+                        '\\' if !escaped => escaped = true,
+                        '"' if !escaped => break,
+                        _ => escaped = false,
+                    }
+                }
+                tokens.push(JsonToken::Str(s));
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut s = String::from(c);
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || matches!(next, ',' | ':' | '}' | ']') {
+                        break;
+                    }
+                    s.push(next);
+                    chars.next();
+                }
+                if c == '-' || c.is_ascii_digit() {
+                    tokens.push(JsonToken::Number(s));
+                } else {
+                    tokens.push(JsonToken::Literal(s));
+                }
+            }
+        }
+    }
+    tokens
+}
+
+/// Re-indent JSON while keeping the original key order.
+fn format_json(theme: &Theme, body: &str) -> Option<Text<'static>> {
+    serde_json::from_str::<serde_json::Value>(body).ok()?;
+
+    let key_style = theme.fg(theme.accent);
+    let string_style = theme.fg(theme.success);
+    let number_style = theme.fg(theme.warning);
+    let literal_style = theme.fg(theme.special);
+
+    let mut lines: Vec<Line<'static>> = Vec::new();
+    let mut current: Vec<Span<'static>> = Vec::new();
+    let mut depth = 0usize;
+    let tokens = tokenize_json(body);
+    let mut iter = tokens.into_iter().peekable();
+
+    while let Some(token) = iter.next() {
+        match token {
+            JsonToken::Open(c) => {
+                current.push(Span::raw(c.to_string()));
+                // keep empty containers on one line
+                if matches!(iter.peek(), Some(JsonToken::Close(_))) {
+                    if let Some(JsonToken::Close(close)) = iter.next() {
+                        current.push(Span::raw(close.to_string()));
+                    }
+                    continue;
+                }
+                depth += 1;
+                lines.push(Line::from(std::mem::take(&mut current)));
+                current.push(Span::raw(INDENT.repeat(depth)));
+            }
+            JsonToken::Close(c) => {
+                depth = depth.saturating_sub(1);
+                lines.push(Line::from(std::mem::take(&mut current)));
+                current.push(Span::raw(INDENT.repeat(depth)));
+                current.push(Span::raw(c.to_string()));
+            }
+            JsonToken::Comma => {
+                current.push(Span::raw(","));
+                lines.push(Line::from(std::mem::take(&mut current)));
+                current.push(Span::raw(INDENT.repeat(depth)));
+            }
+            JsonToken::Colon => current.push(Span::raw(": ")),
+            JsonToken::Key(s) => current.push(Span::styled(s, key_style)),
+            JsonToken::Str(s) => current.push(Span::styled(s, string_style)),
+            JsonToken::Number(s) => current.push(Span::styled(s, number_style)),
+            JsonToken::Literal(s) => current.push(Span::styled(s, literal_style)),
+        }
+    }
+    if !current.is_empty() {
+        lines.push(Line::from(current));
+    }
+    Some(Text::from(lines))
+}
+
+/// Put every XML tag on its own line, indented by nesting depth.
+fn format_xml(theme: &Theme, body: &str) -> Text<'static> {
+    let tag_style = theme.fg(theme.secondary);
+    let mut lines = Vec::new();
+    let mut depth = 0usize;
+    let mut rest = body;
+
+    while !rest.is_empty() {
+        if rest.starts_with('<') {
+            let end = rest.find('>').map(|i| i + 1).unwrap_or(rest.len());
+            let tag = &rest[..end];
+            rest = &rest[end..];
+
+            let closing = tag.starts_with("</");
+            let standalone = tag.ends_with("/>") || tag.starts_with("<?") || tag.starts_with("<!");
+            if closing {
+                depth = depth.saturating_sub(1);
+            }
+            lines.push(Line::from(vec![
+                Span::raw(INDENT.repeat(depth)),
+                Span::styled(tag.to_string(), tag_style),
+            ]));
+            if !closing && !standalone {
+                depth += 1;
+            }
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            let text = rest[..end].trim();
+            rest = &rest[end..];
+            if !text.is_empty() {
+                lines.push(Line::from(vec![Span::raw(INDENT.repeat(depth)), Span::raw(text.to_string())]));
+            }
+        }
+    }
+    Text::from(lines)
+}
+
+/// Show each decoded form field as `key = value`.
+fn format_form(theme: &Theme, body: &str) -> Text<'static> {
+    let lines: Vec<Line> = url::form_urlencoded::parse(body.as_bytes())
+        .map(|(key, value)| {
+            Line::from(vec![
+                Span::styled(key.into_owned(), theme.fg(theme.accent)),
+                Span::raw(" = "),
+                Span::styled(value.into_owned(), theme.fg(theme.success)),
+            ])
+        })
+        .collect();
+    Text::from(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn plain(text: &Text) -> Vec<String> {
+        text.lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn test_format_json_keeps_key_order() {
+        let text = format_body(&Theme::default(), Some("application/json"), r#"{"b":1,"a":[true,null],"c":{}}"#);
+        assert_eq!(
+            plain(&text),
+            vec![
+                "{",
+                "  \"b\": 1,",
+                "  \"a\": [",
+                "    true,",
+                "    null",
+                "  ],",
+                "  \"c\": {}",
+                "}",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_json_escaped_quotes() {
+        let text = format_body(&Theme::default(), None, r#"{"k":"a\"b,c"}"#);
+        assert_eq!(plain(&text), vec!["{", "  \"k\": \"a\\\"b,c\"", "}"]);
+    }
+
+    #[test]
+    fn test_invalid_json_is_untouched() {
+        let text = format_body(&Theme::default(), Some("application/json"), "{not json}");
+        assert_eq!(plain(&text), vec!["{not json}"]);
+    }
+
+    #[test]
+    fn test_format_xml() {
+        let text = format_body(&Theme::default(), Some("text/xml"), "<a><b x=\"1\">hi</b><c/></a>");
+        assert_eq!(plain(&text), vec!["<a>", "  <b x=\"1\">", "    hi", "  </b>", "  <c/>", "</a>"]);
+    }
+
+    #[test]
+    fn test_format_form() {
+        let text = format_body(&Theme::default(), Some("application/x-www-form-urlencoded"), "a=1&b=hello+world");
+        assert_eq!(plain(&text), vec!["a = 1", "b = hello world"]);
+    }
+}
@@ -0,0 +1,138 @@
+//! Text bodies in other encodings than UTF-8, such as Shift_JIS, GBK or
+//! Latin-1, transcoded for display. Captures keep the original bytes.
+
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_8};
+
+/// How far into a body a `<meta charset>` or XML declaration is looked for,
+/// as browsers do.
+const PRESCAN_BYTES: usize = 1024;
+
+/// Where the encoding of a body was found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    Bom,
+    ContentType,
+    /// A `<meta charset>` or `<?xml encoding?>` declaration.
+    Markup,
+    /// Guessed from the bytes, which weren't UTF-8.
+    Guess,
+}
+
+impl Source {
+    fn describe(self) -> &'static str {
+        match self {
+            Source::Bom => "from its byte order mark",
+            Source::ContentType => "as the Content-Type says",
+            Source::Markup => "as the document declares",
+            Source::Guess => "guessed",
+        }
+    }
+}
+
+/// Whether a body of `content_type` is meant to be read as text.
+pub fn is_text(content_type: Option<&str>) -> bool {
+    let Some(content_type) = content_type.map(str::to_ascii_lowercase) else {
+        return false;
+    };
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+    essence.starts_with("text/")
+        || ["json", "xml", "javascript", "x-www-form-urlencoded"].iter().any(|kind| essence.contains(kind))
+        || charset(Some(&content_type)).is_some()
+}
+
+/// The `charset` parameter of a content type.
+fn charset(content_type: Option<&str>) -> Option<&str> {
+    content_type?.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim().eq_ignore_ascii_case("charset").then(|| value.trim().trim_matches('"'))
+    })
+}
+
+/// The label after `key` in `head`, e.g. `shift_jis` in `charset="shift_jis"`.
+fn declared(head: &str, key: &str) -> Option<&'static Encoding> {
+    let at = head.find(key)? + key.len();
+    let label: String = head[at..]
+        .trim_start_matches(['"', '\'', ' '])
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+        .collect();
+    Encoding::for_label(label.as_bytes())
+}
+
+/// A charset declared in the document itself, for HTML and XML.
+fn markup(content_type: Option<&str>, bytes: &[u8]) -> Option<&'static Encoding> {
+    let content_type = content_type.unwrap_or_default().to_ascii_lowercase();
+    if !content_type.is_empty() && !content_type.contains("html") && !content_type.contains("xml") {
+        return None;
+    }
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(PRESCAN_BYTES)]).to_ascii_lowercase();
+    if head.trim_start().starts_with("<?xml") {
+        return declared(&head, "encoding=");
+    }
+    declared(&head, "charset=")
+}
+
+/// The encoding of `bytes`, and where it was found; `None` when they are
+/// plain UTF-8.
+pub fn detect(content_type: Option<&str>, bytes: &[u8]) -> (&'static Encoding, Option<Source>) {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return (encoding, Some(Source::Bom));
+    }
+    if let Some(encoding) = charset(content_type).and_then(|label| Encoding::for_label(label.as_bytes())) {
+        return (encoding, Some(Source::ContentType));
+    }
+    if let Some(encoding) = markup(content_type, bytes) {
+        return (encoding, Some(Source::Markup));
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return (UTF_8, None);
+    }
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    (detector.guess(None, true), Some(Source::Guess))
+}
+
+/// `bytes` as text, with a note naming the encoding unless it was UTF-8.
+/// Bytes the encoding can't map become U+FFFD.
+pub fn decode(content_type: Option<&str>, bytes: &[u8]) -> (String, Option<String>) {
+    let (encoding, source) = detect(content_type, bytes);
+    let (text, _) = encoding.decode_with_bom_removal(bytes);
+    let note = source
+        .filter(|_| encoding != UTF_8)
+        .map(|source| format!("Decoded from {}, {}", encoding.name(), source.describe()));
+    (text.into_owned(), note)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_detect_and_decode() {
+        // "日本語" in Shift_JIS
+        let sjis = b"\x93\xfa\x96\x7b\x8c\xea";
+        assert_eq!(
+            decode(Some("text/plain; charset=Shift_JIS"), sjis),
+            ("日本語".to_string(), Some("Decoded from Shift_JIS, as the Content-Type says".to_string()))
+        );
+        assert_eq!(decode(Some("text/plain"), "naïve".as_bytes()), ("naïve".to_string(), None));
+        assert_eq!(detect(None, b"\xef\xbb\xbfhi"), (UTF_8, Some(Source::Bom)));
+
+        let html = b"<html><head><meta charset=\"gbk\"></head><body>\xc4\xe3\xba\xc3</body></html>";
+        let (text, note) = decode(Some("text/html"), html);
+        assert!(text.contains("你好"));
+        assert_eq!(note.as_deref(), Some("Decoded from GBK, as the document declares"));
+        let xml = b"<?xml version=\"1.0\" encoding='ISO-8859-1'?><a>caf\xe9</a>";
+        assert_eq!(decode(Some("application/xml"), xml).0, "<?xml version=\"1.0\" encoding='ISO-8859-1'?><a>café</a>");
+
+        assert_eq!(detect(Some("text/plain"), "Größe und Länge".as_bytes()), (UTF_8, None));
+        assert_eq!(detect(Some("text/plain"), b"Gr\xf6\xdfe und L\xe4nge").1, Some(Source::Guess));
+
+        assert!(is_text(Some("application/problem+json")));
+        assert!(is_text(Some("application/x-custom; charset=euc-kr")));
+        assert!(!is_text(Some("image/png")));
+    }
+}
@@ -0,0 +1,269 @@
+use std::{
+    collections::BTreeMap,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use color_eyre::eyre::eyre;
+use crossterm::event::{KeyEvent, MouseEvent, MouseEventKind};
+use ratatui::{prelude::*, widgets::*};
+use serde_json::Value;
+
+use super::{Component, proxy::ClientsShared, rules::config_file};
+use crate::{
+    app::Mode,
+    clients::{ClientProfile, ClientProfiles, ClientRow},
+    config::Config,
+    framework::{Action, Command, Updater},
+    state::SharedState,
+};
+
+/// Replace `clients.profiles` in a parsed config file, leaving everything
+/// else as it is.
+fn merge_profiles(document: &mut Value, profiles: &BTreeMap<IpAddr, ClientProfile>) -> color_eyre::Result<()> {
+    if document.is_null() {
+        *document = Value::Object(Default::default());
+    }
+    let clients = document
+        .as_object_mut()
+        .ok_or_else(|| eyre!("the config is not an object"))?
+        .entry("clients")
+        .or_insert_with(|| Value::Object(Default::default()))
+        .as_object_mut()
+        .ok_or_else(|| eyre!("`clients` in the config is not an object"))?;
+    clients.insert("profiles".to_string(), serde_json::to_value(profiles)?);
+    Ok(())
+}
+
+/// Write the profiles into the config file, so they apply from the next start on.
+async fn save(profiles: &ClientProfiles, dir: &Path) -> color_eyre::Result<PathBuf> {
+    let path = config_file(dir);
+    let mut document = match tokio::fs::read_to_string(&path).await {
+        Ok(content) => json5::from_str(&content)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Value::Null,
+        Err(e) => return Err(e.into()),
+    };
+    merge_profiles(&mut document, &profiles.profiles())?;
+    tokio::fs::create_dir_all(dir).await?;
+    tokio::fs::write(&path, serde_json::to_string_pretty(&document)? + "\n").await?;
+    Ok(path)
+}
+
+/// Overlay listing the clients seen this run and those with a profile. A
+/// client's capture can be turned off and its throttle and rewrite set
+/// switched while the proxy runs, then saved to the config file.
+#[derive(Default)]
+pub struct Clients {
+    profiles: Arc<ClientProfiles>,
+    config: Config,
+    state: SharedState,
+    visible: bool,
+    selected: usize,
+    /// Rows as last read, so keys act on what is shown.
+    rows: Vec<ClientRow>,
+    updater: Option<Updater>,
+    commands: Vec<Command>,
+    /// Where the overlay was last drawn, for click-outside-to-close.
+    popup_area: Rect,
+}
+
+impl Clients {
+    pub fn new(state: SharedState) -> Self {
+        Self {
+            state,
+            ..Self::default()
+        }
+    }
+
+    fn render_update(&self) {
+        if let Some(updater) = &self.updater {
+            updater.update();
+        }
+    }
+
+    fn save(&mut self) {
+        let (profiles, state, dir) = (self.profiles.clone(), self.state.clone(), self.config.config.config_dir.clone());
+        self.commands.push(Command::then_render(async move {
+            match save(&profiles, &dir).await {
+                Ok(path) => state.notify(format!("Saved the client profiles to {}", path.display())),
+                Err(e) => state.error(format!("Failed to save the client profiles: {}", e)),
+            }
+        }));
+    }
+}
+
+impl Component for Clients {
+    fn component_will_mount(&mut self, config: Config) -> color_eyre::Result<()> {
+        self.config = config;
+        Ok(())
+    }
+
+    fn component_did_mount(&mut self, _area: Size, updater: Updater) -> color_eyre::Result<()> {
+        self.updater = Some(updater);
+        Ok(())
+    }
+
+    fn captures_key(&self, key: KeyEvent) -> bool {
+        self.visible && self.config.keybindings.action_for(Mode::Home, key) != Some(&Action::ToggleClients)
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) -> color_eyre::Result<Option<Action>> {
+        let selected = self.rows.get(self.selected).map(|row| row.ip);
+        match (self.config.keybindings.action_for(Mode::Panel, key), selected) {
+            (Some(Action::Close), _) => return Ok(Some(Action::ToggleClients)),
+            (Some(Action::SelectNext), _) => {
+                self.selected = (self.selected + 1).min(self.rows.len().saturating_sub(1));
+            }
+            (Some(Action::SelectPrev), _) => self.selected = self.selected.saturating_sub(1),
+            (Some(Action::ToggleItem), Some(ip)) => self.profiles.toggle_capture(ip),
+            (Some(Action::CycleThrottle), Some(ip)) => self.profiles.next_throttle(ip),
+            (Some(Action::CycleRewrites), Some(ip)) => self.profiles.next_rewrites(ip),
+            (Some(Action::SaveItem), _) => self.save(),
+            _ => return Ok(None),
+        }
+        self.render_update();
+        Ok(None)
+    }
+
+    fn captures_mouse(&self, _mouse: MouseEvent) -> bool {
+        self.visible
+    }
+
+    fn handle_mouse_event(&mut self, mouse: MouseEvent) -> color_eyre::Result<Option<Action>> {
+        let outside = !self.popup_area.contains(Position::new(mouse.column, mouse.row));
+        match mouse.kind {
+            MouseEventKind::Down(_) if outside => Ok(Some(Action::ToggleClients)),
+            _ => Ok(None),
+        }
+    }
+
+    fn take_commands(&mut self) -> Vec<Command> {
+        std::mem::take(&mut self.commands)
+    }
+
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        match action {
+            Action::ConfigReloaded(reloaded) => self.component_will_mount(reloaded.config())?,
+            Action::ToggleClients => {
+                self.visible = !self.visible;
+                self.render_update();
+            }
+            Action::Message(message) => {
+                if let Some(ClientsShared(profiles)) = message.downcast_ref::<ClientsShared>() {
+                    self.profiles = profiles.clone();
+                }
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect) -> color_eyre::Result<()> {
+        if !self.visible {
+            return Ok(());
+        }
+        self.rows = self.profiles.rows();
+        self.selected = self.selected.min(self.rows.len().saturating_sub(1));
+
+        let popup_area = area.inner(Margin {
+            horizontal: area.width / 10,
+            vertical: area.height / 10,
+        });
+        self.popup_area = popup_area;
+        let theme = &self.config.theme;
+        let address_width = self.rows.iter().map(|row| row.ip.to_string().len()).max().unwrap_or_default();
+        let name_width = self.rows.iter().filter_map(|row| row.name.as_ref()).map(|name| name.chars().count()).max();
+        let name_width = name_width.unwrap_or_default();
+        let items: Vec<ListItem> = if self.rows.is_empty() {
+            vec![ListItem::new(Line::styled(
+                "No clients yet, they are listed once they send a request",
+                theme.fg(theme.muted),
+            ))]
+        } else {
+            self.rows
+                .iter()
+                .enumerate()
+                .map(|(index, row)| {
+                    let profile = &row.profile;
+                    let text = if profile.capture { theme.fg(theme.text) } else { theme.fg(theme.muted) };
+                    let setting = |name: &Option<String>| match name {
+                        Some(name) => Span::styled(format!("{:<10}", name), theme.fg(theme.secondary)),
+                        None => Span::styled(format!("{:<10}", "-"), theme.fg(theme.muted)),
+                    };
+                    let line = Line::from(vec![
+                        Span::styled(if profile.capture { "[x] " } else { "[ ] " }, theme.fg(theme.accent)),
+                        Span::styled(format!("{:<address_width$}  ", row.ip), text.add_modifier(Modifier::BOLD)),
+                        Span::styled(format!("{:<name_width$}  ", row.name.as_deref().unwrap_or_default()), text),
+                        Span::styled(format!("{:>6} requests  ", row.requests), text),
+                        Span::styled("throttle ", theme.fg(theme.muted)),
+                        setting(&profile.throttle),
+                        Span::styled("rewrites ", theme.fg(theme.muted)),
+                        setting(&profile.rewrites),
+                    ]);
+                    ListItem::new(line).style(theme.row(index == self.selected))
+                })
+                .collect()
+        };
+        let hint = |action| self.config.keybindings.hint(Mode::Panel, &action);
+        let block = Block::default()
+            .title(format!(
+                "Clients ({} capture, {} throttle, {} rewrites, {} save to config, {} close)",
+                hint(Action::ToggleItem),
+                hint(Action::CycleThrottle),
+                hint(Action::CycleRewrites),
+                hint(Action::SaveItem),
+                hint(Action::Close)
+            ))
+            .borders(Borders::ALL)
+            .border_style(theme.fg(theme.title));
+        let mut state = ListState::default().with_selected(Some(self.selected));
+
+        frame.render_widget(Clear, popup_area);
+        frame.render_stateful_widget(List::new(items).block(block), popup_area, &mut state);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_saved_profiles() {
+        let profiles = BTreeMap::from([
+            (
+                "192.168.1.23".parse().unwrap(),
+                ClientProfile {
+                    throttle: Some("3g".to_string()),
+                    ..ClientProfile::default()
+                },
+            ),
+            (
+                "192.168.1.40".parse().unwrap(),
+                ClientProfile {
+                    capture: false,
+                    ..ClientProfile::default()
+                },
+            ),
+        ]);
+        let mut document: Value =
+            json5::from_str(r#"{ clients: { labels: { "192.168.1.23": "pixel" } }, theme: "light" }"#).unwrap();
+        merge_profiles(&mut document, &profiles).unwrap();
+        assert_eq!(
+            document,
+            serde_json::json!({
+                "clients": {
+                    "labels": { "192.168.1.23": "pixel" },
+                    "profiles": {
+                        "192.168.1.23": { "capture": true, "throttle": "3g" },
+                        "192.168.1.40": { "capture": false },
+                    },
+                },
+                "theme": "light",
+            })
+        );
+    }
+}
@@ -0,0 +1,363 @@
+use std::path::{Path, PathBuf};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{prelude::*, widgets::*};
+
+use super::{
+    Component,
+    line_edit::LineEdit,
+    proxy::{LoadSession, SaveSession},
+    proxy_list::ReplayBatch,
+};
+use crate::{
+    app::Mode,
+    config::{Config, KeyBindings},
+    framework::{Action, Message},
+    state::SharedState,
+    theme::Theme,
+};
+
+/// Most palette entries shown at once.
+const PALETTE_HEIGHT: usize = 10;
+
+/// Actions offered by the palette, with their descriptions.
+const PALETTE_ACTIONS: [(Action, &str); 22] = [
+    (Action::ToggleStats, "Toggle statistics panel"),
+    (Action::ToggleCookies, "Toggle cookie jar"),
+    (Action::ToggleRules, "Show rules and their hits"),
+    (Action::ToggleComposer, "Compose a new request"),
+    (Action::ToggleSessions, "Show or save a named session"),
+    (Action::ToggleClients, "Show clients and their profiles"),
+    (Action::ToggleTimeline, "Toggle request timeline"),
+    (Action::ToggleTransfers, "Hide or show download progress"),
+    (Action::ToggleBandwidth, "Toggle bandwidth graph"),
+    (Action::ToggleHelp, "Show keybindings"),
+    (Action::ToggleCapture, "Pause or resume capturing"),
+    (Action::ToggleTree, "Toggle host tree"),
+    (Action::ToggleFollow, "Follow new requests"),
+    (Action::CycleSort, "Sort by next column"),
+    (Action::ReverseSort, "Reverse sort order"),
+    (Action::ToggleRepeats, "Fold repeated requests"),
+    (Action::FocusFilter, "Edit filter"),
+    (Action::ExportOpenApi, "Export OpenAPI drafts"),
+    (Action::ReplayFiltered, "Replay the listed requests"),
+    (Action::ClearSession, "Clear session"),
+    (Action::Suspend, "Suspend"),
+    (Action::Quit, "Quit"),
+];
+
+/// A command typed after `:`.
+#[derive(Debug, PartialEq)]
+enum LineCommand {
+    /// Save the session, to a timestamped file in the data directory by default.
+    Write(Option<PathBuf>),
+    /// Load a saved session.
+    Edit(PathBuf),
+    /// Start recording a named session.
+    Start(String),
+    /// Stop recording the named session.
+    Stop,
+    /// Replay the listed requests, this many at once or as configured.
+    Replay(Option<usize>),
+    Quit,
+}
+
+/// The command on `line`, `None` if it names none, or a usage error.
+fn parse(line: &str) -> Option<Result<LineCommand, String>> {
+    let line = line.trim();
+    let (name, argument) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let argument = argument.trim();
+    let path = (!argument.is_empty()).then(|| expand_home(argument));
+    let command = match (name, path) {
+        ("w" | "write", path) => Ok(LineCommand::Write(path)),
+        ("e" | "edit", Some(path)) => Ok(LineCommand::Edit(path)),
+        ("e" | "edit", None) => Err("Usage: :e <file>".to_string()),
+        ("start", Some(_)) => Ok(LineCommand::Start(argument.to_string())),
+        ("start", None) => Err("Usage: :start <label>".to_string()),
+        ("stop", _) => Ok(LineCommand::Stop),
+        ("replay", None) => Ok(LineCommand::Replay(None)),
+        ("replay", Some(_)) => match argument.parse() {
+            Ok(concurrency) if concurrency > 0 => Ok(LineCommand::Replay(Some(concurrency))),
+            _ => Err("Usage: :replay [concurrency]".to_string()),
+        },
+        ("q" | "quit", _) => Ok(LineCommand::Quit),
+        _ => return None,
+    };
+    Some(command)
+}
+
+/// `path` with a leading `~/` in the home directory.
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => Path::new(&home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// How well `query` matches `candidate` as a case-insensitive subsequence,
+/// `None` if it doesn't. Consecutive characters and word starts score higher.
+fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let candidate: Vec<char> = candidate.chars().collect();
+    let mut score = 0;
+    let mut previous: Option<usize> = None;
+    let mut position = 0;
+    for q in query.chars().filter(|c| !c.is_whitespace()) {
+        let q = q.to_ascii_lowercase();
+        let found = (position..candidate.len()).find(|&i| candidate[i].to_ascii_lowercase() == q)?;
+        score += 1;
+        if previous.is_some_and(|p| p + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !candidate[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+struct PaletteEntry {
+    label: String,
+    action: Action,
+    /// Key bound to the action, as a hint.
+    key: Option<String>,
+}
+
+/// Vim-style command line at the bottom of the screen, opened with `:`. Above
+/// it, a palette lists the actions matching what has been typed.
+#[derive(Default)]
+pub struct CommandLine {
+    line: LineEdit,
+    open: bool,
+    /// Mode to return to when the command line closes.
+    previous: Mode,
+    keybindings: KeyBindings,
+    /// Where `:w` without a file name saves to.
+    sessions_dir: PathBuf,
+    entries: Vec<PaletteEntry>,
+    /// Index into the current matches.
+    selected: usize,
+    state: SharedState,
+    theme: Theme,
+}
+
+impl CommandLine {
+    pub fn new(state: SharedState) -> Self {
+        Self {
+            state,
+            ..Self::default()
+        }
+    }
+
+    /// Palette entries matching the line, best first.
+    fn matches(&self) -> Vec<&PaletteEntry> {
+        let query = self.line.text();
+        let mut matches: Vec<(i32, usize, &PaletteEntry)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| fuzzy_score(query, &entry.label).map(|score| (score, index, entry)))
+            .collect();
+        matches.sort_by_key(|(score, index, _)| (-score, *index));
+        matches.into_iter().map(|(_, _, entry)| entry).collect()
+    }
+
+    fn close(&mut self) {
+        self.open = false;
+        self.line.clear();
+        self.selected = 0;
+        self.state.set_mode(self.previous);
+    }
+
+    fn run(&mut self) -> Option<Action> {
+        let command = parse(self.line.text());
+        let selected = self.matches().get(self.selected).map(|entry| entry.action.clone());
+        let typed = self.line.text().trim().to_string();
+        self.close();
+        match (command, selected) {
+            (Some(Ok(LineCommand::Write(path))), _) => {
+                let path = path.unwrap_or_else(|| {
+                    let name = chrono::Local::now().format("%Y%m%d-%H%M%S");
+                    self.sessions_dir.join(format!("{}.json", name))
+                });
+                Some(Action::Message(Message::new(SaveSession { path, session: None })))
+            }
+            (Some(Ok(LineCommand::Edit(path))), _) => Some(Action::Message(Message::new(LoadSession(path)))),
+            (Some(Ok(LineCommand::Start(label))), _) => {
+                match self.state.start_session(&label) {
+                    Ok(()) => self.state.notify(format!("Recording session {}", label)),
+                    Err(e) => self.state.error(e),
+                }
+                Some(Action::Render)
+            }
+            (Some(Ok(LineCommand::Stop)), _) => {
+                match self.state.stop_session() {
+                    Some(label) => self.state.notify(format!("Stopped session {}", label)),
+                    None => self.state.error("No session is being recorded"),
+                }
+                Some(Action::Render)
+            }
+            (Some(Ok(LineCommand::Replay(concurrency))), _) => {
+                Some(Action::Message(Message::new(ReplayBatch { concurrency })))
+            }
+            (Some(Ok(LineCommand::Quit)), _) => Some(Action::Quit),
+            (Some(Err(e)), _) => {
+                self.state.error(e);
+                Some(Action::Render)
+            }
+            (None, Some(action)) => Some(action),
+            (None, None) => {
+                self.state.error(format!("Unknown command: {}", typed));
+                Some(Action::Render)
+            }
+        }
+    }
+}
+
+impl Component for CommandLine {
+    fn component_will_mount(&mut self, config: Config) -> color_eyre::Result<()> {
+        self.sessions_dir = config.config.data_dir.join("sessions");
+        self.theme = config.theme;
+        let key_for = |action: &Action| {
+            config
+                .keybindings
+                .key_for(Mode::Home, action)
+                .or_else(|| config.keybindings.key_for(Mode::List, action))
+        };
+        self.entries = PALETTE_ACTIONS
+            .iter()
+            .map(|(action, label)| PaletteEntry {
+                label: label.to_string(),
+                action: action.clone(),
+                key: key_for(action),
+            })
+            .collect();
+        self.entries.extend(config.filter.presets.iter().map(|(name, filter)| PaletteEntry {
+            label: format!("Filter: {}", name),
+            action: Action::SetFilter(filter.clone()),
+            key: None,
+        }));
+        self.keybindings = config.keybindings;
+        Ok(())
+    }
+
+    fn captures_key(&self, _key: KeyEvent) -> bool {
+        self.open
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) -> color_eyre::Result<Option<Action>> {
+        // backspace on an empty line leaves, like in vim
+        if self.line.text().is_empty() && key.code == KeyCode::Backspace {
+            self.close();
+            return Ok(Some(Action::Render));
+        }
+        match self.keybindings.action_for(Mode::Command, key) {
+            Some(Action::RunCommand) => return Ok(self.run()),
+            Some(Action::Close) => self.close(),
+            Some(Action::SelectNext) => {
+                self.selected = (self.selected + 1).min(self.matches().len().saturating_sub(1));
+            }
+            Some(Action::SelectPrev) => self.selected = self.selected.saturating_sub(1),
+            Some(action) if self.line.apply(action) => self.selected = 0,
+            Some(_) => {}
+            None if let KeyCode::Char(c) = key.code
+                && (key.modifiers - KeyModifiers::SHIFT).is_empty() =>
+            {
+                self.line.insert(c);
+                self.selected = 0;
+            }
+            None => {}
+        }
+        Ok(Some(Action::Render))
+    }
+
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        // settings are only kept, so take them again
+        if let Action::ConfigReloaded(reloaded) = &action {
+            self.component_will_mount(reloaded.config())?;
+            return Ok(None);
+        }
+        if action == Action::OpenCommand && !self.open {
+            self.open = true;
+            self.previous = self.state.subscribe().borrow().mode;
+            self.state.set_mode(Mode::Command);
+            return Ok(Some(Action::Render));
+        }
+        Ok(None)
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect) -> color_eyre::Result<()> {
+        if !self.open || area.height == 0 {
+            return Ok(());
+        }
+        // drawn over the status bar, like vim's command line
+        let row = Rect::new(area.x, area.bottom() - 1, area.width, 1);
+        frame.render_widget(Clear, row);
+        frame.render_widget(Paragraph::new(format!(":{}", self.line.text())), row);
+        frame.set_cursor_position((row.x + 1 + self.line.cursor_column() as u16, row.y));
+
+        let matches = self.matches();
+        if matches.is_empty() || parse(self.line.text()).is_some() {
+            return Ok(());
+        }
+        // keep the selection in view
+        let first = self.selected.saturating_sub(PALETTE_HEIGHT - 1);
+        let shown = matches.len().min(PALETTE_HEIGHT);
+        let height = (shown as u16 + 2).min(row.y.saturating_sub(area.y));
+        let width = area.width.min(60);
+        let popup = Rect::new(area.x, row.y - height, width, height);
+        let items: Vec<ListItem> = matches
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(shown)
+            .map(|(index, entry)| {
+                let key = entry.key.as_deref().unwrap_or_default();
+                let label_width = (width as usize).saturating_sub(key.len() + 4);
+                let line = Line::from(vec![
+                    Span::raw(format!(" {:<label_width$}", entry.label)),
+                    Span::styled(key.to_string(), self.theme.fg(self.theme.accent)),
+                ]);
+                ListItem::new(line).style(self.theme.row(index == self.selected))
+            })
+            .collect();
+        let block = Block::default()
+            .title("Actions (tab to select, enter to run)")
+            .borders(Borders::ALL)
+            .border_style(self.theme.fg(self.theme.title));
+        frame.render_widget(Clear, popup);
+        frame.render_widget(List::new(items).block(block), popup);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(parse("w"), Some(Ok(LineCommand::Write(None))));
+        assert_eq!(parse(" w  login.json "), Some(Ok(LineCommand::Write(Some(PathBuf::from("login.json"))))));
+        assert_eq!(parse("e /tmp/a b.json"), Some(Ok(LineCommand::Edit(PathBuf::from("/tmp/a b.json")))));
+        assert_eq!(parse("e"), Some(Err("Usage: :e <file>".to_string())));
+        assert_eq!(parse("start login flow 3"), Some(Ok(LineCommand::Start("login flow 3".to_string()))));
+        assert_eq!(parse("stop"), Some(Ok(LineCommand::Stop)));
+        assert_eq!(parse("replay"), Some(Ok(LineCommand::Replay(None))));
+        assert_eq!(parse("replay 8"), Some(Ok(LineCommand::Replay(Some(8)))));
+        assert_eq!(parse("replay 0"), Some(Err("Usage: :replay [concurrency]".to_string())));
+        assert_eq!(parse("q"), Some(Ok(LineCommand::Quit)));
+        assert_eq!(parse("stats"), None);
+    }
+
+    #[test]
+    fn test_fuzzy_score_prefers_word_starts() {
+        assert_eq!(fuzzy_score("xyz", "Toggle statistics panel"), None);
+        assert!(fuzzy_score("", "Quit").is_some());
+        assert!(fuzzy_score("tsp", "Toggle statistics panel") > fuzzy_score("tsp", "Toggle host tree panel"));
+        assert!(fuzzy_score("stat", "Toggle statistics panel") > fuzzy_score("stat", "Suspend the app"));
+    }
+}
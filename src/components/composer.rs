@@ -0,0 +1,453 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
+use http_body_util::Full;
+use hyper::{
+    Request,
+    body::Bytes,
+    header::{HOST, HeaderName, HeaderValue},
+};
+use ratatui::{prelude::*, widgets::*};
+
+use super::{Component, environment::Environment, line_edit::LineEdit};
+use crate::{
+    app::Mode,
+    client::ProxyClient,
+    config::Config,
+    framework::{Action, Command, Updater},
+    state::SharedState,
+    tokens::Tokens,
+};
+
+const METHODS: [&str; 7] = ["GET", "POST", "PUT", "PATCH", "DELETE", "HEAD", "OPTIONS"];
+
+/// How long a composed request may take before it is given up on.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The parts of the request, in the order `tab` moves through them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Field {
+    #[default]
+    Method,
+    Url,
+    Headers,
+    Body,
+    Variables,
+}
+
+impl Field {
+    fn next(self) -> Self {
+        match self {
+            Field::Method => Field::Url,
+            Field::Url => Field::Headers,
+            Field::Headers => Field::Body,
+            Field::Body => Field::Variables,
+            Field::Variables => Field::Method,
+        }
+    }
+
+    fn prev(self) -> Self {
+        match self {
+            Field::Method => Field::Variables,
+            Field::Url => Field::Method,
+            Field::Headers => Field::Url,
+            Field::Body => Field::Headers,
+            Field::Variables => Field::Body,
+        }
+    }
+}
+
+/// Several lines of text with a cursor, for the headers, the body and the variables.
+#[derive(Debug)]
+struct TextArea {
+    lines: Vec<LineEdit>,
+    row: usize,
+}
+
+impl Default for TextArea {
+    fn default() -> Self {
+        Self {
+            lines: vec![LineEdit::default()],
+            row: 0,
+        }
+    }
+}
+
+impl TextArea {
+    fn text(&self) -> String {
+        self.lines.iter().map(LineEdit::text).collect::<Vec<_>>().join("\n")
+    }
+
+    fn line(&mut self) -> &mut LineEdit {
+        &mut self.lines[self.row]
+    }
+
+    fn newline(&mut self) {
+        let rest = self.line().split_off();
+        self.row += 1;
+        self.lines.insert(self.row, rest);
+    }
+
+    /// `backspace` at the start of a line joins it to the one above.
+    fn join_up(&mut self) -> bool {
+        if self.row == 0 || !self.lines[self.row].at_start() {
+            return false;
+        }
+        let line = self.lines.remove(self.row);
+        self.row -= 1;
+        self.line().join(&line);
+        true
+    }
+
+    /// Move to another line, keeping the cursor's column where possible.
+    fn move_to(&mut self, row: usize) {
+        let column = self.lines[self.row].cursor_column();
+        self.row = row.min(self.lines.len() - 1);
+        self.line().click(column);
+    }
+}
+
+/// Overlay to write a request from scratch and send it through the proxy,
+/// which captures it like any other. `{{name}}` in the request is replaced
+/// by the variable set in the side panel, for the rest of the session.
+#[derive(Default)]
+pub struct Composer {
+    config: Config,
+    state: SharedState,
+    visible: bool,
+    field: Field,
+    method: usize,
+    url: LineEdit,
+    headers: TextArea,
+    body: TextArea,
+    /// `name = value` lines of the [`Environment`].
+    variables: TextArea,
+    /// Bearer tokens for the hosts of `config.tokens`, fetched on first use.
+    tokens: Arc<Tokens>,
+    updater: Option<Updater>,
+    commands: Vec<Command>,
+    /// Where the overlay was last drawn, for click-outside-to-close.
+    popup_area: Rect,
+}
+
+impl Composer {
+    pub fn new(state: SharedState) -> Self {
+        Self {
+            state,
+            ..Self::default()
+        }
+    }
+
+    fn render_update(&self) {
+        if let Some(updater) = &self.updater {
+            updater.update();
+        }
+    }
+
+    fn send(&mut self) -> Option<Action> {
+        let method = METHODS[self.method];
+        let expanded = Environment::parse(&self.variables.text()).and_then(|env| {
+            Ok((env.expand(self.url.text())?, env.expand(&self.headers.text())?, env.expand(&self.body.text())?))
+        });
+        let request = match expanded.and_then(|(url, headers, body)| build_request(method, &url, &headers, &body)) {
+            Ok(request) => request,
+            Err(e) => {
+                self.state.error(e);
+                return None;
+            }
+        };
+        let Some(&listen) = self.state.subscribe().borrow().listen.first() else {
+            self.state.error("The proxy isn't listening yet");
+            return None;
+        };
+        let (state, tokens) = (self.state.clone(), self.tokens.clone());
+        let client = ProxyClient::new(listen, &self.config.proxy.auth);
+        self.commands.push(Command::then_render(async move {
+            let target = request.uri().to_string();
+            let started = Instant::now();
+            match tokio::time::timeout(TIMEOUT, tokens.send(&client, request)).await {
+                Ok(Ok(response)) => state.notify(format!(
+                    "{} {} → {} in {} ms",
+                    method,
+                    target,
+                    response.status(),
+                    started.elapsed().as_millis()
+                )),
+                Ok(Err(e)) => state.error(format!("Failed to send {} {}: {}", method, target, e)),
+                Err(_) => state.error(format!("{} {} timed out", method, target)),
+            }
+        }));
+        // close to show the exchange arriving in the list; the draft is kept
+        Some(Action::ToggleComposer)
+    }
+
+    /// Typing and cursor movement in the focused text field.
+    fn edit(&mut self, key: KeyEvent) {
+        let action = self.config.keybindings.action_for(Mode::Filter, key).cloned();
+        let line = match self.field {
+            Field::Method => return,
+            Field::Url => &mut self.url,
+            Field::Headers | Field::Body | Field::Variables => {
+                let area = match self.field {
+                    Field::Headers => &mut self.headers,
+                    Field::Body => &mut self.body,
+                    _ => &mut self.variables,
+                };
+                match key.code {
+                    KeyCode::Enter => return area.newline(),
+                    KeyCode::Up => return area.move_to(area.row.saturating_sub(1)),
+                    KeyCode::Down => return area.move_to(area.row + 1),
+                    KeyCode::Backspace if area.join_up() => return,
+                    _ => area.line(),
+                }
+            }
+        };
+        match action {
+            Some(action) => {
+                line.apply(&action);
+            }
+            None if let KeyCode::Char(c) = key.code
+                && (key.modifiers - KeyModifiers::SHIFT).is_empty() =>
+            {
+                line.insert(c)
+            }
+            None => {}
+        }
+    }
+
+    fn text_area<'a>(&self, area: &'a TextArea, field: Field, title: &'a str, height: u16) -> Paragraph<'a> {
+        let theme = &self.config.theme;
+        let scroll = (area.row + 1).saturating_sub(height.saturating_sub(2) as usize) as u16;
+        let lines: Vec<Line> = area.lines.iter().map(|line| Line::raw(line.text())).collect();
+        Paragraph::new(lines)
+            .style(theme.fg(theme.text))
+            .scroll((scroll, 0))
+            .block(
+                Block::default()
+                    .title(title)
+                    .borders(Borders::ALL)
+                    .border_style(theme.pane(self.field == field)),
+            )
+    }
+}
+
+impl Component for Composer {
+    fn component_will_mount(&mut self, config: Config) -> color_eyre::Result<()> {
+        self.tokens = Arc::new(Tokens::new(config.tokens.clone()));
+        self.config = config;
+        Ok(())
+    }
+
+    fn component_did_mount(&mut self, _area: Size, updater: Updater) -> color_eyre::Result<()> {
+        self.updater = Some(updater);
+        Ok(())
+    }
+
+    fn captures_key(&self, key: KeyEvent) -> bool {
+        self.visible && self.config.keybindings.action_for(Mode::Home, key) != Some(&Action::ToggleComposer)
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) -> color_eyre::Result<Option<Action>> {
+        // bindings that don't apply to the focused field leave the key to it
+        match (self.config.keybindings.action_for(Mode::Composer, key), self.field) {
+            (Some(Action::Close), _) => return Ok(Some(Action::ToggleComposer)),
+            (Some(Action::SendRequest), _) | (Some(Action::RunCommand), Field::Method | Field::Url) => {
+                return Ok(self.send());
+            }
+            (Some(Action::FocusNext), _) => self.field = self.field.next(),
+            (Some(Action::FocusPrev), _) => self.field = self.field.prev(),
+            (Some(Action::CursorLeft), Field::Method) => self.method = (self.method + METHODS.len() - 1) % METHODS.len(),
+            (Some(Action::CursorRight), Field::Method) => self.method = (self.method + 1) % METHODS.len(),
+            _ => self.edit(key),
+        }
+        self.render_update();
+        Ok(None)
+    }
+
+    fn captures_mouse(&self, _mouse: MouseEvent) -> bool {
+        self.visible
+    }
+
+    fn handle_mouse_event(&mut self, mouse: MouseEvent) -> color_eyre::Result<Option<Action>> {
+        let outside = !self.popup_area.contains(Position::new(mouse.column, mouse.row));
+        if matches!(mouse.kind, MouseEventKind::Down(_)) && outside {
+            return Ok(Some(Action::ToggleComposer));
+        }
+        Ok(None)
+    }
+
+    fn take_commands(&mut self) -> Vec<Command> {
+        std::mem::take(&mut self.commands)
+    }
+
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        match action {
+            Action::ConfigReloaded(reloaded) => self.component_will_mount(reloaded.config())?,
+            Action::ToggleComposer => {
+                self.visible = !self.visible;
+                self.render_update();
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect) -> color_eyre::Result<()> {
+        if !self.visible {
+            return Ok(());
+        }
+        let popup_area = area.inner(Margin {
+            horizontal: area.width / 10,
+            vertical: area.height / 10,
+        });
+        self.popup_area = popup_area;
+        let theme = &self.config.theme;
+        let hint = |action| self.config.keybindings.hint(Mode::Composer, &action);
+        let block = Block::default()
+            .title(format!(
+                "New request ({} next field, {} send, {} close)",
+                hint(Action::FocusNext),
+                hint(Action::SendRequest),
+                hint(Action::Close)
+            ))
+            .borders(Borders::ALL)
+            .border_style(theme.fg(theme.title));
+        let inner = block.inner(popup_area);
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(block, popup_area);
+
+        let [request_area, variables_area] =
+            Layout::horizontal([Constraint::Percentage(70), Constraint::Percentage(30)]).areas(inner);
+        let headers_height = (self.headers.lines.len() as u16 + 2).clamp(3, inner.height / 3);
+        let [top, headers_area, body_area] =
+            Layout::vertical([Constraint::Length(3), Constraint::Length(headers_height), Constraint::Min(3)])
+                .areas(request_area);
+        let [method_area, url_area] = Layout::horizontal([Constraint::Length(11), Constraint::Min(0)]).areas(top);
+
+        let method = METHODS[self.method];
+        let pane = |field: Field, title: &'static str| {
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(theme.pane(self.field == field))
+        };
+        frame.render_widget(
+            Paragraph::new(Line::styled(
+                format!("{:^7}", method),
+                theme.fg(theme.method(method)).add_modifier(Modifier::BOLD),
+            ))
+            .block(pane(Field::Method, "Method")),
+            method_area,
+        );
+        frame.render_widget(
+            Paragraph::new(self.url.text())
+                .style(theme.fg(theme.text))
+                .block(pane(Field::Url, "URL")),
+            url_area,
+        );
+        frame.render_widget(
+            self.text_area(&self.headers, Field::Headers, "Headers (Name: value)", headers_area.height),
+            headers_area,
+        );
+        frame.render_widget(self.text_area(&self.body, Field::Body, "Body", body_area.height), body_area);
+        frame.render_widget(
+            self.text_area(&self.variables, Field::Variables, "Variables (name = value)", variables_area.height),
+            variables_area,
+        );
+
+        let cursor = match self.field {
+            Field::Method => None,
+            Field::Url => Some((url_area, &self.url, 0)),
+            Field::Headers => Some((headers_area, &self.headers.lines[self.headers.row], self.headers.row)),
+            Field::Body => Some((body_area, &self.body.lines[self.body.row], self.body.row)),
+            Field::Variables => Some((variables_area, &self.variables.lines[self.variables.row], self.variables.row)),
+        };
+        if let Some((area, line, row)) = cursor {
+            let row = (row as u16).min(area.height.saturating_sub(3));
+            frame.set_cursor_position((area.x + 1 + line.cursor_column() as u16, area.y + 1 + row));
+        }
+        Ok(())
+    }
+}
+
+/// The request described by the composer's fields. A URL without a scheme
+/// is taken as `http`.
+fn build_request(method: &str, url: &str, headers: &str, body: &str) -> Result<Request<Full<Bytes>>, String> {
+    let url = url.trim();
+    if url.is_empty() {
+        return Err("Enter a URL to send the request to".to_string());
+    }
+    let url = if url.contains("://") { url.to_string() } else { format!("http://{}", url) };
+    let parsed = url::Url::parse(&url).map_err(|e| format!("Invalid URL {}: {}", url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("Unsupported scheme in {}", url));
+    }
+
+    let mut request = Request::builder().method(method).uri(parsed.as_str());
+    let mut has_host = false;
+    for (number, line) in headers.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| format!("Header line {} is not `Name: value`", number + 1))?;
+        let name = HeaderName::try_from(name.trim()).map_err(|_| format!("Invalid header name `{}`", name.trim()))?;
+        let value = HeaderValue::try_from(value.trim()).map_err(|_| format!("Invalid value for {}", name))?;
+        has_host |= name == HOST;
+        request = request.header(name, value);
+    }
+    // hyper's connection-level client leaves the Host header to the caller
+    if !has_host && let Some(host) = parsed.host_str() {
+        let host = match parsed.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        };
+        request = request.header(HOST, host);
+    }
+    request
+        .body(Full::new(Bytes::from(body.to_string())))
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_build_request() {
+        let request = build_request(
+            "POST",
+            "localhost:3000/users?page=2",
+            "Content-Type: application/json\n\nX-Trace:  abc ",
+            "{\"name\": \"yap\"}",
+        )
+        .unwrap();
+        assert_eq!(request.method(), "POST");
+        assert_eq!(request.uri().to_string(), "http://localhost:3000/users?page=2");
+        assert_eq!(request.headers()["content-type"], "application/json");
+        assert_eq!(request.headers()["x-trace"], "abc");
+        assert_eq!(request.headers()["host"], "localhost:3000");
+
+        let request = build_request("GET", "https://example.com", "Host: api.test", "").unwrap();
+        assert_eq!(request.headers()["host"], "api.test");
+
+        assert!(build_request("GET", "", "", "").is_err());
+        assert!(build_request("GET", "ftp://example.com", "", "").is_err());
+        assert!(build_request("GET", "example.com", "Content-Type application/json", "").is_err());
+    }
+
+    #[test]
+    fn test_text_area_lines() {
+        let mut area = TextArea::default();
+        "ab".chars().for_each(|c| area.line().insert(c));
+        area.line().apply(&Action::CursorLeft);
+        area.newline();
+        assert_eq!(area.text(), "a\nb");
+        assert!(area.join_up());
+        assert_eq!(area.text(), "ab");
+        assert!(!area.join_up());
+    }
+}
@@ -0,0 +1,288 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use crossterm::event::{KeyEvent, MouseEvent, MouseEventKind};
+use ratatui::{prelude::*, widgets::*};
+
+use super::{
+    Component,
+    host_tree::host_of,
+    proxy::{HttpLog, LogReceiver},
+};
+use crate::{
+    app::Mode,
+    config::Config,
+    framework::{Action, Updater},
+};
+
+/// What is known about one cookie of one host.
+#[derive(Debug, Default, PartialEq)]
+struct CookieInfo {
+    value: String,
+    /// `Expires` or `Max-Age`, as sent.
+    expires: Option<String>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<String>,
+    /// Set by the server at least once.
+    set: bool,
+    /// Sent by the client at least once.
+    sent: bool,
+    /// How often the value differed from the one seen before.
+    changes: usize,
+}
+
+impl CookieInfo {
+    fn observe(&mut self, value: &str) {
+        if (self.set || self.sent) && self.value != value {
+            self.changes += 1;
+        }
+        self.value = value.to_string();
+    }
+}
+
+type CookieJar = BTreeMap<String, BTreeMap<String, CookieInfo>>;
+
+/// Cookies sent and set per host, in capture order.
+fn aggregate(logs: &[Arc<HttpLog>]) -> CookieJar {
+    let mut hosts = CookieJar::new();
+    for log in logs {
+        let cookies = hosts.entry(host_of(&log.uri)).or_default();
+        for pair in log.cookie.iter().flat_map(|header| header.split(';')) {
+            if let Some((name, value)) = pair.trim().split_once('=') {
+                let info = cookies.entry(name.to_string()).or_default();
+                info.observe(value);
+                info.sent = true;
+            }
+        }
+        for header in &log.set_cookies {
+            let mut parts = header.split(';').map(str::trim);
+            let Some((name, value)) = parts.next().and_then(|pair| pair.split_once('=')) else {
+                continue;
+            };
+            let info = cookies.entry(name.to_string()).or_default();
+            info.observe(value);
+            info.set = true;
+            for attribute in parts {
+                let (key, val) = attribute.split_once('=').unwrap_or((attribute, ""));
+                match key.to_ascii_lowercase().as_str() {
+                    "expires" | "max-age" => info.expires = Some(format!("{}={}", key, val)),
+                    "secure" => info.secure = true,
+                    "httponly" => info.http_only = true,
+                    "samesite" => info.same_site = Some(val.to_string()),
+                    _ => {}
+                }
+            }
+        }
+    }
+    hosts.retain(|_, cookies| !cookies.is_empty());
+    hosts
+}
+
+/// Overlay listing the cookies of every host seen, highlighting the ones
+/// whose value changed between requests.
+#[derive(Default)]
+pub struct Cookies {
+    logs: Option<LogReceiver>,
+    config: Config,
+    visible: bool,
+    scroll: u16,
+    updater: Option<Updater>,
+    /// Where the overlay was last drawn, for click-outside-to-close.
+    popup_area: Rect,
+}
+
+impl Cookies {
+    pub fn new(logs: LogReceiver) -> Self {
+        Self {
+            logs: Some(logs),
+            ..Self::default()
+        }
+    }
+
+    fn lines(&self) -> Vec<Line<'static>> {
+        let Some(logs) = &self.logs else {
+            return Vec::new();
+        };
+        let theme = &self.config.theme;
+        let hosts = aggregate(&logs.borrow());
+        if hosts.is_empty() {
+            return vec![Line::styled("No cookies seen yet", theme.fg(theme.muted))];
+        }
+        let mut lines = Vec::new();
+        for (host, cookies) in hosts {
+            lines.push(Line::styled(host, theme.fg(theme.title).add_modifier(Modifier::BOLD)));
+            for (name, info) in cookies {
+                let mut flags = Vec::new();
+                if info.secure {
+                    flags.push("Secure".to_string());
+                }
+                if info.http_only {
+                    flags.push("HttpOnly".to_string());
+                }
+                if let Some(same_site) = &info.same_site {
+                    flags.push(format!("SameSite={}", same_site));
+                }
+                if let Some(expires) = &info.expires {
+                    flags.push(expires.clone());
+                }
+                let origin = match (info.set, info.sent) {
+                    (true, true) => "set+sent",
+                    (true, false) => "set",
+                    _ => "sent",
+                };
+                let value_style = if info.changes > 0 {
+                    theme.fg(theme.badge).bg(theme.warning)
+                } else {
+                    theme.fg(theme.success)
+                };
+                let mut spans = vec![
+                    Span::styled(format!("  {}", name), theme.fg(theme.accent)),
+                    Span::raw(" = "),
+                    Span::styled(info.value, value_style),
+                    Span::styled(format!("  [{}] ", origin), theme.fg(theme.border)),
+                    Span::raw(flags.join(" ")),
+                ];
+                if info.changes > 0 {
+                    spans.push(Span::styled(
+                        format!("  changed {}x", info.changes),
+                        theme.fg(theme.warning),
+                    ));
+                }
+                lines.push(Line::from(spans));
+            }
+            lines.push(Line::from(""));
+        }
+        lines
+    }
+
+    fn render_update(&self) {
+        if let Some(updater) = &self.updater {
+            updater.update();
+        }
+    }
+}
+
+impl Component for Cookies {
+    fn component_will_mount(&mut self, config: Config) -> color_eyre::Result<()> {
+        self.config = config;
+        Ok(())
+    }
+
+    fn component_did_mount(&mut self, _area: Size, updater: Updater) -> color_eyre::Result<()> {
+        self.updater = Some(updater);
+        Ok(())
+    }
+
+    fn captures_key(&self, key: KeyEvent) -> bool {
+        // modal like the help overlay, except for the key that closes it
+        self.visible && self.config.keybindings.action_for(Mode::Home, key) != Some(&Action::ToggleCookies)
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) -> color_eyre::Result<Option<Action>> {
+        // the list moves a line at a time, there's nothing to select
+        match self.config.keybindings.action_for(Mode::Panel, key) {
+            Some(Action::Close) => return Ok(Some(Action::ToggleCookies)),
+            Some(Action::SelectNext) => self.scroll = self.scroll.saturating_add(1),
+            Some(Action::SelectPrev) => self.scroll = self.scroll.saturating_sub(1),
+            _ => return Ok(None),
+        }
+        self.render_update();
+        Ok(None)
+    }
+
+    fn captures_mouse(&self, _mouse: MouseEvent) -> bool {
+        self.visible
+    }
+
+    fn handle_mouse_event(&mut self, mouse: MouseEvent) -> color_eyre::Result<Option<Action>> {
+        let outside = !self.popup_area.contains(Position::new(mouse.column, mouse.row));
+        match mouse.kind {
+            MouseEventKind::Down(_) if outside => return Ok(Some(Action::ToggleCookies)),
+            MouseEventKind::ScrollDown => self.scroll = self.scroll.saturating_add(1),
+            MouseEventKind::ScrollUp => self.scroll = self.scroll.saturating_sub(1),
+            _ => return Ok(None),
+        }
+        self.render_update();
+        Ok(None)
+    }
+
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        // settings are only kept, so take them again
+        if let Action::ConfigReloaded(reloaded) = &action {
+            self.component_will_mount(reloaded.config())?;
+            return Ok(None);
+        }
+        if action == Action::ToggleCookies {
+            self.visible = !self.visible;
+            self.scroll = 0;
+            self.render_update();
+        }
+        Ok(None)
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect) -> color_eyre::Result<()> {
+        if !self.visible {
+            return Ok(());
+        }
+        let popup_area = area.inner(Margin {
+            horizontal: area.width / 10,
+            vertical: area.height / 10,
+        });
+        self.popup_area = popup_area;
+        let block = Block::default()
+            .title(format!(
+                "Cookies by host ({} to close)",
+                self.config.keybindings.hint(Mode::Panel, &Action::Close)
+            ))
+            .borders(Borders::ALL)
+            .border_style(self.config.theme.fg(self.config.theme.title));
+
+        let lines = self.lines();
+        // no further than the last line reaching the bottom, which moves as the terminal is resized
+        let last = lines.len().saturating_sub(popup_area.height.saturating_sub(2) as usize);
+        self.scroll = self.scroll.min(last.min(u16::MAX as usize) as u16);
+
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(Paragraph::new(lines).block(block).scroll((self.scroll, 0)), popup_area);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_aggregate_tracks_flags_and_changes() {
+        let log = |cookie: Option<&str>, set_cookies: &[&str]| {
+            Arc::new(HttpLog {
+                uri: "http://example.com/".to_string(),
+                cookie: cookie.map(str::to_string),
+                set_cookies: set_cookies.iter().map(|s| s.to_string()).collect(),
+                ..Default::default()
+            })
+        };
+        let logs = [
+            log(None, &["sid=1; Path=/; Secure; HttpOnly; SameSite=Lax", "theme=dark"]),
+            log(Some("sid=1; theme=dark"), &["sid=2; Max-Age=60"]),
+        ];
+        let jar = aggregate(&logs);
+        let cookies = &jar["example.com"];
+        assert_eq!(
+            cookies["sid"],
+            CookieInfo {
+                value: "2".to_string(),
+                expires: Some("Max-Age=60".to_string()),
+                secure: true,
+                http_only: true,
+                same_site: Some("Lax".to_string()),
+                set: true,
+                sent: true,
+                changes: 1,
+            }
+        );
+        assert_eq!(cookies["theme"].changes, 0);
+    }
+}
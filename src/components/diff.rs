@@ -0,0 +1,150 @@
+use ratatui::prelude::*;
+
+use super::{body_format::format_body, proxy::CapturedExchange};
+use crate::theme::Theme;
+
+/// Above this many cells the LCS table gets too large, and we fall back to
+/// showing everything as removed then added.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiffLine {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+/// Line-based diff using the longest common subsequence of `old` and `new`.
+pub fn diff_lines(old: &[String], new: &[String]) -> Vec<DiffLine> {
+    if old.len().saturating_mul(new.len()) > MAX_DIFF_CELLS {
+        return old
+            .iter()
+            .cloned()
+            .map(DiffLine::Removed)
+            .chain(new.iter().cloned().map(DiffLine::Added))
+            .collect();
+    }
+
+    // lcs[i][j] is the LCS length of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut result = Vec::new();
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            result.push(DiffLine::Same(old[i].clone()));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            result.push(DiffLine::Removed(old[i].clone()));
+            i += 1;
+        } else {
+            result.push(DiffLine::Added(new[j].clone()));
+            j += 1;
+        }
+    }
+    result.extend(old[i..].iter().cloned().map(DiffLine::Removed));
+    result.extend(new[j..].iter().cloned().map(DiffLine::Added));
+    result
+}
+
+fn header_lines(exchange: &CapturedExchange) -> Vec<String> {
+    let mut lines: Vec<String> = exchange
+        .headers
+        .iter()
+        .map(|(name, value)| format!("{}: {}", name, value))
+        .collect();
+    lines.sort();
+    lines
+}
+
+fn body_lines(exchange: &CapturedExchange) -> Vec<String> {
+    // diff the pretty-printed form so structured bodies compare line by line
+    format_body(&Theme::default(), exchange.content_type(), &exchange.body)
+        .lines
+        .iter()
+        .map(|line| line.to_string())
+        .collect()
+}
+
+fn push_diff(theme: &Theme, lines: &mut Vec<Line<'static>>, diff: Vec<DiffLine>) {
+    for line in diff {
+        lines.push(match line {
+            DiffLine::Same(s) => Line::from(format!("  {}", s)),
+            DiffLine::Removed(s) => Line::styled(format!("- {}", s), theme.fg(theme.error)),
+            DiffLine::Added(s) => Line::styled(format!("+ {}", s), theme.fg(theme.success)),
+        });
+    }
+}
+
+/// Unified diff of the status, headers and body of two exchanges.
+pub fn exchange_diff(
+    theme: &Theme,
+    baseline_uri: &str,
+    baseline: &CapturedExchange,
+    other_uri: &str,
+    other: &CapturedExchange,
+) -> Text<'static> {
+    let section = theme.fg(theme.title).add_modifier(Modifier::BOLD);
+    let mut lines = vec![
+        Line::styled(format!("--- {}", baseline_uri), theme.fg(theme.error)),
+        Line::styled(format!("+++ {}", other_uri), theme.fg(theme.success)),
+        Line::from(""),
+        Line::styled("Status", section),
+    ];
+    push_diff(
+        theme,
+        &mut lines,
+        diff_lines(std::slice::from_ref(&baseline.status), std::slice::from_ref(&other.status)),
+    );
+
+    lines.push(Line::from(""));
+    lines.push(Line::styled("Headers", section));
+    push_diff(theme, &mut lines, diff_lines(&header_lines(baseline), &header_lines(other)));
+
+    lines.push(Line::from(""));
+    lines.push(Line::styled("Body", section));
+    push_diff(theme, &mut lines, diff_lines(&body_lines(baseline), &body_lines(other)));
+
+    Text::from(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn lines(s: &str) -> Vec<String> {
+        s.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let diff = diff_lines(&lines("a\nb\nc"), &lines("a\nc\nd"));
+        assert_eq!(
+            diff,
+            vec![
+                DiffLine::Same("a".to_string()),
+                DiffLine::Removed("b".to_string()),
+                DiffLine::Same("c".to_string()),
+                DiffLine::Added("d".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_identical() {
+        let diff = diff_lines(&lines("x\ny"), &lines("x\ny"));
+        assert!(diff.iter().all(|line| matches!(line, DiffLine::Same(_))));
+    }
+}
@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+
+/// Values substituted for `{{name}}` in composed requests, e.g. a token or a
+/// base URL, so that they can be swapped without retyping every request.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Environment(BTreeMap<String, String>);
+
+impl Environment {
+    /// Read `name = value` lines; empty lines and lines starting with `#` are skipped.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut variables = BTreeMap::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("Variable line {} is not `name = value`", number + 1))?;
+            variables.insert(name.trim().to_string(), value.trim().to_string());
+        }
+        Ok(Self(variables))
+    }
+
+    /// `text` with every `{{name}}` replaced by its value, or the first name
+    /// that has none.
+    pub fn expand(&self, text: &str) -> Result<String, String> {
+        let mut expanded = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            let Some(end) = rest[start..].find("}}") else {
+                break;
+            };
+            let name = rest[start + 2..start + end].trim();
+            let value = self.0.get(name).ok_or_else(|| format!("Unknown variable {{{{{}}}}}", name))?;
+            expanded.push_str(&rest[..start]);
+            expanded.push_str(value);
+            rest = &rest[start + end + 2..];
+        }
+        expanded.push_str(rest);
+        Ok(expanded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_expand_variables() {
+        let env = Environment::parse("# staging\nbase = https://staging.test \n\ntoken=abc=\n").unwrap();
+        assert_eq!(
+            env.expand("{{base}}/users?t={{ token }}&x={{base}}").unwrap(),
+            "https://staging.test/users?t=abc=&x=https://staging.test"
+        );
+        assert_eq!(env.expand("{\"a\": 1} {{token").unwrap(), "{\"a\": 1} {{token");
+        assert_eq!(env.expand("{{host}}").unwrap_err(), "Unknown variable {{host}}");
+        assert!(Environment::parse("base: x").is_err());
+    }
+}
@@ -0,0 +1,143 @@
+use std::{collections::HashMap, sync::Arc};
+
+use super::proxy::HttpLog;
+
+/// What the filter box matches against a request: its URL, GraphQL
+/// operation, tag, listener label, client address and name and trace id,
+/// ignoring case. Every word has to
+/// match, except that a word starting with `-` or `!` hides the requests
+/// it matches, e.g. `api -telemetry`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Filter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl Filter {
+    pub fn parse(query: &str) -> Self {
+        let mut filter = Self::default();
+        for word in query.to_lowercase().split_whitespace() {
+            match word.strip_prefix(['-', '!']) {
+                // a lone `-` is still being typed
+                Some("") => {}
+                Some(term) => filter.exclude.push(term.to_string()),
+                None => filter.include.push(word.to_string()),
+            }
+        }
+        filter
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    pub fn matches(&self, log: &HttpLog) -> bool {
+        let fields = [
+            Some(log.uri.to_lowercase()),
+            log.graphql.as_ref().map(|op| op.label().to_lowercase()),
+            log.tag.as_ref().map(|tag| tag.to_lowercase()),
+            log.listener.as_ref().map(|label| label.to_lowercase()),
+            log.client.map(|client| client.ip().to_string()),
+            log.client_name.as_ref().map(|name| name.to_lowercase()),
+            log.trace_id.as_ref().map(|id| id.to_lowercase()),
+        ];
+        let found = |term: &String| fields.iter().flatten().any(|field| field.contains(term.as_str()));
+        self.include.iter().all(found) && !self.exclude.iter().any(found)
+    }
+
+    /// Whether every request this filter matches is also matched by
+    /// `previous`, as when more is typed.
+    fn narrows(&self, previous: &Filter) -> bool {
+        previous.include.iter().all(|old| self.include.iter().any(|new| new.contains(old.as_str())))
+            && previous.exclude.iter().all(|old| self.exclude.iter().any(|new| old.contains(new.as_str())))
+    }
+}
+
+/// Filters the list on every render, reusing what it found last time for
+/// the requests that haven't changed since.
+#[derive(Default)]
+pub struct FilterCache {
+    filter: Filter,
+    /// Whether each request matched, by id; a changed request is a new `Arc`.
+    results: HashMap<u64, (Arc<HttpLog>, bool)>,
+}
+
+impl FilterCache {
+    pub fn filter(&self) -> &Filter {
+        &self.filter
+    }
+
+    /// The requests among `logs` matching `query`, in order.
+    pub fn apply<'a>(&mut self, query: &str, logs: &'a [Arc<HttpLog>]) -> Vec<&'a HttpLog> {
+        let filter = Filter::parse(query);
+        if filter != self.filter {
+            if filter.narrows(&self.filter) {
+                // what didn't match still doesn't, only the matches are checked again
+                self.results.retain(|_, (_, matched)| !*matched);
+            } else {
+                self.results.clear();
+            }
+            self.filter = filter;
+        }
+        if self.filter.is_empty() {
+            self.results.clear();
+            return logs.iter().map(AsRef::as_ref).collect();
+        }
+
+        let mut results = HashMap::with_capacity(logs.len());
+        let matches = logs
+            .iter()
+            .filter(|log| {
+                let matched = match self.results.get(&log.id) {
+                    Some((seen, matched)) if Arc::ptr_eq(seen, log) => *matched,
+                    _ => self.filter.matches(log),
+                };
+                results.insert(log.id, (Arc::clone(log), matched));
+                matched
+            })
+            .map(AsRef::as_ref)
+            .collect();
+        self.results = results;
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn log(id: u64, uri: &str) -> Arc<HttpLog> {
+        Arc::new(HttpLog {
+            id,
+            uri: uri.to_string(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_cache_follows_typing_and_changed_requests() {
+        let mut logs = vec![log(1, "http://api.test/users"), log(2, "http://API.test/orders"), log(3, "http://cdn.test/")];
+        let mut cache = FilterCache::default();
+        let ids = |matches: Vec<&HttpLog>| matches.iter().map(|log| log.id).collect::<Vec<_>>();
+        assert_eq!(ids(cache.apply("", &logs)), vec![1, 2, 3]);
+        assert_eq!(ids(cache.apply("ap", &logs)), vec![1, 2]);
+        assert_eq!(ids(cache.apply("api.test/o", &logs)), vec![2]);
+        // a request tagged after its response now matches too
+        Arc::make_mut(&mut logs[2]).tag = Some("api.test/orders".to_string());
+        assert_eq!(ids(cache.apply("api.test/o", &logs)), vec![2, 3]);
+        assert_eq!(ids(cache.apply("users", &logs)), vec![1]);
+        assert_eq!(ids(cache.apply("api -users", &logs)), vec![2, 3]);
+        assert_eq!(ids(cache.apply("api -users !cdn", &logs)), vec![2]);
+        assert_eq!(ids(cache.apply("-", &logs)), vec![1, 2, 3]);
+
+        // by the device that sent them
+        Arc::make_mut(&mut logs[0]).client = Some("192.168.1.23:50412".parse().unwrap());
+        Arc::make_mut(&mut logs[0]).client_name = Some("Pixel".to_string());
+        Arc::make_mut(&mut logs[1]).client = Some("192.168.1.40:61001".parse().unwrap());
+        assert_eq!(ids(cache.apply("pixel", &logs)), vec![1]);
+        assert_eq!(ids(cache.apply("192.168.1.", &logs)), vec![1, 2]);
+        assert_eq!(ids(cache.apply("-192.168.1.40", &logs)), vec![1, 3]);
+    }
+}
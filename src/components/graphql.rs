@@ -0,0 +1,223 @@
+//! GraphQL requests: which operation a request runs, and readable query text.
+
+use ratatui::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::body_format::format_body;
+use crate::theme::Theme;
+
+const INDENT: &str = "  ";
+
+/// The operation a GraphQL request runs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GraphqlOperation {
+    /// `query`, `mutation` or `subscription`.
+    pub kind: String,
+    pub name: Option<String>,
+    pub query: String,
+    /// The variables object as JSON, if any were sent.
+    pub variables: Option<String>,
+}
+
+impl GraphqlOperation {
+    /// Short label for the request list, e.g. `mutation AddUser`.
+    pub fn label(&self) -> String {
+        match &self.name {
+            Some(name) => format!("{} {}", self.kind, name),
+            None => self.kind.clone(),
+        }
+    }
+
+    /// The query, re-indented, followed by the variables.
+    pub fn text(&self, theme: &Theme) -> Text<'static> {
+        let mut lines = vec![Line::styled(
+            format!("GraphQL {}", self.label()),
+            theme.fg(theme.title).add_modifier(Modifier::BOLD),
+        )];
+        lines.extend(format_query(&self.query).into_iter().map(Line::raw));
+        if let Some(variables) = &self.variables {
+            lines.push(Line::styled("Variables:", theme.fg(theme.title)));
+            lines.extend(format_body(theme, Some("application/json"), variables).lines);
+        }
+        Text::from(lines)
+    }
+}
+
+/// Find a GraphQL operation in a request: a JSON body with a `query` string
+/// (the first one of a batch), an `application/graphql` body, or a `query`
+/// URL parameter.
+pub fn parse_request(content_type: Option<&str>, url_query: Option<&str>, body: &[u8]) -> Option<GraphqlOperation> {
+    let content_type = content_type.unwrap_or_default().to_lowercase();
+    let (query, operation_name, variables) = if content_type.starts_with("application/graphql") {
+        (String::from_utf8_lossy(body).into_owned(), None, None)
+    } else if content_type.contains("json") {
+        let json: Value = serde_json::from_slice(body).ok()?;
+        let request = match json {
+            Value::Array(mut batch) if !batch.is_empty() => batch.swap_remove(0),
+            json => json,
+        };
+        let query = request.get("query")?.as_str()?.to_string();
+        let operation_name = request.get("operationName").and_then(Value::as_str).map(str::to_string);
+        let variables = request.get("variables").filter(|v| !v.is_null()).map(Value::to_string);
+        (query, operation_name, variables)
+    } else {
+        let params: Vec<(String, String)> = url::form_urlencoded::parse(url_query?.as_bytes()).into_owned().collect();
+        let param = |name: &str| params.iter().find(|(key, _)| key == name).map(|(_, value)| value.clone());
+        (param("query")?, param("operationName"), param("variables"))
+    };
+
+    let operations = operations(&query);
+    let (kind, name) = operations
+        .iter()
+        .find(|(_, name)| operation_name.is_some() && *name == operation_name)
+        .or(operations.first())
+        .cloned()?;
+    Some(GraphqlOperation {
+        kind,
+        name: name.or(operation_name),
+        query,
+        variables,
+    })
+}
+
+/// Every operation defined in `query` as (kind, name); a bare `{ ... }`
+/// selection set is an anonymous query. Fragments are skipped.
+fn operations(query: &str) -> Vec<(String, Option<String>)> {
+    let mut operations = Vec::new();
+    let mut depth = 0usize;
+    let mut words: Vec<String> = Vec::new();
+    let mut word = String::new();
+    let mut chars = query.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '#' => {
+                // comment to end of line
+                chars.by_ref().find(|&c| c == '\n');
+            }
+            '"' => {
+                let mut escaped = false;
+                for c in chars.by_ref() {
+                    match c {
+                        '\\' if !escaped => escaped = true,
+                        '"' if !escaped => break,
+                        _ => escaped = false,
+                    }
+                }
+            }
+            '{' => {
+                if depth == 0 {
+                    if !word.is_empty() {
+                        words.push(std::mem::take(&mut word));
+                    }
+                    match words.first().map(String::as_str) {
+                        Some(kind @ ("query" | "mutation" | "subscription")) => {
+                            operations.push((kind.to_string(), words.get(1).cloned()));
+                        }
+                        None => operations.push(("query".to_string(), None)),
+                        _ => {}
+                    }
+                    words.clear();
+                }
+                depth += 1;
+            }
+            '}' => depth = depth.saturating_sub(1),
+            '(' if depth == 0 => {
+                // variable definitions: skip to the closing paren
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                chars.by_ref().find(|&c| c == ')');
+            }
+            c if depth == 0 && (c.is_alphanumeric() || c == '_') => word.push(c),
+            _ if depth == 0 && !word.is_empty() => words.push(std::mem::take(&mut word)),
+            _ => {}
+        }
+    }
+    operations
+}
+
+/// Re-indent a query: one field per line, nested by selection set.
+fn format_query(query: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+    let mut parens = 0usize;
+    let flush = |current: &mut String, lines: &mut Vec<String>, depth: usize| {
+        let trimmed = current.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !trimmed.is_empty() {
+            lines.push(format!("{}{}", INDENT.repeat(depth), trimmed));
+        }
+        current.clear();
+    };
+    for line in query.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        for c in line.chars() {
+            match c {
+                '(' => {
+                    parens += 1;
+                    current.push(c);
+                }
+                ')' => {
+                    parens = parens.saturating_sub(1);
+                    current.push(c);
+                }
+                '{' if parens == 0 => {
+                    current.push_str(" {");
+                    flush(&mut current, &mut lines, depth);
+                    depth += 1;
+                }
+                '}' if parens == 0 => {
+                    flush(&mut current, &mut lines, depth);
+                    depth = depth.saturating_sub(1);
+                    lines.push(format!("{}}}", INDENT.repeat(depth)));
+                }
+                ',' if parens == 0 => flush(&mut current, &mut lines, depth),
+                c => current.push(c),
+            }
+        }
+        if parens == 0 {
+            flush(&mut current, &mut lines, depth);
+        } else {
+            current.push(' ');
+        }
+    }
+    flush(&mut current, &mut lines, depth);
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_json_request() {
+        let body = br#"{"query":"mutation AddUser($name: String!) { addUser(name: $name) { id } }","variables":{"name":"a"}}"#;
+        let operation = parse_request(Some("application/json"), None, body).unwrap();
+        assert_eq!(operation.label(), "mutation AddUser");
+        assert_eq!(operation.variables.as_deref(), Some(r#"{"name":"a"}"#));
+    }
+
+    #[test]
+    fn test_operation_name_picks_from_document() {
+        let body = br#"{"query":"fragment F on User { id } query A { a } mutation B { b }","operationName":"B"}"#;
+        assert_eq!(parse_request(Some("application/json"), None, body).unwrap().label(), "mutation B");
+        let operation = parse_request(None, Some("query=%7B%20me%20%7B%20id%20%7D%20%7D"), b"").unwrap();
+        assert_eq!(operation.label(), "query");
+    }
+
+    #[test]
+    fn test_non_graphql_json_is_ignored() {
+        assert_eq!(parse_request(Some("application/json"), None, br#"{"name":"a"}"#), None);
+    }
+
+    #[test]
+    fn test_format_query() {
+        assert_eq!(
+            format_query("query Q($id: ID!) { user(id: $id) { id, name } }"),
+            vec!["query Q($id: ID!) {", "  user(id: $id) {", "    id", "    name", "  }", "}"]
+        );
+    }
+}
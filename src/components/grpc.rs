@@ -0,0 +1,473 @@
+//! gRPC bodies: length-prefixed framing around protobuf messages.
+//!
+//! Messages are decoded to JSON when a descriptor set (`protoc
+//! --descriptor_set_out`) describes them, otherwise shown as a raw breakdown
+//! of field numbers and wire types. Protobuf bodies sent on their own, and
+//! binary bodies of unknown type that turn out to be one, get the same raw
+//! breakdown.
+
+use std::collections::HashMap;
+
+use base64::Engine;
+use ratatui::prelude::*;
+use serde_json::{Map, Value, json};
+
+use super::body_format::format_body;
+use crate::theme::Theme;
+
+const INDENT: &str = "  ";
+
+pub fn is_grpc(content_type: Option<&str>) -> bool {
+    content_type.is_some_and(|ct| ct.to_lowercase().starts_with("application/grpc"))
+}
+
+/// A protobuf message in a body of its own, e.g. `application/x-protobuf`.
+pub fn is_protobuf(content_type: Option<&str>) -> bool {
+    content_type.is_some_and(|ct| {
+        let ct = ct.to_lowercase();
+        let essence = ct.split(';').next().unwrap_or_default().trim();
+        matches!(essence, "application/x-protobuf" | "application/protobuf" | "application/vnd.google.protobuf")
+    })
+}
+
+/// One length-prefixed message of a gRPC body.
+#[derive(Debug, PartialEq)]
+pub struct GrpcFrame<'a> {
+    pub compressed: bool,
+    pub payload: &'a [u8],
+}
+
+/// Split a body into its frames: a compression flag, a big-endian u32
+/// length and that many bytes of message, repeated.
+pub fn frames(mut body: &[u8]) -> Result<Vec<GrpcFrame<'_>>, String> {
+    let mut frames = Vec::new();
+    while !body.is_empty() {
+        if body.len() < 5 {
+            return Err(format!("truncated frame header ({} bytes)", body.len()));
+        }
+        let len = u32::from_be_bytes([body[1], body[2], body[3], body[4]]) as usize;
+        let Some(payload) = body.get(5..5 + len) else {
+            return Err(format!("frame of {} bytes, only {} left", len, body.len() - 5));
+        };
+        frames.push(GrpcFrame {
+            compressed: body[0] != 0,
+            payload,
+        });
+        body = &body[5 + len..];
+    }
+    Ok(frames)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum WireValue<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+    Fixed32(u32),
+}
+
+fn read_varint(buf: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *buf.get(*pos)?;
+        *pos += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Every field of a protobuf message in wire order, or `None` if `buf`
+/// isn't a well-formed message.
+fn wire_fields(buf: &[u8]) -> Option<Vec<(u32, WireValue<'_>)>> {
+    let mut fields = Vec::new();
+    let mut pos = 0;
+    while pos < buf.len() {
+        let key = read_varint(buf, &mut pos)?;
+        let number = u32::try_from(key >> 3).ok().filter(|n| *n > 0)?;
+        let value = match key & 7 {
+            0 => WireValue::Varint(read_varint(buf, &mut pos)?),
+            1 => {
+                let bytes = buf.get(pos..pos + 8)?;
+                pos += 8;
+                WireValue::Fixed64(u64::from_le_bytes(bytes.try_into().ok()?))
+            }
+            2 => {
+                let len = read_varint(buf, &mut pos)? as usize;
+                let bytes = buf.get(pos..pos.checked_add(len)?)?;
+                pos += len;
+                WireValue::Bytes(bytes)
+            }
+            5 => {
+                let bytes = buf.get(pos..pos + 4)?;
+                pos += 4;
+                WireValue::Fixed32(u32::from_le_bytes(bytes.try_into().ok()?))
+            }
+            _ => return None,
+        };
+        fields.push((number, value));
+    }
+    Some(fields)
+}
+
+/// Field numbers and wire types, recursing into anything that parses as a
+/// nested message.
+fn raw_lines(theme: &Theme, buf: &[u8], depth: usize, lines: &mut Vec<Line<'static>>) {
+    let Some(fields) = wire_fields(buf) else {
+        lines.push(Line::raw(format!("{}<malformed message>", INDENT.repeat(depth))));
+        return;
+    };
+    let number_style = theme.fg(theme.accent);
+    for (number, value) in fields {
+        let (kind, shown) = match value {
+            WireValue::Varint(v) => ("varint", v.to_string()),
+            WireValue::Fixed64(v) => ("fixed64", format!("{} ({})", v, f64::from_bits(v))),
+            WireValue::Fixed32(v) => ("fixed32", format!("{} ({})", v, f32::from_bits(v))),
+            WireValue::Bytes(bytes) => {
+                // printable text could also parse as a message; text is the likelier reading
+                let text = std::str::from_utf8(bytes)
+                    .ok()
+                    .filter(|text| !text.chars().any(|c| c.is_control() && !c.is_whitespace()));
+                if let Some(text) = text {
+                    ("string", format!("{:?}", text))
+                } else if wire_fields(bytes).is_some() {
+                    lines.push(Line::from(vec![
+                        Span::raw(INDENT.repeat(depth)),
+                        Span::styled(format!("{}", number), number_style),
+                        Span::raw(": message {"),
+                    ]));
+                    raw_lines(theme, bytes, depth + 1, lines);
+                    lines.push(Line::raw(format!("{}}}", INDENT.repeat(depth))));
+                    continue;
+                } else {
+                    ("bytes", base64::engine::general_purpose::STANDARD.encode(bytes))
+                }
+            }
+        };
+        lines.push(Line::from(vec![
+            Span::raw(INDENT.repeat(depth)),
+            Span::styled(format!("{}", number), number_style),
+            Span::raw(format!(": {} ", kind)),
+            Span::styled(shown, theme.fg(theme.success)),
+        ]));
+    }
+}
+
+#[derive(Debug)]
+struct FieldType {
+    name: String,
+    /// `FieldDescriptorProto.Type`, e.g. 9 for string and 11 for message.
+    kind: u64,
+    type_name: String,
+    repeated: bool,
+}
+
+/// Message types and method signatures from a `FileDescriptorSet`.
+#[derive(Debug, Default)]
+pub struct Descriptors {
+    /// Fields of each message type, keyed by fully qualified name (`.pkg.Msg`).
+    messages: HashMap<String, HashMap<u32, FieldType>>,
+    /// Input and output types of each method, keyed by its path (`/pkg.Service/Method`).
+    methods: HashMap<String, (String, String)>,
+}
+
+fn string_of(value: &WireValue) -> String {
+    match value {
+        WireValue::Bytes(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+        _ => String::new(),
+    }
+}
+
+impl Descriptors {
+    pub fn parse(set: &[u8]) -> Result<Self, String> {
+        let mut descriptors = Self::default();
+        let files = wire_fields(set).ok_or("not a FileDescriptorSet")?;
+        for (_, file) in files.iter().filter(|(n, _)| *n == 1) {
+            let WireValue::Bytes(file) = file else { continue };
+            let fields = wire_fields(file).ok_or("malformed FileDescriptorProto")?;
+            let package = fields.iter().find(|(n, _)| *n == 2).map(|(_, v)| string_of(v)).unwrap_or_default();
+            let scope = if package.is_empty() { String::new() } else { format!(".{}", package) };
+            for (number, value) in &fields {
+                match (number, value) {
+                    (4, WireValue::Bytes(message)) => descriptors.add_message(&scope, message)?,
+                    (6, WireValue::Bytes(service)) => descriptors.add_service(&package, service)?,
+                    _ => {}
+                }
+            }
+        }
+        Ok(descriptors)
+    }
+
+    fn add_message(&mut self, scope: &str, message: &[u8]) -> Result<(), String> {
+        let fields = wire_fields(message).ok_or("malformed DescriptorProto")?;
+        let name = fields.iter().find(|(n, _)| *n == 1).map(|(_, v)| string_of(v)).unwrap_or_default();
+        let full_name = format!("{}.{}", scope, name);
+        let mut types = HashMap::new();
+        for (number, value) in &fields {
+            match (number, value) {
+                (2, WireValue::Bytes(field)) => {
+                    let field = wire_fields(field).ok_or("malformed FieldDescriptorProto")?;
+                    let mut field_type = FieldType {
+                        name: String::new(),
+                        kind: 0,
+                        type_name: String::new(),
+                        repeated: false,
+                    };
+                    let mut field_number = 0;
+                    for (n, v) in &field {
+                        match (n, v) {
+                            (1, v) => field_type.name = string_of(v),
+                            (3, WireValue::Varint(v)) => field_number = *v as u32,
+                            (4, WireValue::Varint(label)) => field_type.repeated = *label == 3,
+                            (5, WireValue::Varint(kind)) => field_type.kind = *kind,
+                            (6, v) => field_type.type_name = string_of(v),
+                            _ => {}
+                        }
+                    }
+                    types.insert(field_number, field_type);
+                }
+                (3, WireValue::Bytes(nested)) => self.add_message(&full_name, nested)?,
+                _ => {}
+            }
+        }
+        self.messages.insert(full_name, types);
+        Ok(())
+    }
+
+    fn add_service(&mut self, package: &str, service: &[u8]) -> Result<(), String> {
+        let fields = wire_fields(service).ok_or("malformed ServiceDescriptorProto")?;
+        let name = fields.iter().find(|(n, _)| *n == 1).map(|(_, v)| string_of(v)).unwrap_or_default();
+        let service_name = if package.is_empty() { name } else { format!("{}.{}", package, name) };
+        for (_, method) in fields.iter().filter(|(n, _)| *n == 2) {
+            let WireValue::Bytes(method) = method else { continue };
+            let method = wire_fields(method).ok_or("malformed MethodDescriptorProto")?;
+            let field = |number| method.iter().find(|(n, _)| *n == number).map(|(_, v)| string_of(v));
+            let (Some(name), Some(input), Some(output)) = (field(1), field(2), field(3)) else {
+                continue;
+            };
+            self.methods.insert(format!("/{}/{}", service_name, name), (input, output));
+        }
+        Ok(())
+    }
+
+    /// The message type carried by requests (or responses) of the method at `path`.
+    pub fn message_type(&self, path: &str, response: bool) -> Option<&str> {
+        let (input, output) = self.methods.get(path)?;
+        Some(if response { output } else { input })
+    }
+
+    /// Decode `buf` as a `type_name` message, or `None` if the type is unknown.
+    pub fn to_json(&self, type_name: &str, buf: &[u8]) -> Option<Value> {
+        let types = self.messages.get(type_name)?;
+        let mut object = Map::new();
+        for (number, value) in wire_fields(buf)? {
+            let Some(field) = types.get(&number) else {
+                object.insert(number.to_string(), json!(format!("{:?}", value)));
+                continue;
+            };
+            let values = match (&value, field.kind) {
+                // packed repeated scalars
+                (WireValue::Bytes(bytes), kind) if field.repeated && !matches!(kind, 9 | 11 | 12) => {
+                    self.packed(kind, bytes)
+                }
+                _ => vec![self.scalar(field, &value)],
+            };
+            if field.repeated {
+                let entry = object.entry(field.name.clone()).or_insert_with(|| json!([]));
+                if let Value::Array(array) = entry {
+                    array.extend(values);
+                }
+            } else if let Some(value) = values.into_iter().next() {
+                object.insert(field.name.clone(), value);
+            }
+        }
+        Some(Value::Object(object))
+    }
+
+    fn packed(&self, kind: u64, bytes: &[u8]) -> Vec<Value> {
+        let mut values = Vec::new();
+        let mut pos = 0;
+        while pos < bytes.len() {
+            let value = match kind {
+                1 | 6 | 16 => bytes.get(pos..pos + 8).map(|b| {
+                    pos += 8;
+                    WireValue::Fixed64(u64::from_le_bytes(b.try_into().unwrap()))
+                }),
+                2 | 7 | 15 => bytes.get(pos..pos + 4).map(|b| {
+                    pos += 4;
+                    WireValue::Fixed32(u32::from_le_bytes(b.try_into().unwrap()))
+                }),
+                _ => read_varint(bytes, &mut pos).map(WireValue::Varint),
+            };
+            let Some(value) = value else { break };
+            values.push(scalar_value(kind, &value));
+        }
+        values
+    }
+
+    fn scalar(&self, field: &FieldType, value: &WireValue) -> Value {
+        match (field.kind, value) {
+            (11, WireValue::Bytes(bytes)) => self
+                .to_json(&field.type_name, bytes)
+                .unwrap_or_else(|| json!(base64::engine::general_purpose::STANDARD.encode(bytes))),
+            (kind, value) => scalar_value(kind, value),
+        }
+    }
+}
+
+fn scalar_value(kind: u64, value: &WireValue) -> Value {
+    match (kind, value) {
+        (1, WireValue::Fixed64(v)) => json!(f64::from_bits(*v)),
+        (2, WireValue::Fixed32(v)) => json!(f32::from_bits(*v)),
+        (3 | 5, WireValue::Varint(v)) => json!(*v as i64),
+        (8, WireValue::Varint(v)) => json!(*v != 0),
+        (9, WireValue::Bytes(bytes)) => json!(String::from_utf8_lossy(bytes)),
+        (12, WireValue::Bytes(bytes)) => json!(base64::engine::general_purpose::STANDARD.encode(bytes)),
+        (15, WireValue::Fixed32(v)) => json!(*v as i32),
+        (16, WireValue::Fixed64(v)) => json!(*v as i64),
+        (17, WireValue::Varint(v)) => json!(((*v >> 1) as i64) ^ -((*v & 1) as i64)),
+        (18, WireValue::Varint(v)) => json!(((*v >> 1) as i64) ^ -((*v & 1) as i64)),
+        (_, WireValue::Varint(v)) => json!(v),
+        (_, WireValue::Fixed64(v)) => json!(v),
+        (_, WireValue::Fixed32(v)) => json!(v),
+        (_, WireValue::Bytes(bytes)) => json!(base64::engine::general_purpose::STANDARD.encode(bytes)),
+    }
+}
+
+/// A body holding a single protobuf message, broken down without a schema,
+/// or `None` if the bytes don't read as one.
+pub fn format_protobuf(theme: &Theme, body: &[u8]) -> Option<Text<'static>> {
+    wire_fields(body).filter(|fields| !fields.is_empty())?;
+    let mut lines = vec![Line::styled(
+        format!("Protocol buffer message ({} bytes), decoded without a schema", body.len()),
+        theme.fg(theme.title),
+    )];
+    raw_lines(theme, body, 0, &mut lines);
+    Some(Text::from(lines))
+}
+
+/// Render every frame of a gRPC body sent to (or from) the method at `path`.
+pub fn format_grpc(
+    theme: &Theme,
+    body: &[u8],
+    path: &str,
+    response: bool,
+    descriptors: Option<&Descriptors>,
+) -> Text<'static> {
+    let frames = match frames(body) {
+        Ok(frames) => frames,
+        Err(e) => return Text::raw(format!("Malformed gRPC body: {}", e)),
+    };
+    let message_type = descriptors.and_then(|d| d.message_type(path, response));
+    let mut lines = Vec::new();
+    for (index, frame) in frames.iter().enumerate() {
+        lines.push(Line::styled(
+            format!(
+                "Message {} ({} bytes{}{})",
+                index + 1,
+                frame.payload.len(),
+                if frame.compressed { ", compressed" } else { "" },
+                message_type.map(|t| format!(", {}", t.trim_start_matches('.'))).unwrap_or_default(),
+            ),
+            theme.fg(theme.title),
+        ));
+        if frame.compressed {
+            lines.push(Line::raw("<compressed payload not decoded>"));
+            continue;
+        }
+        match message_type.and_then(|t| descriptors?.to_json(t, frame.payload)) {
+            Some(json) => lines.extend(format_body(theme, Some("application/json"), &json.to_string()).lines),
+            None => raw_lines(theme, frame.payload, 0, &mut lines),
+        }
+    }
+    Text::from(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn plain(text: &Text) -> Vec<String> {
+        text.lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    fn framed(payload: &[u8]) -> Vec<u8> {
+        let mut body = vec![0];
+        body.extend((payload.len() as u32).to_be_bytes());
+        body.extend(payload);
+        body
+    }
+
+    fn bytes_field(number: u8, bytes: &[u8]) -> Vec<u8> {
+        let mut field = vec![number << 3 | 2, bytes.len() as u8];
+        field.extend(bytes);
+        field
+    }
+
+    // message HelloReply { string message = 1; int32 count = 2; }
+    // service Greeter { rpc SayHello (HelloRequest) returns (HelloReply); }
+    fn descriptor_set() -> Vec<u8> {
+        let message_field = [bytes_field(1, b"message"), vec![3 << 3, 1, 5 << 3, 9]].concat();
+        let count_field = [bytes_field(1, b"count"), vec![3 << 3, 2, 5 << 3, 5]].concat();
+        let reply = [bytes_field(1, b"HelloReply"), bytes_field(2, &message_field), bytes_field(2, &count_field)].concat();
+        let method = [bytes_field(1, b"SayHello"), bytes_field(2, b".demo.HelloRequest"), bytes_field(3, b".demo.HelloReply")]
+            .concat();
+        let service = [bytes_field(1, b"Greeter"), bytes_field(2, &method)].concat();
+        let file = [bytes_field(2, b"demo"), bytes_field(4, &reply), bytes_field(6, &service)].concat();
+        bytes_field(1, &file)
+    }
+
+    #[test]
+    fn test_frames() {
+        let body = [framed(b"\x08\x01"), framed(b"")].concat();
+        let parsed = frames(&body).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].payload, b"\x08\x01");
+        assert!(frames(&body[..4]).is_err());
+    }
+
+    #[test]
+    fn test_raw_breakdown_without_descriptors() {
+        let payload = [bytes_field(1, b"hi"), vec![2 << 3, 150, 1]].concat();
+        let text = format_grpc(&Theme::default(), &framed(&payload), "/demo.Greeter/SayHello", true, None);
+        assert_eq!(plain(&text), vec!["Message 1 (7 bytes)", "1: string \"hi\"", "2: varint 150"]);
+    }
+
+    #[test]
+    fn test_protobuf_body() {
+        assert!(is_protobuf(Some("application/x-protobuf; messageType=demo.User")));
+        assert!(!is_protobuf(Some("application/grpc")));
+
+        let body = [bytes_field(1, &[bytes_field(1, b"ada"), vec![2 << 3, 36]].concat()), vec![3 << 3 | 5, 0, 0, 128, 63]]
+            .concat();
+        let text = format_protobuf(&Theme::default(), &body).unwrap();
+        assert_eq!(
+            plain(&text),
+            vec![
+                "Protocol buffer message (14 bytes), decoded without a schema",
+                "1: message {",
+                "  1: string \"ada\"",
+                "  2: varint 36",
+                "}",
+                "3: fixed32 1065353216 (1)",
+            ]
+        );
+        assert!(format_protobuf(&Theme::default(), b"\x89PNG\r\n").is_none());
+        assert!(format_protobuf(&Theme::default(), b"").is_none());
+    }
+
+    #[test]
+    fn test_decode_with_descriptors() {
+        let descriptors = Descriptors::parse(&descriptor_set()).unwrap();
+        assert_eq!(descriptors.message_type("/demo.Greeter/SayHello", true), Some(".demo.HelloReply"));
+        let payload = [bytes_field(1, b"hi"), vec![2 << 3, 3]].concat();
+        assert_eq!(
+            descriptors.to_json(".demo.HelloReply", &payload),
+            Some(json!({ "message": "hi", "count": 3 }))
+        );
+    }
+}
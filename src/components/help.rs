@@ -0,0 +1,128 @@
+use ratatui::{prelude::*, widgets::*};
+
+use super::Component;
+use crate::{
+    app::Mode,
+    config::{Config, key_event_to_string},
+    framework::{Action, Updater},
+};
+
+/// Overlay listing the active keybindings of every mode.
+#[derive(Default)]
+pub struct Help {
+    config: Config,
+    visible: bool,
+    updater: Option<Updater>,
+    /// Where the overlay was last drawn, for click-outside-to-close.
+    popup_area: Rect,
+}
+
+impl Help {
+    fn lines(&self) -> Vec<Line<'static>> {
+        let theme = &self.config.theme;
+        let mut modes: Vec<_> = self.config.keybindings.iter().collect();
+        modes.sort_by_key(|(mode, _)| **mode);
+
+        let mut lines = Vec::new();
+        for (mode, bindings) in modes {
+            let title = match mode {
+                Mode::Home => "Global".to_string(),
+                mode => format!("{:?}", mode),
+            };
+            lines.push(Line::styled(title, theme.fg(theme.title).add_modifier(Modifier::BOLD)));
+
+            let mut bindings: Vec<_> = bindings
+                .iter()
+                .map(|(keys, action)| {
+                    let keys = keys.iter().map(key_event_to_string).collect::<Vec<_>>().join(" ");
+                    (action.to_string(), keys)
+                })
+                .collect();
+            bindings.sort();
+            for (action, keys) in bindings {
+                lines.push(Line::from(vec![
+                    Span::styled(format!("  {:<14}", keys), theme.fg(theme.accent)),
+                    Span::raw(action),
+                ]));
+            }
+            lines.push(Line::from(""));
+        }
+        lines
+    }
+}
+
+impl Component for Help {
+    fn component_will_mount(&mut self, config: Config) -> color_eyre::Result<()> {
+        self.config = config;
+        Ok(())
+    }
+
+    fn component_did_mount(&mut self, _area: Size, updater: Updater) -> color_eyre::Result<()> {
+        self.updater = Some(updater);
+        Ok(())
+    }
+
+    fn captures_key(&self, key: crossterm::event::KeyEvent) -> bool {
+        // the overlay is modal: nothing reaches the panes below while it is open
+        self.visible && self.config.keybindings.action_for(Mode::Home, key) != Some(&Action::ToggleHelp)
+    }
+
+    fn handle_key_event(&mut self, key: crossterm::event::KeyEvent) -> color_eyre::Result<Option<Action>> {
+        if matches!(key.code, crossterm::event::KeyCode::Esc | crossterm::event::KeyCode::Char('q')) {
+            return Ok(Some(Action::ToggleHelp));
+        }
+        Ok(None)
+    }
+
+    fn captures_mouse(&self, _mouse: crossterm::event::MouseEvent) -> bool {
+        self.visible
+    }
+
+    fn handle_mouse_event(&mut self, mouse: crossterm::event::MouseEvent) -> color_eyre::Result<Option<Action>> {
+        let outside = !self.popup_area.contains(Position::new(mouse.column, mouse.row));
+        if outside && matches!(mouse.kind, crossterm::event::MouseEventKind::Down(_)) {
+            return Ok(Some(Action::ToggleHelp));
+        }
+        Ok(None)
+    }
+
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        // settings are only kept, so take them again
+        if let Action::ConfigReloaded(reloaded) = &action {
+            self.component_will_mount(reloaded.config())?;
+            return Ok(None);
+        }
+        if action == Action::ToggleHelp {
+            self.visible = !self.visible;
+            if let Some(updater) = &self.updater {
+                updater.update();
+            }
+        }
+        Ok(None)
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect) -> color_eyre::Result<()> {
+        if !self.visible {
+            return Ok(());
+        }
+        let popup_area = area.inner(Margin {
+            horizontal: area.width / 6,
+            vertical: area.height / 10,
+        });
+        self.popup_area = popup_area;
+        let lines = self.lines();
+        let half = lines.len().div_ceil(2);
+        let block = Block::default()
+            .title("Keybindings (? or esc to close)")
+            .borders(Borders::ALL)
+            .border_style(self.config.theme.fg(self.config.theme.title));
+        let inner = block.inner(popup_area);
+        let [left, right] = Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(inner);
+
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(block, popup_area);
+        frame.render_widget(Paragraph::new(lines[..half].to_vec()), left);
+        frame.render_widget(Paragraph::new(lines[half..].to_vec()), right);
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+use std::path::PathBuf;
+
+use tracing::warn;
+
+/// Previously applied filters, oldest first, recalled like shell history and
+/// kept one per line in a file so they survive restarts.
+#[derive(Debug, Default)]
+pub struct History {
+    entries: Vec<String>,
+    limit: usize,
+    /// Entry being shown while browsing, `None` when editing a new line.
+    position: Option<usize>,
+    /// The line that was being edited when browsing started.
+    draft: String,
+    path: Option<PathBuf>,
+}
+
+impl History {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            ..Default::default()
+        }
+    }
+
+    /// Read the entries saved in `path`, and save there from now on.
+    pub fn load(limit: usize, path: PathBuf) -> Self {
+        let mut history = Self::new(limit);
+        if let Ok(content) = std::fs::read_to_string(&path) {
+            content.lines().for_each(|line| history.remember(line));
+        }
+        history.path = Some(path);
+        history
+    }
+
+    /// Record an applied filter as the most recent entry and stop browsing.
+    pub fn push(&mut self, entry: &str) {
+        self.position = None;
+        if self.limit == 0 || entry.trim().is_empty() || self.entries.last().is_some_and(|last| last == entry) {
+            return;
+        }
+        self.remember(entry);
+        if let Some(path) = &self.path
+            && let Err(e) = std::fs::write(path, self.entries.join("\n") + "\n")
+        {
+            warn!("could not save the filter history to {}: {}", path.display(), e);
+        }
+    }
+
+    fn remember(&mut self, entry: &str) {
+        self.entries.retain(|e| e != entry);
+        self.entries.push(entry.to_string());
+        let excess = self.entries.len().saturating_sub(self.limit);
+        self.entries.drain(..excess);
+    }
+
+    /// The entry before the one shown, keeping `current` as the draft when
+    /// browsing starts; `None` at the oldest entry.
+    pub fn previous(&mut self, current: &str) -> Option<&str> {
+        let position = match self.position {
+            None if self.entries.is_empty() => return None,
+            None => {
+                self.draft = current.to_string();
+                self.entries.len() - 1
+            }
+            Some(0) => return None,
+            Some(position) => position - 1,
+        };
+        self.position = Some(position);
+        Some(&self.entries[position])
+    }
+
+    /// The entry after the one shown, then the draft; `None` when not browsing.
+    pub fn next(&mut self) -> Option<&str> {
+        let position = self.position?;
+        if position + 1 < self.entries.len() {
+            self.position = Some(position + 1);
+            Some(&self.entries[position + 1])
+        } else {
+            self.position = None;
+            Some(&self.draft)
+        }
+    }
+
+    /// Stop browsing, after the recalled line was edited.
+    pub fn reset(&mut self) {
+        self.position = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_history_recall() {
+        let mut history = History::new(3);
+        for filter in ["a", "b", "a", "c", "d", ""] {
+            history.push(filter);
+        }
+        assert_eq!(history.entries, vec!["a", "c", "d"]);
+        assert_eq!(history.next(), None);
+        assert_eq!(history.previous("dra"), Some("d"));
+        assert_eq!(history.previous("d"), Some("c"));
+        assert_eq!(history.previous("c"), Some("a"));
+        assert_eq!(history.previous("a"), None);
+        assert_eq!(history.next(), Some("c"));
+        assert_eq!(history.next(), Some("d"));
+        assert_eq!(history.next(), Some("dra"));
+        assert_eq!(history.next(), None);
+    }
+}
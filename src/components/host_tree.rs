@@ -0,0 +1,157 @@
+use std::{borrow::Borrow, collections::HashSet};
+
+use ratatui::{prelude::*, widgets::*};
+
+use super::proxy::HttpLog;
+use crate::theme::Theme;
+
+/// A single visible row of the ProxyList, either in list or tree view.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TreeRow {
+    /// A collapsible host node with the number of requests below it.
+    Host {
+        host: String,
+        count: usize,
+        expanded: bool,
+    },
+    /// A request, as an index into the filtered logs.
+    Request(usize),
+    /// Consecutive requests with the same method and URL, shown as the most
+    /// recent one. `first` is the id of the earliest.
+    Repeats {
+        first: u64,
+        index: usize,
+        count: usize,
+        expanded: bool,
+    },
+    /// One of the requests of an expanded [`TreeRow::Repeats`].
+    Occurrence(usize),
+    /// An attempt of a retry, below the request it retries.
+    Attempt(usize),
+}
+
+impl TreeRow {
+    /// Index of the request the row shows, if it shows one.
+    pub fn request(&self) -> Option<usize> {
+        match self {
+            TreeRow::Request(index)
+            | TreeRow::Occurrence(index)
+            | TreeRow::Attempt(index)
+            | TreeRow::Repeats { index, .. } => Some(*index),
+            TreeRow::Host { .. } => None,
+        }
+    }
+}
+
+/// Groups requests under their host, like the network tab of browser devtools.
+#[derive(Default)]
+pub struct HostTree {
+    collapsed: HashSet<String>,
+}
+
+impl HostTree {
+    /// Build the visible rows for `logs`, keeping hosts in order of first appearance.
+    pub fn rows<L: Borrow<HttpLog>>(&self, logs: &[L]) -> Vec<TreeRow> {
+        let mut hosts: Vec<(String, Vec<usize>)> = Vec::new();
+        for (idx, log) in logs.iter().enumerate() {
+            let host = host_of(&log.borrow().uri);
+            match hosts.iter_mut().find(|(h, _)| *h == host) {
+                Some((_, indices)) => indices.push(idx),
+                None => hosts.push((host, vec![idx])),
+            }
+        }
+
+        let mut rows = Vec::new();
+        for (host, indices) in hosts {
+            let expanded = !self.collapsed.contains(&host);
+            rows.push(TreeRow::Host {
+                host,
+                count: indices.len(),
+                expanded,
+            });
+            if expanded {
+                rows.extend(indices.into_iter().map(TreeRow::Request));
+            }
+        }
+        rows
+    }
+
+    pub fn toggle(&mut self, host: &str) {
+        if !self.collapsed.remove(host) {
+            self.collapsed.insert(host.to_string());
+        }
+    }
+
+    pub fn set_expanded(&mut self, host: &str, expanded: bool) {
+        if expanded {
+            self.collapsed.remove(host);
+        } else {
+            self.collapsed.insert(host.to_string());
+        }
+    }
+
+    pub fn host_item(theme: &Theme, host: &str, count: usize, expanded: bool) -> ListItem<'static> {
+        let marker = if expanded { "▼" } else { "▶" };
+        ListItem::new(Line::from(vec![
+            Span::styled(format!("{} ", marker), theme.fg(theme.accent)),
+            Span::styled(host.to_string(), Style::default().add_modifier(Modifier::BOLD)),
+            Span::styled(format!(" ({})", count), theme.fg(theme.muted)),
+        ]))
+    }
+}
+
+/// Extract the host part of a request URI. CONNECT requests use the
+/// `host:port` authority form, which `url` can't parse on its own.
+pub fn host_of(uri: &str) -> String {
+    url::Url::parse(uri)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .or_else(|| uri.parse::<hyper::http::uri::Authority>().ok().map(|authority| authority.host().to_string()))
+        .unwrap_or_else(|| uri.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn log(uri: &str) -> HttpLog {
+        HttpLog {
+            method: "GET".to_string(),
+            uri: uri.to_string(),
+            timestamp: Utc::now(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rows_group_by_host() {
+        let logs = vec![
+            log("http://a.com/1"),
+            log("http://b.com/1"),
+            log("http://a.com/2"),
+        ];
+        let mut tree = HostTree::default();
+        tree.toggle("b.com");
+        assert_eq!(
+            tree.rows(&logs),
+            vec![
+                TreeRow::Host { host: "a.com".to_string(), count: 2, expanded: true },
+                TreeRow::Request(0),
+                TreeRow::Request(2),
+                TreeRow::Host { host: "b.com".to_string(), count: 1, expanded: false },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_host_of_connect_authority() {
+        assert_eq!(host_of("example.com:443"), "example.com");
+        assert_eq!(host_of("http://example.com:8080/x"), "example.com");
+        // IPv6 literals keep their brackets either way, and their colons
+        assert_eq!(host_of("[2001:db8::1]:443"), "[2001:db8::1]");
+        assert_eq!(host_of("http://[::1]:8080/x"), "[::1]");
+    }
+}
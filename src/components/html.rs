@@ -0,0 +1,451 @@
+//! HTML bodies as readable text: tags stripped, headings and lists kept
+//! apart, links numbered with their targets listed below, so error pages
+//! and simple responses read without the markup.
+
+use ratatui::prelude::*;
+
+use crate::theme::Theme;
+
+/// Elements whose content is not text.
+const SKIPPED: [&str; 5] = ["script", "style", "template", "svg", "noscript"];
+
+/// Elements that start on a line of their own.
+const BLOCKS: [&str; 30] = [
+    "address", "article", "aside", "blockquote", "body", "br", "dd", "details", "div", "dl", "dt", "fieldset",
+    "figcaption", "figure", "footer", "form", "header", "hr", "li", "main", "nav", "ol", "p", "pre", "section",
+    "summary", "table", "title", "tr", "ul",
+];
+
+pub fn is_html(content_type: Option<&str>) -> bool {
+    content_type.is_some_and(|content_type| content_type.to_ascii_lowercase().contains("html"))
+}
+
+/// Text of an HTML document, with the links resolved against `base`, the
+/// URL it was fetched from.
+pub fn text(theme: &Theme, html: &str, base: &str) -> Text<'static> {
+    let mut page = Page::new(theme, url::Url::parse(base).ok());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        page.text(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let Some(end) = tag_end(rest) else {
+            break;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+        if tag.starts_with(['!', '?']) {
+            continue;
+        }
+        let (name, closing) = match tag.strip_prefix('/') {
+            Some(name) => (tag_name(name), true),
+            None => (tag_name(tag), false),
+        };
+        if closing {
+            page.close(&name);
+        } else if SKIPPED.contains(&name.as_str()) && !tag.ends_with('/') {
+            // their content may hold anything, `<` included
+            rest = raw_end(rest, &name);
+        } else {
+            page.open(&name, tag);
+        }
+    }
+    page.text(rest);
+    page.finish()
+}
+
+/// Where the tag opening `text` ends, skipping quoted attribute values.
+fn tag_end(text: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in text.char_indices().skip(1) {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn tag_name(tag: &str) -> String {
+    tag.split(|c: char| c.is_whitespace() || c == '/').next().unwrap_or_default().to_ascii_lowercase()
+}
+
+/// What follows the closing tag of raw text element `name` in `text`.
+fn raw_end<'a>(text: &'a str, name: &str) -> &'a str {
+    let closing = format!("</{}", name);
+    let Some(start) = text.to_ascii_lowercase().find(&closing) else {
+        return "";
+    };
+    let after = &text[start..];
+    after.find('>').map_or("", |end| &after[end + 1..])
+}
+
+/// The value of attribute `name` in the tag `tag`.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let mut rest = tag.split_once(|c: char| c.is_whitespace())?.1;
+    loop {
+        rest = rest.trim_start();
+        let end = rest.find(|c: char| c == '=' || c.is_whitespace() || c == '>' || c == '/').unwrap_or(rest.len());
+        if end == 0 {
+            rest = rest.get(1..)?;
+            continue;
+        }
+        let key = &rest[..end];
+        rest = rest[end..].trim_start();
+        let value = match rest.strip_prefix('=') {
+            Some(value) => {
+                let value = value.trim_start();
+                let (value, after) = match value.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let close = value[1..].find(quote).map_or(value.len(), |close| close + 1);
+                        (&value[1..close], value.get(close + 1..).unwrap_or_default())
+                    }
+                    _ => value.split_at(value.find(char::is_whitespace).unwrap_or(value.len())),
+                };
+                rest = after;
+                value
+            }
+            None => "",
+        };
+        if key.eq_ignore_ascii_case(name) {
+            return Some(decode_entities(value));
+        }
+    }
+}
+
+/// `text` with character references like `&amp;` and `&#233;` replaced.
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(';').filter(|&end| end <= 10);
+        let character = end.and_then(|end| match &rest[1..end] {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            "copy" => Some('©'),
+            "reg" => Some('®'),
+            "hellip" => Some('…'),
+            "mdash" => Some('—'),
+            "ndash" => Some('–'),
+            "lsquo" => Some('‘'),
+            "rsquo" => Some('’'),
+            "ldquo" => Some('“'),
+            "rdquo" => Some('”'),
+            "middot" => Some('·'),
+            "bull" => Some('•'),
+            entity => {
+                let number = entity.strip_prefix('#')?;
+                let code = match number.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => number.parse().ok()?,
+                };
+                char::from_u32(code)
+            }
+        });
+        match (character, end) {
+            (Some(character), Some(end)) => {
+                decoded.push(character);
+                rest = &rest[end + 1..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// The text of a document, built up as its tags go by.
+struct Page<'t> {
+    theme: &'t Theme,
+    base: Option<url::Url>,
+    lines: Vec<Line<'static>>,
+    current: Vec<Span<'static>>,
+    /// Whitespace was collapsed before the next word.
+    space: bool,
+    bold: usize,
+    italic: usize,
+    heading: Option<usize>,
+    /// Inside `<pre>`, where whitespace is kept.
+    pre: usize,
+    /// Counters of the open lists, `None` for bullets.
+    lists: Vec<Option<usize>>,
+    /// Target of the link being read.
+    link: Option<String>,
+    /// Link targets, numbered in order.
+    links: Vec<String>,
+    /// A table cell was written on the current line.
+    cell: bool,
+}
+
+impl<'t> Page<'t> {
+    fn new(theme: &'t Theme, base: Option<url::Url>) -> Self {
+        Self {
+            theme,
+            base,
+            lines: Vec::new(),
+            current: Vec::new(),
+            space: false,
+            bold: 0,
+            italic: 0,
+            heading: None,
+            pre: 0,
+            lists: Vec::new(),
+            link: None,
+            links: Vec::new(),
+            cell: false,
+        }
+    }
+
+    fn style(&self) -> Style {
+        let theme = self.theme;
+        let mut style = Style::default();
+        if self.heading.is_some() {
+            style = theme.fg(theme.title).add_modifier(Modifier::BOLD);
+        }
+        if self.link.is_some() {
+            style = theme.fg(theme.accent).add_modifier(Modifier::UNDERLINED);
+        }
+        if self.bold > 0 {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        if self.italic > 0 {
+            style = style.add_modifier(Modifier::ITALIC);
+        }
+        style
+    }
+
+    fn push(&mut self, text: String, style: Style) {
+        match self.current.last_mut() {
+            Some(last) if last.style == style => last.content.to_mut().push_str(&text),
+            _ => self.current.push(Span::styled(text, style)),
+        }
+    }
+
+    fn text(&mut self, raw: &str) {
+        let text = decode_entities(raw);
+        let style = self.style();
+        if self.pre > 0 {
+            let mut lines = text.split('\n');
+            if let Some(first) = lines.next() {
+                self.push(first.to_string(), style);
+            }
+            for line in lines {
+                self.break_line();
+                self.push(line.to_string(), style);
+            }
+            return;
+        }
+        let leading = text.starts_with(char::is_whitespace);
+        for (i, word) in text.split_whitespace().enumerate() {
+            // no space at the start of a line, nor after a list marker or cell separator
+            let space = (i > 0 || self.space || leading)
+                && self.current.last().is_some_and(|span| !span.content.ends_with(' '));
+            self.push(if space { format!(" {}", word) } else { word.to_string() }, style);
+        }
+        if !text.trim().is_empty() {
+            self.space = false;
+        }
+        if text.ends_with(char::is_whitespace) {
+            self.space = true;
+        }
+    }
+
+    fn break_line(&mut self) {
+        if !self.current.is_empty() {
+            self.lines.push(Line::from(std::mem::take(&mut self.current)));
+        }
+        self.space = false;
+        self.cell = false;
+    }
+
+    /// End the line and leave an empty one, unless there is one already.
+    fn paragraph(&mut self) {
+        self.break_line();
+        if self.lines.last().is_some_and(|line| line.width() > 0) {
+            self.lines.push(Line::default());
+        }
+    }
+
+    fn open(&mut self, name: &str, tag: &str) {
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.paragraph();
+                let level = name[1..].parse().unwrap_or(1);
+                self.heading = Some(level);
+                self.push(format!("{} ", "#".repeat(level)), self.style());
+            }
+            "p" | "blockquote" | "table" | "pre" | "ul" | "ol" | "dl" | "hr" if self.lists.is_empty() || name == "p" => {
+                self.paragraph();
+            }
+            "li" => {
+                self.break_line();
+                let depth = self.lists.len().saturating_sub(1);
+                let marker = match self.lists.last_mut() {
+                    Some(Some(counter)) => {
+                        *counter += 1;
+                        format!("{}. ", counter)
+                    }
+                    _ => "• ".to_string(),
+                };
+                self.push(format!("{}{}", "  ".repeat(depth), marker), self.theme.fg(self.theme.muted));
+            }
+            "td" | "th" => {
+                if self.cell {
+                    self.push(" │ ".to_string(), self.theme.fg(self.theme.muted));
+                }
+                self.cell = true;
+                self.space = false;
+            }
+            "img" => {
+                if let Some(alt) = attribute(tag, "alt").filter(|alt| !alt.trim().is_empty()) {
+                    let space = if self.current.is_empty() { "" } else { " " };
+                    self.push(format!("{}[{}]", space, alt.trim()), self.theme.fg(self.theme.muted));
+                }
+            }
+            "a" => {
+                self.link = attribute(tag, "href")
+                    .filter(|href| !href.starts_with('#') && !href.starts_with("javascript:"))
+                    .map(|href| match self.base.as_ref().and_then(|base| base.join(&href).ok()) {
+                        Some(url) => url.to_string(),
+                        None => href,
+                    });
+            }
+            "b" | "strong" => self.bold += 1,
+            "i" | "em" => self.italic += 1,
+            name if BLOCKS.contains(&name) => self.break_line(),
+            _ => {}
+        }
+        match name {
+            "ul" => self.lists.push(None),
+            "ol" => self.lists.push(Some(0)),
+            "pre" => self.pre += 1,
+            "hr" => {
+                self.push("────".to_string(), self.theme.fg(self.theme.muted));
+                self.break_line();
+            }
+            _ => {}
+        }
+    }
+
+    fn close(&mut self, name: &str) {
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.heading = None;
+                self.paragraph();
+            }
+            "p" | "blockquote" | "table" | "pre" | "dl" | "title" => self.paragraph(),
+            "ul" | "ol" => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.paragraph();
+                } else {
+                    self.break_line();
+                }
+            }
+            "a" => {
+                if let Some(link) = self.link.take() {
+                    let number = match self.links.iter().position(|known| *known == link) {
+                        Some(index) => index + 1,
+                        None => {
+                            self.links.push(link);
+                            self.links.len()
+                        }
+                    };
+                    self.push(format!("[{}]", number), self.theme.fg(self.theme.muted));
+                }
+            }
+            "b" | "strong" => self.bold = self.bold.saturating_sub(1),
+            "i" | "em" => self.italic = self.italic.saturating_sub(1),
+            name if BLOCKS.contains(&name) => self.break_line(),
+            _ => {}
+        }
+        if name == "pre" {
+            self.pre = self.pre.saturating_sub(1);
+        }
+    }
+
+    /// The lines, with the links listed at the end.
+    fn finish(mut self) -> Text<'static> {
+        self.paragraph();
+        for (i, link) in self.links.iter().enumerate() {
+            self.lines.push(Line::styled(format!("[{}] {}", i + 1, link), self.theme.fg(self.theme.muted)));
+        }
+        while self.lines.last().is_some_and(|line| line.width() == 0) {
+            self.lines.pop();
+        }
+        Text::from(self.lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_text() {
+        let html = r#"<!DOCTYPE html>
+<html><head><title>502 Bad Gateway</title>
+<style>body { color: red } a > b { }</style>
+<script>if (a < b) { document.write("<p>no</p>") }</script></head>
+<body>
+  <h1>Bad   Gateway</h1>
+  <!-- upstream: 10.0.0.7 -->
+  <p>The server <b>didn't</b> answer in time &mdash; see the
+     <a href="/status">status page</a> or <a href='https://help.test/502'>help</a>.</p>
+  <ul><li>Retry &amp; wait</li><li>Check <a href="/status">status</a></li></ul>
+  <ol><li>one</li><li>two</li></ol>
+  <table><tr><th>Code</th><th>Where</th></tr><tr><td>502</td><td>edge</td></tr></table>
+  <img src="x.png" alt="Logo"><br>
+  <pre>a  b
+  c</pre>
+</body></html>"#;
+        let text = text(&Theme::default(), html, "http://api.test/v1/users");
+        let lines: Vec<String> = text.lines.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "502 Bad Gateway",
+                "",
+                "# Bad Gateway",
+                "",
+                "The server didn't answer in time — see the status page[1] or help[2].",
+                "",
+                "• Retry & wait",
+                "• Check status[1]",
+                "",
+                "1. one",
+                "2. two",
+                "",
+                "Code │ Where",
+                "502 │ edge",
+                "",
+                "[Logo]",
+                "",
+                "a  b",
+                "  c",
+                "",
+                "[1] http://api.test/status",
+                "[2] https://help.test/502",
+            ]
+        );
+        assert_eq!(text.lines[4].spans[1].style.add_modifier, Modifier::BOLD);
+        assert!(is_html(Some("text/html; charset=utf-8")));
+        assert!(!is_html(Some("application/json")));
+    }
+}
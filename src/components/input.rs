@@ -1,47 +1,146 @@
+use std::time::Duration;
+
 use color_eyre::eyre::Ok;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::watch;
+
+use crossterm::event::{KeyModifiers, MouseButton, MouseEventKind};
+use ratatui::widgets::{Block, Borders, Paragraph};
 
-use crate::framework::{Action, Component, Updater};
+use super::{history::History, line_edit::LineEdit};
+use crate::{
+    app::Mode,
+    config::{Config, KeyBindings},
+    framework::{Action, Component, Updater},
+    state::SharedState,
+    theme::Theme,
+};
 
-pub type SharedFilter = Arc<RwLock<String>>;
+/// The filter as typed, and how long typing has to pause before it applies.
+#[derive(Debug, Default)]
+struct Typed {
+    text: String,
+    debounce: Duration,
+}
 
-#[derive(Clone, Debug)]
+#[derive(Debug, Default)]
 pub struct Input {
-    hostname: String,
-    cursor_position: usize,
+    line: LineEdit,
+    history: History,
     updater: Option<Updater>,
-    filter: Option<SharedFilter>,
+    filter: Option<watch::Sender<String>>,
+    /// How long typing has to pause before the filter is applied.
+    debounce: Duration,
+    /// Edits for the debounce timer, which applies the last one of a burst.
+    typed: watch::Sender<Typed>,
+    state: SharedState,
+    keybindings: KeyBindings,
+    focused: bool,
+    /// Where the filter box was last drawn, for click-to-focus.
+    area: ratatui::layout::Rect,
+    /// Columns of the filter scrolled out on the left.
+    scroll: u16,
+    theme: Theme,
 }
 
-impl Default for Input {
-    fn default() -> Self {
+impl Input {
+    pub fn new(filter: watch::Sender<String>, state: SharedState) -> Self {
         Self {
-            hostname: String::new(),
-            cursor_position: 0,
+            line: LineEdit::default(),
+            history: History::default(),
             updater: None,
-            filter: None,
+            filter: Some(filter),
+            debounce: Duration::ZERO,
+            typed: watch::Sender::default(),
+            state,
+            keybindings: KeyBindings::default(),
+            focused: false,
+            area: ratatui::layout::Rect::default(),
+            scroll: 0,
+            theme: Theme::default(),
         }
     }
 }
 
 impl Input {
-    pub fn new(filter: SharedFilter) -> Self {
-        Self {
-            hostname: String::new(),
-            cursor_position: 0,
-            updater: None,
-            filter: Some(filter),
+    /// Apply the filter once typing pauses for the debounce delay.
+    fn edited(&mut self) {
+        if self.debounce.is_zero() {
+            self.apply();
+            return;
+        }
+        self.typed.send_replace(Typed {
+            text: self.line.text().to_string(),
+            debounce: self.debounce,
+        });
+    }
+
+    /// Send the filter as typed to the list, so a pending edit has nothing left to apply.
+    fn apply(&mut self) {
+        if let Some(sender) = &self.filter {
+            send_filter(sender, self.line.text());
+        }
+        // without waking the timer, it would only send the same text again
+        self.typed.send_if_modified(|typed| {
+            typed.text = self.line.text().to_string();
+            false
+        });
+    }
+}
+
+fn send_filter(sender: &watch::Sender<String>, text: &str) {
+    sender.send_if_modified(|filter| {
+        let changed = filter != text;
+        if changed {
+            *filter = text.to_string();
+        }
+        changed
+    });
+}
+
+/// The debounce timer: every edit pushes the deadline back, and the filter
+/// is sent once it passes.
+async fn debounce(mut typed: watch::Receiver<Typed>, filter: watch::Sender<String>, updater: Updater) {
+    let timer = tokio::time::sleep(Duration::ZERO);
+    tokio::pin!(timer);
+    let mut pending = false;
+    loop {
+        tokio::select! {
+            changed = typed.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+                let debounce = typed.borrow_and_update().debounce;
+                timer.as_mut().reset(tokio::time::Instant::now() + debounce);
+                pending = true;
+            }
+            _ = &mut timer, if pending => {
+                pending = false;
+                send_filter(&filter, &typed.borrow().text);
+                updater.update();
+            }
+            _ = updater.cancelled() => break,
         }
     }
 }
 
 impl Component for Input {
+    fn component_will_mount(&mut self, config: Config) -> color_eyre::Result<()> {
+        self.keybindings = config.keybindings;
+        self.theme = config.theme;
+        self.debounce = Duration::from_millis(config.filter.debounce_ms);
+        let path = config.config.data_dir.join("filter_history");
+        self.history = History::load(config.filter.history, path);
+        Ok(())
+    }
+
     fn component_did_mount(
         &mut self,
         _area: ratatui::prelude::Size,
         updater: Updater,
     ) -> color_eyre::Result<()> {
+        if let Some(filter) = &self.filter {
+            updater.spawn(debounce(self.typed.subscribe(), filter.clone(), updater.clone()));
+        }
         self.updater = Some(updater);
         Ok(())
     }
@@ -51,91 +150,143 @@ impl Component for Input {
         frame: &mut ratatui::Frame,
         area: ratatui::prelude::Rect,
     ) -> color_eyre::Result<()> {
-        // Draw the input text
-        let input = ratatui::widgets::Paragraph::new(self.hostname.as_str());
+        self.area = area;
+        let block = Block::default()
+            .title("Filter (/ to focus)")
+            .borders(Borders::ALL)
+            .border_style(self.theme.pane(self.focused));
+        let inner = block.inner(area);
+
+        // Draw the input text, scrolled so that the cursor stays inside the box
+        let column = self.line.cursor_column() as u16;
+        self.scroll = self.scroll.min(column).max(column.saturating_sub(inner.width.saturating_sub(1)));
+        let input = Paragraph::new(self.line.text()).block(block).scroll((0, self.scroll));
         frame.render_widget(input, area);
 
-        // Set the native cursor position
-        frame.set_cursor_position((area.x + self.cursor_position as u16, area.y));
+        // Set the native cursor position, only while typing here
+        if self.focused {
+            frame.set_cursor_position((inner.x + column - self.scroll, inner.y));
+        }
 
         Ok(())
     }
 
+    fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+        if focused {
+            self.state.set_mode(Mode::Filter);
+        }
+    }
+
+    fn handle_mouse_event(
+        &mut self,
+        mouse: crossterm::event::MouseEvent,
+    ) -> color_eyre::Result<Option<Action>> {
+        let inside = self.area.contains(ratatui::layout::Position::new(mouse.column, mouse.row));
+        if !inside || mouse.kind != MouseEventKind::Down(MouseButton::Left) {
+            return Ok(None);
+        }
+        // place the cursor on the clicked character, past the border
+        self.line.click((mouse.column.saturating_sub(self.area.x + 1) + self.scroll) as usize);
+        Ok(Some(Action::FocusFilter))
+    }
+
+    fn captures_key(&self, key: crossterm::event::KeyEvent) -> bool {
+        // while focused every bound or printable key edits the filter
+        self.focused
+            && (self.keybindings.action_for(Mode::Filter, key).is_some()
+                || matches!(key.code, crossterm::event::KeyCode::Char(_))
+                    && (key.modifiers - KeyModifiers::SHIFT).is_empty())
+    }
+
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        match action {
+            Action::ConfigReloaded(reloaded) => {
+                self.component_will_mount(reloaded.config())?;
+                Ok(Some(Action::Render))
+            }
+            Action::SetFilter(filter) => {
+                self.line.set(&filter);
+                self.apply();
+                Ok(Some(Action::Render))
+            }
+            _ => Ok(None),
+        }
+    }
+
     fn handle_key_event(
         &mut self,
         key: crossterm::event::KeyEvent,
     ) -> color_eyre::Result<Option<crate::framework::Action>> {
-        // when push any key without modifier, add the character to the hostname
-        // When push backspace, remove the last character from the hostname
+        // bound keys edit the filter, any other plain character is typed into it
         let mut filter_changed = false;
         
-        if key.modifiers.is_empty() {
-            match key.code {
-                crossterm::event::KeyCode::Char(c) => {
-                    self.hostname.insert(self.cursor_position, c);
-                    self.cursor_position += c.len_utf8();
-                    filter_changed = true;
+        if let Some(action) = self.keybindings.action_for(Mode::Filter, key) {
+            match action {
+                Action::FocusList => {
+                    self.apply();
+                    self.history.push(self.line.text());
+                    return Ok(Some(Action::FocusList));
                 }
-                crossterm::event::KeyCode::Backspace => {
-                    if self.cursor_position > 0 {
-                        // Find the previous character boundary
-                        let mut new_pos = self.cursor_position - 1;
-                        while new_pos > 0 && !self.hostname.is_char_boundary(new_pos) {
-                            new_pos -= 1;
-                        }
-                        self.hostname.remove(new_pos);
-                        self.cursor_position = new_pos;
+                Action::HistoryPrev | Action::HistoryNext => {
+                    let recalled = if *action == Action::HistoryPrev {
+                        self.history.previous(self.line.text())
+                    } else {
+                        self.history.next()
+                    };
+                    if let Some(filter) = recalled {
+                        self.line.set(filter);
                         filter_changed = true;
                     }
                 }
-                crossterm::event::KeyCode::Left => {
-                    if self.cursor_position > 0 {
-                        // Move to previous character boundary
-                        let mut new_pos = self.cursor_position.saturating_sub(1);
-                        while new_pos > 0 && !self.hostname.is_char_boundary(new_pos) {
-                            new_pos -= 1;
-                        }
-                        self.cursor_position = new_pos;
-                    }
-                }
-                crossterm::event::KeyCode::Right => {
-                    if self.cursor_position < self.hostname.len() {
-                        // Move to next character boundary
-                        let mut new_pos = self.cursor_position + 1;
-                        while new_pos < self.hostname.len() && !self.hostname.is_char_boundary(new_pos) {
-                            new_pos += 1;
-                        }
-                        self.cursor_position = new_pos.min(self.hostname.len());
+                action => {
+                    filter_changed = self.line.apply(action);
+                    if filter_changed {
+                        self.history.reset();
                     }
                 }
-                crossterm::event::KeyCode::Home => {
-                    self.cursor_position = 0;
-                }
-                crossterm::event::KeyCode::End => {
-                    self.cursor_position = self.hostname.len();
-                }
-                crossterm::event::KeyCode::Delete => {
-                    if self.cursor_position < self.hostname.len() {
-                        self.hostname.remove(self.cursor_position);
-                        filter_changed = true;
-                    }
-                }
-                _ => {}
             }
+        } else if let crossterm::event::KeyCode::Char(c) = key.code
+            && (key.modifiers - KeyModifiers::SHIFT).is_empty()
+        {
+            self.line.insert(c);
+            self.history.reset();
+            filter_changed = true;
         }
         
-        // Update the shared filter if it changed
         if filter_changed {
-            let filter = self.filter.clone();
-            let hostname = self.hostname.clone();
-            tokio::spawn(async move {
-                if let Some(filter) = filter {
-                    let mut filter_guard = filter.write().await;
-                    *filter_guard = hostname;
-                }
-            });
+            self.edited();
         }
         
         Ok(Action::Render.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::framework::testing::Harness;
+
+    #[tokio::test]
+    async fn test_typing_applies_the_filter_once_settled() {
+        let (filter, mut filtered) = watch::channel(String::new());
+        let mut harness = Harness::new(Input::new(filter, SharedState::default()), 30, 3);
+        harness.component.set_focused(true);
+
+        harness.keys("users?x<backspace>");
+        // still debouncing
+        assert_eq!(*filtered.borrow(), "");
+        tokio::time::timeout(Duration::from_secs(1), filtered.changed()).await.unwrap().unwrap();
+        assert_eq!(*filtered.borrow_and_update(), "users?");
+        // the whole burst was one update
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(!filtered.has_changed().unwrap());
+        harness.settle().await;
+        harness.assert_snapshot("input");
+
+        harness.keys("<enter>");
+        assert_eq!(harness.emitted.last(), Some(&Action::FocusList));
+    }
+}
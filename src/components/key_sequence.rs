@@ -0,0 +1,129 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::{
+    app::Mode,
+    config::{KeyBindings, SequenceMatch, key_event_to_string},
+    framework::Action,
+};
+
+/// Largest count accepted before further digits are ignored.
+const MAX_COUNT: usize = 99_999;
+
+/// What a key press did to a [`KeySequence`].
+#[derive(Debug, PartialEq)]
+pub enum Step {
+    /// More keys are needed.
+    Pending,
+    /// A binding was completed, with the count typed before it.
+    Complete { count: Option<usize>, action: Action },
+    /// The keys match no binding; the sequence was dropped.
+    Unbound,
+}
+
+/// Vim-style key sequences: an optional count followed by the keys of a
+/// binding, e.g. `10j` or `gg`.
+#[derive(Debug, Default)]
+pub struct KeySequence {
+    count: Option<usize>,
+    keys: Vec<KeyEvent>,
+}
+
+impl KeySequence {
+    /// Whether a count or the start of a binding has been typed.
+    pub fn is_pending(&self) -> bool {
+        self.count.is_some() || !self.keys.is_empty()
+    }
+
+    /// Whether `key` would extend the sequence rather than drop it.
+    pub fn accepts(&self, bindings: &KeyBindings, mode: Mode, key: KeyEvent) -> bool {
+        self.count_digit(bindings, mode, key).is_some() || self.lookup(bindings, mode, key) != SequenceMatch::Unbound
+    }
+
+    pub fn push(&mut self, bindings: &KeyBindings, mode: Mode, key: KeyEvent) -> Step {
+        if let Some(digit) = self.count_digit(bindings, mode, key) {
+            let count = self.count.unwrap_or(0).saturating_mul(10).saturating_add(digit);
+            self.count = Some(count.min(MAX_COUNT));
+            return Step::Pending;
+        }
+        let step = match self.lookup(bindings, mode, key) {
+            SequenceMatch::Bound(action) => Step::Complete {
+                count: self.count,
+                action: action.clone(),
+            },
+            SequenceMatch::Prefix => {
+                self.keys.push(key);
+                return Step::Pending;
+            }
+            SequenceMatch::Unbound => Step::Unbound,
+        };
+        *self = Self::default();
+        step
+    }
+
+    /// The count and keys typed so far, like vim's `showcmd`.
+    pub fn display(&self) -> String {
+        let count = self.count.map(|count| count.to_string()).unwrap_or_default();
+        let keys: String = self.keys.iter().map(key_event_to_string).collect();
+        format!("{}{}", count, keys)
+    }
+
+    fn lookup<'a>(&self, bindings: &'a KeyBindings, mode: Mode, key: KeyEvent) -> SequenceMatch<'a> {
+        let mut keys = self.keys.clone();
+        keys.push(key);
+        bindings.match_sequence(mode, &keys)
+    }
+
+    /// The digit `key` adds to the count, unless it is bound itself. A leading
+    /// zero isn't a count.
+    fn count_digit(&self, bindings: &KeyBindings, mode: Mode, key: KeyEvent) -> Option<usize> {
+        let KeyCode::Char(c) = key.code else {
+            return None;
+        };
+        let digit = c.to_digit(10)? as usize;
+        let plain = (key.modifiers - KeyModifiers::SHIFT).is_empty();
+        let starts_count = digit != 0 || self.count.is_some();
+        (plain && starts_count && self.keys.is_empty() && bindings.action_for(mode, key).is_none()).then_some(digit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::config::parse_key_sequence;
+
+    fn bindings() -> KeyBindings {
+        let list = [("<j>", Action::SelectNext), ("<g><g>", Action::SelectFirst), ("<shift-g>", Action::SelectLast)]
+            .into_iter()
+            .map(|(keys, action)| (parse_key_sequence(keys).unwrap(), action))
+            .collect();
+        KeyBindings(HashMap::from([(Mode::List, list)]))
+    }
+
+    fn press(sequence: &mut KeySequence, keys: &str) -> Step {
+        let bindings = bindings();
+        let mut step = Step::Pending;
+        for c in keys.chars() {
+            let modifiers = if c.is_uppercase() { KeyModifiers::SHIFT } else { KeyModifiers::NONE };
+            step = sequence.push(&bindings, Mode::List, KeyEvent::new(KeyCode::Char(c), modifiers));
+        }
+        step
+    }
+
+    #[test]
+    fn test_counts_and_sequences() {
+        let mut sequence = KeySequence::default();
+        let complete = |count, action| Step::Complete { count, action };
+        assert_eq!(press(&mut sequence, "10j"), complete(Some(10), Action::SelectNext));
+        assert_eq!(press(&mut sequence, "g"), Step::Pending);
+        assert_eq!(sequence.display(), "g");
+        assert_eq!(press(&mut sequence, "g"), complete(None, Action::SelectFirst));
+        assert_eq!(press(&mut sequence, "0j"), complete(None, Action::SelectNext));
+        assert_eq!(press(&mut sequence, "3gx"), Step::Unbound);
+        assert!(!sequence.is_pending());
+        assert_eq!(press(&mut sequence, "25G"), complete(Some(25), Action::SelectLast));
+    }
+}
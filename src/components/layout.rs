@@ -1,34 +1,122 @@
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Instant;
+
+use tokio::sync::watch;
 
 use crate::{
-    components::{input::Input, proxy::Proxy, proxy_list::ProxyList},
-    framework::{Children, Component},
+    components::{cookies::Cookies, help::Help, input::Input, proxy::Proxy, proxy_list::ProxyList, rules::Rules, stats::Stats},
+    components::{clients::Clients, command_line::CommandLine, composer::Composer, sessions::Sessions, timeline::Timeline},
+    components::{split::SplitPane, status_bar::StatusBar, transfers::{self, TransferList}},
+    components::bandwidth::BandwidthGraph,
+    framework::{Action, Children, Component, FocusManager},
+    state::SharedState,
+    transfers::Transfers,
+    tui::Event,
 };
 
+/// Height of the filter box, including its border.
+const FILTER_HEIGHT: u16 = 3;
+/// Height of the timeline, including its border.
+const TIMELINE_HEIGHT: u16 = 7;
+/// Height of the bandwidth graph, including its border.
+const BANDWIDTH_HEIGHT: u16 = 10;
+const STATUS_HEIGHT: u16 = 1;
+/// Height the request list keeps, borders included, before the bandwidth
+/// graph and transfers give way to it in a short terminal.
+const MIN_LIST_HEIGHT: u16 = 6;
+
+// indices into `Layout::children`
+const LIST: usize = 1;
+const INPUT: usize = 2;
+const STATS: usize = 3;
+const HELP: usize = 4;
+const STATUS: usize = 5;
+const COOKIES: usize = 6;
+const COMMAND: usize = 7;
+const RULES: usize = 8;
+const COMPOSER: usize = 9;
+const SESSIONS: usize = 10;
+const TIMELINE: usize = 11;
+const TRANSFERS: usize = 12;
+const BANDWIDTH: usize = 13;
+const CLIENTS: usize = 14;
+/// Modal overlays, topmost last.
+const OVERLAYS: [usize; 7] = [HELP, COOKIES, RULES, SESSIONS, CLIENTS, COMPOSER, COMMAND];
+
 pub struct Layout {
     children: Vec<Box<dyn Component>>,
+    show_stats: bool,
+    show_timeline: bool,
+    show_bandwidth: bool,
+    /// The transfers panel shows up while a download runs, unless hidden.
+    hide_transfers: bool,
+    transfers: Transfers,
+    focus: FocusManager,
+    /// Split between the request list and the statistics panel.
+    stats_split: SplitPane,
 }
 
 impl Default for Layout {
     fn default() -> Self {
         // Create shared filter state
-        let filter = Arc::new(RwLock::new(String::new()));
+        let (filter_tx, filter_rx) = watch::channel(String::new());
         
+        // State shown in the status bar
+        let state = SharedState::default();
+
         // Create the proxy component and get shared logs
-        let proxy = Proxy::default();
-        let log = proxy.get_logs();
+        let proxy = Proxy::new(state.clone());
+        let log = proxy.subscribe();
         
         // Create components with shared state
-        let input = Input::new(filter.clone());
-        let proxy_list = ProxyList::new(log, filter);
+        let input = Input::new(filter_tx, state.clone());
+        let stats = Stats::new(log.clone(), proxy.connections());
+        let transfers = proxy.transfers();
+        let bandwidth = BandwidthGraph::new(proxy.connections());
+        let cookies = Cookies::new(log.clone());
+        let status_bar = StatusBar::new(state.clone(), log.clone(), filter_rx.clone());
+        let command_line = CommandLine::new(state.clone());
+        let rules = Rules::new(state.clone());
+        let composer = Composer::new(state.clone());
+        let sessions = Sessions::new(log.clone(), state.clone());
+        let clients = Clients::new(state.clone());
+        let timeline = Timeline::new(log.clone(), state.clone());
+        let proxy_list = ProxyList::new(log, filter_rx, state);
 
         Self {
             children: vec![
                 Box::new(proxy), 
                 Box::new(proxy_list),
                 Box::new(input), 
+                Box::new(stats),
+                Box::new(Help::default()),
+                Box::new(status_bar),
+                Box::new(cookies),
+                Box::new(command_line),
+                Box::new(rules),
+                Box::new(composer),
+                Box::new(sessions),
+                Box::new(timeline),
+                Box::new(TransferList::new(transfers.clone())),
+                Box::new(bandwidth),
+                Box::new(clients),
             ],
+            show_stats: false,
+            show_timeline: false,
+            show_bandwidth: false,
+            hide_transfers: false,
+            transfers,
+            focus: FocusManager::new(vec![LIST, INPUT]),
+            stats_split: SplitPane::default(),
+        }
+    }
+}
+
+impl Layout {
+    // tell every focusable child whether it holds the focus now
+    fn sync_focus(&mut self) {
+        for index in [LIST, INPUT, TIMELINE] {
+            let focused = self.focus.is_focused(index);
+            self.children[index].set_focused(focused);
         }
     }
 }
@@ -41,6 +129,7 @@ impl Children for Layout {
 
 impl Component for Layout {
     fn component_will_mount(&mut self, config: crate::config::Config) -> color_eyre::Result<()> {
+        self.stats_split = SplitPane::from_config(&config.layout);
         self.children_will_mount(config)
     }
 
@@ -49,6 +138,7 @@ impl Component for Layout {
         area: ratatui::prelude::Size,
         updater: crate::framework::Updater,
     ) -> color_eyre::Result<()> {
+        self.sync_focus();
         self.children_did_mount(area, updater)
     }
 
@@ -56,7 +146,77 @@ impl Component for Layout {
         &mut self,
         event: Option<crate::tui::Event>,
     ) -> color_eyre::Result<Option<crate::framework::Action>> {
-        let action = self.propagate_events(event)?;
+        // an open overlay takes the keys it captures before the focused pane
+        let modal = OVERLAYS.into_iter().rev().find(|&index| match &event {
+            Some(Event::Key(key)) => self.children[index].captures_key(*key),
+            Some(Event::Mouse(mouse)) => self.children[index].captures_mouse(*mouse),
+            _ => false,
+        });
+        if let Some(index) = modal {
+            return self.children[index].handle_events(event);
+        }
+        let action = self.propagate_focused_events(event, self.focus.focused())?;
+        Ok(action.into_iter().next())
+    }
+
+    fn take_commands(&mut self) -> Vec<crate::framework::Command> {
+        self.children_commands()
+    }
+
+    fn captures_key(&self, key: crossterm::event::KeyEvent) -> bool {
+        OVERLAYS.iter().any(|&index| self.children[index].captures_key(key))
+            || self
+                .focus
+                .focused()
+                .is_some_and(|index| self.children[index].captures_key(key))
+    }
+
+    fn update(
+        &mut self,
+        action: crate::framework::Action,
+    ) -> color_eyre::Result<Option<crate::framework::Action>> {
+        match action {
+            Action::ToggleStats => self.show_stats = !self.show_stats,
+            Action::ToggleTransfers => self.hide_transfers = !self.hide_transfers,
+            Action::ToggleBandwidth => self.show_bandwidth = !self.show_bandwidth,
+            // the timeline takes the focus while it is shown, tab moves on from it
+            Action::ToggleTimeline => {
+                self.show_timeline = !self.show_timeline;
+                if self.show_timeline {
+                    self.focus = FocusManager::new(vec![LIST, TIMELINE, INPUT]);
+                    self.focus.focus(TIMELINE);
+                } else {
+                    self.focus = FocusManager::new(vec![LIST, INPUT]);
+                }
+                self.sync_focus();
+                return Ok(Some(Action::Render));
+            }
+            Action::ResizeLeft | Action::ResizeRight | Action::ResizeUp | Action::ResizeDown => {
+                if self.show_stats && self.stats_split.resize(&action) {
+                    return Ok(Some(Action::Render));
+                }
+                return Ok(None);
+            }
+            Action::FocusNext => self.focus.next(),
+            Action::FocusPrev => self.focus.prev(),
+            Action::FocusFilter => {
+                self.focus.focus(INPUT);
+            }
+            // the clear confirmation is answered from the list
+            Action::FocusList | Action::ClearSession => {
+                self.focus.focus(LIST);
+            }
+            _ => {}
+        }
+        if matches!(
+            action,
+            Action::FocusNext | Action::FocusPrev | Action::FocusFilter | Action::FocusList
+        ) {
+            self.sync_focus();
+            return Ok(Some(Action::Render));
+        }
+        self.sync_focus();
+        let action = self.propagate_update(action)?;
         Ok(action.into_iter().next())
     }
 
@@ -65,24 +225,62 @@ impl Component for Layout {
         frame: &mut ratatui::Frame,
         area: ratatui::prelude::Rect,
     ) -> color_eyre::Result<()> {
-        // on top we render one line for the input
-        let input_area = ratatui::prelude::Rect {
-            x: area.x,
-            y: 0,
-            width: area.width,
-            height: area.height - 1,
+        // the request list takes everything above the filter box
+        let [main_area, filter_area, status_area] = ratatui::layout::Layout::vertical([
+            ratatui::layout::Constraint::Min(0),
+            ratatui::layout::Constraint::Length(FILTER_HEIGHT),
+            ratatui::layout::Constraint::Length(STATUS_HEIGHT),
+        ])
+        .areas(area);
+        let main_area = if self.show_timeline {
+            let [main_area, timeline_area] = ratatui::layout::Layout::vertical([
+                ratatui::layout::Constraint::Min(0),
+                ratatui::layout::Constraint::Length(TIMELINE_HEIGHT),
+            ])
+            .areas(main_area);
+            self.children[TIMELINE].render(frame, timeline_area)?;
+            main_area
+        } else {
+            main_area
+        };
+        let main_area = if self.show_bandwidth && main_area.height >= BANDWIDTH_HEIGHT + MIN_LIST_HEIGHT {
+            let [main_area, bandwidth_area] = ratatui::layout::Layout::vertical([
+                ratatui::layout::Constraint::Min(0),
+                ratatui::layout::Constraint::Length(BANDWIDTH_HEIGHT),
+            ])
+            .areas(main_area);
+            self.children[BANDWIDTH].render(frame, bandwidth_area)?;
+            main_area
+        } else {
+            main_area
         };
-        self.children[1].render(frame, input_area)?;
-
-        // render proxy list on remaining area
-        let proxy_area = ratatui::prelude::Rect {
-            x: area.x,
-            y: area.height - 1,
-            width: area.width,
-            height: 1,
+        let shown = if self.hide_transfers { 0 } else { self.transfers.shown(Instant::now()).len() };
+        let height = shown.min(transfers::MAX_ROWS) as u16 + 2;
+        let main_area = if shown > 0 && main_area.height >= height + MIN_LIST_HEIGHT {
+            let [main_area, transfers_area] = ratatui::layout::Layout::vertical([
+                ratatui::layout::Constraint::Min(0),
+                ratatui::layout::Constraint::Length(height),
+            ])
+            .areas(main_area);
+            self.children[TRANSFERS].render(frame, transfers_area)?;
+            main_area
+        } else {
+            main_area
         };
+        if self.show_stats {
+            let [list_area, stats_area] = self.stats_split.areas(main_area);
+            self.children[LIST].render(frame, list_area)?;
+            self.children[STATS].render(frame, stats_area)?;
+        } else {
+            self.children[LIST].render(frame, main_area)?;
+        }
+        self.children[INPUT].render(frame, filter_area)?;
+        self.children[STATUS].render(frame, status_area)?;
 
-        self.children[2].render(frame, proxy_area)?;
+        // overlays are drawn last so they sit above everything else
+        for index in OVERLAYS {
+            self.children[index].render(frame, area)?;
+        }
 
         Ok(())
     }
@@ -0,0 +1,186 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::framework::Action;
+
+/// A single line of text and its cursor, as a byte offset between grapheme
+/// clusters, so that an emoji or a letter with combining marks moves and
+/// deletes as one character.
+#[derive(Debug, Default, Clone)]
+pub struct LineEdit {
+    text: String,
+    cursor: usize,
+}
+
+impl LineEdit {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Terminal column of the cursor: wide characters like CJK take two.
+    pub fn cursor_column(&self) -> usize {
+        self.text[..self.cursor].width()
+    }
+
+    /// Replace the text, with the cursor at its end.
+    pub fn set(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.text.len();
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    /// Whether the cursor is before the first character.
+    pub fn at_start(&self) -> bool {
+        self.cursor == 0
+    }
+
+    /// Cut the text after the cursor into a line of its own, as `enter` does
+    /// in a multi-line editor.
+    pub fn split_off(&mut self) -> LineEdit {
+        let text = self.text.split_off(self.cursor);
+        LineEdit { text, cursor: 0 }
+    }
+
+    /// Add `other` at the end, with the cursor where they meet.
+    pub fn join(&mut self, other: &LineEdit) {
+        self.cursor = self.text.len();
+        self.text.push_str(&other.text);
+    }
+
+    /// Put the cursor on the character drawn at terminal `column`, or at the end.
+    pub fn click(&mut self, column: usize) {
+        let mut start = 0;
+        for (index, grapheme) in self.text.grapheme_indices(true) {
+            start += grapheme.width();
+            if start > column {
+                self.cursor = index;
+                return;
+            }
+        }
+        self.cursor = self.text.len();
+    }
+
+    /// Apply a cursor movement or deletion; returns whether the text changed.
+    pub fn apply(&mut self, action: &Action) -> bool {
+        match action {
+            Action::DeleteBackward if self.cursor > 0 => {
+                let start = self.previous_boundary();
+                self.delete(start..self.cursor)
+            }
+            Action::DeleteForward if self.cursor < self.text.len() => {
+                let end = self.next_boundary();
+                self.delete(self.cursor..end)
+            }
+            Action::DeleteWordBackward if self.cursor > 0 => {
+                let start = self.word_start();
+                self.delete(start..self.cursor)
+            }
+            Action::CursorLeft => self.move_to(self.previous_boundary()),
+            Action::CursorRight => self.move_to(self.next_boundary()),
+            Action::WordLeft => self.move_to(self.word_start()),
+            Action::WordRight => self.move_to(self.word_end()),
+            Action::CursorStart => self.move_to(0),
+            Action::CursorEnd => self.move_to(self.text.len()),
+            _ => false,
+        }
+    }
+
+    fn move_to(&mut self, cursor: usize) -> bool {
+        self.cursor = cursor;
+        false
+    }
+
+    fn delete(&mut self, range: std::ops::Range<usize>) -> bool {
+        self.cursor = range.start;
+        self.text.replace_range(range, "");
+        true
+    }
+
+    fn previous_boundary(&self) -> usize {
+        self.text[..self.cursor]
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(index, _)| index)
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.text[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map_or(self.cursor, |grapheme| self.cursor + grapheme.len())
+    }
+
+    /// Start of the word before the cursor, skipping separators like `/` first.
+    fn word_start(&self) -> usize {
+        let mut graphemes = self.text[..self.cursor].grapheme_indices(true).rev().peekable();
+        while graphemes.next_if(|(_, g)| !is_word(g)).is_some() {}
+        let mut start = graphemes.peek().map_or(0, |(index, g)| index + g.len());
+        while let Some((index, _)) = graphemes.next_if(|(_, g)| is_word(g)) {
+            start = index;
+        }
+        start
+    }
+
+    /// End of the word after the cursor, skipping separators first.
+    fn word_end(&self) -> usize {
+        let mut graphemes = self.text[self.cursor..].grapheme_indices(true).peekable();
+        while graphemes.next_if(|(_, g)| !is_word(g)).is_some() {}
+        while graphemes.next_if(|(_, g)| is_word(g)).is_some() {}
+        graphemes.peek().map_or(self.text.len(), |(index, _)| self.cursor + index)
+    }
+}
+
+fn is_word(grapheme: &str) -> bool {
+    grapheme.chars().next().is_some_and(|c| c.is_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn typed(text: &str) -> LineEdit {
+        let mut line = LineEdit::default();
+        text.chars().for_each(|c| line.insert(c));
+        line
+    }
+
+    #[test]
+    fn test_wide_and_combined_characters() {
+        // a family emoji is one grapheme of several chars, CJK is two columns wide
+        let mut line = typed("日本👨‍👩‍👧e\u{301}");
+        assert_eq!(line.cursor_column(), 7);
+        line.apply(&Action::CursorLeft);
+        assert_eq!(line.cursor_column(), 6);
+        line.apply(&Action::DeleteBackward);
+        assert_eq!(line.text(), "日本e\u{301}");
+        line.click(3);
+        assert_eq!(line.cursor_column(), 2);
+        line.insert('x');
+        assert_eq!(line.text(), "日x本e\u{301}");
+    }
+
+    #[test]
+    fn test_word_movement_and_deletion() {
+        let mut line = typed("api.example.com/v1/users");
+        line.apply(&Action::WordLeft);
+        assert_eq!(line.cursor_column(), 19);
+        line.apply(&Action::WordLeft);
+        line.apply(&Action::WordRight);
+        assert_eq!(line.cursor_column(), 18);
+        line.apply(&Action::CursorEnd);
+        line.apply(&Action::DeleteWordBackward);
+        line.apply(&Action::DeleteWordBackward);
+        assert_eq!(line.text(), "api.example.com/");
+    }
+}
@@ -1,11 +1,38 @@
 // Re-export framework traits for convenience
-pub use crate::framework::{Component};
+pub use crate::framework::Component;
 
-pub mod home;
-pub mod counter;
-pub mod auto_counter;
-pub mod random_text;
 pub mod proxy;
 pub mod proxy_list;
+pub mod repeats;
+pub mod rules;
+pub mod sessions;
+pub mod setup;
+pub mod host_tree;
+pub mod stats;
+pub mod bandwidth;
+pub mod body_format;
+pub mod charset;
+pub mod clients;
+pub mod command_line;
+pub mod composer;
+pub mod cookies;
+pub mod diff;
+pub mod environment;
+pub mod filter;
+pub mod graphql;
+pub mod grpc;
+pub mod help;
+pub mod history;
+pub mod html;
 pub mod input;
-pub mod layout;
\ No newline at end of file
+pub mod key_sequence;
+pub mod layout;
+pub mod line_edit;
+pub mod multipart;
+pub mod sort;
+pub mod split;
+pub mod sse;
+pub mod status_bar;
+pub mod timeline;
+pub mod trace;
+pub mod transfers;
\ No newline at end of file
@@ -0,0 +1,235 @@
+//! `multipart/form-data` bodies split into their parts, as browsers send
+//! forms with file uploads.
+
+use hyper::body::Bytes;
+use ratatui::prelude::*;
+
+use super::body_format::format_body;
+use super::stats::bytes;
+use crate::theme::Theme;
+
+const INDENT: &str = "    ";
+
+/// The boundary of a `multipart/form-data` content type.
+pub fn boundary(content_type: Option<&str>) -> Option<String> {
+    let mut params = content_type?.split(';');
+    if !params.next()?.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    params.find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim().eq_ignore_ascii_case("boundary").then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+/// One field or file of a form.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FormPart {
+    pub name: Option<String>,
+    /// Set for file uploads.
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+    pub headers: Vec<(String, String)>,
+    pub body: Bytes,
+}
+
+impl FormPart {
+    /// A name to save the part under: its file name, else the field name.
+    pub fn file_name(&self) -> String {
+        let name = self.filename.as_deref().or(self.name.as_deref()).unwrap_or("part");
+        // only the last segment, uploads sometimes carry a client-side path
+        name.rsplit(['/', '\\']).next().filter(|name| !name.is_empty()).unwrap_or("part").to_string()
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// Split `body` into its parts. Lines may end in a bare `\n` as well as the
+/// `\r\n` the format asks for, since hand-written requests often do.
+pub fn parse(body: &Bytes, boundary: &str) -> Result<Vec<FormPart>, String> {
+    let delimiter = format!("--{}", boundary);
+    let delimiter = delimiter.as_bytes();
+    let mut at = find(body, delimiter).ok_or("no boundary in the body")? + delimiter.len();
+    let mut parts = Vec::new();
+    loop {
+        let rest = &body[at..];
+        if rest.starts_with(b"--") {
+            return Ok(parts);
+        }
+        // the delimiter line may be padded with whitespace
+        let line_end = find(rest, b"\n").ok_or("body ends after a boundary")?;
+        at += line_end + 1;
+        let rest = &body[at..];
+        let (head_len, separator) = match (find(rest, b"\r\n\r\n"), find(rest, b"\n\n")) {
+            // a part without headers starts with the blank line
+            _ if rest.starts_with(b"\r\n") => (0, 2),
+            _ if rest.starts_with(b"\n") => (0, 1),
+            (Some(crlf), Some(lf)) if lf < crlf => (lf, 2),
+            (Some(crlf), _) => (crlf, 4),
+            (None, Some(lf)) => (lf, 2),
+            (None, None) => return Err(format!("part {} has no end of headers", parts.len() + 1)),
+        };
+        let head = String::from_utf8_lossy(&rest[..head_len]);
+        let start = at + head_len + separator;
+        let end = find(&body[start..], delimiter)
+            .map(|len| start + len)
+            .ok_or_else(|| format!("part {} is cut off", parts.len() + 1))?;
+        // the line break before the next delimiter belongs to it
+        let content = &body[start..end];
+        let content = content.strip_suffix(b"\n").unwrap_or(content);
+        let content = content.strip_suffix(b"\r").unwrap_or(content);
+        let mut part = FormPart {
+            body: body.slice_ref(content),
+            ..Default::default()
+        };
+        for line in head.lines().filter(|line| !line.trim().is_empty()) {
+            let Some((name, value)) = line.split_once(':') else {
+                continue;
+            };
+            let (name, value) = (name.trim(), value.trim());
+            if name.eq_ignore_ascii_case("content-disposition") {
+                part.name = parameter(value, "name");
+                part.filename = parameter(value, "filename*")
+                    .and_then(|value| percent_decode(value.split_once("''").map_or(value.as_str(), |(_, v)| v)))
+                    .or_else(|| parameter(value, "filename"));
+            } else if name.eq_ignore_ascii_case("content-type") {
+                part.content_type = Some(value.to_string());
+            }
+            part.headers.push((name.to_string(), value.to_string()));
+        }
+        parts.push(part);
+        at = end + delimiter.len();
+    }
+}
+
+/// The value of parameter `name` of a header like `form-data; name="a"`,
+/// unquoted. Quoted values may contain `;`.
+fn parameter(header: &str, name: &str) -> Option<String> {
+    let mut rest = header.split_once(';')?.1;
+    loop {
+        rest = rest.trim_start();
+        let (key, after) = rest.split_once('=')?;
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let mut value = String::new();
+                let mut chars = quoted.char_indices();
+                let end = loop {
+                    match chars.next()? {
+                        (_, '\\') => value.push(chars.next()?.1),
+                        (i, '"') => break i + 1,
+                        (_, c) => value.push(c),
+                    }
+                };
+                (value, &quoted[end..])
+            }
+            None => {
+                let end = after.find(';').unwrap_or(after.len());
+                (after[..end].trim().to_string(), &after[end..])
+            }
+        };
+        if key.trim().eq_ignore_ascii_case(name) {
+            return Some(value);
+        }
+        rest = after.split_once(';')?.1;
+    }
+}
+
+/// `%XX` escapes decoded, for `filename*=UTF-8''na%C3%AFve.txt`.
+fn percent_decode(value: &str) -> Option<String> {
+    let mut decoded = Vec::new();
+    let mut bytes = value.bytes();
+    while let Some(byte) = bytes.next() {
+        if byte == b'%' {
+            let hex = [bytes.next()?, bytes.next()?];
+            decoded.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            decoded.push(byte);
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// One line per part, with the expanded ones followed by their headers and
+/// body. Binary bodies are only sized.
+pub fn format_parts(theme: &Theme, parts: &[FormPart], selected: usize, expanded: &[bool]) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    for (index, part) in parts.iter().enumerate() {
+        let open = expanded.get(index).copied().unwrap_or(false);
+        let mut spans = vec![
+            Span::styled(if open { "▼ " } else { "▶ " }, theme.fg(theme.accent)),
+            Span::styled(
+                part.name.clone().unwrap_or_else(|| "(unnamed)".to_string()),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+        ];
+        if let Some(filename) = &part.filename {
+            spans.push(Span::raw(format!("  {}", filename)));
+        }
+        if let Some(content_type) = &part.content_type {
+            spans.push(Span::styled(format!("  {}", content_type), theme.fg(theme.muted)));
+        }
+        spans.push(Span::styled(format!("  {}", bytes(part.body.len() as u64)), theme.fg(theme.muted)));
+        let line = Line::from(spans);
+        if index == selected {
+            lines.push(line.style(Style::default().add_modifier(Modifier::REVERSED)));
+        } else {
+            lines.push(line);
+        }
+        if !open {
+            continue;
+        }
+        for (name, value) in &part.headers {
+            lines.push(Line::from(vec![
+                Span::raw(INDENT),
+                Span::styled(format!("{}: ", name), theme.fg(theme.accent)),
+                Span::raw(value.clone()),
+            ]));
+        }
+        let body = match std::str::from_utf8(&part.body) {
+            Ok(text) => format_body(theme, part.content_type.as_deref(), text).lines,
+            Err(_) => {
+                let size = bytes(part.body.len() as u64);
+                vec![Line::styled(format!("[{} of binary data]", size), theme.fg(theme.muted))]
+            }
+        };
+        lines.extend(body.into_iter().map(|mut line| {
+            line.spans.insert(0, Span::raw(INDENT));
+            line
+        }));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_form() {
+        assert_eq!(boundary(Some("multipart/form-data; boundary=\"xyz\"")).as_deref(), Some("xyz"));
+        assert_eq!(boundary(Some("multipart/mixed; boundary=xyz")), None);
+
+        let body = Bytes::from_static(
+            b"preamble\r\n--xyz\r\nContent-Disposition: form-data; name=\"note\"\r\n\r\nhi; there\r\n\
+              --xyz  \r\nContent-Disposition: form-data; name=\"file\"; filename=\"C:\\\\tmp\\\\a;b.bin\"\r\n\
+              Content-Type: application/octet-stream\r\n\r\n\x00\x01\r\n--xyz\r\n\
+              Content-Disposition: form-data; name=\"doc\"; filename*=UTF-8''na%C3%AFve.txt\n\nline\n--xyz--\r\n",
+        );
+        let parts = parse(&body, "xyz").unwrap();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0].name.as_deref(), Some("note"));
+        assert_eq!(parts[0].body, Bytes::from("hi; there"));
+        assert_eq!(parts[1].filename.as_deref(), Some("C:\\tmp\\a;b.bin"));
+        assert_eq!(parts[1].file_name(), "a;b.bin");
+        assert_eq!(parts[1].content_type.as_deref(), Some("application/octet-stream"));
+        assert_eq!(parts[1].body, Bytes::from_static(b"\x00\x01"));
+        assert_eq!(parts[2].filename.as_deref(), Some("naïve.txt"));
+        assert_eq!(parts[2].body, Bytes::from("line"));
+
+        assert_eq!(parse(&Bytes::from("--xyz\r\n\r\nno end"), "xyz"), Err("part 1 is cut off".to_string()));
+    }
+}
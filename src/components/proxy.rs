@@ -1,121 +1,863 @@
 use std::collections::VecDeque;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
-use std::path::PathBuf;
-use tokio::sync::RwLock;
-use tracing::{info, error};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock, mpsc, watch};
+use tracing::{info, error, warn};
 use hyper::server::conn::http1;
 use hyper::service::service_fn;
-use hyper::{Request, Response, body::Incoming, StatusCode, Method};
-use hyper_util::rt::TokioIo;
-use tokio::net::TcpListener;
-use http_body_util::{Full, BodyExt};
+use hyper::{HeaderMap, Request, Response, body::Incoming, StatusCode, Method, header::HeaderValue};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use hyper_util::client::legacy::{Client, connect::HttpConnector};
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use http_body_util::{BodyExt, Full, StreamBody, combinators::BoxBody};
 use hyper::body::Bytes;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Local, Utc};
+use futures::{StreamExt, TryStreamExt, stream};
+use hyper::body::Frame;
+use encoding_rs::UTF_8;
+use tokio_util::io::ReaderStream;
 use tokio::fs::{self, OpenOptions};
 use tokio::io::AsyncWriteExt;
 
 use super::Component;
-use crate::{config::Config, framework::Updater};
+use super::host_tree::host_of;
+use super::charset;
+use super::grpc;
+use super::graphql::{self, GraphqlOperation};
+use super::sse::{self, SseEvent, SseParser};
+use super::trace;
+use crate::{
+    access_log::{AccessLog, AccessRecord},
+    api::{self, ApiContext},
+    assertions::{self, SharedAssertions},
+    clients::{ClientNames, ClientProfiles},
+    compression::{self, BodyWriter},
+    config::{
+        CaptureConfig, Config, DnsConfig, EvictionPolicy, OtlpConfig, ProxyConfig, ReplayConfig, ReplayMiss,
+        RestorePolicy, SESSION_FORMAT, UpstreamConfig,
+    },
+    connections::{ConnectionStats, CountingConnector, TlsVersions},
+    control,
+    dns::{HostMap, MappedConnector, Resolver},
+    faults::{self, Fault, SharedFaults},
+    framework::{Action, Command, Updater},
+    limits::{Limiter, Permit, Rejected},
+    listener,
+    openapi,
+    otlp::{Exporter, SpanStart},
+    pac,
+    recovery,
+    replay,
+    retention,
+    redact::RedactConfig,
+    retry::{ATTEMPT_HEADER, Attempt},
+    rewrite::{self, SharedRules},
+    scripts::{self, Scripts, SharedScripts},
+    session,
+    state::SharedState,
+    tee::Tee,
+    tls,
+    transfers::{Progress, Transfers},
+};
 
-#[derive(Clone, Debug)]
-#[allow(dead_code)]
+#[derive(Clone, Debug, Default)]
 pub struct HttpLog {
+    /// Unique, monotonically increasing id of the exchange.
+    pub id: u64,
     pub method: String,
     pub uri: String,
     pub timestamp: DateTime<Utc>,
-    pub path: String,
+    /// Capture file the exchange is written to once the response arrives.
+    pub path: PathBuf,
+    /// Upstream response status, once the response has arrived.
+    pub status: Option<u16>,
+    /// Time between receiving the request and receiving the full response.
+    pub duration: Option<Duration>,
+    /// Pinned requests are listed first and never evicted.
+    pub pinned: bool,
+    /// The operation, for GraphQL requests.
+    pub graphql: Option<Arc<GraphqlOperation>>,
+    /// The request's `Cookie` header.
+    pub cookie: Option<String>,
+    /// Every `Set-Cookie` header of the response.
+    pub set_cookies: Vec<String>,
+    /// Set by an `on_response` script.
+    pub tag: Option<String>,
+    /// The fault a fault rule injected, if any.
+    pub fault: Option<String>,
+    /// Label of the additional listener the request arrived on.
+    pub listener: Option<String>,
+    /// Address and port of the client that sent the request; unknown for
+    /// requests restored from captures written before it was recorded.
+    pub client: Option<SocketAddr>,
+    /// The client's label from `clients.labels`, else its host name.
+    pub client_name: Option<String>,
+    /// Label of the named session recorded when the request arrived.
+    pub session: Option<String>,
+    /// Hash of the request body, telling apart requests to the same URL.
+    pub body_hash: u64,
+    /// The latest earlier request with the same method, URL and body, when
+    /// its response had a different status than this one.
+    pub differs_from: Option<u64>,
+    /// Bytes in the response body, once the response has arrived.
+    pub size: Option<u64>,
+    /// Trace id or `X-Request-Id` the request shares with the other
+    /// requests of one flow across services.
+    pub trace_id: Option<String>,
+    /// Set on the attempts of a retry started from the list.
+    pub attempt: Option<Attempt>,
+    /// What the response breaks of the assertion rules.
+    pub failed_assertions: Vec<String>,
+}
+
+static NEXT_LOG_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Longest file name we write, leaving room for extensions under the usual
+/// 255 byte limit.
+const MAX_FILE_NAME: usize = 200;
+
+/// Names Windows keeps for devices, whatever the extension: `nul.txt` is NUL too.
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "LPT1", "LPT2",
+    "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Capture file lines pointing at a body stored in a separate file.
+const BINARY_BODY: &str = "[Binary data stored in: ";
+const SPILLED_BODY: &str = "[Body stored in: ";
+/// Capture file line pointing at the request body, stored next to it.
+const REQUEST_BODY: &str = "[Request body stored in: ";
+
+/// The body of either side of an exchange.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BodyPart {
+    Request,
+    Response,
+}
+
+/// An exchange read back from its capture file.
+#[derive(Clone, Debug, Default)]
+pub struct CapturedExchange {
+    pub method: String,
+    pub uri: String,
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Where the request came from, in captures that say.
+    pub client: Option<SocketAddr>,
+    pub status: String,
+    /// Request headers, with sensitive values masked.
+    pub request_headers: Vec<(String, String)>,
+    pub headers: Vec<(String, String)>,
+    /// Trailer fields sent after the response body.
+    pub trailers: Vec<(String, String)>,
+    pub body: String,
+    /// Where a binary or oversized body was stored instead of inline.
+    pub body_file: Option<PathBuf>,
+    /// The body was too large to keep in memory; it may be text.
+    pub spilled: bool,
+    /// Where the request body was stored, unless it was empty.
+    pub request_body_file: Option<PathBuf>,
+}
+
+impl CapturedExchange {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.header("content-type")
+    }
+
+    /// Parse the format written by `Proxy::save_request_to_file`.
+    pub fn parse(content: &str) -> Self {
+        let mut exchange = Self {
+            status: String::from("Unknown"),
+            ..Default::default()
+        };
+        let mut in_request_headers = false;
+        let mut in_headers = false;
+        let mut in_trailers = false;
+        let mut in_body = false;
+
+        for line in content.lines() {
+            if in_body {
+                if let Some(path) = line.strip_prefix(BINARY_BODY).and_then(|l| l.strip_suffix(']')) {
+                    exchange.body_file = Some(PathBuf::from(path));
+                } else if let Some(path) = line.strip_prefix(SPILLED_BODY).and_then(|l| l.strip_suffix(']')) {
+                    exchange.body_file = Some(PathBuf::from(path));
+                    exchange.spilled = true;
+                }
+                exchange.body.push_str(line);
+                exchange.body.push('\n');
+            } else if let Some(path) = line.strip_prefix(REQUEST_BODY).and_then(|l| l.strip_suffix(']')) {
+                exchange.request_body_file = Some(PathBuf::from(path));
+            } else if let Some(method) = line.strip_prefix("Method:") {
+                exchange.method = method.trim().to_string();
+            } else if let Some(uri) = line.strip_prefix("URI:") {
+                exchange.uri = uri.trim().to_string();
+            } else if let Some(client) = line.strip_prefix("Client:") {
+                exchange.client = client.trim().parse().ok();
+            } else if let Some(timestamp) = line.strip_prefix("Timestamp:") {
+                exchange.timestamp =
+                    DateTime::parse_from_rfc3339(timestamp.trim()).ok().map(|timestamp| timestamp.with_timezone(&Utc));
+            } else if line.starts_with("Status:") {
+                exchange.status = line.trim_start_matches("Status:").trim().to_string();
+            } else if line.starts_with("Request Headers:") {
+                in_request_headers = true;
+            } else if line.starts_with("Response Headers:") {
+                in_request_headers = false;
+                in_headers = true;
+            } else if line.starts_with("Response Trailers:") {
+                in_headers = false;
+                in_trailers = true;
+            } else if line.starts_with("Response Body:") {
+                in_body = true;
+            } else if (in_headers || in_request_headers || in_trailers)
+                && let Some((name, value)) = line.trim_start().split_once(':')
+            {
+                let headers = if in_trailers {
+                    &mut exchange.trailers
+                } else if in_headers {
+                    &mut exchange.headers
+                } else {
+                    &mut exchange.request_headers
+                };
+                headers.push((name.to_string(), value.trim().to_string()));
+            }
+        }
+        exchange.body = exchange.body.trim().to_string();
+        exchange
+    }
+
+    pub async fn load(path: &Path) -> std::io::Result<Self> {
+        let content = fs::read_to_string(path).await?;
+        Ok(Self::parse(&content))
+    }
+
+    /// A body of the capture at `path` byte for byte, as it went through the proxy.
+    pub async fn load_body(path: &Path, part: BodyPart) -> std::io::Result<Vec<u8>> {
+        let content = fs::read_to_string(path).await?;
+        let exchange = Self::parse(&content);
+        match (part, exchange.request_body_file, exchange.body_file) {
+            (BodyPart::Request, Some(file), _) | (BodyPart::Response, _, Some(file)) => compression::read(&file).await,
+            (BodyPart::Request, None, _) => Ok(Vec::new()),
+            (BodyPart::Response, _, None) => Ok(inline_body(&content).as_bytes().to_vec()),
+        }
+    }
+}
+
+/// The response body written into a capture file, untrimmed.
+fn inline_body(content: &str) -> &str {
+    match content.split_once("\nResponse Body:\n") {
+        Some((_, "[Empty]\n")) | None => "",
+        Some((_, body)) => body,
+    }
+}
+
+type SharedLogs = Arc<RwLock<VecDeque<Arc<HttpLog>>>>;
+
+/// Read-only copy of the captured requests, in timestamp order. Entries are
+/// shared with the proxy, so publishing a snapshot doesn't copy the logs themselves.
+pub type LogSnapshot = Arc<Vec<Arc<HttpLog>>>;
+
+/// Receives a new snapshot after every change to the captured requests.
+pub type LogReceiver = watch::Receiver<LogSnapshot>;
+
+/// Message asking the proxy to drop every captured request, and optionally
+/// the capture files of this session.
+pub struct ClearLogs {
+    pub delete_captures: bool,
+}
+
+/// Sent once the proxy is up, handing its rules to the rules panel.
+pub struct RulesShared {
+    pub rewrites: SharedRules,
+    pub faults: SharedFaults,
+    pub limiter: Arc<Limiter>,
+}
+
+/// Sent once the proxy is up, handing the client profiles to the clients panel.
+pub struct ClientsShared(pub Arc<ClientProfiles>);
+
+/// Message asking the proxy to pin or unpin the request with this id.
+pub struct TogglePin(pub u64);
+
+/// Sent while a `text/event-stream` response to request `id` streams, so its
+/// events can be shown before the capture is written.
+pub enum EventStream {
+    Opened {
+        id: u64,
+        status: u16,
+        /// Response headers, with sensitive values masked.
+        headers: Vec<(String, String)>,
+    },
+    Events {
+        id: u64,
+        events: Vec<SseEvent>,
+    },
+    /// The stream ended and its capture has been written.
+    Closed {
+        id: u64,
+    },
+}
+
+/// Message asking the proxy to write the captured requests to an archive.
+pub struct SaveSession {
+    pub path: PathBuf,
+    /// Only the requests of this named session.
+    pub session: Option<String>,
+}
+
+/// Message asking the proxy to replace the captured requests with those of
+/// an archive.
+pub struct LoadSession(pub PathBuf);
+
+/// Message telling the list that the previous session can be restored.
+pub struct RestorableSession {
+    pub dir: PathBuf,
+    /// How many capture files it holds.
+    pub requests: usize,
+}
+
+/// Message asking the proxy to add the requests captured in an earlier
+/// session's directory ahead of those captured since.
+pub struct RestoreSession(pub PathBuf);
+
+/// Body of the responses yap sends to clients.
+type ProxyBody = BoxBody<Bytes, std::io::Error>;
+
+fn full(body: impl Into<Bytes>) -> ProxyBody {
+    Full::new(body.into()).map_err(|never| match never {}).boxed()
+}
+
+/// Body of the requests yap forwards upstream.
+pub type UpstreamBody = BoxBody<Bytes, std::convert::Infallible>;
+
+/// Frames ahead of a slow client before a streamed response waits for it.
+const STREAM_FRAMES: usize = 16;
+
+/// Trailer fields that ended an upstream response body, kept in the
+/// response's extensions.
+#[derive(Clone, Debug)]
+struct Trailers(HeaderMap);
+
+/// `data` followed by `trailers`. No length is given, so HTTP/1 sends it
+/// chunked, the only encoding that can carry trailers.
+fn with_trailers<E: 'static>(data: Bytes, trailers: HeaderMap) -> BoxBody<Bytes, E> {
+    let frames = [Frame::data(data), Frame::trailers(trailers)];
+    BodyExt::boxed(StreamBody::new(stream::iter(frames.into_iter().map(Ok))))
+}
+
+/// Announce `trailers` in a `Trailer` header unless the sender did, since
+/// HTTP/1 only sends trailer fields that were announced.
+fn announce_trailers(headers: &mut HeaderMap, trailers: &HeaderMap) {
+    if trailers.is_empty() || headers.contains_key(hyper::header::TRAILER) {
+        return;
+    }
+    let names: Vec<&str> = trailers.keys().map(|name| name.as_str()).collect();
+    if let Ok(value) = HeaderValue::from_str(&names.join(", ")) {
+        headers.insert(hyper::header::TRAILER, value);
+    }
+}
+
+/// A response body as received from upstream: in memory, or spilled to a
+/// file once it outgrew `capture.max_body_bytes`.
+enum ResponseBody {
+    Memory(Bytes),
+    Spilled { path: PathBuf, len: u64 },
+}
+
+impl ResponseBody {
+    fn len(&self) -> u64 {
+        match self {
+            ResponseBody::Memory(bytes) => bytes.len() as u64,
+            ResponseBody::Spilled { len, .. } => *len,
+        }
+    }
+}
+
+/// A response body being received, kept in memory until it outgrows
+/// `capture.max_body_bytes` and written to `path` from then on, compressed
+/// at `compression` when set.
+struct BodyRecorder {
+    path: PathBuf,
+    max: u64,
+    compression: Option<i32>,
+    buffer: Vec<u8>,
+    spill: Option<BodyWriter>,
+    len: u64,
+    /// Shows the body coming in on the transfers panel.
+    progress: Progress,
+}
+
+impl BodyRecorder {
+    fn new(path: PathBuf, capture: &CaptureConfig, progress: Progress) -> Self {
+        Self {
+            path,
+            max: capture.max_body_bytes,
+            compression: capture.compression,
+            buffer: Vec::new(),
+            spill: None,
+            len: 0,
+            progress,
+        }
+    }
+
+    fn spill_error(path: &Path, e: std::io::Error) -> String {
+        format!("Failed to spill response to {}: {}", path.display(), e)
+    }
+
+    async fn push(&mut self, data: &[u8]) -> Result<(), String> {
+        self.len += data.len() as u64;
+        self.progress.add(data.len() as u64);
+        let path = &self.path;
+        match &mut self.spill {
+            Some(file) => file.write_all(data).await.map_err(|e| Self::spill_error(path, e))?,
+            None if self.len > self.max => {
+                info!("Response exceeds {} bytes, spilling to {}", self.max, path.display());
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).await.map_err(|e| Self::spill_error(path, e))?;
+                }
+                let mut file = compression::create(path, self.compression).await.map_err(|e| Self::spill_error(path, e))?;
+                file.write_all(&self.buffer).await.map_err(|e| Self::spill_error(path, e))?;
+                file.write_all(data).await.map_err(|e| Self::spill_error(path, e))?;
+                self.buffer = Vec::new();
+                self.spill = Some(file);
+            }
+            None => self.buffer.extend_from_slice(data),
+        }
+        Ok(())
+    }
+
+    async fn finish(self) -> Result<ResponseBody, String> {
+        match self.spill {
+            Some(mut file) => {
+                file.shutdown().await.map_err(|e| Self::spill_error(&self.path, e))?;
+                Ok(ResponseBody::Spilled {
+                    path: self.path,
+                    len: self.len,
+                })
+            }
+            None => Ok(ResponseBody::Memory(Bytes::from(self.buffer))),
+        }
+    }
+}
+
+/// A request on its way upstream, with what it takes to record the exchange
+/// once the response is complete.
+struct Exchange {
+    /// `None` when the request wasn't captured.
+    id: Option<u64>,
+    method: Method,
+    uri: hyper::Uri,
+    headers: HeaderMap,
+    body: Bytes,
+    timestamp: DateTime<Utc>,
+    started: Instant,
+    span: Option<SpanStart>,
+    capture_path: PathBuf,
+    client: SocketAddr,
+}
+
+impl Exchange {
+    fn record(&self, status: StatusCode, bytes: u64) -> (AccessRecord, Duration) {
+        let duration = self.started.elapsed();
+        let record = AccessRecord::new(self.timestamp, self.method.as_str(), &self.uri, status.as_u16(), bytes, duration)
+            .client(self.client);
+        (record, duration)
+    }
+
+    /// Record an answer yap gave itself, which leaves no capture.
+    async fn answered(&self, status: StatusCode, ctx: &ProxyContext) {
+        let (record, duration) = self.record(status, 0);
+        let response = Completed::default();
+        Proxy::complete_request(self.id, record, duration, response, self.span.as_ref(), ctx).await;
+    }
+
+    /// Record the response and write the capture file.
+    async fn finish(
+        &self,
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: &ResponseBody,
+        trailers: Option<&HeaderMap>,
+        tag: Option<String>,
+        ctx: &ProxyContext,
+    ) {
+        let (record, duration) = self.record(status, body.len());
+        let set_cookies = headers
+            .get_all(hyper::header::SET_COOKIE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .map(str::to_string)
+            .collect();
+        let memory = match body {
+            ResponseBody::Memory(bytes) => Some(bytes.as_ref()),
+            ResponseBody::Spilled { .. } => None,
+        };
+        let failed_assertions = assertions::check(
+            &ctx.assertions.read().await,
+            self.method.as_str(),
+            &self.uri,
+            status.as_u16(),
+            headers,
+            memory,
+        );
+        let response = Completed {
+            set_cookies,
+            tag,
+            failed_assertions,
+        };
+        Proxy::complete_request(self.id, record, duration, response, self.span.as_ref(), ctx).await;
+
+        if let Err(e) = Proxy::save_request_to_file(
+            &self.capture_path,
+            self.method.as_str(),
+            &self.uri.to_string(),
+            Some(self.client),
+            &self.headers,
+            Some(&self.body),
+            status.as_u16(),
+            headers,
+            body,
+            trailers,
+            self.timestamp,
+            &ctx.capture.redact,
+            ctx.capture.compression,
+        )
+        .await
+        {
+            error!("Failed to save request to file: {}", e);
+        }
+    }
+}
+
+/// What the list learns about a request once its response is complete.
+#[derive(Default)]
+struct Completed {
+    set_cookies: Vec<String>,
+    tag: Option<String>,
+    failed_assertions: Vec<String>,
+}
+
+fn content_length(headers: &HeaderMap) -> Option<u64> {
+    headers.get(hyper::header::CONTENT_LENGTH)?.to_str().ok()?.parse().ok()
+}
+
+fn bad_gateway(message: impl Into<Bytes>) -> Response<ProxyBody> {
+    Response::builder().status(StatusCode::BAD_GATEWAY).body(full(message)).unwrap()
 }
 
-pub type SharedLogs = Arc<RwLock<VecDeque<HttpLog>>>;
+/// Returned by the request handler to drop the connection without answering.
+#[derive(Debug)]
+pub struct InjectedReset;
 
+impl std::fmt::Display for InjectedReset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("connection reset injected by a fault rule")
+    }
+}
+
+impl std::error::Error for InjectedReset {}
+
+/// Pooled client every request is forwarded with.
+pub type UpstreamClient =
+    Client<TlsVersions<HttpsConnector<CountingConnector<MappedConnector<HttpConnector<Resolver>>>>>, UpstreamBody>;
+
+pub fn upstream_client(
+    config: &UpstreamConfig,
+    dns: &DnsConfig,
+    connections: ConnectionStats,
+) -> color_eyre::Result<UpstreamClient> {
+    let mut connector = HttpConnector::new_with_resolver(Resolver::new(dns));
+    connector.set_connect_timeout(Some(Duration::from_secs(config.connect_timeout_secs)));
+    connector.set_nodelay(true);
+    connector.enforce_http(false);
+    let connector = HttpsConnectorBuilder::new()
+        .with_tls_config(tls::client_config(&config.tls)?)
+        .https_or_http()
+        .enable_all_versions()
+        .wrap_connector(CountingConnector::new(
+            MappedConnector::new(connector, HostMap::new(&dns.hosts)),
+            connections,
+        ));
+    Ok(Client::builder(TokioExecutor::new())
+        .pool_max_idle_per_host(config.max_idle_per_host)
+        .pool_idle_timeout(Duration::from_secs(config.idle_timeout_secs))
+        .pool_timer(TokioTimer::new())
+        .http2_only(config.http2_only)
+        .build(TlsVersions(connector)))
+}
+
+/// State shared by the listener and every connection task it spawns.
 #[derive(Clone)]
+struct ProxyContext {
+    logs: SharedLogs,
+    snapshot: watch::Sender<LogSnapshot>,
+    updater: Updater,
+    listen: SocketAddr,
+    /// Upstream base URL in reverse-proxy mode.
+    reverse: Option<url::Url>,
+    /// Set for the listeners configured in `proxy.listeners`.
+    label: Option<String>,
+    clients: Arc<ClientNames>,
+    profiles: Arc<ClientProfiles>,
+    proxy: Arc<ProxyConfig>,
+    capture: CaptureConfig,
+    capture_dir: PathBuf,
+    access_log: Arc<Mutex<AccessLog>>,
+    /// Cleared to pause recording; requests are still forwarded.
+    capturing: Arc<AtomicBool>,
+    rules: SharedRules,
+    faults: SharedFaults,
+    assertions: SharedAssertions,
+    state: SharedState,
+    /// Offline mode: where responses are served from instead of the upstream.
+    replay: ReplayConfig,
+    scripts: SharedScripts,
+    limiter: Arc<Limiter>,
+    client: UpstreamClient,
+    /// Set when spans are exported to an OpenTelemetry collector.
+    otlp: Option<Exporter>,
+    /// Set by `--tee`.
+    tee: Option<Tee>,
+    transfers: Transfers,
+}
+
 pub struct Proxy {
     logs: SharedLogs,
+    snapshot: watch::Sender<LogSnapshot>,
     updater: Option<Updater>,
+    capture: CaptureConfig,
+    proxy: ProxyConfig,
+    /// Session directory resolved from the capture config at mount.
+    capture_dir: PathBuf,
+    access_log: Arc<Mutex<AccessLog>>,
+    capturing: Arc<AtomicBool>,
+    rules: SharedRules,
+    faults: SharedFaults,
+    assertions: SharedAssertions,
+    limiter: Arc<Limiter>,
+    api: Option<SocketAddr>,
+    /// Path of the control socket.
+    control: Option<PathBuf>,
+    replay: ReplayConfig,
+    scripts: SharedScripts,
+    state: SharedState,
+    clients: Arc<ClientNames>,
+    profiles: Arc<ClientProfiles>,
+    /// Counters of the upstream connections, kept across config reloads.
+    connections: ConnectionStats,
+    /// Response bodies being received, for the transfers panel.
+    transfers: Transfers,
+    otlp: OtlpConfig,
+    commands: Vec<Command>,
 }
 
 impl Default for Proxy {
     fn default() -> Self {
         Self {
-            logs: Arc::new(RwLock::new(VecDeque::with_capacity(10000))),
+            logs: Arc::new(RwLock::new(VecDeque::new())),
+            snapshot: watch::Sender::new(LogSnapshot::default()),
             updater: None,
+            capture: CaptureConfig::default(),
+            proxy: ProxyConfig::default(),
+            capture_dir: PathBuf::new(),
+            access_log: Arc::new(Mutex::new(AccessLog::new(Path::new(""), Default::default()))),
+            capturing: Arc::new(AtomicBool::new(true)),
+            rules: SharedRules::default(),
+            faults: SharedFaults::default(),
+            assertions: SharedAssertions::default(),
+            limiter: Arc::default(),
+            api: None,
+            control: None,
+            replay: ReplayConfig::default(),
+            scripts: Arc::new(Scripts::new(PathBuf::new())),
+            state: SharedState::default(),
+            clients: Arc::default(),
+            profiles: Arc::default(),
+            connections: ConnectionStats::default(),
+            transfers: Transfers::default(),
+            otlp: OtlpConfig::default(),
+            commands: Vec::new(),
         }
     }
 }
 
-#[allow(dead_code)]
 impl Proxy {
-    pub fn get_logs(&self) -> SharedLogs {
-        self.logs.clone()
+    pub fn new(state: SharedState) -> Self {
+        Self {
+            state,
+            ..Self::default()
+        }
     }
+}
 
-    async fn log_request(
-        method: &str,
-        uri: &str,
-        logs: SharedLogs,
-        updater: &Option<Updater>,
-    ) {
-        let timestamp = Utc::now();
-        
-        // Store the log
-        {
-            let mut logs_guard = logs.write().await;
-            let id = uri.to_string();
-            if logs_guard.len() >= 10000 {
-                logs_guard.pop_front();
-            }
-            logs_guard.push_back(HttpLog {
-                method: method.to_string(),
-                uri: uri.to_string(),
-                timestamp,
-                path: id,
-            });
+impl Proxy {
+    /// Subscribe to snapshots of the captured requests.
+    pub fn subscribe(&self) -> LogReceiver {
+        self.snapshot.subscribe()
+    }
+
+    /// Counters of the connections to the upstream hosts.
+    pub fn connections(&self) -> ConnectionStats {
+        self.connections.clone()
+    }
+
+    /// Progress of the response bodies being received.
+    pub fn transfers(&self) -> Transfers {
+        self.transfers.clone()
+    }
+
+    /// Publish the current logs to every subscriber. Call with the write lock held
+    /// so snapshots are sent in the same order as the changes.
+    fn publish(logs: &VecDeque<Arc<HttpLog>>, snapshot: &watch::Sender<LogSnapshot>) {
+        snapshot.send_replace(Arc::new(logs.iter().cloned().collect()));
+    }
+
+    /// Record a request that just arrived and return its id, or `None` if it
+    /// wasn't captured; `id` and `path` of `log` are filled in here.
+    async fn log_request(mut log: HttpLog, ctx: &ProxyContext) -> Option<u64> {
+        if !ctx.capturing.load(Ordering::Relaxed) || log.client.is_some_and(|client| !ctx.profiles.captures(client.ip())) {
+            return None;
         }
+        let log_id = NEXT_LOG_ID.fetch_add(1, Ordering::Relaxed);
+        log.id = log_id;
+        log.path = Self::capture_path(&ctx.capture_dir, &log.uri, Some(log_id));
 
-        // Write to file
-        if let Err(e) = Self::write_log_to_file(method, uri, timestamp).await {
-            error!("Failed to write log to file: {}", e);
+        // Store the log, evicting according to the configured policy
+        let (accepted, evicted) = {
+            let mut logs_guard = ctx.logs.write().await;
+            let mut evicted = None;
+            let mut accept = true;
+            if logs_guard.len() >= ctx.capture.capacity.max(1) {
+                // pinned requests are skipped; if everything is pinned nothing can go
+                let oldest = logs_guard.iter().position(|log| !log.pinned);
+                match (ctx.capture.eviction, oldest) {
+                    (EvictionPolicy::DropOldest, Some(oldest)) => {
+                        logs_guard.remove(oldest);
+                    }
+                    (EvictionPolicy::DiskSpill, Some(oldest)) => {
+                        evicted = logs_guard.remove(oldest);
+                    }
+                    _ => {
+                        accept = false;
+                    }
+                }
+            }
+            if accept {
+                // concurrent requests can get here out of order; keeping the
+                // timestamps sorted lets time windows be found by bisection
+                let at = logs_guard.partition_point(|logged| logged.timestamp <= log.timestamp);
+                logs_guard.insert(at, Arc::new(log));
+                Self::publish(&logs_guard, &ctx.snapshot);
+            } else {
+                warn!("Log capacity reached, not capturing {} {}", log.method, log.uri);
+            }
+            (accept, evicted)
+        };
+        ctx.state.set_capturing(accepted);
+
+        if let Some(evicted) = evicted {
+            let spill_path = ctx.capture_dir.join(session::SPILL_FILE);
+            if let Err(e) = session::spill(&spill_path, &evicted).await {
+                error!("Failed to spill evicted log to {}: {}", spill_path.display(), e);
+            }
         }
 
         // Trigger UI update
-        if let Some(updater) = updater {
-            let _ = updater.update();
-        }
-    }
+        ctx.updater.update();
 
-    async fn write_log_to_file(
-        method: &str,
-        uri: &str,
-        timestamp: DateTime<Utc>,
-    ) -> std::io::Result<()> {
-        let log_line = format!(
-            "{} {} {}\n",
-            timestamp.to_rfc3339(),
-            method,
-            uri
-        );
+        accepted.then_some(log_id)
+    }
 
-        let mut file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open("proxy_requests.log")
-            .await?;
+    /// Attach the response outcome to a previously logged request, append
+    /// it to the access log and the tee, and export its span.
+    async fn complete_request(
+        id: Option<u64>,
+        record: AccessRecord,
+        duration: Duration,
+        response: Completed,
+        span: Option<&SpanStart>,
+        ctx: &ProxyContext,
+    ) {
+        if let Err(e) = ctx.access_log.lock().await.append(&record).await {
+            error!("Failed to write access log: {}", e);
+        }
+        if let Some(tee) = &ctx.tee {
+            tee.write(&record);
+        }
+        if let (Some(exporter), Some(span)) = (&ctx.otlp, span) {
+            exporter.export(span.finish(record.status, record.bytes, duration));
+        }
+        let Some(id) = id else {
+            return;
+        };
+        {
+            let mut logs_guard = ctx.logs.write().await;
+            if let Some(index) = logs_guard.iter().rposition(|log| log.id == id) {
+                let differs_from = differs_from(&logs_guard, index, record.status);
+                // copies the entry only if a snapshot still holds it
+                let log = Arc::make_mut(&mut logs_guard[index]);
+                log.differs_from = differs_from;
+                log.status = Some(record.status);
+                log.duration = Some(duration);
+                log.size = Some(record.bytes);
+                log.set_cookies = response.set_cookies;
+                log.tag = response.tag;
+                log.failed_assertions = response.failed_assertions;
+                Self::publish(&logs_guard, &ctx.snapshot);
+            }
+        }
+        ctx.updater.update();
+    }
 
-        file.write_all(log_line.as_bytes()).await?;
-        file.flush().await?;
+    /// Fill in the name of `ip` on its requests logged before it was known.
+    async fn name_client(ctx: &ProxyContext, ip: IpAddr, name: String) {
+        ctx.profiles.set_name(ip, name.clone());
+        {
+            let mut logs_guard = ctx.logs.write().await;
+            let mut named = false;
+            for log in logs_guard.iter_mut() {
+                if log.client_name.is_none() && log.client.is_some_and(|client| client.ip() == ip) {
+                    Arc::make_mut(log).client_name = Some(name.clone());
+                    named = true;
+                }
+            }
+            if named {
+                Self::publish(&logs_guard, &ctx.snapshot);
+            }
+        }
+        ctx.updater.update();
+    }
 
-        Ok(())
+    /// Where the capture of the exchange `id` with `uri` goes under `root`:
+    /// the file of [`Proxy::uri_to_file_path`] with the id before the
+    /// extension, so repeated requests to a URL each keep their own.
+    /// Exchanges that weren't listed have no id and share the plain name.
+    pub fn capture_path(root: &Path, uri: &str, id: Option<u64>) -> PathBuf {
+        let path = Self::uri_to_file_path(root, uri);
+        match id {
+            Some(id) => path.with_extension(format!("{}.yap", id)),
+            None => path,
+        }
     }
 
-    pub fn uri_to_file_path(uri: &str) -> PathBuf {
+    /// Where the capture of `uri` goes under `root`, one directory per host
+    /// and path segment.
+    pub fn uri_to_file_path(root: &Path, uri: &str) -> PathBuf {
         // Parse the URI to extract hostname and path
         let parsed = match url::Url::parse(uri) {
             Ok(url) => url,
             Err(_) => {
                 // If parsing fails, create a safe filename from the raw URI
-                let safe_name = uri.replace(['/', ':', '?', '&', '='], "_");
-                return PathBuf::from(".yap").join("unknown").join(format!("{}.yap", safe_name));
+                return root.join("unknown").join(format!("{}.yap", safe_file_name(uri)));
             }
         };
 
@@ -123,7 +865,7 @@ impl Proxy {
         let path = parsed.path();
         
         // Create the base directory structure
-        let mut file_path = PathBuf::from(".yap").join(host);
+        let mut file_path = root.join(safe_file_name(host));
         
         // Convert path to filesystem-safe structure
         let path_parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
@@ -133,17 +875,14 @@ impl Proxy {
             file_path.push("index");
         } else {
             for part in path_parts {
-                // Sanitize each part to be filesystem-safe
-                let safe_part = part.replace([':', '?', '&', '=', '*', '<', '>', '|', '"'], "_");
-                file_path.push(safe_part);
+                file_path.push(safe_file_name(part));
             }
         }
         
         // Add query parameters to the filename if present
         if let Some(query) = parsed.query() {
-            let query_safe = query.replace(['/', ':', '?', '&', '=', '*', '<', '>', '|', '"'], "_");
             let current_name = file_path.file_name().unwrap_or_default().to_string_lossy().to_string();
-            file_path.set_file_name(format!("{}_{}", current_name, query_safe));
+            file_path.set_file_name(safe_file_name(&format!("{}_{}", current_name, query)));
         }
         
         // Add .yap extension
@@ -163,23 +902,29 @@ impl Proxy {
                 || ct_lower.starts_with("application/pdf")
                 || ct_lower.starts_with("application/zip")
                 || ct_lower.starts_with("font/")
+                || ct_lower.starts_with("application/grpc")
+                || grpc::is_protobuf(Some(&ct_lower))
         } else {
             false
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn save_request_to_file(
+        file_path: &Path,
         method: &str,
         uri: &str,
-        _headers: &hyper::HeaderMap,
-        _body: Option<&Bytes>,
+        client: Option<SocketAddr>,
+        headers: &hyper::HeaderMap,
+        body: Option<&Bytes>,
         response_status: u16,
         response_headers: &hyper::HeaderMap,
-        response_body: &Bytes,
+        response_body: &ResponseBody,
+        response_trailers: Option<&hyper::HeaderMap>,
         timestamp: DateTime<Utc>,
+        redact: &RedactConfig,
+        compression: Option<i32>,
     ) -> std::io::Result<()> {
-        let file_path = Self::uri_to_file_path(uri);
-        
         // Create parent directories
         if let Some(parent) = file_path.parent() {
             fs::create_dir_all(parent).await?;
@@ -198,43 +943,76 @@ impl Proxy {
         content.push_str(&format!("Timestamp: {}\n", timestamp.to_rfc3339()));
         content.push_str(&format!("Method: {}\n", method));
         content.push_str(&format!("URI: {}\n", uri));
-        content.push_str(&format!("Status: {}\n\n", response_status));
+        if let Some(client) = client {
+            content.push_str(&format!("Client: {}\n", client));
+        }
+        content.push_str(&format!("Status: {}\n", response_status));
+        if let Some(body) = body.filter(|body| !body.is_empty()) {
+            let body_path = file_path.with_extension("req");
+            compression::write(&body_path, body, compression).await?;
+            content.push_str(&format!("{}{}]\n", REQUEST_BODY, body_path.display()));
+        }
+        content.push('\n');
+
+        content.push_str("Request Headers:\n");
+        for (name, value) in headers.iter() {
+            if let Ok(value_str) = value.to_str() {
+                content.push_str(&format!("  {}: {}\n", name, redact.redact(name.as_str(), value_str)));
+            }
+        }
+        content.push('\n');
         
         content.push_str("Response Headers:\n");
         for (name, value) in response_headers.iter() {
             if let Ok(value_str) = value.to_str() {
-                content.push_str(&format!("  {}: {}\n", name, value_str));
+                content.push_str(&format!("  {}: {}\n", name, redact.redact(name.as_str(), value_str)));
             }
         }
-        content.push_str("\n");
-        
-        if is_binary {
-            // Save binary data to a separate file
-            let binary_file_path = file_path.with_extension("bin");
-            let mut binary_file = OpenOptions::new()
-                .create(true)
-                .write(true)
-                .truncate(true)
-                .open(&binary_file_path)
-                .await?;
-            
-            binary_file.write_all(response_body).await?;
-            binary_file.flush().await?;
-            
-            content.push_str("Response Body:\n");
-            content.push_str(&format!("[Binary data stored in: {}]\n", binary_file_path.display()));
-            content.push_str(&format!("Size: {} bytes\n", response_body.len()));
-            
-            info!("Saved binary data to: {}", binary_file_path.display());
-        } else {
-            content.push_str("Response Body:\n");
-            if response_body.is_empty() {
-                content.push_str("[Empty]\n");
-            } else {
-                content.push_str(&String::from_utf8_lossy(response_body));
+        content.push('\n');
+
+        if let Some(trailers) = response_trailers.filter(|trailers| !trailers.is_empty()) {
+            content.push_str("Response Trailers:\n");
+            for (name, value) in trailers.iter() {
+                if let Ok(value_str) = value.to_str() {
+                    content.push_str(&format!("  {}: {}\n", name, redact.redact(name.as_str(), value_str)));
+                }
             }
+            content.push('\n');
         }
         
+        match response_body {
+            ResponseBody::Spilled { path, len } => {
+                content.push_str("Response Body:\n");
+                content.push_str(&format!("{}{}]\n", SPILLED_BODY, path.display()));
+                content.push_str(&format!("Size: {} bytes\n", len));
+            }
+            // bodies that aren't UTF-8 would not survive as text, nor would
+            // those in another charset that happen to be valid UTF-8
+            ResponseBody::Memory(response_body)
+                if is_binary
+                    || std::str::from_utf8(response_body).is_err()
+                    || (!response_body.is_ascii() && charset::detect(content_type, response_body).0 != UTF_8) =>
+            {
+                // Save binary data to a separate file
+                let binary_file_path = file_path.with_extension("bin");
+                compression::write(&binary_file_path, response_body, compression).await?;
+
+                content.push_str("Response Body:\n");
+                content.push_str(&format!("{}{}]\n", BINARY_BODY, binary_file_path.display()));
+                content.push_str(&format!("Size: {} bytes\n", response_body.len()));
+
+                info!("Saved binary data to: {}", binary_file_path.display());
+            }
+            ResponseBody::Memory(response_body) => {
+                content.push_str("Response Body:\n");
+                if response_body.is_empty() {
+                    content.push_str("[Empty]\n");
+                } else {
+                    content.push_str(&String::from_utf8_lossy(response_body));
+                }
+            }
+        }
+
         // Write log to file
         let mut file = OpenOptions::new()
             .create(true)
@@ -251,58 +1029,427 @@ impl Proxy {
         Ok(())
     }
 
+    /// Point an incoming request at `upstream`, keeping its path and query
+    /// below the upstream base path and rewriting the Host header.
+    fn rewrite_reverse<B>(req: Request<B>, upstream: &url::Url) -> Request<B> {
+        let target = reverse_target(upstream, req.uri().path_and_query().map_or("/", |pq| pq.as_str()));
+        match target {
+            Ok(uri) => Self::retarget(req, uri),
+            Err(e) => {
+                error!("Failed to build upstream URI for {}: {}", req.uri(), e);
+                req
+            }
+        }
+    }
+
+    /// Apply the first matching rewrite rule, of the client's rewrite set
+    /// and then of `proxy.rewrites`, if any.
+    async fn rewrite_rules<B>(req: Request<B>, ctx: &ProxyContext, client: IpAddr) -> Request<B> {
+        let url = req.uri().to_string();
+        let rewritten = match ctx.profiles.rewrite(client, &url) {
+            Some(rewritten) => rewritten,
+            None => match rewrite::apply(&mut ctx.rules.write().await, &url) {
+                Some(rewritten) => rewritten,
+                None => return req,
+            },
+        };
+        match rewritten.parse() {
+            Ok(uri) => {
+                info!("Rewrote {} to {}", req.uri(), rewritten);
+                Self::retarget(req, uri)
+            }
+            Err(e) => {
+                error!("Rewrite of {} produced an invalid URI {}: {}", req.uri(), rewritten, e);
+                req
+            }
+        }
+    }
+
+    /// Wait until the client's throttle and `proxy.limits` both let a
+    /// request to `host` through, or reject it.
+    async fn acquire(ctx: &ProxyContext, client: IpAddr, host: &str) -> Result<Permit, Rejected> {
+        let permit = match ctx.profiles.limiter(client) {
+            Some(throttle) => Some(throttle.acquire(host).await?),
+            None => None,
+        };
+        let global = ctx.limiter.acquire(host).await?;
+        Ok(match permit {
+            Some(permit) => permit.join(global),
+            None => global,
+        })
+    }
+
+    /// Point `req` at `uri`, keeping the Host header in line with it.
+    fn retarget<B>(req: Request<B>, uri: hyper::Uri) -> Request<B> {
+        let (mut parts, body) = req.into_parts();
+        if let Some(authority) = uri.authority()
+            && let Ok(host) = hyper::header::HeaderValue::from_str(authority.as_str())
+        {
+            parts.headers.insert(hyper::header::HOST, host);
+        }
+        parts.uri = uri;
+        Request::from_parts(parts, body)
+    }
+
+    /// Origin-form requests in forward mode are addressed to yap itself.
+    fn is_pac_request(req: &Request<Incoming>, ctx: &ProxyContext) -> bool {
+        ctx.reverse.is_none() && req.uri().authority().is_none() && req.uri().path() == pac::PAC_PATH
+    }
+
+    /// `407` unless the client may use the forward proxy. Reverse mode and
+    /// the PAC file, which browsers fetch without credentials, are open.
+    fn check_auth(req: &mut Request<Incoming>, ctx: &ProxyContext) -> Option<Response<ProxyBody>> {
+        if ctx.reverse.is_some() || !ctx.proxy.auth.enabled() || Self::is_pac_request(req, ctx) {
+            return None;
+        }
+        if ctx.proxy.auth.allows(req.headers()) {
+            // meant for yap, not the upstream
+            req.headers_mut().remove(hyper::header::PROXY_AUTHORIZATION);
+            return None;
+        }
+        info!("Rejecting unauthenticated {} {}", req.method(), req.uri());
+        Some(
+            Response::builder()
+                .status(StatusCode::PROXY_AUTHENTICATION_REQUIRED)
+                .header(hyper::header::PROXY_AUTHENTICATE, ctx.proxy.auth.challenge())
+                .body(full(Bytes::from("Proxy authentication required")))
+                .unwrap(),
+        )
+    }
+
+    /// Answer a request for the PAC file made to the proxy itself.
+    fn serve_pac(req: &Request<Incoming>, ctx: &ProxyContext) -> Response<ProxyBody> {
+        // the address the client reached us on works better than a wildcard bind address
+        let addr = req
+            .headers()
+            .get(hyper::header::HOST)
+            .and_then(|host| host.to_str().ok())
+            .map(str::to_string)
+            .unwrap_or_else(|| ctx.listen.to_string());
+        info!("Serving PAC file for {}", addr);
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(hyper::header::CONTENT_TYPE, pac::PAC_CONTENT_TYPE)
+            .body(full(Bytes::from(pac::generate(&ctx.proxy, &addr))))
+            .unwrap()
+    }
+
+    /// Send `req` upstream and return the response once its head arrived.
+    async fn send_upstream(req: Request<UpstreamBody>, ctx: &ProxyContext) -> Result<Response<Incoming>, String> {
+        let response = ctx.client.request(req);
+        let response = match ctx.proxy.upstream.request_timeout_secs {
+            Some(secs) => tokio::time::timeout(Duration::from_secs(secs), response)
+                .await
+                .map_err(|_| format!("Upstream did not answer within {}s", secs))?,
+            None => response.await,
+        };
+        response.map_err(|e| format!("Failed to forward request: {}", e))
+    }
+
+    /// Send `req` upstream and receive the whole response, moving the body to
+    /// `spill_path` once it grows past `max_body` bytes. Its trailers are
+    /// kept in the extensions.
+    async fn forward(
+        req: Request<UpstreamBody>,
+        ctx: &ProxyContext,
+        spill_path: &Path,
+    ) -> Result<Response<ResponseBody>, String> {
+        let (method, uri) = (req.method().clone(), req.uri().clone());
+        let (mut parts, mut body) = Self::send_upstream(req, ctx).await?.into_parts();
+        let progress = ctx.transfers.start(method.as_str(), &uri.to_string(), content_length(&parts.headers));
+        let mut recorder = BodyRecorder::new(spill_path.to_path_buf(), &ctx.capture, progress);
+        while let Some(frame) = body.frame().await {
+            let frame = frame.map_err(|e| format!("Failed to read response: {}", e))?;
+            match frame.into_data() {
+                Ok(data) => recorder.push(&data).await?,
+                Err(frame) => {
+                    if let Ok(trailers) = frame.into_trailers() {
+                        parts.extensions.insert(Trailers(trailers));
+                    }
+                }
+            }
+        }
+        Ok(Response::from_parts(parts, recorder.finish().await?))
+    }
+
+    /// Send `req` upstream and pass the response on to the client frame by
+    /// frame as it arrives, keeping its chunks and trailers. The exchange is
+    /// recorded once the body has ended, or the client went away.
+    async fn stream(req: Request<UpstreamBody>, exchange: Exchange, permit: Permit, ctx: ProxyContext) -> Response<ProxyBody> {
+        let response = match Self::send_upstream(req, &ctx).await {
+            Ok(response) => response,
+            Err(message) => {
+                error!("{}", message);
+                exchange.answered(StatusCode::BAD_GATEWAY, &ctx).await;
+                return bad_gateway(message);
+            }
+        };
+        let (parts, mut upstream) = response.into_parts();
+        let (status, headers) = (parts.status, parts.headers.clone());
+        let content_type = headers.get(hyper::header::CONTENT_TYPE).and_then(|v| v.to_str().ok());
+        // event streams can stay open for good, so their events are shown as they come
+        let mut events = match exchange.id {
+            Some(id) if sse::is_event_stream(content_type) => {
+                let headers = headers
+                    .iter()
+                    .map(|(name, value)| {
+                        let value = String::from_utf8_lossy(value.as_bytes());
+                        (name.to_string(), ctx.capture.redact.redact(name.as_str(), &value).into_owned())
+                    })
+                    .collect();
+                ctx.updater.send(EventStream::Opened { id, status: status.as_u16(), headers });
+                Some((id, SseParser::default()))
+            }
+            _ => None,
+        };
+        let (frames, client_frames) = mpsc::channel(STREAM_FRAMES);
+        let updater = ctx.updater.clone();
+        updater.spawn(async move {
+            // the limit counts the request until its body is through
+            let _permit = permit;
+            let progress =
+                ctx.transfers.start(exchange.method.as_str(), &exchange.uri.to_string(), content_length(&headers));
+            let mut recorder = BodyRecorder::new(exchange.capture_path.with_extension("bin"), &ctx.capture, progress);
+            let mut trailers = None;
+            loop {
+                let frame = tokio::select! {
+                    frame = upstream.frame() => frame,
+                    _ = ctx.updater.cancelled() => return,
+                };
+                let frame = match frame {
+                    Some(Ok(frame)) => frame,
+                    Some(Err(e)) => {
+                        // the status is out already, so the client sees the body break off
+                        warn!("Response to {} {} broke off: {}", exchange.method, exchange.uri, e);
+                        let _ = frames.send(Err(std::io::Error::other(e))).await;
+                        break;
+                    }
+                    None => break,
+                };
+                if let Some(data) = frame.data_ref()
+                    && let Err(e) = recorder.push(data).await
+                {
+                    error!("{}", e);
+                    let _ = frames.send(Err(std::io::Error::other(e))).await;
+                    exchange.answered(StatusCode::BAD_GATEWAY, &ctx).await;
+                    if let Some((id, _)) = events {
+                        ctx.updater.send(EventStream::Closed { id });
+                    }
+                    return;
+                }
+                if let (Some(data), Some((id, parser))) = (frame.data_ref(), &mut events) {
+                    let parsed = parser.push(data);
+                    if !parsed.is_empty() {
+                        ctx.updater.send(EventStream::Events { id: *id, events: parsed });
+                    }
+                }
+                if let Some(fields) = frame.trailers_ref() {
+                    trailers = Some(fields.clone());
+                }
+                if frames.send(Ok(frame)).await.is_err() {
+                    info!("Client went away during the response to {} {}", exchange.method, exchange.uri);
+                    break;
+                }
+            }
+            match recorder.finish().await {
+                Ok(body) => exchange.finish(status, &headers, &body, trailers.as_ref(), None, &ctx).await,
+                Err(e) => {
+                    error!("{}", e);
+                    exchange.answered(StatusCode::BAD_GATEWAY, &ctx).await;
+                }
+            }
+            if let Some((id, _)) = events {
+                ctx.updater.send(EventStream::Closed { id });
+            }
+        });
+        let body = stream::unfold(client_frames, |mut frames| async move {
+            frames.recv().await.map(|frame| (frame, frames))
+        });
+        Response::from_parts(parts, BodyExt::boxed(StreamBody::new(body)))
+    }
+
     async fn handle_request(
         req: Request<Incoming>,
-        logs: SharedLogs,
-        updater: Option<Updater>,
-    ) -> Result<Response<Full<Bytes>>, hyper::Error> {
+        ctx: ProxyContext,
+        client: SocketAddr,
+        client_name: Option<String>,
+    ) -> Result<Response<ProxyBody>, InjectedReset> {
+        // origin-form requests in forward mode are addressed to yap itself
+        if Self::is_pac_request(&req, &ctx) {
+            return Ok(Self::serve_pac(&req, &ctx));
+        }
+        let req = match &ctx.reverse {
+            Some(upstream) => Self::rewrite_reverse(req, upstream),
+            None => req,
+        };
+        ctx.profiles.request(client.ip(), client_name.clone());
+        let req = Self::rewrite_rules(req, &ctx, client.ip()).await;
+
+        // buffered so the request can be inspected before it is forwarded
+        let (mut parts, body) = req.into_parts();
+        let attempt = parts.headers.remove(ATTEMPT_HEADER).and_then(|v| v.to_str().ok().and_then(Attempt::parse));
+        let (mut body, request_trailers) = match body.collect().await {
+            Ok(collected) => {
+                let trailers = collected.trailers().cloned();
+                (collected.to_bytes(), trailers)
+            }
+            Err(e) => {
+                error!("Failed to read request body: {}", e);
+                return Ok(Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(full(Bytes::from("Failed to read request")))
+                    .unwrap());
+            }
+        };
+        let scripted = if ctx.scripts.is_empty() {
+            None
+        } else {
+            ctx.scripts.on_request(&mut parts, &mut body)
+        };
+        let content_type = parts.headers.get(hyper::header::CONTENT_TYPE).and_then(|v| v.to_str().ok());
+        let graphql = graphql::parse_request(content_type, parts.uri.query(), &body);
+        let body_hash = {
+            let mut hasher = DefaultHasher::new();
+            body.hash(&mut hasher);
+            hasher.finish()
+        };
+        let request_body = body.clone();
+        let req = match request_trailers {
+            Some(trailers) => {
+                announce_trailers(&mut parts.headers, &trailers);
+                Request::from_parts(parts, with_trailers(body, trailers))
+            }
+            None => Request::from_parts(parts, Full::new(body).boxed()),
+        };
+
         let method = req.method().clone();
         let uri = req.uri().clone();
         let req_headers = req.headers().clone();
         let timestamp = Utc::now();
+        let started = Instant::now();
         
         info!("Received {} {}", method, uri);
 
+        let fault = faults::pick(&mut ctx.faults.write().await, &host_of(&uri.to_string()), uri.path(), fastrand::f64);
+        if let Some(fault) = &fault {
+            warn!("Injecting fault {:?} into {} {}", fault, method, uri);
+        }
+
         // Log the request
-        Self::log_request(method.as_str(), &uri.to_string(), logs.clone(), &updater).await;
+        let log_id = Self::log_request(
+            HttpLog {
+                method: method.to_string(),
+                uri: uri.to_string(),
+                timestamp,
+                graphql: graphql.map(Arc::new),
+                cookie: req_headers
+                    .get(hyper::header::COOKIE)
+                    .and_then(|v| v.to_str().ok())
+                    .map(str::to_string),
+                fault: fault.as_ref().map(Fault::label),
+                listener: ctx.label.clone(),
+                client: Some(client),
+                client_name,
+                session: ctx.state.recording(),
+                body_hash,
+                trace_id: trace::trace_id(&req_headers),
+                attempt,
+                ..Default::default()
+            },
+            &ctx,
+        )
+        .await;
+        let exchange = Exchange {
+            id: log_id,
+            span: ctx.otlp.as_ref().map(|_| SpanStart::new(method.as_str(), &uri, &req_headers, timestamp)),
+            capture_path: Self::capture_path(&ctx.capture_dir, &uri.to_string(), log_id),
+            client,
+            method,
+            uri,
+            headers: req_headers,
+            body: request_body,
+            timestamp,
+            started,
+        };
+        let (method, uri) = (&exchange.method, &exchange.uri);
 
         // For regular HTTP requests (not CONNECT), forward them
         if method != Method::CONNECT {
-            // Build the client request
-            let client = hyper_util::client::legacy::Client::builder(hyper_util::rt::TokioExecutor::new())
-                .build_http();
+            if let Some(delay) = fault.as_ref().and_then(Fault::delay) {
+                tokio::time::sleep(delay).await;
+            }
+            let replayed = match (&scripted, &ctx.replay.from) {
+                (None, Some(dir)) => replay::lookup(dir, method.as_str(), &uri.to_string()).await,
+                _ => None,
+            };
+            let upstream = match scripted.or(replayed) {
+                Some(response) => Ok(response.map(ResponseBody::Memory)),
+                None if ctx.replay.from.is_some() && ctx.replay.on_miss == ReplayMiss::Fail => {
+                    warn!("No capture to replay for {} {}", method, uri);
+                    exchange.answered(StatusCode::SERVICE_UNAVAILABLE, &ctx).await;
+                    return Ok(Response::builder()
+                        .status(StatusCode::SERVICE_UNAVAILABLE)
+                        .body(full(Bytes::from(format!("No capture to replay for {} {}", method, uri))))
+                        .unwrap());
+                }
+                None if let Some(Fault::Status(status)) = fault => {
+                    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+                    Ok(Response::builder()
+                        .status(status)
+                        .body(ResponseBody::Memory(Bytes::from(format!("Fault injected by yap: {}", status))))
+                        .unwrap())
+                }
+                None => match Self::acquire(&ctx, exchange.client.ip(), &host_of(&uri.to_string())).await {
+                    // nothing needs the whole body before the client gets it
+                    Ok(permit) if fault.is_none() && ctx.scripts.is_empty() => {
+                        return Ok(Self::stream(req, exchange, permit, ctx).await);
+                    }
+                    Ok(_permit) => {
+                        Self::forward(req, &ctx, &exchange.capture_path.with_extension("bin")).await
+                    }
+                    Err(rejected) => {
+                        exchange.answered(StatusCode::TOO_MANY_REQUESTS, &ctx).await;
+                        let mut response = Response::builder().status(StatusCode::TOO_MANY_REQUESTS);
+                        if let Some(wait) = rejected.retry_after {
+                            let seconds = wait.as_secs_f64().ceil().max(1.0) as u64;
+                            response = response.header(hyper::header::RETRY_AFTER, seconds);
+                        }
+                        return Ok(response.body(full(Bytes::from("Rate limited by yap"))).unwrap());
+                    }
+                },
+            };
 
-            match client.request(req).await {
+            match upstream {
                 Ok(response) => {
-                    let status = response.status();
-                    let headers = response.headers().clone();
-                    
-                    // Read the body
-                    let body_bytes = match response.into_body().collect().await {
-                        Ok(collected) => collected.to_bytes(),
-                        Err(e) => {
-                            error!("Failed to read response body: {}", e);
-                            return Ok(Response::builder()
-                                .status(StatusCode::BAD_GATEWAY)
-                                .body(Full::new(Bytes::from("Failed to read response")))
-                                .unwrap());
-                        }
-                    };
+                    let (mut parts, mut body) = response.into_parts();
+                    // spilled bodies are too big to hand to scripts
+                    let mut tag = None;
+                    if let ResponseBody::Memory(bytes) = &mut body
+                        && !ctx.scripts.is_empty()
+                    {
+                        let mut response = Response::from_parts(parts, std::mem::take(bytes));
+                        tag = ctx.scripts.on_response(method, uri, &exchange.headers, &mut response);
+                        (parts, *bytes) = response.into_parts();
+                    }
+                    let status = parts.status;
+                    let mut headers = parts.headers;
+                    let trailers = parts.extensions.remove::<Trailers>().map(|Trailers(trailers)| trailers);
+                    exchange.finish(status, &headers, &body, trailers.as_ref(), tag, &ctx).await;
 
-                    // Save the request and response to file (without request body for now)
-                    if let Err(e) = Self::save_request_to_file(
-                        method.as_str(),
-                        &uri.to_string(),
-                        &req_headers,
-                        None,  // We don't save request body to avoid consuming the stream
-                        status.as_u16(),
-                        &headers,
-                        &body_bytes,
-                        timestamp,
-                    ).await {
-                        error!("Failed to save request to file: {}", e);
+                    match fault {
+                        Some(Fault::Reset) => return Err(InjectedReset),
+                        // the headers still promise the whole body
+                        Some(Fault::Truncate) => {
+                            if let ResponseBody::Memory(bytes) = &mut body {
+                                *bytes = bytes.slice(..bytes.len() / 2);
+                            }
+                        }
+                        _ => {}
                     }
 
+                    if let Some(trailers) = &trailers {
+                        announce_trailers(&mut headers, trailers);
+                    }
                     let mut resp = Response::builder()
                         .status(status);
                     
@@ -311,14 +1458,26 @@ impl Proxy {
                         resp = resp.header(name, value);
                     }
 
-                    return Ok(resp.body(Full::new(body_bytes)).unwrap());
+                    let body = match (body, trailers) {
+                        (ResponseBody::Memory(bytes), Some(trailers)) => with_trailers(bytes, trailers),
+                        (ResponseBody::Memory(bytes), None) => full(bytes),
+                        (ResponseBody::Spilled { path, .. }, trailers) => match compression::open(&path).await {
+                            Ok(file) => {
+                                let trailers = stream::iter(trailers.map(|trailers| Ok(Frame::trailers(trailers))));
+                                BodyExt::boxed(StreamBody::new(ReaderStream::new(file).map_ok(Frame::data).chain(trailers)))
+                            }
+                            Err(e) => {
+                                error!("Failed to reopen spilled body {}: {}", path.display(), e);
+                                return Ok(bad_gateway("Failed to read spilled response"));
+                            }
+                        },
+                    };
+                    return Ok(resp.body(body).unwrap());
                 }
-                Err(e) => {
-                    error!("Failed to forward request: {}", e);
-                    return Ok(Response::builder()
-                        .status(StatusCode::BAD_GATEWAY)
-                        .body(Full::new(Bytes::from(format!("Failed to forward request: {}", e))))
-                        .unwrap());
+                Err(message) => {
+                    error!("{}", message);
+                    exchange.answered(StatusCode::BAD_GATEWAY, &ctx).await;
+                    return Ok(bad_gateway(message));
                 }
             }
         }
@@ -326,66 +1485,107 @@ impl Proxy {
         // For CONNECT, return OK (shouldn't reach here as CONNECT is handled separately)
         Ok(Response::builder()
             .status(StatusCode::OK)
-            .body(Full::new(Bytes::new()))
+            .body(full(Bytes::new()))
             .unwrap())
     }
 
-    async fn run_server(logs: SharedLogs, updater: Option<Updater>) {
-        let addr = SocketAddr::from(([127, 0, 0, 1], 9999));
-        
-        let listener = match TcpListener::bind(addr).await {
+    async fn run_server(ctx: ProxyContext) {
+        let addr = ctx.listen;
+
+        if let Err(e) = fs::create_dir_all(&ctx.capture_dir).await {
+            error!("Failed to create capture directory {}: {}", ctx.capture_dir.display(), e);
+            ctx.state.error(format!("Failed to create {}: {}", ctx.capture_dir.display(), e));
+        }
+
+        let listener = match listener::bind(addr) {
             Ok(listener) => {
-                info!("Proxy server listening on {}", addr);
+                match &ctx.reverse {
+                    Some(upstream) => info!("Reverse proxy listening on {}, forwarding to {}", addr, upstream),
+                    None => info!("Proxy server listening on {}", addr),
+                }
+                ctx.state.add_listen(addr);
                 listener
             }
             Err(e) => {
                 error!("Failed to bind to {}: {}", addr, e);
+                ctx.state.error(format!("Failed to bind to {}: {}", addr, e));
                 return;
             }
         };
 
         loop {
-            let (stream, _) = match listener.accept().await {
+            let accepted = tokio::select! {
+                accepted = listener.accept() => accepted,
+                _ = ctx.updater.cancelled() => {
+                    info!("Proxy server shutting down");
+                    return;
+                }
+            };
+            let (stream, client) = match accepted {
                 Ok(conn) => conn,
                 Err(e) => {
                     error!("Failed to accept connection: {}", e);
                     continue;
                 }
             };
+            // IPv4 clients of a `[::]` listener arrive as `::ffff:a.b.c.d`
+            let client = SocketAddr::new(client.ip().to_canonical(), client.port());
 
-            let logs = logs.clone();
-            let updater = updater.clone();
+            let ctx = ctx.clone();
+            let updater = ctx.updater.clone();
+            // the first connection from an address doesn't wait for its name,
+            // its requests are named once the lookup is done
+            if let Some(lookup) = ctx.clients.lookup(client.ip()) {
+                let ctx = ctx.clone();
+                updater.spawn(async move {
+                    if let Some(name) = lookup.await {
+                        Self::name_client(&ctx, client.ip(), name).await;
+                    }
+                });
+            }
 
-            tokio::spawn(async move {
+            updater.spawn(async move {
                 // Peek at the first request to see if it's CONNECT
                 let io = TokioIo::new(stream);
-                
-                if let Err(err) = http1::Builder::new()
+                let shutdown = ctx.updater.clone();
+
+                let conn = http1::Builder::new()
                     .preserve_header_case(true)
                     .title_case_headers(true)
                     .serve_connection(
                         io,
-                        service_fn(move |req| {
-                            let logs = logs.clone();
-                            let updater = updater.clone();
+                        service_fn(move |mut req| {
+                            let (ctx, client_name) = (ctx.clone(), ctx.clients.name(client.ip()));
                             async move {
+                                if let Some(denied) = Self::check_auth(&mut req, &ctx) {
+                                    return Ok(denied);
+                                }
                                 if req.method() == Method::CONNECT {
                                     // For CONNECT, we need to hijack the connection
                                     // Return a special response that won't be sent
                                     // This is a limitation - we'll handle it differently
-                                    Ok::<_, hyper::Error>(Response::builder()
+                                    Ok::<_, InjectedReset>(Response::builder()
                                         .status(StatusCode::OK)
-                                        .body(Full::new(Bytes::new()))
+                                        .body(full(Bytes::new()))
                                         .unwrap())
                                 } else {
-                                    Self::handle_request(req, logs, updater).await
+                                    Self::handle_request(req, ctx, client, client_name).await
                                 }
                             }
                         }),
                     )
-                    .with_upgrades()
-                    .await
-                {
+                    .with_upgrades();
+                tokio::pin!(conn);
+
+                // on shutdown let the in-flight request finish, then close
+                let result = tokio::select! {
+                    result = conn.as_mut() => result,
+                    _ = shutdown.cancelled() => {
+                        conn.as_mut().graceful_shutdown();
+                        conn.await
+                    }
+                };
+                if let Err(err) = result {
                     error!("Error serving connection: {:?}", err);
                 }
             });
@@ -393,9 +1593,208 @@ impl Proxy {
     }
 }
 
+impl Proxy {
+    /// Take the rules of a reloaded config. Other proxy settings, like the
+    /// listen address, only change on restart.
+    fn reload_rules(&mut self, config: Config) {
+        self.profiles.set_config(&config.clients);
+        let proxy = config.proxy;
+        self.limiter.set_rules(proxy.limits);
+        let (rules, faults, assertions) = (self.rules.clone(), self.faults.clone(), self.assertions.clone());
+        self.commands.push(Command::new(async move {
+            *rules.write().await = proxy.rewrites;
+            *faults.write().await = proxy.faults;
+            *assertions.write().await = config.assertions;
+            None
+        }));
+    }
+
+    /// Write a draft OpenAPI document per host of this session to the
+    /// `openapi` directory next to the captures.
+    fn export_openapi(&mut self) {
+        let logs = self.snapshot.borrow().clone();
+        let dir = self.capture_dir.join("openapi");
+        let state = self.state.clone();
+        self.commands.push(Command::new(async move {
+            let mut samples = Vec::with_capacity(logs.len());
+            for log in logs.iter() {
+                let exchange = CapturedExchange::load(&log.path).await.ok();
+                // bodies stored beside the capture are only referenced in it
+                let body = |part| async move {
+                    let body = CapturedExchange::load_body(&log.path, part).await.ok()?;
+                    String::from_utf8(body).ok()
+                };
+                let request_content_type = exchange.as_ref().and_then(|e| {
+                    e.request_headers
+                        .iter()
+                        .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+                        .map(|(_, value)| value.clone())
+                });
+                samples.push(openapi::Sample {
+                    method: log.method.clone(),
+                    url: log.uri.clone(),
+                    status: log.status,
+                    content_type: exchange.as_ref().and_then(|e| e.content_type().map(str::to_string)),
+                    body: body(BodyPart::Response).await,
+                    request_content_type,
+                    request_body: body(BodyPart::Request).await,
+                });
+            }
+            let documents = openapi::generate(&samples);
+            if documents.is_empty() {
+                state.notify("Nothing captured to describe");
+                return None;
+            }
+            let written: std::io::Result<()> = async {
+                fs::create_dir_all(&dir).await?;
+                for (host, document) in &documents {
+                    let path = dir.join(format!("{}.json", host.replace(':', "_")));
+                    fs::write(&path, serde_json::to_string_pretty(document)?).await?;
+                }
+                Ok(())
+            }
+            .await;
+            match written {
+                Ok(()) => {
+                    info!("Wrote {} OpenAPI document(s) to {}", documents.len(), dir.display());
+                    state.notify(format!("Wrote {} OpenAPI draft(s) to {}", documents.len(), dir.display()));
+                }
+                Err(e) => {
+                    error!("Failed to write OpenAPI documents to {}: {}", dir.display(), e);
+                    state.error(format!("Failed to write {}: {}", dir.display(), e));
+                }
+            }
+            None
+        }));
+    }
+}
+
+impl Proxy {
+    fn save_session(&mut self, path: PathBuf, session: Option<String>) {
+        let mut logs = self.snapshot.borrow().to_vec();
+        if session.is_some() {
+            logs.retain(|log| log.session == session);
+        }
+        let state = self.state.clone();
+        self.commands.push(Command::new(async move {
+            match session::save(&path, &logs).await {
+                Ok(count) => {
+                    info!("Saved {} requests to {}", count, path.display());
+                    state.notify(format!("Saved {} requests to {}", count, path.display()));
+                }
+                Err(e) => {
+                    error!("Failed to save session to {}: {}", path.display(), e);
+                    state.error(format!("Failed to save {}: {}", path.display(), e));
+                }
+            }
+            None
+        }));
+    }
+
+    /// Load an archive in place of the captured requests. Its capture files
+    /// are restored under `loaded/` in this session's directory; requests
+    /// arriving afterwards are added as usual.
+    fn load_session(&mut self, path: PathBuf) {
+        let name = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        let dir = self.capture_dir.join("loaded").join(name);
+        let (logs, snapshot, state) = (self.logs.clone(), self.snapshot.clone(), self.state.clone());
+        let capacity = self.capture.capacity.max(1);
+        self.commands.push(Command::then_render(async move {
+            let loaded = match session::load(&path, &dir).await {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    error!("Failed to load session from {}: {}", path.display(), e);
+                    state.error(format!("Failed to load {}: {}", path.display(), e));
+                    return;
+                }
+            };
+            let skipped = loaded.len().saturating_sub(capacity);
+            let mut logs = logs.write().await;
+            logs.clear();
+            for mut log in loaded.into_iter().skip(skipped) {
+                log.id = NEXT_LOG_ID.fetch_add(1, Ordering::Relaxed);
+                logs.push_back(Arc::new(log));
+            }
+            Self::publish(&logs, &snapshot);
+            info!("Loaded {} requests from {}", logs.len(), path.display());
+            if skipped > 0 {
+                state.notify(format!("Loaded the last {} requests of {}", logs.len(), path.display()));
+            } else {
+                state.notify(format!("Loaded {} requests from {}", logs.len(), path.display()));
+            }
+        }));
+    }
+
+    /// Add the requests captured in the session directory `dir` ahead of
+    /// those captured since, as far as the capacity allows.
+    fn restore_session(&mut self, dir: PathBuf) {
+        let (logs, snapshot, state) = (self.logs.clone(), self.snapshot.clone(), self.state.clone());
+        let capacity = self.capture.capacity.max(1);
+        let clients = self.clients.clone();
+        self.commands.push(Command::then_render(async move {
+            let restored = match recovery::restore(&dir).await {
+                Ok(restored) => restored,
+                Err(e) => {
+                    error!("Failed to restore the session in {}: {}", dir.display(), e);
+                    state.error(format!("Failed to restore {}: {}", dir.display(), e));
+                    return;
+                }
+            };
+            let mut logs = logs.write().await;
+            let skipped = restored.len().saturating_sub(capacity.saturating_sub(logs.len()));
+            let restored: Vec<_> = restored
+                .into_iter()
+                .skip(skipped)
+                .map(|mut log| {
+                    log.id = NEXT_LOG_ID.fetch_add(1, Ordering::Relaxed);
+                    log.client_name = log.client.and_then(|client| clients.label(client.ip()));
+                    Arc::new(log)
+                })
+                .collect();
+            let count = restored.len();
+            for log in restored.into_iter().rev() {
+                logs.push_front(log);
+            }
+            Self::publish(&logs, &snapshot);
+            let name = dir.file_name().unwrap_or_default().to_string_lossy();
+            info!("Restored {} requests from {}", count, dir.display());
+            state.notify(format!("Restored {} requests of session {}", count, name));
+        }));
+    }
+}
+
 impl Component for Proxy {
-    fn component_will_mount(&mut self, _config: Config) -> color_eyre::Result<()> {
+    fn component_will_mount(&mut self, config: Config) -> color_eyre::Result<()> {
         info!("Proxy::component_will_mount - Initializing proxy");
+        self.rules = Arc::new(RwLock::new(config.proxy.rewrites.clone()));
+        self.faults = Arc::new(RwLock::new(config.proxy.faults.clone()));
+        self.assertions = Arc::new(RwLock::new(config.assertions.clone()));
+        self.limiter = Arc::new(Limiter::new(config.proxy.limits.clone()));
+        self.api = config.api.listen;
+        self.control = config.api.socket.clone();
+        if let Some(dir) = &config.replay.from {
+            info!("Replaying responses from {}", dir.display());
+        }
+        self.replay = config.replay;
+        let scripts_dir = config.scripts.dir.unwrap_or_else(|| config.config.config_dir.join("scripts"));
+        self.scripts = Arc::new(Scripts::new(scripts_dir));
+        if let Some(errors) = self.scripts.reload()
+            && let Some(first) = errors.first()
+        {
+            for e in &errors {
+                error!("Failed to load script {}", e);
+            }
+            self.state.error(format!("Failed to load script {}", first));
+        }
+        self.clients = Arc::new(ClientNames::new(&config.clients, &config.proxy.dns));
+        self.profiles = Arc::new(ClientProfiles::new(&config.clients));
+        self.proxy = config.proxy;
+        let session = Local::now().format(SESSION_FORMAT).to_string();
+        self.capture_dir = config.capture.session_dir(&config.config.data_dir, &session);
+        info!("Writing captures to {}", self.capture_dir.display());
+        self.access_log = Arc::new(Mutex::new(AccessLog::new(&self.capture_dir, config.capture.access_log.clone())));
+        self.capture = config.capture;
+        self.otlp = config.otlp;
         Ok(())
     }
 
@@ -406,17 +1805,182 @@ impl Component for Proxy {
     ) -> color_eyre::Result<()> {
         info!("Proxy::component_did_mount - Starting proxy server");
         self.updater = Some(updater.clone());
-        
-        let logs = self.logs.clone();
-        let updater_clone = Some(updater);
-        
-        tokio::spawn(async move {
-            Self::run_server(logs, updater_clone).await;
+
+        let ctx = ProxyContext {
+            logs: self.logs.clone(),
+            snapshot: self.snapshot.clone(),
+            updater: updater.clone(),
+            listen: self.proxy.listen,
+            reverse: self.proxy.reverse.as_deref().map(url::Url::parse).transpose()?,
+            label: None,
+            clients: self.clients.clone(),
+            profiles: self.profiles.clone(),
+            proxy: Arc::new(self.proxy.clone()),
+            capture: self.capture.clone(),
+            capture_dir: self.capture_dir.clone(),
+            access_log: self.access_log.clone(),
+            capturing: self.capturing.clone(),
+            rules: self.rules.clone(),
+            faults: self.faults.clone(),
+            assertions: self.assertions.clone(),
+            state: self.state.clone(),
+            replay: self.replay.clone(),
+            scripts: self.scripts.clone(),
+            limiter: self.limiter.clone(),
+            client: upstream_client(&self.proxy.upstream, &self.proxy.dns, self.connections.clone())?,
+            otlp: Exporter::spawn(&self.otlp, self.state.clone(), &updater)?,
+            tee: (self.capture.tee.as_deref()).and_then(|target| Tee::spawn(target, self.state.clone(), &updater)),
+            transfers: self.transfers.clone(),
+        };
+
+        let mut listeners = vec![ctx.clone()];
+        for listener in &self.proxy.listeners {
+            listeners.push(ProxyContext {
+                listen: listener.listen,
+                reverse: listener.reverse.as_deref().map(url::Url::parse).transpose()?,
+                label: Some(listener.label.clone().unwrap_or_else(|| listener.listen.to_string())),
+                ..ctx.clone()
+            });
+        }
+        for ctx in listeners {
+            if let Some(upstream) = &ctx.reverse
+                && !matches!(upstream.scheme(), "http" | "https")
+            {
+                warn!("Reverse proxy upstream {} is neither http nor https and can't be reached", upstream);
+            }
+            updater.spawn(Self::run_server(ctx));
+        }
+        updater.spawn(scripts::watch(self.scripts.clone(), self.state.clone(), updater.clone()));
+        // sessions are what the janitor removes, so it needs them in directories of their own
+        let retention = &self.capture.retention;
+        if retention.enabled()
+            && self.capture.per_session
+            && let Some(root) = self.capture_dir.parent()
+        {
+            let keep = std::iter::once(self.capture_dir.clone()).chain(self.replay.from.clone()).collect();
+            let (root, state) = (root.to_path_buf(), self.state.clone());
+            updater.spawn(retention::run(root, keep, retention.clone(), state, updater.clone()));
+        }
+        if self.capture.restore != RestorePolicy::Never
+            && self.capture.per_session
+            && let Some(root) = self.capture_dir.parent()
+        {
+            let (root, current, policy) = (root.to_path_buf(), self.capture_dir.clone(), self.capture.restore);
+            let sender = updater.clone();
+            updater.spawn(async move {
+                match recovery::last_session(&root, &current).await {
+                    Ok(Some((dir, _))) if policy == RestorePolicy::Always => sender.send(RestoreSession(dir)),
+                    Ok(Some((dir, requests))) => sender.send(RestorableSession { dir, requests }),
+                    Ok(None) => {}
+                    // no captures yet
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => error!("Failed to look for the last session in {}: {}", root.display(), e),
+                }
+            });
+        }
+        updater.send(RulesShared {
+            rewrites: self.rules.clone(),
+            faults: self.faults.clone(),
+            limiter: self.limiter.clone(),
         });
+        updater.send(ClientsShared(self.profiles.clone()));
+
+        let api = ApiContext {
+            logs: self.subscribe(),
+            capturing: self.capturing.clone(),
+            rules: self.rules.clone(),
+            state: self.state.clone(),
+            updater: updater.clone(),
+        };
+        if let Some(listen) = self.api {
+            updater.spawn(api::serve(listen, api.clone()));
+        }
+        if let Some(path) = &self.control {
+            updater.spawn(control::serve(path.clone(), api));
+        }
         
         Ok(())
     }
 
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        if let Action::ConfigReloaded(reloaded) = &action {
+            self.reload_rules(reloaded.config());
+            return Ok(None);
+        }
+        if action == Action::ExportOpenApi {
+            self.export_openapi();
+            return Ok(None);
+        }
+        if action == Action::ToggleCapture {
+            let capturing = !self.capturing.fetch_xor(true, Ordering::Relaxed);
+            self.state.set_capturing(capturing);
+            self.state.notify(if capturing { "Capture resumed" } else { "Capture paused" });
+            return Ok(None);
+        }
+        let Action::Message(message) = &action else {
+            return Ok(None);
+        };
+        let logs = self.logs.clone();
+        let snapshot = self.snapshot.clone();
+        if let Some(clear) = message.downcast_ref::<ClearLogs>() {
+            // without a directory per session the captures of earlier runs
+            // share it, so there's nothing to delete on its own
+            if clear.delete_captures && !self.capture.per_session {
+                self.state.error("Captures are only deleted with `capture.per_session`, nothing was cleared");
+                return Ok(None);
+            }
+            self.state.set_capturing(self.capturing.load(Ordering::Relaxed));
+            let (dir, delete, state) = (self.capture_dir.clone(), clear.delete_captures, self.state.clone());
+            let access_log = self.access_log.clone();
+            self.commands.push(Command::then_render(async move {
+                let mut logs = logs.write().await;
+                logs.clear();
+                Self::publish(&logs, &snapshot);
+                drop(logs);
+                if !delete {
+                    state.notify("Session cleared");
+                    return;
+                }
+                access_log.lock().await.close();
+                match fs::remove_dir_all(&dir).await {
+                    Ok(()) => {
+                        info!("Removed capture directory {}", dir.display());
+                        state.notify("Session cleared, captures deleted");
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => state.notify("Session cleared"),
+                    Err(e) => {
+                        error!("Failed to remove {}: {}", dir.display(), e);
+                        state.error(format!("Failed to remove {}: {}", dir.display(), e));
+                    }
+                }
+                // later requests of this session are captured again
+                if let Err(e) = fs::create_dir_all(&dir).await {
+                    error!("Failed to recreate {}: {}", dir.display(), e);
+                }
+            }));
+        } else if let Some(SaveSession { path, session }) = message.downcast_ref::<SaveSession>() {
+            self.save_session(path.clone(), session.clone());
+        } else if let Some(RestoreSession(dir)) = message.downcast_ref::<RestoreSession>() {
+            self.restore_session(dir.clone());
+        } else if let Some(LoadSession(path)) = message.downcast_ref::<LoadSession>() {
+            self.load_session(path.clone());
+        } else if let Some(&TogglePin(id)) = message.downcast_ref::<TogglePin>() {
+            self.commands.push(Command::then_render(async move {
+                let mut logs = logs.write().await;
+                if let Some(log) = logs.iter_mut().rev().find(|log| log.id == id) {
+                    let log = Arc::make_mut(log);
+                    log.pinned = !log.pinned;
+                    Self::publish(&logs, &snapshot);
+                }
+            }));
+        }
+        Ok(None)
+    }
+
+    fn take_commands(&mut self) -> Vec<Command> {
+        std::mem::take(&mut self.commands)
+    }
+
     fn render(
         &mut self,
         _frame: &mut ratatui::Frame,
@@ -426,3 +1990,180 @@ impl Component for Proxy {
         Ok(())
     }
 }
+
+/// The latest request before `logs[index]` with the same method, URL and
+/// body, if it got another status than `status`.
+fn differs_from(logs: &VecDeque<Arc<HttpLog>>, index: usize, status: u16) -> Option<u64> {
+    let log = &logs[index];
+    (logs.range(..index).rev())
+        .filter(|earlier| earlier.status.is_some())
+        .find(|earlier| earlier.method == log.method && earlier.uri == log.uri && earlier.body_hash == log.body_hash)
+        .filter(|earlier| earlier.status != Some(status))
+        .map(|earlier| earlier.id)
+}
+
+/// `name` as a file or directory name any platform takes: the characters
+/// Windows forbids replaced, along with `&` and `=` of queries, a device name
+/// like `CON` or `nul.txt` given a `_`, no trailing dots or spaces, and
+/// shortened to fit. Whole paths can be longer than Windows' 260 characters,
+/// as the standard library turns them into `\\?\` paths there.
+fn safe_file_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '?' | '&' | '=' | '*' | '<' | '>' | '|' | '"' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let name = fit_file_name(&name);
+    let kept = name.trim_end_matches(['.', ' ']).len();
+    let name = format!("{}{}", &name[..kept], "_".repeat(name.len() - kept));
+    let stem = name.split('.').next().unwrap_or_default();
+    if RESERVED_NAMES.iter().any(|reserved| stem.eq_ignore_ascii_case(reserved)) {
+        format!("{}_{}", stem, &name[stem.len()..])
+    } else {
+        name
+    }
+}
+
+/// Shorten names over `MAX_FILE_NAME` bytes, keeping a hash of the full name
+/// so different long URLs don't end up in the same file.
+fn fit_file_name(name: &str) -> String {
+    if name.len() <= MAX_FILE_NAME {
+        return name.to_string();
+    }
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    let suffix = format!("~{:016x}", hasher.finish());
+    let mut end = MAX_FILE_NAME - suffix.len();
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}{}", &name[..end], suffix)
+}
+
+/// Join the origin-form `path_and_query` of a request onto the `upstream` base URL.
+fn reverse_target(upstream: &url::Url, path_and_query: &str) -> Result<hyper::Uri, hyper::http::uri::InvalidUri> {
+    let base = upstream.as_str().trim_end_matches('/');
+    let path_and_query = if path_and_query.starts_with('/') {
+        path_and_query.to_string()
+    } else {
+        format!("/{}", path_and_query)
+    };
+    format!("{}{}", base, path_and_query).parse()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_uri_to_file_path_shortens_long_segments() {
+        let root = Path::new("captures");
+        let short = Proxy::uri_to_file_path(root, "http://example.com/api/users?page=2");
+        assert_eq!(short, Path::new("captures/example.com/api/users_page_2.yap"));
+
+        let long_a = format!("http://example.com/search?q={}", "a".repeat(300));
+        let long_b = format!("http://example.com/search?q={}b", "a".repeat(300));
+        let (path_a, path_b) = (Proxy::uri_to_file_path(root, &long_a), Proxy::uri_to_file_path(root, &long_b));
+        assert!(path_a.file_name().unwrap().len() <= MAX_FILE_NAME + ".yap".len());
+        assert_ne!(path_a, path_b);
+    }
+
+    #[test]
+    fn test_capture_paths_are_per_exchange() {
+        let root = Path::new("captures");
+        let path = |id| Proxy::capture_path(root, "http://example.com/report.txt?page=2", id);
+        assert_eq!(path(Some(7)), Path::new("captures/example.com/report.txt_page_2.7.yap"));
+        assert_eq!(path(Some(7)).with_extension("bin"), Path::new("captures/example.com/report.txt_page_2.7.bin"));
+        assert_ne!(path(Some(7)), path(Some(8)));
+        assert_eq!(path(None), Path::new("captures/example.com/report.txt_page_2.yap"));
+    }
+
+    #[test]
+    fn test_uri_to_file_path_is_safe_on_windows() {
+        let root = Path::new("captures");
+        let path = |uri| Proxy::uri_to_file_path(root, uri).to_string_lossy().into_owned();
+        assert_eq!(path("http://example.com/con/Nul.txt/aux"), "captures/example.com/con_/Nul_.txt/aux_.yap");
+        assert_eq!(path("http://example.com/C:/Windows/a%20b./x"), "captures/example.com/C_/Windows/a%20b_/x.yap");
+        assert_eq!(path("http://[::1]:8080/console/lpt10"), "captures/[__1]/console/lpt10.yap");
+        assert_eq!(path("http://example.com/report.txt?to=a|b"), "captures/example.com/report.txt_to_a_b.yap");
+        assert_eq!(path("not a uri\\..\\"), "captures/unknown/not a uri_.._.yap");
+    }
+
+    #[test]
+    fn test_parse_spilled_body() {
+        let exchange = CapturedExchange::parse(
+            "Status: 200\n\nResponse Headers:\n  content-type: text/plain\n\nResponse Body:\n[Body stored in: big.bin]\nSize: 99999999 bytes\n",
+        );
+        assert_eq!(exchange.body_file.as_deref(), Some(Path::new("big.bin")));
+        assert!(exchange.spilled);
+        assert_eq!(exchange.content_type(), Some("text/plain"));
+    }
+
+    #[test]
+    fn test_parse_request_body_and_inline_body() {
+        let content = "Timestamp: 2026-01-02T03:04:05+00:00\nMethod: POST\nURI: http://a/x\nClient: [::1]:50312\n\
+                       Status: 200\n[Request body stored in: a.req]\n\nRequest Headers:\n  host: a\n\n\
+                       Response Headers:\n\nResponse Body:\n  indented\n\n";
+        let exchange = CapturedExchange::parse(content);
+        assert_eq!((exchange.method.as_str(), exchange.uri.as_str()), ("POST", "http://a/x"));
+        assert_eq!(exchange.timestamp.map(|t| t.to_rfc3339()).as_deref(), Some("2026-01-02T03:04:05+00:00"));
+        assert_eq!(exchange.client, Some(SocketAddr::from(([0, 0, 0, 0, 0, 0, 0, 1], 50312))));
+        assert_eq!(exchange.request_body_file.as_deref(), Some(Path::new("a.req")));
+        assert_eq!(exchange.request_headers, vec![("host".to_string(), "a".to_string())]);
+        assert_eq!(inline_body(content), "  indented\n\n");
+        assert_eq!(inline_body("Response Headers:\n\nResponse Body:\n[Empty]\n"), "");
+    }
+
+    #[test]
+    fn test_parse_response_trailers() {
+        let content = "Status: 200\n\nResponse Headers:\n  trailer: grpc-status\n\n\
+                       Response Trailers:\n  grpc-status: 0\n\nResponse Body:\nok\n";
+        let exchange = CapturedExchange::parse(content);
+        assert_eq!(exchange.headers, vec![("trailer".to_string(), "grpc-status".to_string())]);
+        assert_eq!(exchange.trailers, vec![("grpc-status".to_string(), "0".to_string())]);
+        assert_eq!(exchange.body, "ok");
+        assert_eq!(inline_body(content), "ok\n");
+    }
+
+    #[test]
+    fn test_reverse_target_keeps_base_path() {
+        let upstream = url::Url::parse("http://localhost:3000/api/").unwrap();
+        let uri = reverse_target(&upstream, "/users?page=2").unwrap();
+        assert_eq!(uri.to_string(), "http://localhost:3000/api/users?page=2");
+        assert_eq!(uri.authority().unwrap().as_str(), "localhost:3000");
+    }
+
+    #[test]
+    fn test_differs_from_compares_the_latest_identical_request() {
+        let log = |id: u64, uri: &str, body_hash: u64, status: Option<u16>| {
+            Arc::new(HttpLog {
+                id,
+                method: "POST".to_string(),
+                uri: uri.to_string(),
+                body_hash,
+                status,
+                ..Default::default()
+            })
+        };
+        let logs = VecDeque::from([
+            log(1, "http://api.test/login", 7, Some(200)),
+            log(2, "http://api.test/login", 8, Some(401)),
+            log(3, "http://api.test/other", 7, Some(500)),
+            log(4, "http://api.test/login", 7, None),
+        ]);
+        assert_eq!(differs_from(&logs, 3, 500), Some(1));
+        assert_eq!(differs_from(&logs, 3, 200), None);
+        assert_eq!(differs_from(&logs, 0, 500), None);
+    }
+
+    #[test]
+    fn test_reverse_target_root() {
+        let upstream = url::Url::parse("http://example.com").unwrap();
+        assert_eq!(reverse_target(&upstream, "/").unwrap().to_string(), "http://example.com/");
+    }
+}
@@ -1,48 +1,1144 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
 use ratatui::{prelude::*, widgets::*};
-use tracing::info;
-use crossterm::event::{KeyCode, KeyEvent};
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use tracing::{error, info};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use tokio::{io::AsyncWriteExt, sync::watch};
 
 use super::Component;
-use super::proxy::{SharedLogs, Proxy};
-use crate::{config::Config, framework::{Updater, Action}};
+use super::body_format::format_body;
+use super::charset;
+use super::command_line::expand_home;
+use super::grpc::{self, Descriptors};
+use super::html;
+use super::diff::exchange_diff;
+use super::filter::FilterCache;
+use super::host_tree::{HostTree, TreeRow};
+use super::key_sequence::{KeySequence, Step};
+use super::line_edit::LineEdit;
+use super::multipart::{self, FormPart};
+use super::repeats::Repeats;
+use super::sort::ListSort;
+use super::sse::{self, SseEvent};
+use super::timeline::in_window;
+use super::trace;
+use super::proxy::{
+    BodyPart, CapturedExchange, ClearLogs, EventStream, HttpLog, LogReceiver, LogSnapshot, RestorableSession, RestoreSession,
+    TogglePin,
+};
+use crate::{
+    app::Mode,
+    auth::ProxyAuth,
+    batch::{self, BatchConfig, Report},
+    client::ProxyClient,
+    compression,
+    config::{Config, KeyBindings},
+    framework::{Action, Command, Message, Updater},
+    state::{AppState, SharedState},
+    highlight::{self, HighlightRule},
+    json_path::{self, JsonPath},
+    redact::RedactConfig,
+    retry::{self, Resend, RetryConfig},
+    theme::Theme,
+    tui::Event,
+};
+
+/// Rows materialized above and below the visible part of the list.
+const OVERSCAN: usize = 20;
+
+/// How much of a spilled body the detail popup reads.
+const PREVIEW_BYTES: u64 = 64 * 1024;
+
+/// Values of a query shown in the detail popup; copying takes all of them.
+const QUERY_VALUES: usize = 100;
+
+/// Events kept of a stream still open, the newest ones.
+const LIVE_EVENTS: usize = 500;
+
+/// Replay the listed requests, `:replay` on the command line; the
+/// concurrency is the configured one unless given.
+pub struct ReplayBatch {
+    pub concurrency: Option<usize>,
+}
+
+/// A `text/event-stream` response still being received.
+struct LiveStream {
+    status: u16,
+    headers: Vec<(String, String)>,
+    events: Vec<SseEvent>,
+    /// Events dropped from the front to stay within `LIVE_EVENTS`.
+    dropped: usize,
+}
+
+impl LiveStream {
+    /// Status and content of the detail popup while the stream is open.
+    fn view(&self, theme: &Theme) -> (String, Text<'static>) {
+        let mut lines: Vec<Line> = self
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                Line::from(vec![
+                    Span::styled(format!("{}: ", name), theme.fg(theme.accent)),
+                    Span::raw(value.clone()),
+                ])
+            })
+            .collect();
+        lines.push(Line::from(""));
+        let received = self.dropped + self.events.len();
+        let mut heading = format!("Streaming · {} event{} so far", received, if received == 1 { "" } else { "s" });
+        if self.dropped > 0 {
+            heading.push_str(&format!(", the first {} not shown", self.dropped));
+        }
+        lines.push(Line::styled(heading, theme.fg(theme.title).add_modifier(Modifier::BOLD)));
+        lines.push(Line::from(""));
+        lines.extend(sse::format_events(theme, &self.events).lines);
+        (format!("{} (streaming)", self.status), Text::from(lines))
+    }
+}
+
+/// Capture file of the exchange shown in the detail popup, loaded off the render path.
+#[derive(Clone)]
+enum DetailContent {
+    Loading,
+    Loaded {
+        exchange: Box<CapturedExchange>,
+        /// The body, already pretty-printed.
+        body: Text<'static>,
+        /// The parts of a `multipart/form-data` request body.
+        form: Option<Result<Vec<FormPart>, String>>,
+    },
+    Failed(String),
+}
+
+/// Sent when the capture file for the detail popup of request `id` has been read.
+struct DetailLoaded {
+    id: u64,
+    content: DetailContent,
+}
+
+/// Sent when a body has been written, or failed to, with the notice to show.
+struct BodySaved(String);
+
+/// What the path prompt of the detail popup saves.
+#[derive(Clone, Copy)]
+enum SaveTarget {
+    Body(BodyPart),
+    /// The body of this part of a form upload.
+    FormPart(usize),
+}
+
+/// Sent when the response body of request `id` has been parsed for a query.
+struct QueryDocument {
+    id: u64,
+    document: Result<Arc<serde_json::Value>, String>,
+}
+
+/// Query bar of the detail popup, picking values out of a JSON response body.
+struct BodyQuery {
+    input: LineEdit,
+    /// Typing into the bar; enter leaves the values shown.
+    editing: bool,
+    /// The parsed body, once read.
+    document: Option<Result<Arc<serde_json::Value>, String>>,
+}
+
+impl BodyQuery {
+    /// The values the path picks, or why there are none.
+    fn values(&self) -> Result<Vec<&serde_json::Value>, String> {
+        let document = match &self.document {
+            None => return Err("Reading the body…".to_string()),
+            Some(Err(e)) => return Err(e.clone()),
+            Some(Ok(document)) => document,
+        };
+        match self.input.text().trim() {
+            "" => Ok(Vec::new()),
+            path => Ok(JsonPath::parse(path)?.select(document)),
+        }
+    }
+}
+
+/// Path prompt of the detail popup, for saving one of the bodies.
+struct SavePrompt {
+    target: SaveTarget,
+    path: LineEdit,
+}
+
+/// File name to offer for a body of the request to `uri`: the last segment
+/// of its path, or a generic name.
+fn suggested_path(uri: &str, part: BodyPart) -> String {
+    let segment = url::Url::parse(uri)
+        .ok()
+        .and_then(|url| url.path_segments()?.next_back().filter(|segment| !segment.is_empty()).map(str::to_string));
+    match (part, segment) {
+        (BodyPart::Response, Some(segment)) => segment,
+        (BodyPart::Request, Some(segment)) => format!("{}.request", segment),
+        (BodyPart::Response, None) => "response.body".to_string(),
+        (BodyPart::Request, None) => "request.body".to_string(),
+    }
+}
+
+/// Extension for a body of `content_type`, so the editor picks a fitting mode.
+fn extension(content_type: Option<&str>) -> &'static str {
+    let essence = content_type.and_then(|ct| ct.split(';').next()).unwrap_or_default().trim().to_ascii_lowercase();
+    match essence.as_str() {
+        ct if ct.ends_with("json") => "json",
+        ct if ct.ends_with("html") => "html",
+        ct if ct.ends_with("xml") => "xml",
+        "text/css" => "css",
+        "text/javascript" | "application/javascript" => "js",
+        "application/x-www-form-urlencoded" => "form",
+        ct if ct.starts_with("text/") => "txt",
+        _ => "bin",
+    }
+}
+
+/// Write a body of `log` to a new temporary file for an external program, named
+/// after the request and with an extension for its content type.
+async fn export_body(log: &HttpLog, part: BodyPart) -> std::io::Result<std::path::PathBuf> {
+    let exchange = CapturedExchange::load(&log.path).await?;
+    let (side, headers) = match part {
+        BodyPart::Request => ("request", &exchange.request_headers),
+        BodyPart::Response => ("response", &exchange.headers),
+    };
+    let content_type = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+        .map(|(_, value)| value.as_str());
+    let body = CapturedExchange::load_body(&log.path, part).await?;
+    // the temp directory is shared with other users, so the name is random
+    // and the file must be new, never one planted there before
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    loop {
+        let name = format!("yap-{}-{}-{:08x}.{}", log.id, side, fastrand::u32(..), extension(content_type));
+        let path = std::env::temp_dir().join(name);
+        match options.open(&path).await {
+            Ok(mut file) => {
+                file.write_all(&body).await?;
+                return Ok(path);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
 
-pub type SharedFilter = Arc<RwLock<String>>;
+/// Sent when the comparison between the exchanges `ids` is ready.
+struct DiffLoaded {
+    ids: (u64, u64),
+    text: Text<'static>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ViewMode {
+    /// Requests in capture order.
+    #[default]
+    List,
+    /// Requests grouped under collapsible host nodes.
+    Tree,
+}
 
 pub struct ProxyList {
-    logs: SharedLogs,
+    logs: LogReceiver,
+    /// Latest published logs, refreshed at the start of every render.
+    snapshot: LogSnapshot,
     updater: Option<Updater>,
     scroll_state: ScrollbarState,
     scroll_offset: usize,
     selected_index: usize,
     items_len: usize,
     show_popup: bool,
+    popup_scroll: u16,
+    /// Header highlighted in the detail popup, used for copying.
+    popup_header: usize,
+    /// Part of a form upload selected in the detail popup, and which parts
+    /// are expanded.
+    form_part: usize,
+    form_expanded: Vec<bool>,
+    /// Short feedback shown in the popup title, e.g. after copying.
+    popup_notice: Option<String>,
+    /// Open while asking where to save a body.
+    save_prompt: Option<SavePrompt>,
+    query: Option<BodyQuery>,
+    /// Show HTML response bodies as their text rather than markup.
+    html_text: bool,
+    confirm_clear: bool,
+    /// The previous session's directory and how many requests it holds,
+    /// while offering to restore them.
+    restore_offer: Option<(std::path::PathBuf, usize)>,
     visible_height: usize,
-    filter: SharedFilter,
+    filter: watch::Receiver<String>,
+    filter_cache: FilterCache,
+    state: SharedState,
+    app: watch::Receiver<AppState>,
+    /// Named session the list is limited to, picked in the sessions overlay.
+    shown_session: Option<String>,
+    view_mode: ViewMode,
+    sort: ListSort,
+    /// Fold runs of identical requests in the list view.
+    fold_repeats: bool,
+    repeats: Repeats,
+    host_tree: HostTree,
+    rows: Vec<TreeRow>,
+    /// Ids of the logs that passed the filter during the last render.
+    filtered: Vec<u64>,
+    /// Exchange marked as the left-hand side of a comparison.
+    baseline: Option<HttpLog>,
+    /// Capture of the request shown in the detail popup, keyed by request id.
+    detail: Option<(u64, DetailContent)>,
+    /// Event streams still open, shown instead of their capture until it is written.
+    live: HashMap<u64, LiveStream>,
+    diff: Option<Text<'static>>,
+    /// Baseline and selected request the open comparison is for.
+    diff_ids: (u64, u64),
+    /// The configured external diff command.
+    diff_tool: Option<String>,
+    diff_scroll: u16,
+    keybindings: KeyBindings,
+    /// Count and keys of a list binding being typed, e.g. `10` or `g`.
+    sequence: KeySequence,
+    focused: bool,
+    /// Jump to every new request; scrolling up turns it off.
+    follow: bool,
+    /// Where the list was last drawn, used to map mouse positions to rows.
+    area: Rect,
+    /// The terminal changed size since the last frame, so the selection and
+    /// scroll positions are fitted to the new one.
+    resized: bool,
+    commands: Vec<Command>,
+    /// Message types for decoding gRPC bodies, from `grpc.descriptor_set`.
+    descriptors: Option<Arc<Descriptors>>,
+    highlight: Vec<HighlightRule>,
+    /// Show the client of each request, from `clients.column`.
+    client_column: bool,
+    /// Settings retries from the list are sent with.
+    retry: RetryConfig,
+    batch: BatchConfig,
+    auth: ProxyAuth,
+    redact: RedactConfig,
+    /// Where batch replay reports are written.
+    reports_dir: std::path::PathBuf,
+    theme: Theme,
 }
 
 impl ProxyList {
-    pub fn new(logs: SharedLogs, filter: SharedFilter) -> Self {
+    pub fn new(logs: LogReceiver, filter: watch::Receiver<String>, state: SharedState) -> Self {
         Self {
             logs,
+            snapshot: LogSnapshot::default(),
             updater: None,
             scroll_state: ScrollbarState::default(),
             scroll_offset: 0,
             selected_index: 0,
             items_len: 0,
             show_popup: false,
+            popup_scroll: 0,
+            popup_header: 0,
+            form_part: 0,
+            form_expanded: Vec::new(),
+            popup_notice: None,
+            save_prompt: None,
+            query: None,
+            html_text: false,
+            confirm_clear: false,
+            restore_offer: None,
             visible_height: 10,
             filter,
+            filter_cache: FilterCache::default(),
+            app: state.subscribe(),
+            state,
+            shown_session: None,
+            view_mode: ViewMode::default(),
+            sort: ListSort::default(),
+            fold_repeats: false,
+            repeats: Repeats::default(),
+            host_tree: HostTree::default(),
+            rows: Vec::new(),
+            filtered: Vec::new(),
+            baseline: None,
+            detail: None,
+            live: HashMap::new(),
+            diff: None,
+            diff_ids: (0, 0),
+            diff_tool: None,
+            diff_scroll: 0,
+            keybindings: KeyBindings::default(),
+            sequence: KeySequence::default(),
+            focused: false,
+            follow: true,
+            area: Rect::default(),
+            resized: false,
+            commands: Vec::new(),
+            descriptors: None,
+            highlight: Vec::new(),
+            client_column: false,
+            retry: RetryConfig::default(),
+            batch: BatchConfig::default(),
+            auth: ProxyAuth::default(),
+            redact: RedactConfig::default(),
+            reports_dir: std::path::PathBuf::new(),
+            theme: Theme::default(),
+        }
+    }
+
+    fn selected_row(&self) -> Option<&TreeRow> {
+        self.rows.get(self.selected_index)
+    }
+
+    fn selected_log(&self) -> Option<HttpLog> {
+        let i = self.selected_row()?.request()?;
+        let id = *self.filtered.get(i)?;
+        self.snapshot
+            .iter()
+            .rev()
+            .find(|log| log.id == id)
+            .map(|log| HttpLog::clone(log))
+    }
+
+    /// Copy part of the exchange shown in the detail popup to the clipboard.
+    fn copy_from_popup(&mut self, action: Action) {
+        let Some(log) = self.selected_log() else {
+            return;
+        };
+        let exchange = match &self.detail {
+            Some((id, DetailContent::Loaded { exchange, .. })) if *id == log.id => Ok(exchange),
+            Some((_, DetailContent::Failed(e))) => Err(color_eyre::eyre::eyre!("{}", e)),
+            _ => Err(color_eyre::eyre::eyre!("capture is still loading")),
+        };
+        let (what, text) = match action {
+            Action::CopyUrl => ("URL", Ok(log.uri.clone())),
+            Action::CopyHeader => (
+                "header",
+                exchange.map(|exchange| {
+                    exchange
+                        .headers
+                        .get(self.popup_header)
+                        .map(|(name, value)| format!("{}: {}", name, value))
+                        .unwrap_or_default()
+                }),
+            ),
+            _ => ("body", exchange.map(|exchange| exchange.body.clone())),
+        };
+        let result = text.and_then(|text| crate::clipboard::copy(&text));
+        self.popup_notice = Some(match result {
+            Ok(()) => format!("Copied {}", what),
+            Err(e) => {
+                error!("Failed to copy {}: {}", what, e);
+                format!("Copy failed: {}", e)
+            }
+        });
+    }
+
+    fn hint(&self, mode: Mode, action: &Action) -> String {
+        self.keybindings.hint(mode, action)
+    }
+
+    /// The keybinding context for the state the list is currently in.
+    fn key_mode(&self) -> Mode {
+        if self.confirm_clear || self.restore_offer.is_some() {
+            Mode::Confirm
+        } else if self.save_prompt.is_some() || self.query.as_ref().is_some_and(|query| query.editing) {
+            // the path is edited like the command line
+            Mode::Command
+        } else if self.diff.is_some() {
+            Mode::Diff
+        } else if self.show_popup {
+            Mode::Detail
+        } else {
+            Mode::List
+        }
+    }
+
+    /// Tell the status bar which context the keys go to, while focused.
+    fn sync_mode(&self) {
+        if self.focused {
+            self.state.set_mode(self.key_mode());
+        }
+    }
+
+    /// Apply an action resolved from the keymap of the current context.
+    fn perform(&mut self, action: Action) {
+        self.perform_counted(action, None)
+    }
+
+    /// Like [`Self::perform`], with the count typed before the keys: motions
+    /// move that many rows, or to that row for `gg` and `G`.
+    fn perform_counted(&mut self, action: Action, count: Option<usize>) {
+        let times = count.unwrap_or(1);
+        match (self.key_mode(), action) {
+            (Mode::Confirm, Action::ConfirmClear) if self.confirm_clear => {
+                self.clear_session(false);
+                self.confirm_clear = false;
+            }
+            (Mode::Confirm, Action::ConfirmClearAndDelete) if self.confirm_clear => {
+                self.clear_session(true);
+                self.confirm_clear = false;
+            }
+            (Mode::Confirm, Action::ConfirmRestore) if self.restore_offer.is_some() => {
+                if let (Some((dir, _)), Some(updater)) = (self.restore_offer.take(), &self.updater) {
+                    updater.send(RestoreSession(dir));
+                }
+            }
+            // keys of the other confirmation cancel this one
+            (Mode::Confirm, _) => self.dismiss_confirm(),
+            (Mode::Diff, Action::Close) => self.diff = None,
+            (Mode::Diff, Action::OpenExternalDiff) => self.open_external_diff(),
+            (Mode::Diff, Action::ScrollDown) => self.diff_scroll = self.diff_scroll.saturating_add(1),
+            (Mode::Diff, Action::ScrollUp) => self.diff_scroll = self.diff_scroll.saturating_sub(1),
+            (Mode::Detail, Action::Close) => {
+                self.show_popup = false;
+                self.detail = None;
+                self.popup_scroll = 0;
+                self.popup_header = 0;
+                self.popup_notice = None;
+                self.query = None;
+            }
+            (Mode::Detail, Action::ScrollDown) => self.popup_scroll = self.popup_scroll.saturating_add(1),
+            (Mode::Detail, Action::ScrollUp) => self.popup_scroll = self.popup_scroll.saturating_sub(1),
+            (Mode::Detail, Action::NextHeader) => self.popup_header = self.popup_header.saturating_add(1),
+            (Mode::Detail, Action::PrevHeader) => self.popup_header = self.popup_header.saturating_sub(1),
+            (Mode::Detail, action @ (Action::CopyUrl | Action::CopyHeader | Action::CopyBody)) => {
+                self.copy_from_popup(action)
+            }
+            (Mode::Detail, action @ (Action::NextInTrace | Action::PrevInTrace)) => {
+                if !self.step_through_trace(action == Action::NextInTrace) {
+                    return;
+                }
+                self.popup_scroll = 0;
+                self.popup_header = 0;
+                self.popup_notice = None;
+                self.load_detail();
+            }
+            (Mode::Detail, Action::ToggleHtmlText) => {
+                self.html_text = !self.html_text;
+                // the query is on the same body
+                let query = self.query.take();
+                self.load_detail();
+                self.query = query;
+            }
+            (Mode::Detail, Action::QueryBody) => self.open_query(),
+            (Mode::Detail, Action::CopyQuery) => self.copy_query(),
+            (Mode::Detail, Action::OpenBody) => self.open_body(BodyPart::Response),
+            (Mode::Detail, Action::OpenRequestBody) => self.open_body(BodyPart::Request),
+            (Mode::Detail, action @ (Action::SaveBody | Action::SaveRequestBody)) => {
+                let Some(log) = self.selected_log() else {
+                    return;
+                };
+                let part = match action {
+                    Action::SaveRequestBody => BodyPart::Request,
+                    _ => BodyPart::Response,
+                };
+                let mut path = LineEdit::default();
+                path.set(&suggested_path(&log.uri, part));
+                self.save_prompt = Some(SavePrompt { target: SaveTarget::Body(part), path });
+            }
+            (Mode::Detail, action @ (Action::NextPart | Action::PrevPart)) => {
+                let count = self.form_parts().map_or(0, <[FormPart]>::len);
+                self.form_part = match action {
+                    Action::NextPart => (self.form_part + 1).min(count.saturating_sub(1)),
+                    _ => self.form_part.saturating_sub(1),
+                };
+            }
+            (Mode::Detail, Action::TogglePart) => {
+                let count = self.form_parts().map_or(0, <[FormPart]>::len);
+                self.form_expanded.resize(count, false);
+                if let Some(expanded) = self.form_expanded.get_mut(self.form_part) {
+                    *expanded = !*expanded;
+                }
+            }
+            (Mode::Detail, Action::SavePart) => {
+                let Some(part) = self.form_parts().and_then(|parts| parts.get(self.form_part)) else {
+                    return;
+                };
+                let mut path = LineEdit::default();
+                path.set(&part.file_name());
+                self.save_prompt = Some(SavePrompt { target: SaveTarget::FormPart(self.form_part), path });
+            }
+            (Mode::List, Action::SelectNext) => {
+                if self.selected_index >= self.items_len.saturating_sub(1) {
+                    return;
+                }
+                self.select(self.selected_index.saturating_add(times));
+            }
+            (Mode::List, Action::SelectPrev) => {
+                self.follow = false;
+                if self.selected_index == 0 {
+                    return;
+                }
+                self.select(self.selected_index.saturating_sub(times));
+            }
+            (Mode::List, Action::SelectFirst) => {
+                self.follow = false;
+                self.select(count.map_or(0, |row| row.saturating_sub(1)));
+            }
+            (Mode::List, Action::SelectLast) => match count {
+                Some(row) => {
+                    self.follow = false;
+                    self.select(row.saturating_sub(1));
+                }
+                None => self.jump_to_end(),
+            },
+            // half pages scroll the list along with the selection, like vim
+            (Mode::List, Action::HalfPageDown) => {
+                let rows = (self.visible_height / 2).max(1).saturating_mul(times);
+                self.scroll_offset = (self.scroll_offset + rows).min(self.items_len.saturating_sub(self.visible_height));
+                self.select(self.selected_index.saturating_add(rows));
+            }
+            (Mode::List, Action::HalfPageUp) => {
+                self.follow = false;
+                let rows = (self.visible_height / 2).max(1).saturating_mul(times);
+                self.scroll_offset = self.scroll_offset.saturating_sub(rows);
+                self.select(self.selected_index.saturating_sub(rows));
+            }
+            (Mode::List, Action::CenterSelection) => {
+                self.scroll_offset = self.selected_index.saturating_sub(self.visible_height / 2);
+            }
+            (Mode::List, Action::OpenDetail) => match self.selected_row().cloned() {
+                // Show popup - content is loaded in the background
+                Some(TreeRow::Request(_) | TreeRow::Occurrence(_) | TreeRow::Attempt(_)) => {
+                    self.show_popup = true;
+                    self.load_detail();
+                }
+                Some(TreeRow::Host { host, .. }) => self.host_tree.toggle(&host),
+                Some(TreeRow::Repeats { first, .. }) => self.repeats.toggle(first),
+                None => return,
+            },
+            (Mode::List, Action::ToggleTree) => {
+                self.view_mode = match self.view_mode {
+                    ViewMode::List => ViewMode::Tree,
+                    ViewMode::Tree => ViewMode::List,
+                };
+                self.selected_index = 0;
+                self.scroll_offset = 0;
+            }
+            (Mode::List, action @ (Action::CycleSort | Action::ReverseSort)) => {
+                if action == Action::CycleSort {
+                    self.sort.cycle();
+                } else {
+                    self.sort.reverse();
+                }
+                self.selected_index = 0;
+                self.scroll_offset = 0;
+            }
+            (Mode::List, Action::ToggleRepeats) => self.fold_repeats = !self.fold_repeats,
+            (Mode::List, action @ (Action::Collapse | Action::Expand)) => match self.selected_row().cloned() {
+                Some(TreeRow::Host { host, .. }) => self.host_tree.set_expanded(&host, action == Action::Expand),
+                Some(TreeRow::Repeats { first, .. }) => self.repeats.set_expanded(first, action == Action::Expand),
+                _ => return,
+            },
+            (Mode::List, Action::MarkBaseline) => {
+                // Mark (or unmark) the selected exchange as the diff baseline
+                let selected = self.selected_log();
+                self.baseline = match (&self.baseline, selected) {
+                    (Some(baseline), Some(selected)) if baseline.id == selected.id => None,
+                    (_, selected) => selected,
+                };
+            }
+            (Mode::List, Action::Compare) => self.open_diff(),
+            (Mode::List, Action::JumpToEarlier) => {
+                let Some(log) = self.selected_log() else {
+                    return;
+                };
+                let Some(earlier) = log.differs_from else {
+                    self.state.error("The status of this request didn't change");
+                    return;
+                };
+                let row = self.rows.iter().position(|row| {
+                    row.request().and_then(|i| self.filtered.get(i)) == Some(&earlier)
+                });
+                let Some(row) = row else {
+                    self.state.error("The earlier request is not in the list");
+                    return;
+                };
+                self.follow = false;
+                self.baseline = Some(log);
+                self.select(row);
+            }
+            (Mode::List, action @ (Action::NextInTrace | Action::PrevInTrace)) => {
+                if !self.step_through_trace(action == Action::NextInTrace) {
+                    return;
+                }
+            }
+            (Mode::List, Action::ToggleFollow) => {
+                self.follow = !self.follow;
+                if self.follow {
+                    self.select_newest();
+                }
+            }
+            (Mode::List, Action::ReplayFiltered) => {
+                self.replay_filtered(None);
+                return;
+            }
+            (Mode::List, Action::RetryRequest) => {
+                self.retry_selected();
+                return;
+            }
+            (Mode::List, Action::TogglePin) => {
+                // the proxy owns pin state so that eviction can respect it
+                if let (Some(log), Some(updater)) = (self.selected_log(), &self.updater) {
+                    updater.send(TogglePin(log.id));
+                }
+                return;
+            }
+            _ => return,
+        }
+
+        // Trigger re-render
+        if let Some(updater) = &self.updater {
+            updater.update();
+        }
+    }
+
+    /// Select the next or previous row of the selected request's trace;
+    /// false if there is none.
+    fn step_through_trace(&mut self, forward: bool) -> bool {
+        let Some(trace_id) = self.selected_log().and_then(|log| log.trace_id) else {
+            self.state.error("This request has no trace id or X-Request-Id");
+            return false;
+        };
+        let in_trace: HashSet<u64> = self
+            .snapshot
+            .iter()
+            .filter(|log| log.trace_id.as_ref() == Some(&trace_id))
+            .map(|log| log.id)
+            .collect();
+        let is_in_trace = |row: &TreeRow| {
+            row.request().and_then(|i| self.filtered.get(i)).is_some_and(|id| in_trace.contains(id))
+        };
+        let row = if forward {
+            (self.selected_index + 1..self.rows.len()).find(|&row| is_in_trace(&self.rows[row]))
+        } else {
+            (0..self.selected_index).rev().find(|&row| is_in_trace(&self.rows[row]))
+        };
+        let Some(row) = row else {
+            let which = if forward { "later" } else { "earlier" };
+            self.state.notify(format!("No {} request of this trace in the list", which));
+            return false;
+        };
+        self.follow = false;
+        self.select(row);
+        true
+    }
+
+    /// Edit the path of the save prompt; `enter` saves, `esc` cancels.
+    fn prompt_key(&mut self, key: KeyEvent) {
+        if self.save_prompt.is_none() {
+            self.query_key(key);
+            return;
+        }
+        let Some(prompt) = &mut self.save_prompt else {
+            return;
+        };
+        match self.keybindings.action_for(Mode::Command, key).cloned() {
+            Some(Action::RunCommand) => {
+                let path = prompt.path.text().trim().to_string();
+                let target = prompt.target;
+                self.save_prompt = None;
+                match target {
+                    _ if path.is_empty() => {}
+                    SaveTarget::Body(part) => self.save_body(part, &path),
+                    SaveTarget::FormPart(index) => self.save_form_part(index, &path),
+                }
+            }
+            Some(Action::Close) => self.save_prompt = None,
+            Some(action) => {
+                prompt.path.apply(&action);
+            }
+            None if let KeyCode::Char(c) = key.code
+                && (key.modifiers - KeyModifiers::SHIFT).is_empty() =>
+            {
+                prompt.path.insert(c)
+            }
+            None => {}
+        }
+        if let Some(updater) = &self.updater {
+            updater.update();
+        }
+    }
+
+    /// Edit the query of the detail popup; its values follow every key.
+    fn query_key(&mut self, key: KeyEvent) {
+        let Some(query) = &mut self.query else {
+            return;
+        };
+        match self.keybindings.action_for(Mode::Command, key).cloned() {
+            Some(Action::RunCommand) => query.editing = false,
+            Some(Action::Close) => self.query = None,
+            Some(action) => {
+                query.input.apply(&action);
+            }
+            None if let KeyCode::Char(c) = key.code
+                && (key.modifiers - KeyModifiers::SHIFT).is_empty() =>
+            {
+                query.input.insert(c)
+            }
+            None => {}
+        }
+        if let Some(updater) = &self.updater {
+            updater.update();
+        }
+    }
+
+    /// Open the query bar, reading the response body of the selected
+    /// request the first time.
+    fn open_query(&mut self) {
+        if let Some(query) = &mut self.query {
+            query.editing = true;
+            return;
+        }
+        let Some(log) = self.selected_log() else {
+            return;
+        };
+        self.query = Some(BodyQuery {
+            input: LineEdit::default(),
+            editing: true,
+            document: None,
+        });
+        self.popup_scroll = 0;
+        self.commands.push(Command::new(async move {
+            let document = match CapturedExchange::load_body(&log.path, BodyPart::Response).await {
+                Ok(body) => serde_json::from_slice(&body).map(Arc::new).map_err(|e| format!("The body is not JSON: {}", e)),
+                Err(e) => Err(format!("Failed to read the body: {}", e)),
+            };
+            Some(Action::Message(Message::new(QueryDocument { id: log.id, document })))
+        }));
+    }
+
+    /// Copy the values the query picked to the clipboard.
+    fn copy_query(&mut self) {
+        let Some(query) = &self.query else {
+            let hint = self.hint(Mode::Detail, &Action::QueryBody);
+            self.popup_notice = Some(format!("Nothing to copy, {} queries the body", hint));
+            return;
+        };
+        let result = query.values().map_err(|e| color_eyre::eyre::eyre!(e)).and_then(|values| {
+            crate::clipboard::copy(&json_path::to_text(&values))?;
+            Ok(values.len())
+        });
+        self.popup_notice = Some(match result {
+            Ok(1) => "Copied 1 value".to_string(),
+            Ok(count) => format!("Copied {} values", count),
+            Err(e) => {
+                error!("Failed to copy the query result: {}", e);
+                format!("Copy failed: {}", e)
+            }
+        });
+    }
+
+    /// Write a body of the selected request to `path`, exactly as captured.
+    fn save_body(&mut self, part: BodyPart, path: &str) {
+        let Some(log) = self.selected_log() else {
+            return;
+        };
+        let path = expand_home(path);
+        self.commands.push(Command::new(async move {
+            let saved = match CapturedExchange::load_body(&log.path, part).await {
+                Ok(body) => tokio::fs::write(&path, &body).await.map(|()| body.len()),
+                Err(e) => Err(e),
+            };
+            let notice = match saved {
+                Ok(len) => format!("Saved {} bytes to {}", len, path.display()),
+                Err(e) => {
+                    error!("Failed to save body of {} to {}: {}", log.uri, path.display(), e);
+                    format!("Save failed: {}", e)
+                }
+            };
+            Some(Action::Message(Message::new(BodySaved(notice))))
+        }));
+    }
+
+    /// Send the selected request again through the proxy until it
+    /// succeeds. Retrying an attempt retries the request it is one of.
+    fn retry_selected(&mut self) {
+        let Some(log) = self.selected_log() else {
+            return;
+        };
+        let Some(&listen) = self.app.borrow().listen.first() else {
+            self.state.error("The proxy isn't listening yet");
+            return;
+        };
+        let client = ProxyClient::new(listen, &self.auth);
+        let (state, config, redact) = (self.state.clone(), self.retry.clone(), self.redact.clone());
+        let of = log.attempt.map_or(log.id, |attempt| attempt.of);
+        self.commands.push(Command::then_render(async move {
+            let loaded = async {
+                let exchange = CapturedExchange::load(&log.path).await.map_err(|e| e.to_string())?;
+                let body = CapturedExchange::load_body(&log.path, BodyPart::Request).await.map_err(|e| e.to_string())?;
+                Resend::new(&log.method, &log.uri, &exchange.request_headers, body.into(), &redact)
+            };
+            match loaded.await {
+                Ok(resend) => retry::run(resend, of, config, client, state).await,
+                Err(e) => state.error(format!("Can't retry {} {}: {}", log.method, log.uri, e)),
+            }
+        }));
+    }
+
+    /// Send every request the list shows again, in arrival order, and write
+    /// a report of the status changes.
+    fn replay_filtered(&mut self, concurrency: Option<usize>) {
+        let filtered: HashSet<u64> = self.filtered.iter().copied().collect();
+        // tunnels can't be sent again, attempts of a retry are the same request
+        let logs: Vec<HttpLog> = (self.snapshot.iter())
+            .filter(|log| filtered.contains(&log.id) && log.method != "CONNECT" && log.attempt.is_none())
+            .map(|log| HttpLog::clone(log))
+            .collect();
+        if logs.is_empty() {
+            self.state.error("No requests to replay");
+            return;
         }
+        let Some(&listen) = self.app.borrow().listen.first() else {
+            self.state.error("The proxy isn't listening yet");
+            return;
+        };
+        let report = Report {
+            filter: self.filter.borrow().clone(),
+            concurrency: concurrency.unwrap_or(self.batch.concurrency).max(1),
+            outcomes: Vec::new(),
+        };
+        self.state.notify(format!("Replaying {} requests, {} at a time", logs.len(), report.concurrency));
+        let client = ProxyClient::new(listen, &self.auth);
+        let (redact, state, dir) = (self.redact.clone(), self.state.clone(), self.reports_dir.clone());
+        self.commands.push(Command::then_render(batch::run(logs, report, client, redact, state, dir)));
     }
 
+    /// The parts of the form upload shown in the detail popup, if it is one.
+    fn form_parts(&self) -> Option<&[FormPart]> {
+        match &self.detail {
+            Some((_, DetailContent::Loaded { form: Some(Ok(parts)), .. })) => Some(parts),
+            _ => None,
+        }
+    }
 
+    /// Write the body of part `index` of the form upload to `path`.
+    fn save_form_part(&mut self, index: usize, path: &str) {
+        let Some(part) = self.form_parts().and_then(|parts| parts.get(index)) else {
+            return;
+        };
+        let body = part.body.clone();
+        let path = expand_home(path);
+        self.commands.push(Command::new(async move {
+            let notice = match tokio::fs::write(&path, &body).await {
+                Ok(()) => format!("Saved {} bytes to {}", body.len(), path.display()),
+                Err(e) => {
+                    error!("Failed to save form part to {}: {}", path.display(), e);
+                    format!("Save failed: {}", e)
+                }
+            };
+            Some(Action::Message(Message::new(BodySaved(notice))))
+        }));
+    }
+
+    /// Write a body of the selected request to a temporary file and open it
+    /// in the editor.
+    fn open_body(&mut self, part: BodyPart) {
+        let Some(log) = self.selected_log() else {
+            return;
+        };
+        self.commands.push(Command::new(async move {
+            match export_body(&log, part).await {
+                Ok(path) => Some(Action::Edit(path)),
+                Err(e) => Some(Action::Error(format!("Failed to open the body of {}: {}", log.uri, e))),
+            }
+        }));
+    }
+
+    /// Hand the response bodies of the open comparison to the diff tool.
+    fn open_external_diff(&mut self) {
+        if self.diff_tool.is_none() {
+            self.state.error("No external diff tool, set diff.command in the config");
+            return;
+        }
+        let selected = self.snapshot.iter().rev().find(|log| log.id == self.diff_ids.1);
+        let (Some(baseline), Some(selected)) = (self.baseline.clone(), selected.map(|log| HttpLog::clone(log))) else {
+            return;
+        };
+        self.commands.push(Command::new(async move {
+            let exported = tokio::try_join!(
+                export_body(&baseline, BodyPart::Response),
+                export_body(&selected, BodyPart::Response)
+            );
+            match exported {
+                Ok((a, b)) => Some(Action::ExternalDiff(a, b)),
+                Err(e) => Some(Action::Error(format!("Failed to export the bodies to compare: {}", e))),
+            }
+        }));
+    }
+
+    /// Read the capture of the selected request for the detail popup.
+    fn load_detail(&mut self) {
+        let Some(log) = self.selected_log() else {
+            return;
+        };
+        self.detail = Some((log.id, DetailContent::Loading));
+        self.query = None;
+        self.form_part = 0;
+        self.form_expanded.clear();
+        let descriptors = self.descriptors.clone();
+        let theme = self.theme;
+        // HTML as text, with its links resolved against the request's URL
+        let page = self.html_text.then(|| log.uri.clone());
+        let toggle = self.hint(Mode::Detail, &Action::ToggleHtmlText);
+        self.commands.push(Command::new(async move {
+            let page = page.as_deref();
+            let content = match CapturedExchange::load(&log.path).await {
+                Ok(exchange) => {
+                    let mut body = match &exchange.body_file {
+                        // gRPC bodies are binary, kept next to the capture
+                        Some(file) if grpc::is_grpc(exchange.content_type()) => match compression::read(file).await {
+                            Ok(bytes) => {
+                                let path = url::Url::parse(&log.uri).map(|url| url.path().to_string()).unwrap_or_default();
+                                grpc::format_grpc(&theme, &bytes, &path, true, descriptors.as_deref())
+                            }
+                            Err(e) => Text::raw(format!("Failed to read {}: {}", file.display(), e)),
+                        },
+                        // large bodies are only read as far as the preview goes
+                        Some(file) if exchange.spilled => match compression::read_prefix(file, PREVIEW_BYTES).await {
+                            Ok(bytes) => {
+                                let mut text = decoded_body(&theme, exchange.content_type(), &bytes, page);
+                                text.lines.insert(
+                                    0,
+                                    Line::styled(
+                                        format!("First {} KiB of the body in {}", PREVIEW_BYTES / 1024, file.display()),
+                                        theme.fg(theme.title),
+                                    ),
+                                );
+                                text
+                            }
+                            Err(e) => Text::raw(format!("Failed to read {}: {}", file.display(), e)),
+                        },
+                        // text that isn't UTF-8 is stored as it came, and transcoded here
+                        Some(file) if charset::is_text(exchange.content_type()) => match compression::read(file).await {
+                            Ok(bytes) => decoded_body(&theme, exchange.content_type(), &bytes, page),
+                            Err(e) => Text::raw(format!("Failed to read {}: {}", file.display(), e)),
+                        },
+                        // protobuf, or binary of no particular type that may well be
+                        Some(file)
+                            if grpc::is_protobuf(exchange.content_type())
+                                || exchange
+                                    .content_type()
+                                    .is_none_or(|ct| ct.to_ascii_lowercase().starts_with("application/octet-stream")) =>
+                        {
+                            match compression::read(file).await {
+                                Ok(bytes) => grpc::format_protobuf(&theme, &bytes).unwrap_or_else(|| {
+                                    formatted_body(&theme, exchange.content_type(), &exchange.body, page)
+                                }),
+                                Err(e) => Text::raw(format!("Failed to read {}: {}", file.display(), e)),
+                            }
+                        }
+                        _ => formatted_body(&theme, exchange.content_type(), &exchange.body, page),
+                    };
+                    if html::is_html(exchange.content_type())
+                        && (!exchange.body.is_empty() || exchange.body_file.is_some())
+                    {
+                        let note = match page {
+                            Some(_) => format!("Text of the page · {} shows the HTML", toggle),
+                            None => format!("HTML · {} shows the text of the page", toggle),
+                        };
+                        body.lines.insert(0, Line::styled(note, theme.fg(theme.muted)));
+                    }
+                    // form uploads are split into their parts
+                    let boundary = exchange
+                        .request_headers
+                        .iter()
+                        .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+                        .and_then(|(_, value)| multipart::boundary(Some(value)));
+                    let form = match (boundary, &exchange.request_body_file) {
+                        (Some(boundary), Some(file)) => Some(match compression::read(file).await {
+                            Ok(bytes) => multipart::parse(&bytes.into(), &boundary),
+                            Err(e) => Err(format!("Failed to read {}: {}", file.display(), e)),
+                        }),
+                        _ => None,
+                    };
+                    DetailContent::Loaded { body, exchange: Box::new(exchange), form }
+                }
+                Err(e) => DetailContent::Failed(e.to_string()),
+            };
+            Some(Action::Message(Message::new(DetailLoaded { id: log.id, content })))
+        }));
+    }
+
+    /// Follow an event stream, redrawing the detail popup if it shows it.
+    fn event_stream(&mut self, update: &EventStream) -> Option<Action> {
+        let id = match update {
+            EventStream::Opened { id, status, headers } => {
+                let stream = LiveStream {
+                    status: *status,
+                    headers: headers.clone(),
+                    events: Vec::new(),
+                    dropped: 0,
+                };
+                self.live.insert(*id, stream);
+                *id
+            }
+            EventStream::Events { id, events } => {
+                let stream = self.live.get_mut(id)?;
+                stream.events.extend(events.iter().cloned());
+                let excess = stream.events.len().saturating_sub(LIVE_EVENTS);
+                stream.events.drain(..excess);
+                stream.dropped += excess;
+                *id
+            }
+            // the capture is complete now, so the popup shows that instead
+            EventStream::Closed { id } => {
+                self.live.remove(id);
+                if self.show_popup && self.selected_log().is_some_and(|log| log.id == *id) {
+                    self.load_detail();
+                }
+                *id
+            }
+        };
+        let shown = self.show_popup && self.detail.as_ref().is_some_and(|(detail, _)| *detail == id);
+        shown.then_some(Action::Render)
+    }
+
+    /// Diff the marked baseline against the selected exchange.
+    fn open_diff(&mut self) {
+        let (Some(baseline), Some(selected)) = (self.baseline.clone(), self.selected_log()) else {
+            return;
+        };
+        let ids = (baseline.id, selected.id);
+        self.diff = Some(Text::raw("Loading captures..."));
+        self.diff_ids = ids;
+        self.diff_scroll = 0;
+        let theme = self.theme;
+        self.commands.push(Command::new(async move {
+            let loaded = tokio::try_join!(
+                CapturedExchange::load(&baseline.path),
+                CapturedExchange::load(&selected.path)
+            );
+            let text = match loaded {
+                Ok((a, b)) => exchange_diff(&theme, &baseline.uri, &a, &selected.uri, &b),
+                Err(e) => Text::raw(format!("Failed to load capture: {}", e)),
+            };
+            Some(Action::Message(Message::new(DiffLoaded { ids, text })))
+        }));
+    }
 }
 
 impl Component for ProxyList {
-    fn component_will_mount(&mut self, _config: Config) -> color_eyre::Result<()> {
+    fn component_will_mount(&mut self, config: Config) -> color_eyre::Result<()> {
         info!("ProxyList::component_will_mount - Initializing component");
+        self.keybindings = config.keybindings;
+        self.highlight = config.highlight;
+        self.client_column = config.clients.column;
+        self.theme = config.theme;
+        self.diff_tool = config.diff.command;
+        self.retry = config.retry;
+        self.auth = config.proxy.auth;
+        self.redact = config.capture.redact;
+        self.batch = config.batch;
+        self.reports_dir = config.config.data_dir.join("reports");
+        if let Some(path) = &config.grpc.descriptor_set {
+            match std::fs::read(path).map_err(|e| e.to_string()).and_then(|set| Descriptors::parse(&set)) {
+                Ok(descriptors) => self.descriptors = Some(Arc::new(descriptors)),
+                Err(e) => error!("Failed to load descriptor set {}: {}", path.display(), e),
+            }
+        }
         Ok(())
     }
 
@@ -56,77 +1152,200 @@ impl Component for ProxyList {
         Ok(())
     }
 
-    fn handle_key_event(&mut self, key: KeyEvent) -> color_eyre::Result<Option<Action>> {
-        if self.show_popup {
-            // Handle popup keys
-            match key.code {
-                KeyCode::Esc | KeyCode::Char('q') => {
-                    self.show_popup = false;
-                    if let Some(updater) = &self.updater {
-                        updater.update();
-                    }
-                }
-                _ => {}
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        // settings are only kept, so take them again
+        if let Action::ConfigReloaded(reloaded) = &action {
+            self.component_will_mount(reloaded.config())?;
+            return Ok(None);
+        }
+        if let Action::Resize(..) = action {
+            self.resized = true;
+            return Ok(None);
+        }
+        if let Action::Message(message) = &action {
+            // results of background loads, dropped if the popup has moved on
+            if let Some(loaded) = message.downcast_ref::<DetailLoaded>()
+                && let Some((id, content)) = &mut self.detail
+                && *id == loaded.id
+            {
+                *content = loaded.content.clone();
+                return Ok(Some(Action::Render));
+            }
+            if let Some(loaded) = message.downcast_ref::<QueryDocument>()
+                && let Some(query) = &mut self.query
+                && self.detail.as_ref().is_some_and(|(id, _)| *id == loaded.id)
+            {
+                query.document = Some(loaded.document.clone());
+                return Ok(Some(Action::Render));
+            }
+            if let Some(BodySaved(notice)) = message.downcast_ref::<BodySaved>()
+                && self.show_popup
+            {
+                self.popup_notice = Some(notice.clone());
+                return Ok(Some(Action::Render));
+            }
+            if let Some(offer) = message.downcast_ref::<RestorableSession>() {
+                self.restore_offer = Some((offer.dir.clone(), offer.requests));
+                self.sync_mode();
+                return Ok(Some(Action::Render));
+            }
+            if let Some(ReplayBatch { concurrency }) = message.downcast_ref::<ReplayBatch>() {
+                self.replay_filtered(*concurrency);
+                return Ok(Some(Action::Render));
+            }
+            if let Some(update) = message.downcast_ref::<EventStream>() {
+                return Ok(self.event_stream(update));
+            }
+            if let Some(loaded) = message.downcast_ref::<DiffLoaded>()
+                && self.diff.is_some()
+                && self.diff_ids == loaded.ids
+            {
+                self.diff = Some(loaded.text.clone());
+                return Ok(Some(Action::Render));
             }
             return Ok(None);
         }
+        // list actions run from the command palette
+        if matches!(
+            action,
+            Action::ToggleTree
+                | Action::ToggleFollow
+                | Action::CycleSort
+                | Action::ReverseSort
+                | Action::ToggleRepeats
+                | Action::ReplayFiltered
+        ) {
+            self.perform(action);
+            return Ok(Some(Action::Render));
+        }
+        if action == Action::ClearSession {
+            self.confirm_clear = true;
+            self.show_popup = false;
+            self.save_prompt = None;
+            self.detail = None;
+            self.sync_mode();
+            if let Some(updater) = &self.updater {
+                updater.update();
+            }
+        }
+        Ok(None)
+    }
 
-        match key.code {
-            KeyCode::Down | KeyCode::Char('j') => {
-                // Move selection down
-                if self.selected_index < self.items_len.saturating_sub(1) {
-                    self.selected_index = self.selected_index.saturating_add(1);
-                    
-                    // Update scroll if needed - keep selection in visible area
-                    let max_visible = self.scroll_offset + self.visible_height.saturating_sub(1);
-                    if self.selected_index > max_visible {
-                        self.scroll_offset = self.selected_index.saturating_sub(self.visible_height.saturating_sub(1));
-                    }
-                    
-                    // Trigger re-render
-                    if let Some(updater) = &self.updater {
-                        updater.update();
-                    }
+    fn take_commands(&mut self) -> Vec<Command> {
+        std::mem::take(&mut self.commands)
+    }
+
+    fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+        self.sync_mode();
+    }
+
+    fn captures_key(&self, key: KeyEvent) -> bool {
+        // popups own their bindings, so e.g. `q` closes them instead of quitting
+        match self.key_mode() {
+            // the save prompt takes the text typed into it
+            Mode::Confirm | Mode::Command => self.focused,
+            // list bindings, counts and unfinished sequences win over global keys
+            Mode::List => {
+                self.focused
+                    && (self.sequence.is_pending() || self.sequence.accepts(&self.keybindings, Mode::List, key))
+            }
+            mode => self.focused && self.keybindings.action_for(mode, key).is_some(),
+        }
+    }
+
+    fn handle_events(&mut self, event: Option<Event>) -> color_eyre::Result<Option<Action>> {
+        let action = match event {
+            Some(Event::Key(key)) => self.handle_key_event(key)?,
+            Some(Event::Mouse(mouse)) => self.handle_mouse_event(mouse)?,
+            _ => None,
+        };
+        // keys and clicks are what open and close the popups
+        self.sync_mode();
+        Ok(action)
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) -> color_eyre::Result<Option<Action>> {
+        let mode = self.key_mode();
+        if mode == Mode::Command {
+            self.prompt_key(key);
+            return Ok(None);
+        }
+        if mode == Mode::List {
+            let was_pending = self.sequence.is_pending();
+            match self.sequence.push(&self.keybindings, mode, key) {
+                Step::Complete { action: Action::FocusFilter, .. } => return Ok(Some(Action::FocusFilter)),
+                // the filter belongs to the input, which shows it
+                Step::Complete { action: Action::FilterTrace, .. } => {
+                    return Ok(match self.selected_log().and_then(|log| log.trace_id) {
+                        Some(trace_id) => Some(Action::SetFilter(trace_id)),
+                        None => {
+                            self.state.error("This request has no trace id or X-Request-Id");
+                            None
+                        }
+                    });
                 }
-                Ok(None)
-            }
-            KeyCode::Up | KeyCode::Char('k') => {
-                // Move selection up
-                if self.selected_index > 0 {
-                    self.selected_index = self.selected_index.saturating_sub(1);
-                    
-                    // Update scroll if needed
-                    if self.selected_index < self.scroll_offset {
-                        self.scroll_offset = self.selected_index;
-                    }
-                    
-                    // Trigger re-render
-                    if let Some(updater) = &self.updater {
+                Step::Complete { count, action } => self.perform_counted(action, count),
+                // show the keys typed so far, or drop them from the title
+                step => {
+                    if (step == Step::Pending || was_pending)
+                        && let Some(updater) = &self.updater
+                    {
                         updater.update();
                     }
                 }
-                Ok(None)
             }
-            KeyCode::Enter => {
-                // Open popup for selected item
-                let logs_snapshot = if let Ok(logs) = self.logs.try_read() {
-                    logs.iter().cloned().collect::<Vec<_>>()
-                } else {
-                    vec![]
-                };
+            return Ok(None);
+        }
+        match self.keybindings.action_for(mode, key).cloned() {
+            Some(Action::FocusFilter) => return Ok(Some(Action::FocusFilter)),
+            Some(action) => self.perform(action),
+            // any unbound key cancels the confirmation
+            None if mode == Mode::Confirm => self.dismiss_confirm(),
+            None => {}
+        }
+        Ok(None)
+    }
 
-                if self.selected_index < logs_snapshot.len() {
-                    // Show popup - content will be loaded during render
-                    self.show_popup = true;
-                    
-                    if let Some(updater) = &self.updater {
-                        updater.update();
+    fn handle_mouse_event(&mut self, mouse: MouseEvent) -> color_eyre::Result<Option<Action>> {
+        let position = Position::new(mouse.column, mouse.row);
+        let mode = self.key_mode();
+        let popup_area = match mode {
+            Mode::Confirm => centered_rect(50, 20, self.area),
+            _ => centered_rect(90, 90, self.area),
+        };
+        match (mode, mouse.kind) {
+            // clicking outside a popup dismisses it
+            (Mode::Confirm, MouseEventKind::Down(_)) if !popup_area.contains(position) => self.dismiss_confirm(),
+            (Mode::Detail | Mode::Diff, MouseEventKind::Down(_)) if !popup_area.contains(position) => {
+                self.perform(Action::Close)
+            }
+            (Mode::Detail | Mode::Diff, MouseEventKind::ScrollDown) => self.perform(Action::ScrollDown),
+            (Mode::Detail | Mode::Diff, MouseEventKind::ScrollUp) => self.perform(Action::ScrollUp),
+            (Mode::List, _) if !self.area.contains(position) => {}
+            (Mode::List, MouseEventKind::ScrollDown) => self.perform(Action::SelectNext),
+            (Mode::List, MouseEventKind::ScrollUp) => self.perform(Action::SelectPrev),
+            (Mode::List, MouseEventKind::Down(MouseButton::Left)) => {
+                // rows start below the top border; the borders themselves only focus
+                let row = mouse.row.saturating_sub(self.area.y + 1) as usize + self.scroll_offset;
+                let on_row = mouse.row > self.area.y && mouse.row < self.area.bottom().saturating_sub(1);
+                if on_row && row < self.rows.len() {
+                    // clicking the selected row again opens it
+                    if row == self.selected_index {
+                        self.perform(Action::OpenDetail);
+                    } else {
+                        self.follow = false;
+                        self.selected_index = row;
+                        if let Some(updater) = &self.updater {
+                            updater.update();
+                        }
                     }
                 }
-                Ok(None)
+                return Ok(Some(Action::FocusList));
             }
-            _ => Ok(None),
+            _ => {}
         }
+        Ok(None)
     }
 
     fn render(
@@ -134,119 +1353,228 @@ impl Component for ProxyList {
         frame: &mut ratatui::Frame,
         area: ratatui::prelude::Rect,
     ) -> color_eyre::Result<()> {
+        self.area = area;
         // Update visible height based on area (subtract 2 for borders)
         self.visible_height = area.height.saturating_sub(2) as usize;
         
-        // Try to read logs non-blocking and clone the data
-        let logs_snapshot = if let Ok(logs) = self.logs.try_read() {
-            logs.iter().cloned().collect::<Vec<_>>()
-        } else {
-            vec![]
-        };
-        
-        // Get the current filter value
-        let filter_value = if let Ok(filter) = self.filter.try_read() {
-            filter.clone()
-        } else {
-            String::new()
+        // Pick up the latest snapshot; filtering only collects references
+        self.snapshot = self.logs.borrow_and_update().clone();
+        let snapshot = self.snapshot.clone();
+
+        // Filter with the latest applied filter, reusing the previous results
+        let query = self.filter.borrow_and_update().clone();
+        let app = self.app.borrow_and_update().clone();
+        let logs = match &app.time_window {
+            Some(window) => in_window(&snapshot, window),
+            None => &snapshot[..],
         };
-        
-        // Filter logs based on hostname (if filter is not empty)
-        let filtered_logs: Vec<_> = if filter_value.is_empty() {
-            logs_snapshot
-        } else {
-            logs_snapshot
-                .into_iter()
-                .filter(|log| {
-                    // Extract hostname from URI and check if it contains the filter
-                    log.uri.to_lowercase().contains(&filter_value.to_lowercase())
-                })
-                .collect()
+        let mut filtered_logs = self.filter_cache.apply(&query, logs);
+        let shown_session = app.shown_session;
+        if shown_session != self.shown_session {
+            self.shown_session = shown_session;
+            self.selected_index = 0;
+            self.scroll_offset = 0;
+        }
+        if let Some(session) = &self.shown_session {
+            filtered_logs.retain(|log| log.session.as_ref() == Some(session));
+        }
+
+        self.sort.apply(&mut filtered_logs);
+
+        self.rows = match self.view_mode {
+            // pinned requests first, each group in the sort order
+            ViewMode::List => {
+                let (pinned, rest): (Vec<usize>, Vec<usize>) =
+                    (0..filtered_logs.len()).partition(|&i| filtered_logs[i].pinned);
+                let order: Vec<usize> = pinned.into_iter().chain(rest).collect();
+                if self.fold_repeats {
+                    self.repeats.rows(&filtered_logs, &order)
+                } else {
+                    nest_attempts(&filtered_logs, order)
+                }
+            }
+            ViewMode::Tree => self.host_tree.rows(&filtered_logs),
         };
-        
+        self.filtered = filtered_logs.iter().map(|log| log.id).collect();
+        // request rows line up with the markers of hosts and folded repeats
+        let indent = if self.view_mode == ViewMode::Tree || self.fold_repeats { "  " } else { "" };
+
+        self.items_len = self.rows.len();
+
+        // Follow new requests unless a popup is showing the selected one
+        if self.follow && self.key_mode() == Mode::List {
+            self.select_newest();
+        } else if self.selected_index >= self.items_len && self.items_len > 0 {
+            self.selected_index = self.items_len.saturating_sub(1);
+        }
+        self.scroll_offset = self.scroll_offset.min(self.items_len.saturating_sub(self.visible_height));
+        // a shorter window shouldn't hide the selected row
+        if self.resized {
+            self.select(self.selected_index);
+        }
+
+        // Only the visible rows plus some overscan are turned into ListItems
+        let window_start = self.scroll_offset.saturating_sub(OVERSCAN);
+        let window_end = (self.scroll_offset + self.visible_height + OVERSCAN).min(self.rows.len());
+
+        let theme = self.theme;
         // Create list items from filtered logs
         let items: Vec<ListItem> = if filtered_logs.is_empty() {
             vec![ListItem::new(Line::from(Span::styled(
-                if filter_value.is_empty() {
+                if app.time_window.is_some() && self.filter_cache.filter().is_empty() {
+                    "No requests in this time span..."
+                } else if self.shown_session.is_some() && self.filter_cache.filter().is_empty() {
+                    "No requests in this session yet..."
+                } else if self.filter_cache.filter().is_empty() {
                     "Waiting for requests..."
                 } else {
                     "No matching requests found..."
                 },
-                Style::default().fg(Color::Gray),
+                theme.fg(theme.muted),
             )))]
         } else {
-            filtered_logs
+            self.rows[window_start..window_end]
                 .iter()
-                .enumerate()
-                .map(|(idx, log)| {
+                .zip(window_start..)
+                .map(|(row, idx)| {
+                    let (log, indent, repeats) = match row {
+                        TreeRow::Request(i) => (filtered_logs[*i], indent, None),
+                        TreeRow::Occurrence(i) => (filtered_logs[*i], "    ", None),
+                        TreeRow::Attempt(i) => (filtered_logs[*i], "  ↳ ", None),
+                        TreeRow::Repeats { index, count, expanded, .. } => {
+                            (filtered_logs[*index], if *expanded { "▼ " } else { "▶ " }, Some(*count))
+                        }
+                        TreeRow::Host { host, count, expanded } => {
+                            let item = HostTree::host_item(&theme, host, *count, *expanded);
+                            return item.style(theme.row(idx == self.selected_index));
+                        }
+                    };
                     let time = log.timestamp.format("%H:%M:%S");
-                    let line = Line::from(vec![
+                    let is_baseline = self.baseline.as_ref().is_some_and(|b| b.id == log.id);
+                    let mut line = Line::from(vec![
+                        Span::styled(indent, theme.fg(theme.accent)),
+                        Span::styled(
+                            if is_baseline { "◆ " } else { "" },
+                            theme.fg(theme.warning),
+                        ),
+                        Span::styled(
+                            if log.pinned { "★ " } else { "" },
+                            theme.fg(theme.special),
+                        ),
                         Span::styled(
                             format!("[{}] ", time),
-                            Style::default().fg(Color::Gray),
+                            theme.fg(theme.muted),
+                        ),
+                        Span::styled(
+                            log.listener.as_ref().map(|label| format!("{} ", label)).unwrap_or_default(),
+                            theme.fg(theme.secondary),
+                        ),
+                        Span::styled(
+                            if self.client_column { format!("{:15} ", client_label(log)) } else { String::new() },
+                            theme.fg(theme.secondary),
                         ),
                         Span::styled(
                             format!("{:8} ", log.method),
-                            Style::default().fg(match log.method.as_str() {
-                                "GET" => Color::Green,
-                                "POST" => Color::Blue,
-                                "CONNECT" => Color::Magenta,
-                                _ => Color::Yellow,
-                            }),
+                            theme.fg(theme.method(&log.method)),
                         ),
                         Span::raw(&log.uri),
+                        Span::styled(
+                            if log.differs_from.is_some() { "  ≠" } else { "" },
+                            theme.fg(theme.warning).add_modifier(Modifier::BOLD),
+                        ),
+                        Span::styled(
+                            log.attempt
+                                .map(|attempt| format!("  attempt {}/{}", attempt.number, attempt.max))
+                                .unwrap_or_default(),
+                            theme.fg(theme.muted),
+                        ),
+                        Span::styled(
+                            repeats.map(|count| format!("  ×{}", count)).unwrap_or_default(),
+                            theme.fg(theme.warning).add_modifier(Modifier::BOLD),
+                        ),
+                        Span::styled(
+                            log.graphql.as_ref().map(|op| format!("  {}", op.label())).unwrap_or_default(),
+                            theme.fg(theme.special),
+                        ),
+                        Span::styled(
+                            log.tag.as_ref().map(|tag| format!("  #{}", tag)).unwrap_or_default(),
+                            theme.fg(theme.accent),
+                        ),
+                        Span::styled(
+                            log.fault.as_ref().map(|fault| format!("  ⚡ {}", fault)).unwrap_or_default(),
+                            theme.fg(theme.error).add_modifier(Modifier::BOLD),
+                        ),
+                        Span::styled(
+                            failed_assertions(&log.failed_assertions),
+                            theme.fg(theme.error).add_modifier(Modifier::BOLD),
+                        ),
                     ]);
-                    
-                    let style = if idx == self.selected_index {
-                        Style::default().bg(Color::DarkGray)
-                    } else {
-                        Style::default()
-                    };
-                    
-                    ListItem::new(line).style(style)
+                    // user rules win over the built-in colors
+                    if let Some(highlight) = highlight::style_for(&self.highlight, log) {
+                        for span in &mut line.spans {
+                            span.style = span.style.patch(highlight);
+                        }
+                    }
+
+                    ListItem::new(line).style(theme.row(idx == self.selected_index))
                 })
                 .collect()
         };
 
-        let old_items_len = self.items_len;
-        self.items_len = items.len();
-        
-        // Auto-scroll to bottom if user was at the bottom and new items were added
-        let was_at_bottom = old_items_len > 0 && self.selected_index == old_items_len.saturating_sub(1);
-        if was_at_bottom && self.items_len > old_items_len {
-            self.selected_index = self.items_len.saturating_sub(1);
-            // Update scroll to keep selection visible
-            if self.items_len > self.visible_height {
-                self.scroll_offset = self.items_len.saturating_sub(self.visible_height);
-            }
-        } else {
-            // If not at bottom, just ensure selected_index is within bounds
-            if self.selected_index >= self.items_len && self.items_len > 0 {
-                self.selected_index = self.items_len.saturating_sub(1);
-            }
-        }
-        
         // Update scroll state based on content length and current position
         // The scrollbar position should reflect where we are in the content
         self.scroll_state = self.scroll_state
-            .content_length(self.items_len.saturating_sub(self.visible_height).max(0))
+            .content_length(self.items_len.saturating_sub(self.visible_height))
             .position(self.scroll_offset);
         
         // Create the list widget with stateful rendering
         let list = List::new(items)
             .block(
                 Block::default()
-                    .title("HTTP Proxy Log (↑/↓ navigate, Enter to view, ESC/q to close)")
+                    .title(match self.view_mode {
+                        ViewMode::List => "HTTP Proxy Log (? for help)",
+                        ViewMode::Tree => "HTTP Proxy Log by host (? for help)",
+                    })
+                    .title(Line::styled(
+                        format!(
+                            " by {}{} ",
+                            self.sort.label(),
+                            if self.fold_repeats { ", repeats folded" } else { "" }
+                        ),
+                        theme.fg(theme.muted),
+                    ))
+                    .title(match &self.shown_session {
+                        Some(session) => Line::styled(format!(" session: {} ", session), theme.fg(theme.warning)),
+                        None => Line::default(),
+                    })
+                    .title(match &app.time_window {
+                        Some(window) => Line::styled(
+                            format!(
+                                " {}–{} ",
+                                window.from.with_timezone(&chrono::Local).format("%H:%M:%S"),
+                                window.to.with_timezone(&chrono::Local).format("%H:%M:%S")
+                            ),
+                            theme.fg(theme.accent),
+                        ),
+                        None => Line::default(),
+                    })
+                    .title(if self.follow {
+                        Line::styled(" FOLLOW ", theme.fg(theme.badge).bg(theme.success))
+                            .right_aligned()
+                    } else {
+                        Line::default()
+                    })
+                    .title_bottom(Line::from(self.sequence.display()).right_aligned())
                     .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Cyan)),
+                    .border_style(theme.pane(self.focused)),
             )
-            .style(Style::default().fg(Color::White))
+            .style(theme.fg(theme.text))
             .scroll_padding(1);
 
-        // Create a stateful list to support scrolling
+        // Create a stateful list to support scrolling, relative to the window
         let mut list_state = ListState::default()
-            .with_selected(Some(self.selected_index))
-            .with_offset(self.scroll_offset);
+            .with_selected(self.selected_index.checked_sub(window_start))
+            .with_offset(self.scroll_offset - window_start);
         frame.render_stateful_widget(list, area, &mut list_state);
         
         // Render scrollbar
@@ -264,69 +1592,382 @@ impl Component for ProxyList {
             &mut self.scroll_state,
         );
         
+        let popup_log = match self.selected_row() {
+            Some(row) if self.show_popup => row.request().and_then(|i| filtered_logs.get(i)).map(|log| (*log).clone()),
+            _ => None,
+        };
+
         // Render popup if needed
         if self.show_popup {
-            self.render_popup(frame, area, &filtered_logs)?;
+            self.render_popup(frame, area, popup_log.as_ref())?;
         }
-        
+
+        if let Some(diff) = &self.diff {
+            let popup_area = centered_rect(90, 90, area);
+            let external = match &self.diff_tool {
+                Some(tool) => format!(" | {} for {}", self.hint(Mode::Diff, &Action::OpenExternalDiff), tool),
+                None => String::new(),
+            };
+            let text = Paragraph::new(diff.clone())
+                .block(
+                    Block::default()
+                        .title(format!(
+                            "Compare baseline (-) with selected (+) | {} to close{}",
+                            self.hint(Mode::Diff, &Action::Close),
+                            external
+                        ))
+                        .borders(Borders::ALL)
+                        .border_style(self.theme.fg(self.theme.title)),
+                );
+            if self.resized {
+                self.diff_scroll = self.diff_scroll.min(max_scroll(&text, popup_area));
+            }
+            let text = text.scroll((self.diff_scroll, 0));
+            frame.render_widget(Clear, popup_area);
+            frame.render_widget(text, popup_area);
+        }
+
+        if self.confirm_clear {
+            self.render_confirm_clear(frame, area);
+        } else if let Some((dir, requests)) = &self.restore_offer {
+            self.render_restore_offer(frame, area, dir, *requests);
+        }
+        self.resized = false;
+
         Ok(())
     }
 }
 
 impl ProxyList {
+    /// Select row `index`, or the last one, and scroll it into view.
+    fn select(&mut self, index: usize) {
+        self.selected_index = index.min(self.items_len.saturating_sub(1));
+        if self.selected_index < self.scroll_offset {
+            self.scroll_offset = self.selected_index;
+        }
+        let max_visible = self.scroll_offset + self.visible_height.saturating_sub(1);
+        if self.selected_index > max_visible {
+            self.scroll_offset = self.selected_index.saturating_sub(self.visible_height.saturating_sub(1));
+        }
+    }
+
+    /// Select the last row and scroll it into view.
+    fn jump_to_end(&mut self) {
+        self.selected_index = self.items_len.saturating_sub(1);
+        self.scroll_offset = self.items_len.saturating_sub(self.visible_height);
+    }
+
+    /// Select the row of the latest request wherever the sort puts it, or the
+    /// last row if no row shows one.
+    fn select_newest(&mut self) {
+        let newest = (self.rows.iter().enumerate())
+            .filter_map(|(row, shown)| Some((*self.filtered.get(shown.request()?)?, row)))
+            .max();
+        match newest {
+            Some((_, row)) => self.select(row),
+            None => self.jump_to_end(),
+        }
+    }
+
+    /// Drop every captured request and reset the list position. When
+    /// `delete_captures` is set the session's capture directory is removed as well.
+    fn clear_session(&mut self, delete_captures: bool) {
+        self.selected_index = 0;
+        self.scroll_offset = 0;
+        self.items_len = 0;
+        self.scroll_state = ScrollbarState::default();
+
+        // the proxy owns the capture files as well as the logs
+        if let Some(updater) = &self.updater {
+            updater.send(ClearLogs { delete_captures });
+        }
+    }
+
+    /// Close the open confirmation without doing anything.
+    fn dismiss_confirm(&mut self) {
+        self.confirm_clear = false;
+        self.restore_offer = None;
+        if let Some(updater) = &self.updater {
+            updater.update();
+        }
+    }
+
+    fn render_restore_offer(&self, frame: &mut ratatui::Frame, area: Rect, dir: &std::path::Path, requests: usize) {
+        let popup_area = centered_rect(50, 20, area);
+        let name = dir.file_name().unwrap_or_default().to_string_lossy();
+        let text = Paragraph::new(vec![
+            Line::from(format!("Restore the {} requests captured in session {}?", requests, name)),
+            Line::from(""),
+            Line::from(vec![
+                Span::styled(self.hint(Mode::Confirm, &Action::ConfirmRestore), self.theme.fg(self.theme.success)),
+                Span::raw(" restore  "),
+                Span::styled("any other key", self.theme.fg(self.theme.muted)),
+                Span::raw(" start empty"),
+            ]),
+        ])
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true })
+        .block(
+            Block::default()
+                .title("Last session")
+                .borders(Borders::ALL)
+                .border_style(self.theme.fg(self.theme.accent)),
+        );
+
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(text, popup_area);
+    }
+
+    fn render_confirm_clear(&self, frame: &mut ratatui::Frame, area: Rect) {
+        let popup_area = centered_rect(50, 20, area);
+        let text = Paragraph::new(vec![
+            Line::from("Clear all captured requests?"),
+            Line::from(""),
+            Line::from(vec![
+                Span::styled(self.hint(Mode::Confirm, &Action::ConfirmClear), self.theme.fg(self.theme.success)),
+                Span::raw(" clear  "),
+                Span::styled(
+                    self.hint(Mode::Confirm, &Action::ConfirmClearAndDelete),
+                    self.theme.fg(self.theme.error),
+                ),
+                Span::raw(" clear and delete captures  "),
+                Span::styled("any other key", self.theme.fg(self.theme.muted)),
+                Span::raw(" cancel"),
+            ]),
+        ])
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true })
+        .block(
+            Block::default()
+                .title("Clear session")
+                .borders(Borders::ALL)
+                .border_style(self.theme.fg(self.theme.error)),
+        );
+
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(text, popup_area);
+    }
+
+    /// What the query of the detail popup picks, each value formatted like a body.
+    fn query_lines(&self, query: &BodyQuery) -> Vec<Line<'static>> {
+        let title = self.theme.fg(self.theme.title).add_modifier(Modifier::BOLD);
+        let values = match query.values() {
+            Ok(values) => values,
+            Err(e) => return vec![Line::styled(format!("Query: {}", e), self.theme.fg(self.theme.muted))],
+        };
+        let mut lines = vec![Line::styled(
+            format!(
+                "Query · {} value{} · {} copies",
+                values.len(),
+                if values.len() == 1 { "" } else { "s" },
+                self.hint(Mode::Detail, &Action::CopyQuery),
+            ),
+            title,
+        )];
+        for value in values.iter().take(QUERY_VALUES) {
+            let text = serde_json::to_string_pretty(value).unwrap_or_default();
+            lines.extend(format_body(&self.theme, Some("application/json"), &text).lines);
+        }
+        if values.len() > QUERY_VALUES {
+            lines.push(Line::styled(
+                format!("… and {} more, all of them copied", values.len() - QUERY_VALUES),
+                self.theme.fg(self.theme.muted),
+            ));
+        }
+        lines
+    }
+
     fn render_popup(
         &mut self,
         frame: &mut ratatui::Frame,
         area: ratatui::prelude::Rect,
-        logs_snapshot: &[super::proxy::HttpLog],
+        log: Option<&HttpLog>,
     ) -> color_eyre::Result<()> {
         // Create a centered popup
         let popup_area = centered_rect(90, 90, area);
         
-        // Load file content synchronously for rendering
-        let (status, url, body) = if self.selected_index < logs_snapshot.len() {
-            let log = &logs_snapshot[self.selected_index];
-            let file_path = Proxy::uri_to_file_path(&log.uri);
-            
-            match std::fs::read_to_string(&file_path) {
-                Ok(content) => {
-                    let mut status = String::from("Unknown");
-                    let mut body = String::new();
-                    let mut in_body = false;
-                    
-                    for line in content.lines() {
-                        if line.starts_with("Status:") {
-                            status = line.trim_start_matches("Status:").trim().to_string();
-                        } else if line.starts_with("Response Body:") {
-                            in_body = true;
-                        } else if in_body {
-                            body.push_str(line);
-                            body.push('\n');
-                        }
+        let detail = self.detail.as_ref().filter(|(id, _)| log.is_some_and(|log| log.id == *id));
+        let graphql = log.and_then(|log| log.graphql.as_ref());
+        let url = match (log, graphql) {
+            (Some(log), Some(operation)) => format!("{} ({})", log.uri, operation.label()),
+            (Some(log), None) => log.uri.clone(),
+            (None, _) => String::new(),
+        };
+        let live = detail.and_then(|(id, _)| self.live.get(id)).map(|stream| stream.view(&self.theme));
+        let (status, body) = match (live, detail.map(|(_, content)| content)) {
+            (Some(live), _) => live,
+            (None, Some(DetailContent::Loaded { exchange, body, form })) => {
+                self.popup_header = self.popup_header.min(exchange.headers.len().saturating_sub(1));
+                // the values queried, the assertions it breaks, the other requests
+                // of its trace, then the request's GraphQL operation, go above the
+                // response
+                let mut lines: Vec<Line> = Vec::new();
+                if let Some(query) = &self.query {
+                    lines.extend(self.query_lines(query));
+                    lines.push(Line::from(""));
+                }
+                if let Some(log) = log
+                    && !log.failed_assertions.is_empty()
+                {
+                    lines.push(Line::styled(
+                        format!("Failed assertions ({})", log.failed_assertions.len()),
+                        self.theme.fg(self.theme.error).add_modifier(Modifier::BOLD),
+                    ));
+                    lines.extend(
+                        (log.failed_assertions.iter())
+                            .map(|failure| Line::styled(format!("✗ {}", failure), self.theme.fg(self.theme.error))),
+                    );
+                    lines.push(Line::from(""));
+                }
+                if let Some(log) = log
+                    && let Some(trace_id) = &log.trace_id
+                {
+                    let mut chain: Vec<&HttpLog> = self
+                        .snapshot
+                        .iter()
+                        .filter(|other| other.trace_id.as_ref() == Some(trace_id))
+                        .map(AsRef::as_ref)
+                        .collect();
+                    chain.sort_by_key(|other| (other.timestamp, other.id));
+                    lines.push(Line::styled(
+                        format!(
+                            "Trace {} · {} request{} · {} and {} step through it, {} filters the list to it",
+                            trace_id,
+                            chain.len(),
+                            if chain.len() == 1 { "" } else { "s" },
+                            self.hint(Mode::Detail, &Action::PrevInTrace),
+                            self.hint(Mode::Detail, &Action::NextInTrace),
+                            self.hint(Mode::List, &Action::FilterTrace),
+                        ),
+                        self.theme.fg(self.theme.title).add_modifier(Modifier::BOLD),
+                    ));
+                    lines.extend(trace::waterfall(&self.theme, &chain, log.id));
+                    lines.push(Line::from(""));
+                }
+                if let Some(operation) = graphql {
+                    lines.extend(operation.text(&self.theme).lines);
+                    lines.push(Line::from(""));
+                }
+                match form {
+                    Some(Ok(parts)) => {
+                        self.form_part = self.form_part.min(parts.len().saturating_sub(1));
+                        lines.push(Line::styled(
+                            format!(
+                                "Request form · {} part{} · {}/{} select, {} expands, {} saves",
+                                parts.len(),
+                                if parts.len() == 1 { "" } else { "s" },
+                                self.hint(Mode::Detail, &Action::NextPart),
+                                self.hint(Mode::Detail, &Action::PrevPart),
+                                self.hint(Mode::Detail, &Action::TogglePart),
+                                self.hint(Mode::Detail, &Action::SavePart),
+                            ),
+                            self.theme.fg(self.theme.title).add_modifier(Modifier::BOLD),
+                        ));
+                        lines.extend(multipart::format_parts(&self.theme, parts, self.form_part, &self.form_expanded));
+                        lines.push(Line::from(""));
+                    }
+                    Some(Err(e)) => {
+                        lines.push(Line::styled(format!("Request form: {}", e), self.theme.fg(self.theme.muted)));
+                        lines.push(Line::from(""));
                     }
-                    
-                    (status, log.uri.clone(), body.trim().to_string())
+                    None => {}
                 }
-                Err(e) => (
-                    "Error".to_string(),
-                    log.uri.clone(),
-                    format!("Failed to load file: {}", e),
-                ),
+                lines.extend(exchange
+                    .headers
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, (name, value))| {
+                        let line = Line::from(vec![
+                            Span::styled(format!("{}: ", name), self.theme.fg(self.theme.accent)),
+                            Span::raw(value.clone()),
+                        ]);
+                        if idx == self.popup_header {
+                            line.style(Style::default().add_modifier(Modifier::REVERSED))
+                        } else {
+                            line
+                        }
+                    }));
+                lines.push(Line::from(""));
+                lines.extend(body.lines.iter().cloned());
+                if !exchange.trailers.is_empty() {
+                    lines.push(Line::from(""));
+                    lines.push(Line::styled("Trailers", self.theme.fg(self.theme.title).add_modifier(Modifier::BOLD)));
+                    lines.extend(exchange.trailers.iter().map(|(name, value)| {
+                        Line::from(vec![
+                            Span::styled(format!("{}: ", name), self.theme.fg(self.theme.accent)),
+                            Span::raw(value.clone()),
+                        ])
+                    }));
+                }
+                (exchange.status.clone(), Text::from(lines))
             }
-        } else {
-            ("Unknown".to_string(), "".to_string(), "".to_string())
+            (None, Some(DetailContent::Failed(e))) => (
+                "Error".to_string(),
+                Text::raw(format!("Failed to load file: {}", e)),
+            ),
+            (None, Some(DetailContent::Loading)) => (
+                "...".to_string(),
+                Text::styled("Loading...", self.theme.fg(self.theme.muted)),
+            ),
+            (None, None) => ("Unknown".to_string(), Text::default()),
         };
-        
+
         // Create popup content
+        let notice = self
+            .popup_notice
+            .as_ref()
+            .map(|notice| format!(" [{}]", notice))
+            .unwrap_or_default();
+        let prompt = match (&self.save_prompt, &self.query) {
+            (Some(prompt), _) => Some(match prompt.target {
+                SaveTarget::Body(BodyPart::Request) => ("Save request body to: ", &prompt.path),
+                SaveTarget::Body(BodyPart::Response) => ("Save response body to: ", &prompt.path),
+                SaveTarget::FormPart(_) => ("Save form part to: ", &prompt.path),
+            }),
+            (None, Some(query)) if query.editing => Some(("Query: ", &query.input)),
+            _ => None,
+        };
+        let bottom = match prompt {
+            Some((label, input)) => {
+                let column = popup_area.x + 1 + (label.len() + input.cursor_column()) as u16;
+                frame.set_cursor_position(Position::new(column.min(popup_area.right() - 2), popup_area.bottom() - 1));
+                Line::from(vec![
+                    Span::styled(label, self.theme.fg(self.theme.accent)),
+                    Span::styled(input.text().to_string(), self.theme.fg(self.theme.text)),
+                ])
+            }
+            None => Line::raw(format!(
+                "{} select header | copy: {} URL, {} header, {} body | save: {}/{} | editor: {}/{} | {} query | ? for help",
+                self.hint(Mode::Detail, &Action::NextHeader),
+                self.hint(Mode::Detail, &Action::CopyUrl),
+                self.hint(Mode::Detail, &Action::CopyHeader),
+                self.hint(Mode::Detail, &Action::CopyBody),
+                self.hint(Mode::Detail, &Action::SaveBody),
+                self.hint(Mode::Detail, &Action::SaveRequestBody),
+                self.hint(Mode::Detail, &Action::OpenBody),
+                self.hint(Mode::Detail, &Action::OpenRequestBody),
+                self.hint(Mode::Detail, &Action::QueryBody),
+            )),
+        };
+        let client = match log.map(|log| (log.client, log.client_name.as_deref())) {
+            Some((Some(client), Some(name))) => format!(" | from {} ({})", name, client),
+            Some((Some(client), None)) => format!(" | from {}", client),
+            _ => String::new(),
+        };
         let popup_block = Block::default()
-            .title(format!("Response - Status: {} | {}", status, url))
+            .title(format!("Response - Status: {} | {}{}{}", status, url, client, notice))
+            .title_bottom(bottom)
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Yellow));
+            .border_style(self.theme.fg(self.theme.title));
         
         let text = Paragraph::new(body)
             .block(popup_block)
-            .wrap(Wrap { trim: false })
-            .scroll((0, 0));
+            .wrap(Wrap { trim: false });
+        // wrapped at the new width, the body may end higher up than it was scrolled to
+        if self.resized {
+            self.popup_scroll = self.popup_scroll.min(max_scroll(&text, popup_area));
+        }
+        let text = text.scroll((self.popup_scroll, 0));
         
         // Clear the area and render popup
         frame.render_widget(Clear, popup_area);
@@ -336,6 +1977,77 @@ impl ProxyList {
     }
 }
 
+/// Rows of `order`, with the attempts of a retry moved below the request
+/// they retry while it is listed.
+fn nest_attempts(logs: &[&HttpLog], order: Vec<usize>) -> Vec<TreeRow> {
+    let listed: HashSet<u64> = order.iter().map(|&i| logs[i].id).collect();
+    let mut attempts: HashMap<u64, Vec<usize>> = HashMap::new();
+    for &i in &order {
+        if let Some(attempt) = logs[i].attempt
+            && listed.contains(&attempt.of)
+        {
+            attempts.entry(attempt.of).or_default().push(i);
+        }
+    }
+    let mut rows = Vec::with_capacity(order.len());
+    for i in order {
+        if logs[i].attempt.is_some_and(|attempt| attempts.contains_key(&attempt.of)) {
+            continue;
+        }
+        rows.push(TreeRow::Request(i));
+        if let Some(nested) = attempts.get(&logs[i].id) {
+            rows.extend(nested.iter().copied().map(TreeRow::Attempt));
+        }
+    }
+    rows
+}
+
+/// `  ✗ status 500, expected 2xx (+2)`: the first assertion a response
+/// breaks, and how many more.
+fn failed_assertions(failures: &[String]) -> String {
+    match failures {
+        [] => String::new(),
+        [failure] => format!("  ✗ {}", failure),
+        [failure, more @ ..] => format!("  ✗ {} (+{})", failure, more.len()),
+    }
+}
+
+/// A body in whatever encoding it came in, formatted, and headed by the
+/// encoding it was decoded from unless that was UTF-8.
+fn decoded_body(theme: &Theme, content_type: Option<&str>, bytes: &[u8], page: Option<&str>) -> Text<'static> {
+    let (body, note) = charset::decode(content_type, bytes);
+    let mut text = formatted_body(theme, content_type, &body, page);
+    if let Some(note) = note {
+        text.lines.insert(0, Line::styled(note, theme.fg(theme.muted)));
+    }
+    text
+}
+
+/// A body formatted for its content type, or the text of an HTML page
+/// fetched from `page`.
+fn formatted_body(theme: &Theme, content_type: Option<&str>, body: &str, page: Option<&str>) -> Text<'static> {
+    match page {
+        Some(url) if html::is_html(content_type) => html::text(theme, body, url),
+        _ => format_body(theme, content_type, body),
+    }
+}
+
+/// The client column of `log`: the client's name, else its address.
+fn client_label(log: &HttpLog) -> String {
+    match (&log.client_name, log.client) {
+        (Some(name), _) => name.clone(),
+        (None, Some(client)) => client.ip().to_string(),
+        (None, None) => "-".to_string(),
+    }
+}
+
+/// How far `paragraph`, drawn with borders in `area`, scrolls before its last
+/// line reaches the bottom.
+fn max_scroll(paragraph: &Paragraph, area: Rect) -> u16 {
+    let lines = paragraph.line_count(area.width.saturating_sub(2));
+    lines.saturating_sub(area.height as usize).min(u16::MAX as usize) as u16
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
@@ -355,3 +2067,241 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         ])
         .split(popup_layout[1])[1]
 }
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_body_file_names() {
+        assert_eq!(suggested_path("https://cdn.test/img/logo.png?v=2", BodyPart::Response), "logo.png");
+        assert_eq!(suggested_path("https://api.test/users", BodyPart::Request), "users.request");
+        assert_eq!(suggested_path("https://api.test/", BodyPart::Response), "response.body");
+        assert_eq!(extension(Some("application/problem+json; charset=utf-8")), "json");
+        assert_eq!(extension(Some("text/plain")), "txt");
+        assert_eq!(extension(None), "bin");
+    }
+
+    #[tokio::test]
+    async fn test_exported_bodies_are_new_private_files() {
+        use crate::framework::testing::{TempDir, capture};
+
+        let dir = TempDir::new();
+        let path = dir.path().join("users.yap");
+        capture("GET", "http://api.test/users/7", 200)
+            .header("content-type", "application/json")
+            .body(r#"{"id":7}"#)
+            .write(&path);
+        let log = HttpLog {
+            id: 7,
+            path,
+            ..Default::default()
+        };
+        let first = export_body(&log, BodyPart::Response).await.unwrap();
+        let second = export_body(&log, BodyPart::Response).await.unwrap();
+        assert_ne!(first, second);
+        assert_eq!(std::fs::read_to_string(&first).unwrap(), "{\"id\":7}\n");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&first).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        for path in [&first, &second] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_attempts_nest_below_retried_request() {
+        let log = |id, attempt: Option<u64>| HttpLog {
+            id,
+            attempt: attempt.map(|of| retry::Attempt { of, number: 1, max: 3 }),
+            ..Default::default()
+        };
+        let logs = [log(1, None), log(2, None), log(3, Some(1)), log(4, Some(1)), log(5, Some(9))];
+        let logs: Vec<&HttpLog> = logs.iter().collect();
+        assert_eq!(
+            nest_attempts(&logs, vec![0, 1, 2, 3, 4]),
+            vec![
+                TreeRow::Request(0),
+                TreeRow::Attempt(2),
+                TreeRow::Attempt(3),
+                TreeRow::Request(1),
+                // the request it retries isn't listed
+                TreeRow::Request(4),
+            ]
+        );
+        // filtered out, so its attempts stay where they are
+        assert_eq!(nest_attempts(&logs, vec![1, 2]), vec![TreeRow::Request(1), TreeRow::Request(2)]);
+    }
+
+    #[tokio::test]
+    async fn test_list_and_detail_popup_snapshots() {
+        use chrono::{Local, TimeZone, Utc};
+
+        use crate::framework::testing::{Harness, capture};
+
+        let (snapshot, logs) = watch::channel(LogSnapshot::default());
+        let (_filter, filter) = watch::channel(String::new());
+        let mut harness = Harness::new(ProxyList::new(logs, filter, SharedState::default()), 80, 14);
+        let path = harness.data_dir().join("users.yap");
+        capture("POST", "http://api.test/users/7", 404)
+            .header("content-type", "application/json")
+            .body(r#"{"error":"no such user"}"#)
+            .write(&path);
+        let at = |second| Local.with_ymd_and_hms(2026, 10, 17, 9, 0, second).unwrap().with_timezone(&Utc);
+        let log = |id, method: &str, uri: &str, status| {
+            Arc::new(HttpLog {
+                id,
+                method: method.to_string(),
+                uri: uri.to_string(),
+                timestamp: at(id as u32),
+                status,
+                duration: status.map(|_| std::time::Duration::from_millis(12)),
+                path: path.clone(),
+                ..Default::default()
+            })
+        };
+        snapshot.send_replace(Arc::new(vec![
+            log(1, "GET", "http://api.test/users", Some(200)),
+            log(2, "POST", "http://api.test/users/7", Some(404)),
+            log(3, "GET", "http://cdn.test/logo.png", None),
+        ]));
+        harness.component.set_focused(true);
+        // following new requests selects the latest
+        harness.assert_snapshot("proxy_list");
+
+        harness.keys("k<enter>");
+        harness.settle().await;
+        harness.assert_snapshot("proxy_list_detail");
+
+        harness.keys("q");
+        assert!(!harness.render().contains("Status: 404"));
+        assert!(!harness.emitted.contains(&Action::Quit), "q closes the popup instead of quitting");
+    }
+
+    #[test]
+    fn test_follow_selects_the_newest_request_in_any_sort() {
+        use crate::{components::sort::SortKey, framework::testing::Harness};
+
+        let (snapshot, logs) = watch::channel(LogSnapshot::default());
+        let (_filter, filter) = watch::channel(String::new());
+        let mut harness = Harness::new(ProxyList::new(logs, filter, SharedState::default()), 80, 12);
+        let log = |id: u64, millis| {
+            Arc::new(HttpLog {
+                id,
+                method: "GET".to_string(),
+                uri: format!("http://api.test/items/{}", id),
+                status: Some(200),
+                duration: Some(std::time::Duration::from_millis(millis)),
+                ..Default::default()
+            })
+        };
+        let mut shown = vec![log(1, 30), log(2, 10), log(3, 20)];
+        snapshot.send_replace(Arc::new(shown.clone()));
+        harness.component.set_focused(true);
+        let selected = |harness: &mut Harness<ProxyList>| {
+            harness.render();
+            harness.component.selected_log().map(|log| log.id)
+        };
+
+        // newest first, so the newest is on top
+        harness.dispatch(Action::ReverseSort);
+        assert_eq!(selected(&mut harness), Some(3));
+        assert_eq!(harness.component.selected_index, 0);
+        shown.push(log(4, 5));
+        snapshot.send_replace(Arc::new(shown.clone()));
+        assert_eq!(selected(&mut harness), Some(4));
+        assert_eq!(harness.component.selected_index, 0);
+
+        // slowest first puts it in the middle
+        harness.component.sort = ListSort {
+            key: SortKey::Duration,
+            descending: true,
+        };
+        shown.push(log(5, 15));
+        snapshot.send_replace(Arc::new(shown.clone()));
+        assert_eq!(selected(&mut harness), Some(5));
+        assert_eq!(harness.component.selected_index, 2);
+    }
+
+    #[tokio::test]
+    async fn test_diff_of_two_exchanges_with_one_url() {
+        use crate::{
+            components::proxy::Proxy,
+            framework::testing::{Harness, capture},
+        };
+
+        let (snapshot, logs) = watch::channel(LogSnapshot::default());
+        let (_filter, filter) = watch::channel(String::new());
+        let mut harness = Harness::new(ProxyList::new(logs, filter, SharedState::default()), 80, 20);
+        let uri = "http://api.test/health";
+        let log = |id, status: u16, body: &str| {
+            let path = Proxy::capture_path(&harness.data_dir(), uri, Some(id));
+            capture("GET", uri, status).header("content-type", "application/json").body(body).write(&path);
+            Arc::new(HttpLog {
+                id,
+                method: "GET".to_string(),
+                uri: uri.to_string(),
+                status: Some(status),
+                path,
+                ..Default::default()
+            })
+        };
+        snapshot.send_replace(Arc::new(vec![log(1, 200, r#"{"db":"up"}"#), log(2, 503, r#"{"db":"down"}"#)]));
+        harness.component.set_focused(true);
+        harness.render();
+
+        harness.keys("ggmjc");
+        harness.settle().await;
+        let screen = harness.render();
+        for line in ["- 200", "+ 503", r#"-   "db": "up""#, r#"+   "db": "down""#] {
+            assert!(screen.contains(line), "{}", screen);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resize_keeps_selection_and_popup_in_view() {
+        use crate::framework::testing::{Harness, capture};
+
+        let (snapshot, logs) = watch::channel(LogSnapshot::default());
+        let (_filter, filter) = watch::channel(String::new());
+        let mut harness = Harness::new(ProxyList::new(logs, filter, SharedState::default()), 80, 30);
+        let path = harness.data_dir().join("item.yap");
+        capture("GET", "http://api.test/items/21", 404)
+            .header("content-type", "application/json")
+            .body(r#"{"error":"no such item"}"#)
+            .write(&path);
+        let log = |id: u64| {
+            Arc::new(HttpLog {
+                id,
+                method: "GET".to_string(),
+                uri: format!("http://api.test/items/{}", id),
+                status: Some(404),
+                path: path.clone(),
+                ..Default::default()
+            })
+        };
+        snapshot.send_replace(Arc::new((1..=40).map(log).collect()));
+        harness.component.set_focused(true);
+
+        harness.render();
+        harness.keys("gg20j");
+        assert!(harness.render().contains("/items/21 "));
+        harness.resize(80, 10);
+        assert!(harness.render().contains("/items/21 "), "the selected row scrolls into view");
+        // clicks are mapped to rows through the offset, so it follows
+        let list = &harness.component;
+        assert!((list.scroll_offset..list.scroll_offset + list.visible_height).contains(&20));
+
+        // scrolled past the end of the body while small, which fits once larger
+        harness.keys("<enter>");
+        harness.settle().await;
+        harness.keys("jjjjjjjjjjjj");
+        assert!(!harness.render().contains("no such item"));
+        harness.resize(80, 30);
+        assert!(harness.render().contains("no such item"));
+    }
+}
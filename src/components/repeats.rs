@@ -0,0 +1,90 @@
+use std::collections::HashSet;
+
+use super::{host_tree::TreeRow, proxy::HttpLog};
+
+/// Folds consecutive requests with the same method and URL into one row with
+/// a counter, like the repeated messages of a browser console.
+#[derive(Default)]
+pub struct Repeats {
+    /// Groups showing their requests, by the id of their first request.
+    expanded: HashSet<u64>,
+}
+
+impl Repeats {
+    /// Rows for the requests `order` indexes into `logs`, folding runs of
+    /// repeats under their most recent request.
+    pub fn rows(&self, logs: &[&HttpLog], order: &[usize]) -> Vec<TreeRow> {
+        let mut rows = Vec::new();
+        for run in order.chunk_by(|&a, &b| same_request(logs[a], logs[b])) {
+            let [index] = run else {
+                let first = logs[run[0]].id;
+                let expanded = self.expanded.contains(&first);
+                rows.push(TreeRow::Repeats {
+                    first,
+                    index: run[run.len() - 1],
+                    count: run.len(),
+                    expanded,
+                });
+                if expanded {
+                    rows.extend(run.iter().copied().map(TreeRow::Occurrence));
+                }
+                continue;
+            };
+            rows.push(TreeRow::Request(*index));
+        }
+        rows
+    }
+
+    pub fn toggle(&mut self, first: u64) {
+        if !self.expanded.remove(&first) {
+            self.expanded.insert(first);
+        }
+    }
+
+    pub fn set_expanded(&mut self, first: u64, expanded: bool) {
+        if expanded {
+            self.expanded.insert(first);
+        } else {
+            self.expanded.remove(&first);
+        }
+    }
+}
+
+fn same_request(a: &HttpLog, b: &HttpLog) -> bool {
+    a.method == b.method && a.uri == b.uri
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn log(id: u64, uri: &str) -> HttpLog {
+        HttpLog {
+            id,
+            method: "GET".to_string(),
+            uri: uri.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rows_fold_consecutive_repeats() {
+        let logs = [log(1, "/poll"), log(2, "/poll"), log(3, "/a"), log(4, "/poll"), log(5, "/poll"), log(6, "/poll")];
+        let logs: Vec<&HttpLog> = logs.iter().collect();
+        let mut repeats = Repeats::default();
+        repeats.toggle(4);
+        assert_eq!(
+            repeats.rows(&logs, &[0, 1, 2, 3, 4, 5]),
+            vec![
+                TreeRow::Repeats { first: 1, index: 1, count: 2, expanded: false },
+                TreeRow::Request(2),
+                TreeRow::Repeats { first: 4, index: 5, count: 3, expanded: true },
+                TreeRow::Occurrence(3),
+                TreeRow::Occurrence(4),
+                TreeRow::Occurrence(5),
+            ]
+        );
+    }
+}
@@ -0,0 +1,478 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use color_eyre::eyre::eyre;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
+use ratatui::{prelude::*, widgets::*};
+use serde_json::Value;
+
+use super::{Component, line_edit::LineEdit, proxy::RulesShared};
+use crate::{
+    app::Mode,
+    config::Config,
+    faults::{FaultRule, SharedFaults},
+    framework::{Action, Command, Updater},
+    limits::{LimitRule, Limiter},
+    rewrite::{RewriteRule, SharedRules},
+    state::SharedState,
+};
+
+/// A rule by its list and position there.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RuleRef {
+    Rewrite(usize),
+    Fault(usize),
+    Limit(usize),
+}
+
+/// One line of the panel.
+#[derive(Debug, PartialEq)]
+struct RuleRow {
+    rule: RuleRef,
+    /// What requests the rule matches: the text a rewrite replaces, or a host pattern.
+    pattern: String,
+    description: String,
+    enabled: bool,
+    hits: u64,
+}
+
+impl RuleRow {
+    fn kind(&self) -> &'static str {
+        match self.rule {
+            RuleRef::Rewrite(_) => "rewrite",
+            RuleRef::Fault(_) => "fault",
+            RuleRef::Limit(_) => "limit",
+        }
+    }
+}
+
+fn rows(rewrites: &[RewriteRule], faults: &[FaultRule], limits: &[LimitRule]) -> Vec<RuleRow> {
+    let rewrites = rewrites.iter().enumerate().map(|(i, rule)| RuleRow {
+        rule: RuleRef::Rewrite(i),
+        pattern: rule.from.clone(),
+        description: format!("→ {}", rule.to),
+        enabled: rule.enabled,
+        hits: rule.hits,
+    });
+    let faults = faults.iter().enumerate().map(|(i, rule)| {
+        let path = rule.path.as_ref().map(|path| format!("{} ", path)).unwrap_or_default();
+        let mut description = format!("{}→ {}", path, rule.fault.label());
+        if rule.probability < 1.0 {
+            description.push_str(&format!(" at {}%", rule.probability * 100.0));
+        }
+        RuleRow {
+            rule: RuleRef::Fault(i),
+            pattern: rule.host.clone(),
+            description,
+            enabled: rule.enabled,
+            hits: rule.hits,
+        }
+    });
+    let limits = limits.iter().enumerate().map(|(i, rule)| {
+        let mut limits = Vec::new();
+        if let Some(rate) = rule.per_second {
+            limits.push(format!("{}/s", rate));
+        }
+        if let Some(max) = rule.max_concurrent {
+            limits.push(format!("{} at once", max));
+        }
+        if rule.reject {
+            limits.push("reject".to_string());
+        }
+        RuleRow {
+            rule: RuleRef::Limit(i),
+            pattern: rule.host.clone(),
+            description: format!("→ {}", limits.join(", ")),
+            enabled: rule.enabled,
+            hits: rule.hits,
+        }
+    });
+    rewrites.chain(faults).chain(limits).collect()
+}
+
+/// Replace the rule lists under `proxy` in a parsed config file, leaving
+/// everything else as it is.
+fn merge_rules(
+    document: &mut Value,
+    rewrites: &[RewriteRule],
+    faults: &[FaultRule],
+    limits: &[LimitRule],
+) -> color_eyre::Result<()> {
+    if document.is_null() {
+        *document = Value::Object(Default::default());
+    }
+    let proxy = document
+        .as_object_mut()
+        .ok_or_else(|| eyre!("the config is not an object"))?
+        .entry("proxy")
+        .or_insert_with(|| Value::Object(Default::default()))
+        .as_object_mut()
+        .ok_or_else(|| eyre!("`proxy` in the config is not an object"))?;
+    let mut rewrites = serde_json::to_value(rewrites)?;
+    // hit counts only make sense for this run
+    for rule in rewrites.as_array_mut().into_iter().flatten() {
+        if let Some(rule) = rule.as_object_mut() {
+            rule.remove("hits");
+        }
+    }
+    proxy.insert("rewrites".to_string(), rewrites);
+    proxy.insert("faults".to_string(), serde_json::to_value(faults)?);
+    proxy.insert("limits".to_string(), serde_json::to_value(limits)?);
+    Ok(())
+}
+
+/// The config file rules are saved to: the JSON5 or JSON one if there is
+/// one, otherwise a new `config.json5`.
+pub fn config_file(dir: &Path) -> PathBuf {
+    ["config.json5", "config.json"]
+        .into_iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.exists())
+        .unwrap_or_else(|| dir.join("config.json5"))
+}
+
+/// The rule lists, as they are now.
+#[derive(Clone, Default)]
+struct RuleSets {
+    rewrites: SharedRules,
+    faults: SharedFaults,
+    limiter: Arc<Limiter>,
+}
+
+impl RuleSets {
+    /// Write the rules into the config file, so they apply from the next start on.
+    async fn save(&self, dir: &Path) -> color_eyre::Result<PathBuf> {
+        let path = config_file(dir);
+        let mut document = match tokio::fs::read_to_string(&path).await {
+            Ok(content) => json5::from_str(&content)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Value::Null,
+            Err(e) => return Err(e.into()),
+        };
+        let limits = self.limiter.rules().clone();
+        merge_rules(&mut document, &self.rewrites.read().await, &self.faults.read().await, &limits)?;
+        tokio::fs::create_dir_all(dir).await?;
+        tokio::fs::write(&path, serde_json::to_string_pretty(&document)? + "\n").await?;
+        Ok(path)
+    }
+
+    async fn toggle(&self, rule: RuleRef) {
+        match rule {
+            RuleRef::Rewrite(i) => {
+                if let Some(rule) = self.rewrites.write().await.get_mut(i) {
+                    rule.enabled = !rule.enabled;
+                }
+            }
+            RuleRef::Fault(i) => {
+                if let Some(rule) = self.faults.write().await.get_mut(i) {
+                    rule.enabled = !rule.enabled;
+                }
+            }
+            RuleRef::Limit(i) => {
+                if let Some(rule) = self.limiter.rules().get_mut(i) {
+                    rule.enabled = !rule.enabled;
+                }
+            }
+        }
+    }
+
+    async fn set_pattern(&self, rule: RuleRef, pattern: String) {
+        match rule {
+            RuleRef::Rewrite(i) => {
+                if let Some(rule) = self.rewrites.write().await.get_mut(i) {
+                    rule.from = pattern;
+                }
+            }
+            RuleRef::Fault(i) => {
+                if let Some(rule) = self.faults.write().await.get_mut(i) {
+                    rule.host = pattern;
+                }
+            }
+            RuleRef::Limit(i) => {
+                if let Some(rule) = self.limiter.rules().get_mut(i) {
+                    rule.host = pattern;
+                }
+            }
+        }
+    }
+}
+
+/// Overlay listing the rewrite, fault and limit rules with how often each
+/// one applied. Rules can be turned off, have what they match edited, and
+/// be saved to the config file, all without a restart.
+#[derive(Default)]
+pub struct Rules {
+    rules: RuleSets,
+    config: Config,
+    state: SharedState,
+    visible: bool,
+    selected: usize,
+    /// The pattern of the selected rule, while it is edited.
+    editing: Option<LineEdit>,
+    /// Rows as last read, kept while the proxy holds the rules.
+    rows: Vec<RuleRow>,
+    updater: Option<Updater>,
+    commands: Vec<Command>,
+    /// Where the overlay was last drawn, for click-outside-to-close.
+    popup_area: Rect,
+}
+
+impl Rules {
+    pub fn new(state: SharedState) -> Self {
+        Self {
+            state,
+            ..Self::default()
+        }
+    }
+
+    fn render_update(&self) {
+        if let Some(updater) = &self.updater {
+            updater.update();
+        }
+    }
+
+    fn selected_rule(&self) -> Option<RuleRef> {
+        self.rows.get(self.selected).map(|row| row.rule)
+    }
+
+    fn toggle_selected(&mut self) {
+        let (Some(rule), rules) = (self.selected_rule(), self.rules.clone()) else {
+            return;
+        };
+        self.commands.push(Command::then_render(async move { rules.toggle(rule).await }));
+    }
+
+    fn save(&mut self) {
+        let (rules, state, dir) = (self.rules.clone(), self.state.clone(), self.config.config.config_dir.clone());
+        self.commands.push(Command::then_render(async move {
+            match rules.save(&dir).await {
+                Ok(path) => state.notify(format!("Saved the rules to {}", path.display())),
+                Err(e) => state.error(format!("Failed to save the rules: {}", e)),
+            }
+        }));
+    }
+
+    /// Keys while a pattern is edited, like the command line: `enter` applies
+    /// it, `esc` drops it.
+    fn edit_key(&mut self, key: KeyEvent) {
+        let Some(line) = &mut self.editing else {
+            return;
+        };
+        match self.config.keybindings.action_for(Mode::Command, key) {
+            Some(Action::RunCommand) => {
+                let pattern = line.text().to_string();
+                self.editing = None;
+                if let Some(rule) = self.selected_rule() {
+                    let rules = self.rules.clone();
+                    self.commands.push(Command::then_render(async move { rules.set_pattern(rule, pattern).await }));
+                }
+            }
+            Some(Action::Close) => self.editing = None,
+            Some(action) => {
+                line.apply(action);
+            }
+            None if let KeyCode::Char(c) = key.code
+                && (key.modifiers - KeyModifiers::SHIFT).is_empty() =>
+            {
+                line.insert(c)
+            }
+            None => {}
+        }
+    }
+}
+
+impl Component for Rules {
+    fn component_will_mount(&mut self, config: Config) -> color_eyre::Result<()> {
+        self.config = config;
+        Ok(())
+    }
+
+    fn component_did_mount(&mut self, _area: Size, updater: Updater) -> color_eyre::Result<()> {
+        self.updater = Some(updater);
+        Ok(())
+    }
+
+    fn captures_key(&self, key: KeyEvent) -> bool {
+        self.visible
+            && (self.editing.is_some()
+                || self.config.keybindings.action_for(Mode::Home, key) != Some(&Action::ToggleRules))
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) -> color_eyre::Result<Option<Action>> {
+        if self.editing.is_some() {
+            self.edit_key(key);
+            self.render_update();
+            return Ok(None);
+        }
+        match self.config.keybindings.action_for(Mode::Panel, key) {
+            Some(Action::Close) => return Ok(Some(Action::ToggleRules)),
+            Some(Action::SelectNext) => {
+                self.selected = (self.selected + 1).min(self.rows.len().saturating_sub(1));
+            }
+            Some(Action::SelectPrev) => self.selected = self.selected.saturating_sub(1),
+            Some(Action::ToggleItem) => self.toggle_selected(),
+            Some(Action::EditItem | Action::OpenItem) => {
+                if let Some(row) = self.rows.get(self.selected) {
+                    let mut line = LineEdit::default();
+                    line.set(&row.pattern);
+                    self.editing = Some(line);
+                }
+            }
+            Some(Action::SaveItem) => self.save(),
+            _ => return Ok(None),
+        }
+        self.render_update();
+        Ok(None)
+    }
+
+    fn captures_mouse(&self, _mouse: MouseEvent) -> bool {
+        self.visible
+    }
+
+    fn handle_mouse_event(&mut self, mouse: MouseEvent) -> color_eyre::Result<Option<Action>> {
+        let outside = !self.popup_area.contains(Position::new(mouse.column, mouse.row));
+        if matches!(mouse.kind, MouseEventKind::Down(_)) && outside {
+            return Ok(Some(Action::ToggleRules));
+        }
+        Ok(None)
+    }
+
+    fn take_commands(&mut self) -> Vec<Command> {
+        std::mem::take(&mut self.commands)
+    }
+
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        match action {
+            Action::ConfigReloaded(reloaded) => self.component_will_mount(reloaded.config())?,
+            Action::ToggleRules => {
+                self.visible = !self.visible;
+                self.editing = None;
+                self.render_update();
+            }
+            Action::Message(message) => {
+                if let Some(shared) = message.downcast_ref::<RulesShared>() {
+                    self.rules = RuleSets {
+                        rewrites: shared.rewrites.clone(),
+                        faults: shared.faults.clone(),
+                        limiter: shared.limiter.clone(),
+                    };
+                }
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect) -> color_eyre::Result<()> {
+        if !self.visible {
+            return Ok(());
+        }
+        // the proxy holds the rules only briefly per request; skip a refresh rather than wait
+        if let (Ok(rewrites), Ok(faults)) = (self.rules.rewrites.try_read(), self.rules.faults.try_read()) {
+            self.rows = rows(&rewrites, &faults, &self.rules.limiter.rules());
+        }
+        self.selected = self.selected.min(self.rows.len().saturating_sub(1));
+
+        let popup_area = area.inner(Margin {
+            horizontal: area.width / 10,
+            vertical: area.height / 10,
+        });
+        self.popup_area = popup_area;
+        let theme = &self.config.theme;
+        let hint = |mode, action| self.config.keybindings.hint(mode, &action);
+        let title = if self.editing.is_some() {
+            format!(
+                "Rules ({} to apply, {} to cancel)",
+                hint(Mode::Command, Action::RunCommand),
+                hint(Mode::Command, Action::Close)
+            )
+        } else {
+            format!(
+                "Rules ({} on/off, {} edit, {} save to config, {} close)",
+                hint(Mode::Panel, Action::ToggleItem),
+                hint(Mode::Panel, Action::EditItem),
+                hint(Mode::Panel, Action::SaveItem),
+                hint(Mode::Panel, Action::Close)
+            )
+        };
+        let block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(theme.fg(theme.title));
+        let inner = block.inner(popup_area);
+
+        // checkbox, kind and hit count come before the pattern
+        const PATTERN_COLUMN: u16 = 4 + 8 + 7 + 7;
+        let lines: Vec<Line> = if self.rows.is_empty() {
+            vec![Line::styled("No rewrite, fault or limit rules configured", theme.fg(theme.muted))]
+        } else {
+            self.rows
+                .iter()
+                .enumerate()
+                .map(|(i, row)| {
+                    let text = if row.enabled { theme.fg(theme.text) } else { theme.fg(theme.muted) };
+                    let hits = if row.hits == 0 { theme.fg(theme.muted) } else { theme.fg(theme.success) };
+                    let pattern = match &self.editing {
+                        Some(line) if i == self.selected => line.text().to_string(),
+                        _ => row.pattern.clone(),
+                    };
+                    Line::from(vec![
+                        Span::styled(if row.enabled { "[x] " } else { "[ ] " }, theme.fg(theme.accent)),
+                        Span::styled(format!("{:<8}", row.kind()), theme.fg(theme.secondary)),
+                        Span::styled(format!("{:>7} hits  ", row.hits), hits),
+                        Span::styled(pattern, text.add_modifier(Modifier::BOLD)),
+                        Span::styled(format!(" {}", row.description), text),
+                    ])
+                    .style(theme.row(i == self.selected))
+                })
+                .collect()
+        };
+        // keep the selection in view
+        let scroll = (self.selected + 1).saturating_sub(inner.height as usize) as u16;
+
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(Paragraph::new(lines).block(block).scroll((scroll, 0)), popup_area);
+        if let Some(line) = &self.editing {
+            let row = inner.y + self.selected as u16 - scroll;
+            frame.set_cursor_position((inner.x + PATTERN_COLUMN + line.cursor_column() as u16, row));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_rows_and_saved_rules() {
+        let rewrites: Vec<RewriteRule> = json5::from_str(r#"[{ from: "api.test", to: "localhost:3000" }]"#).unwrap();
+        let faults: Vec<FaultRule> = json5::from_str(
+            r#"[{ host: "*.test", path: "/slow", probability: 0.25, fault: { Delay: 2 }, enabled: false }]"#,
+        )
+        .unwrap();
+        let rows = rows(&rewrites, &faults, &[]);
+        assert_eq!(rows[0].pattern, "api.test");
+        assert_eq!(rows[0].description, "→ localhost:3000");
+        assert_eq!((rows[1].rule, rows[1].enabled), (RuleRef::Fault(0), false));
+        assert_eq!(rows[1].description, "/slow → delay 2s at 25%");
+
+        let mut document: Value = json5::from_str(r#"{ proxy: { listen: "127.0.0.1:8080" }, theme: "light" }"#).unwrap();
+        merge_rules(&mut document, &rewrites, &[], &[]).unwrap();
+        assert_eq!(
+            document,
+            serde_json::json!({
+                "proxy": {
+                    "listen": "127.0.0.1:8080",
+                    "rewrites": [{ "from": "api.test", "to": "localhost:3000", "enabled": true }],
+                    "faults": [],
+                    "limits": [],
+                },
+                "theme": "light",
+            })
+        );
+    }
+}
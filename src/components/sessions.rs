@@ -0,0 +1,304 @@
+use std::{path::PathBuf, sync::Arc};
+
+use chrono::{DateTime, Local};
+use crossterm::event::{KeyEvent, MouseEvent, MouseEventKind};
+use ratatui::{prelude::*, widgets::*};
+
+use super::{
+    Component,
+    proxy::{HttpLog, LogReceiver, SaveSession},
+};
+use crate::{
+    app::Mode,
+    config::Config,
+    framework::{Action, Message, Updater},
+    state::{NamedSession, SharedState},
+};
+
+/// A line of the picker: every request, or one named session.
+#[derive(Debug, Default, PartialEq)]
+struct SessionRow {
+    /// `None` for every request.
+    label: Option<String>,
+    requests: usize,
+    started: Option<DateTime<Local>>,
+    stopped: Option<DateTime<Local>>,
+    recording: bool,
+}
+
+/// The picker's rows: every request, the named sessions in the order they
+/// were started, then labels only known from loaded archives.
+fn rows(sessions: &[NamedSession], logs: &[Arc<HttpLog>]) -> Vec<SessionRow> {
+    let mut rows = vec![SessionRow {
+        requests: logs.len(),
+        ..SessionRow::default()
+    }];
+    rows.extend(sessions.iter().map(|session| SessionRow {
+        label: Some(session.label.clone()),
+        started: Some(session.started),
+        stopped: session.stopped,
+        recording: session.stopped.is_none(),
+        ..SessionRow::default()
+    }));
+    for label in logs.iter().filter_map(|log| log.session.as_ref()) {
+        match rows.iter_mut().find(|row| row.label.as_ref() == Some(label)) {
+            Some(row) => row.requests += 1,
+            None => rows.push(SessionRow {
+                label: Some(label.clone()),
+                requests: 1,
+                ..SessionRow::default()
+            }),
+        }
+    }
+    rows
+}
+
+/// Archive name for a session label, e.g. `login-flow-attempt-3.json`.
+fn file_name(label: &str) -> String {
+    let name: String = label
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    format!("{}.json", name.trim_matches('-'))
+}
+
+/// Overlay listing the named sessions, to limit the request list to one of
+/// them or save one to its own archive.
+#[derive(Default)]
+pub struct Sessions {
+    logs: Option<LogReceiver>,
+    state: SharedState,
+    config: Config,
+    visible: bool,
+    selected: usize,
+    /// Where exported sessions are saved.
+    sessions_dir: PathBuf,
+    updater: Option<Updater>,
+    /// Where the overlay was last drawn, for click-outside-to-close.
+    popup_area: Rect,
+}
+
+impl Sessions {
+    pub fn new(logs: LogReceiver, state: SharedState) -> Self {
+        Self {
+            logs: Some(logs),
+            state,
+            ..Self::default()
+        }
+    }
+
+    fn rows(&self) -> Vec<SessionRow> {
+        let Some(logs) = &self.logs else {
+            return Vec::new();
+        };
+        rows(&self.state.subscribe().borrow().sessions, &logs.borrow())
+    }
+
+    fn render_update(&self) {
+        if let Some(updater) = &self.updater {
+            updater.update();
+        }
+    }
+
+    fn show(&mut self) -> Option<Action> {
+        let row = self.rows().into_iter().nth(self.selected)?;
+        self.state.show_session(row.label);
+        Some(Action::ToggleSessions)
+    }
+
+    fn export(&mut self) -> Option<Action> {
+        let label = self.rows().into_iter().nth(self.selected)?.label?;
+        let path = self.sessions_dir.join(file_name(&label));
+        Some(Action::Message(Message::new(SaveSession {
+            path,
+            session: Some(label),
+        })))
+    }
+}
+
+impl Component for Sessions {
+    fn component_will_mount(&mut self, config: Config) -> color_eyre::Result<()> {
+        self.sessions_dir = config.config.data_dir.join("sessions");
+        self.config = config;
+        Ok(())
+    }
+
+    fn component_did_mount(&mut self, _area: Size, updater: Updater) -> color_eyre::Result<()> {
+        self.updater = Some(updater);
+        Ok(())
+    }
+
+    fn captures_key(&self, key: KeyEvent) -> bool {
+        self.visible && self.config.keybindings.action_for(Mode::Home, key) != Some(&Action::ToggleSessions)
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) -> color_eyre::Result<Option<Action>> {
+        match self.config.keybindings.action_for(Mode::Panel, key) {
+            Some(Action::Close) => return Ok(Some(Action::ToggleSessions)),
+            Some(Action::OpenItem) => return Ok(self.show()),
+            Some(Action::SaveItem) => return Ok(self.export()),
+            Some(Action::SelectNext) => self.selected = (self.selected + 1).min(self.rows().len().saturating_sub(1)),
+            Some(Action::SelectPrev) => self.selected = self.selected.saturating_sub(1),
+            _ => return Ok(None),
+        }
+        self.render_update();
+        Ok(None)
+    }
+
+    fn captures_mouse(&self, _mouse: MouseEvent) -> bool {
+        self.visible
+    }
+
+    fn handle_mouse_event(&mut self, mouse: MouseEvent) -> color_eyre::Result<Option<Action>> {
+        let outside = !self.popup_area.contains(Position::new(mouse.column, mouse.row));
+        match mouse.kind {
+            MouseEventKind::Down(_) if outside => Ok(Some(Action::ToggleSessions)),
+            _ => Ok(None),
+        }
+    }
+
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        // settings are only kept, so take them again
+        if let Action::ConfigReloaded(reloaded) = &action {
+            self.component_will_mount(reloaded.config())?;
+            return Ok(None);
+        }
+        if action == Action::ToggleSessions {
+            self.visible = !self.visible;
+            // start on the session being shown
+            let shown = self.state.subscribe().borrow().shown_session.clone();
+            self.selected = self.rows().iter().position(|row| row.label == shown).unwrap_or(0);
+            self.render_update();
+        }
+        Ok(None)
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect) -> color_eyre::Result<()> {
+        if !self.visible {
+            return Ok(());
+        }
+        let popup_area = area.inner(Margin {
+            horizontal: area.width / 10,
+            vertical: area.height / 10,
+        });
+        self.popup_area = popup_area;
+        let theme = &self.config.theme;
+        let shown = self.state.subscribe().borrow().shown_session.clone();
+        let rows = self.rows();
+        let label_width = rows.iter().filter_map(|row| row.label.as_ref()).map(|label| label.chars().count()).max();
+        let label_width = label_width.unwrap_or_default().max("All requests".len());
+        let mut items: Vec<ListItem> = rows
+            .iter()
+            .enumerate()
+            .map(|(index, row)| {
+                let marker = if row.label == shown { "▸ " } else { "  " };
+                let time = |time: DateTime<Local>| time.format("%H:%M:%S").to_string();
+                let span = match (row.started, row.stopped) {
+                    (Some(started), Some(stopped)) => format!("{}–{}", time(started), time(stopped)),
+                    (Some(started), None) => format!("{}–", time(started)),
+                    _ => String::new(),
+                };
+                let mut spans = vec![
+                    Span::styled(marker, theme.fg(theme.accent)),
+                    Span::raw(format!(
+                        "{:<label_width$}  {:>5} requests  ",
+                        row.label.as_deref().unwrap_or("All requests"),
+                        row.requests
+                    )),
+                    Span::styled(span, theme.fg(theme.muted)),
+                ];
+                if row.recording {
+                    spans.push(Span::styled("  ● recording", theme.fg(theme.error)));
+                }
+                ListItem::new(Line::from(spans)).style(theme.row(index == self.selected))
+            })
+            .collect();
+        if rows.len() == 1 {
+            items.push(ListItem::new(Line::styled(
+                "  No named sessions yet, :start <label> records one",
+                theme.fg(theme.muted),
+            )));
+        }
+        let hint = |action| self.config.keybindings.hint(Mode::Panel, &action);
+        let block = Block::default()
+            .title(format!(
+                "Sessions ({} to show, {} to save, {} to close)",
+                hint(Action::OpenItem),
+                hint(Action::SaveItem),
+                hint(Action::Close)
+            ))
+            .borders(Borders::ALL)
+            .border_style(theme.fg(theme.title));
+
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(List::new(items).block(block), popup_area);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_rows_count_requests_per_session() {
+        let started = Local::now();
+        let sessions = [
+            NamedSession {
+                label: "login".to_string(),
+                started,
+                stopped: Some(started),
+            },
+            NamedSession {
+                label: "checkout".to_string(),
+                started,
+                stopped: None,
+            },
+        ];
+        let log = |session: Option<&str>| {
+            Arc::new(HttpLog {
+                session: session.map(str::to_string),
+                ..Default::default()
+            })
+        };
+        let logs = [log(None), log(Some("login")), log(Some("archived")), log(Some("login"))];
+        let counts: Vec<_> = rows(&sessions, &logs)
+            .into_iter()
+            .map(|row| (row.label, row.requests, row.recording))
+            .collect();
+        assert_eq!(
+            counts,
+            [
+                (None, 4, false),
+                (Some("login".to_string()), 2, false),
+                (Some("checkout".to_string()), 0, true),
+                (Some("archived".to_string()), 1, false),
+            ]
+        );
+        assert_eq!(file_name("login flow / attempt 3"), "login-flow---attempt-3.json");
+    }
+
+    #[tokio::test]
+    async fn test_keys_follow_the_bindings() {
+        use tokio::sync::watch;
+
+        use crate::framework::testing::{self, Harness};
+
+        let mut config = testing::config();
+        let panel = config.keybindings.get_mut(&Mode::Panel).unwrap();
+        panel.retain(|_, action| *action != Action::Close);
+        panel.insert(testing::parse_keys("x"), Action::Close);
+        let (_logs, logs) = watch::channel(Default::default());
+        let mut harness = Harness::with_config(Sessions::new(logs, SharedState::default()), config, 80, 12);
+        harness.dispatch(Action::ToggleSessions);
+        assert!(harness.render().contains("x to close"));
+
+        harness.keys("<esc>q");
+        assert!(harness.render().contains("Sessions"), "the old keys are unbound");
+        harness.keys("x");
+        assert_eq!(harness.emitted.last(), Some(&Action::ToggleSessions));
+        assert!(!harness.render().contains("Sessions"));
+    }
+}
@@ -0,0 +1,364 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use chrono::Utc;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{prelude::*, widgets::*};
+use tracing::info;
+
+use super::{Component, line_edit::LineEdit};
+use crate::{
+    app::Mode,
+    ca,
+    config::{Config, ProxyConfig},
+    framework::{Action, Updater},
+};
+
+/// The questions of the setup, in order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+enum Step {
+    #[default]
+    Listen,
+    CaptureDir,
+    Ca,
+    Confirm,
+}
+
+/// Shown instead of the proxy on the first start, when there is no config
+/// file: asks for the listen address, the capture directory and whether to
+/// generate the CA certificate, then writes `config.json5` and the CA.
+#[derive(Default)]
+pub struct Setup {
+    config: Config,
+    step: Step,
+    listen: LineEdit,
+    capture_dir: LineEdit,
+    /// `yes` or `no`, to generate the CA certificate.
+    generate_ca: LineEdit,
+    /// Why the answer to the current question was not taken.
+    error: Option<String>,
+    /// Set once the config is written or the setup is skipped, to go on to the proxy.
+    done: Arc<AtomicBool>,
+    updater: Option<Updater>,
+}
+
+impl Setup {
+    pub fn new(done: Arc<AtomicBool>) -> Self {
+        Self { done, ..Self::default() }
+    }
+
+    fn default_capture_dir(&self) -> PathBuf {
+        (self.config.capture.dir.clone()).unwrap_or_else(|| self.config.config.data_dir.join("captures"))
+    }
+
+    fn config_path(&self) -> PathBuf {
+        self.config.config.config_dir.join("config.json5")
+    }
+
+    fn line(&mut self) -> Option<&mut LineEdit> {
+        match self.step {
+            Step::Listen => Some(&mut self.listen),
+            Step::CaptureDir => Some(&mut self.capture_dir),
+            Step::Ca => Some(&mut self.generate_ca),
+            Step::Confirm => None,
+        }
+    }
+
+    fn ca_exists(&self) -> bool {
+        ca::cert_path(&self.config.config.data_dir).exists()
+    }
+
+    /// The capture directory to write, `None` when the default was kept.
+    fn capture_dir(&self) -> Option<PathBuf> {
+        let dir = self.capture_dir.text().trim();
+        (!dir.is_empty() && Path::new(dir) != self.default_capture_dir()).then(|| PathBuf::from(dir))
+    }
+
+    fn next(&mut self) -> Option<Action> {
+        self.error = None;
+        match self.step {
+            Step::Listen => match parse_listen(self.listen.text()) {
+                Ok(listen) => {
+                    self.listen.set(&listen.to_string());
+                    self.step = Step::CaptureDir;
+                }
+                Err(e) => self.error = Some(e),
+            },
+            Step::CaptureDir => self.step = Step::Ca,
+            Step::Ca => match parse_yes_no(self.generate_ca.text()) {
+                Ok(generate) => {
+                    self.generate_ca.set(if generate { "yes" } else { "no" });
+                    self.step = Step::Confirm;
+                }
+                Err(e) => self.error = Some(e),
+            },
+            Step::Confirm => return self.write(),
+        }
+        None
+    }
+
+    fn back(&mut self) -> Option<Action> {
+        self.error = None;
+        match self.step {
+            // nothing is written, so the setup comes back on the next start
+            Step::Listen => {
+                self.done.store(true, Ordering::Relaxed);
+                return Some(Action::Quit);
+            }
+            Step::CaptureDir => self.step = Step::Listen,
+            Step::Ca => self.step = Step::CaptureDir,
+            Step::Confirm => self.step = Step::Ca,
+        }
+        None
+    }
+
+    fn write(&mut self) -> Option<Action> {
+        let Ok(listen) = parse_listen(self.listen.text()) else {
+            self.step = Step::Listen;
+            return None;
+        };
+        if parse_yes_no(self.generate_ca.text()) == Ok(true) {
+            let data_dir = &self.config.config.data_dir;
+            match ca::generate(data_dir, Utc::now()) {
+                Ok(_) => info!("Generated the CA certificate {}", ca::cert_path(data_dir).display()),
+                Err(e) => {
+                    self.error = Some(format!("Failed to generate the CA certificate: {}", e));
+                    return None;
+                }
+            }
+            // written, so going back and confirming again doesn't replace it
+            self.generate_ca.set("no");
+        }
+        let path = self.config_path();
+        let written = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, config_file(listen, self.capture_dir().as_deref())));
+        match written {
+            Ok(()) => {
+                self.done.store(true, Ordering::Relaxed);
+                Some(Action::Quit)
+            }
+            Err(e) => {
+                self.error = Some(format!("Failed to write {}: {}", path.display(), e));
+                None
+            }
+        }
+    }
+
+    fn lines(&self) -> Vec<Line<'static>> {
+        let theme = &self.config.theme;
+        let question = |step: Step, text: &str| {
+            let style = if step == self.step {
+                theme.fg(theme.title).add_modifier(Modifier::BOLD)
+            } else {
+                theme.fg(theme.muted)
+            };
+            Line::styled(text.to_string(), style)
+        };
+        let answer = |text: String| Line::styled(format!("  {}", text), theme.fg(theme.text));
+
+        let mut lines = vec![
+            Line::styled("There is no config file yet, a few questions to write one.", theme.fg(theme.text)),
+            Line::from(""),
+            question(Step::Listen, "Address to listen on (an address and port, or a port)"),
+            answer(self.listen.text().to_string()),
+            Line::from(""),
+            question(Step::CaptureDir, "Directory to write captures to"),
+            answer(self.capture_dir.text().to_string()),
+            Line::from(""),
+        ];
+        if self.step >= Step::Ca {
+            let ca_question = if self.ca_exists() {
+                "Generate a new CA certificate? (yes or no, there is one already)"
+            } else {
+                "Generate the CA certificate for HTTPS? (yes or no)"
+            };
+            lines.extend([
+                question(Step::Ca, ca_question),
+                answer(self.generate_ca.text().to_string()),
+                Line::from(""),
+            ]);
+        }
+        if self.step == Step::Confirm {
+            lines.extend([
+                question(Step::Confirm, "Write the config?"),
+                answer(self.config_path().display().to_string()),
+            ]);
+            if parse_yes_no(self.generate_ca.text()) == Ok(true) {
+                lines.push(answer(ca::cert_path(&self.config.config.data_dir).display().to_string()));
+                lines.push(Line::from(""));
+                lines.push(Line::styled(
+                    "`yap cert install` then makes the system trust the CA certificate.",
+                    theme.fg(theme.muted),
+                ));
+            }
+            lines.push(Line::from(""));
+        }
+        if let Some(error) = &self.error {
+            lines.push(Line::styled(error.clone(), theme.fg(theme.error)));
+            lines.push(Line::from(""));
+        }
+        let hint = match self.step {
+            Step::Listen => "enter next, esc skip the setup for now, ctrl-c quit",
+            Step::CaptureDir | Step::Ca => "enter next, esc back, ctrl-c quit",
+            Step::Confirm => "enter write and start, esc back, ctrl-c quit",
+        };
+        lines.push(Line::styled(hint, theme.fg(theme.muted)));
+        lines
+    }
+}
+
+impl Component for Setup {
+    fn component_will_mount(&mut self, config: Config) -> color_eyre::Result<()> {
+        self.config = config;
+        self.listen.set(&self.config.proxy.listen.to_string());
+        self.capture_dir.set(&self.default_capture_dir().display().to_string());
+        self.generate_ca.set(if self.ca_exists() { "no" } else { "yes" });
+        Ok(())
+    }
+
+    fn component_did_mount(&mut self, _area: Size, updater: Updater) -> color_eyre::Result<()> {
+        self.updater = Some(updater);
+        Ok(())
+    }
+
+    fn captures_key(&self, key: KeyEvent) -> bool {
+        // everything but quitting is typed into the answers
+        !(key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL))
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) -> color_eyre::Result<Option<Action>> {
+        let action = match key.code {
+            KeyCode::Enter => self.next(),
+            KeyCode::Esc => self.back(),
+            _ => {
+                let action = self.config.keybindings.action_for(Mode::Filter, key).cloned();
+                if let Some(line) = self.line() {
+                    match action {
+                        Some(action) => {
+                            line.apply(&action);
+                        }
+                        None if let KeyCode::Char(c) = key.code
+                            && (key.modifiers - KeyModifiers::SHIFT).is_empty() =>
+                        {
+                            line.insert(c)
+                        }
+                        None => {}
+                    }
+                }
+                None
+            }
+        };
+        if let Some(updater) = &self.updater {
+            updater.update();
+        }
+        Ok(action)
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect) -> color_eyre::Result<()> {
+        let theme = &self.config.theme;
+        let popup_area = area.inner(Margin {
+            horizontal: area.width / 10,
+            vertical: area.height / 10,
+        });
+        let block = Block::default()
+            .title("Welcome to yap")
+            .borders(Borders::ALL)
+            .border_style(theme.fg(theme.title));
+        let inner = block.inner(popup_area);
+        frame.render_widget(Paragraph::new(self.lines()).block(block).wrap(Wrap { trim: false }), popup_area);
+
+        // the answer being typed is on the line below its question
+        let answer = match self.step {
+            Step::Listen => Some((&self.listen, 3)),
+            Step::CaptureDir => Some((&self.capture_dir, 6)),
+            Step::Ca => Some((&self.generate_ca, 9)),
+            Step::Confirm => None,
+        };
+        if let Some((line, row)) = answer {
+            frame.set_cursor_position((inner.x + 2 + line.cursor_column() as u16, inner.y + row));
+        }
+        Ok(())
+    }
+}
+
+/// Read an answer like `127.0.0.1:8080`, or just a port for localhost.
+fn parse_listen(answer: &str) -> Result<SocketAddr, String> {
+    let answer = answer.trim();
+    if let Ok(port) = answer.parse::<u16>() {
+        return Ok(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port));
+    }
+    answer
+        .parse()
+        .map_err(|_| format!("`{}` is not an address like {} or a port", answer, ProxyConfig::default().listen))
+}
+
+/// Read a `yes` or `no` answer, or its first letter.
+fn parse_yes_no(answer: &str) -> Result<bool, String> {
+    match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => Ok(true),
+        "n" | "no" => Ok(false),
+        answer => Err(format!("`{}` is neither yes nor no", answer)),
+    }
+}
+
+/// The config written by the setup, with only what was asked.
+fn config_file(listen: SocketAddr, capture_dir: Option<&Path>) -> String {
+    let quote = |text: String| serde_json::to_string(&text).unwrap_or_default();
+    let mut file = String::from("// Written by the first-run setup; the README describes every setting.\n{\n");
+    file.push_str(&format!("  proxy: {{\n    listen: {},\n  }},\n", quote(listen.to_string())));
+    if let Some(dir) = capture_dir {
+        file.push_str(&format!("  capture: {{\n    dir: {},\n  }},\n", quote(dir.display().to_string())));
+    }
+    file.push_str("}\n");
+    file
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_answers_make_a_config() {
+        assert_eq!(parse_listen(" 8080 "), Ok("127.0.0.1:8080".parse().unwrap()));
+        assert_eq!(parse_listen("0.0.0.0:9000"), Ok("0.0.0.0:9000".parse().unwrap()));
+        assert!(parse_listen("localhost").is_err());
+        assert_eq!(parse_yes_no(" Y "), Ok(true));
+        assert_eq!(parse_yes_no("no"), Ok(false));
+        assert!(parse_yes_no("maybe").is_err());
+
+        let file = config_file("127.0.0.1:8080".parse().unwrap(), Some(Path::new("/tmp/my \"captures\"")));
+        let config: Config = json5::from_str(&file).unwrap();
+        assert_eq!(config.proxy.listen, "127.0.0.1:8080".parse().unwrap());
+        assert_eq!(config.capture.dir, Some(PathBuf::from("/tmp/my \"captures\"")));
+        let config: Config = json5::from_str(&config_file("[::1]:9999".parse().unwrap(), None)).unwrap();
+        assert_eq!(config.capture.dir, None);
+    }
+
+    #[tokio::test]
+    async fn test_setup_writes_the_config_and_the_ca() {
+        use crate::framework::testing::Harness;
+
+        let done = Arc::new(AtomicBool::new(false));
+        let mut harness = Harness::new(Setup::new(done.clone()), 100, 30);
+        harness.keys("<enter><enter>");
+        assert!(harness.render().contains("Generate the CA certificate for HTTPS?"));
+        harness.keys("<enter>");
+        let cert = ca::cert_path(&harness.data_dir());
+        assert!(harness.render().contains(&cert.display().to_string()));
+        harness.keys("<enter>");
+
+        assert!(done.load(Ordering::Relaxed));
+        assert!(harness.component.config_path().exists());
+        assert!(ca::load(&harness.data_dir()).unwrap().is_some());
+    }
+}
@@ -0,0 +1,123 @@
+use super::{host_tree::host_of, proxy::HttpLog};
+
+/// Column the request list is ordered by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortKey {
+    /// Capture order.
+    #[default]
+    Time,
+    Host,
+    Method,
+    Status,
+    Duration,
+    Size,
+}
+
+impl SortKey {
+    fn next(self) -> Self {
+        match self {
+            SortKey::Time => SortKey::Host,
+            SortKey::Host => SortKey::Method,
+            SortKey::Method => SortKey::Status,
+            SortKey::Status => SortKey::Duration,
+            SortKey::Duration => SortKey::Size,
+            SortKey::Size => SortKey::Time,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SortKey::Time => "time",
+            SortKey::Host => "host",
+            SortKey::Method => "method",
+            SortKey::Status => "status",
+            SortKey::Duration => "duration",
+            SortKey::Size => "size",
+        }
+    }
+}
+
+/// Order of the request list, cycled with `s` and reversed with `S`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ListSort {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+impl ListSort {
+    pub fn cycle(&mut self) {
+        self.key = self.key.next();
+        self.descending = false;
+    }
+
+    pub fn reverse(&mut self) {
+        self.descending = !self.descending;
+    }
+
+    /// Sort `logs`, which are in capture order. Ties keep that order, and
+    /// requests still waiting for a response come first when ascending.
+    pub fn apply(&self, logs: &mut [&HttpLog]) {
+        match self.key {
+            SortKey::Time => {}
+            SortKey::Host => logs.sort_by_cached_key(|log| host_of(&log.uri)),
+            SortKey::Method => logs.sort_by(|a, b| a.method.cmp(&b.method)),
+            SortKey::Status => logs.sort_by_key(|log| log.status),
+            SortKey::Duration => logs.sort_by_key(|log| log.duration),
+            SortKey::Size => logs.sort_by_key(|log| log.size),
+        }
+        if self.descending {
+            logs.reverse();
+        }
+    }
+
+    /// Shown in the list's title, e.g. `status ↓`.
+    pub fn label(&self) -> String {
+        format!("{} {}", self.key.label(), if self.descending { "↓" } else { "↑" })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn log(id: u64, uri: &str, status: Option<u16>, duration_ms: u64) -> HttpLog {
+        HttpLog {
+            id,
+            method: "GET".to_string(),
+            uri: uri.to_string(),
+            status,
+            duration: Some(Duration::from_millis(duration_ms)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_sort_is_stable_and_reversible() {
+        let logs = [
+            log(1, "http://b.test/", Some(200), 30),
+            log(2, "http://a.test/", Some(404), 10),
+            log(3, "http://b.test/x", None, 20),
+            log(4, "http://a.test/y", Some(200), 40),
+        ];
+        let sorted = |sort: ListSort| {
+            let mut refs: Vec<&HttpLog> = logs.iter().collect();
+            sort.apply(&mut refs);
+            refs.iter().map(|log| log.id).collect::<Vec<_>>()
+        };
+        let mut sort = ListSort::default();
+        assert_eq!(sorted(sort), vec![1, 2, 3, 4]);
+        sort.cycle();
+        assert_eq!(sorted(sort), vec![2, 4, 1, 3]);
+        (sort.key, sort.descending) = (SortKey::Status, false);
+        assert_eq!(sorted(sort), vec![3, 1, 4, 2]);
+        sort.reverse();
+        assert_eq!(sorted(sort), vec![2, 4, 1, 3]);
+        assert_eq!(sort.label(), "status ↓");
+        (sort.key, sort.descending) = (SortKey::Duration, true);
+        assert_eq!(sorted(sort), vec![4, 1, 3, 2]);
+    }
+}
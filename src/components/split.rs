@@ -0,0 +1,102 @@
+use ratatui::prelude::*;
+
+use crate::{
+    config::{LayoutConfig, SplitDirection},
+    framework::Action,
+};
+
+/// How far one resize keypress moves the divider, in percent.
+const STEP: u16 = 5;
+const MIN_RATIO: u16 = 10;
+const MAX_RATIO: u16 = 90;
+
+/// Two panes sharing an area, with a divider that can be moved from the keyboard.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SplitPane {
+    direction: SplitDirection,
+    /// Percentage of the area given to the first pane.
+    ratio: u16,
+}
+
+impl Default for SplitPane {
+    fn default() -> Self {
+        Self::new(SplitDirection::default(), 50)
+    }
+}
+
+impl SplitPane {
+    pub fn new(direction: SplitDirection, ratio: u16) -> Self {
+        Self {
+            direction,
+            ratio: ratio.clamp(MIN_RATIO, MAX_RATIO),
+        }
+    }
+
+    /// The statistics split described by the `layout` config section.
+    pub fn from_config(config: &LayoutConfig) -> Self {
+        Self::new(config.stats_direction, config.stats_ratio)
+    }
+
+    /// Split `area` into the first and second pane.
+    pub fn areas(&self, area: Rect) -> [Rect; 2] {
+        let constraints = [
+            Constraint::Percentage(self.ratio),
+            Constraint::Percentage(100 - self.ratio),
+        ];
+        match self.direction {
+            SplitDirection::Horizontal => Layout::horizontal(constraints).areas(area),
+            SplitDirection::Vertical => Layout::vertical(constraints).areas(area),
+        }
+    }
+
+    /// Move the divider for a resize action along this split's axis.
+    /// Returns false if the action doesn't apply or the divider is at its limit.
+    pub fn resize(&mut self, action: &Action) -> bool {
+        let ratio = match (self.direction, action) {
+            (SplitDirection::Horizontal, Action::ResizeLeft) | (SplitDirection::Vertical, Action::ResizeUp) => {
+                self.ratio.saturating_sub(STEP)
+            }
+            (SplitDirection::Horizontal, Action::ResizeRight) | (SplitDirection::Vertical, Action::ResizeDown) => {
+                self.ratio + STEP
+            }
+            _ => return false,
+        }
+        .clamp(MIN_RATIO, MAX_RATIO);
+        let changed = ratio != self.ratio;
+        self.ratio = ratio;
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_resize_along_axis() {
+        let mut split = SplitPane::new(SplitDirection::Horizontal, 60);
+        assert!(split.resize(&Action::ResizeRight));
+        assert_eq!(split.ratio, 65);
+        assert!(!split.resize(&Action::ResizeUp));
+        assert!(split.resize(&Action::ResizeLeft));
+        assert_eq!(split.ratio, 60);
+    }
+
+    #[test]
+    fn test_resize_is_clamped() {
+        let mut split = SplitPane::new(SplitDirection::Vertical, 88);
+        assert!(split.resize(&Action::ResizeDown));
+        assert_eq!(split.ratio, MAX_RATIO);
+        assert!(!split.resize(&Action::ResizeDown));
+    }
+
+    #[test]
+    fn test_areas() {
+        let split = SplitPane::new(SplitDirection::Vertical, 25);
+        let [top, bottom] = split.areas(Rect::new(0, 0, 10, 100));
+        assert_eq!(top, Rect::new(0, 0, 10, 25));
+        assert_eq!(bottom, Rect::new(0, 25, 10, 75));
+    }
+}
@@ -0,0 +1,161 @@
+//! Server-Sent Events: `text/event-stream` bodies split into their events,
+//! as they stream in or from a finished capture.
+
+use ratatui::prelude::*;
+
+use super::body_format::format_body;
+use crate::theme::Theme;
+
+pub fn is_event_stream(content_type: Option<&str>) -> bool {
+    content_type.is_some_and(|ct| ct.trim().to_lowercase().starts_with("text/event-stream"))
+}
+
+/// One dispatched event of a stream.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SseEvent {
+    /// The `event:` field; clients treat a missing one as `message`.
+    pub event: Option<String>,
+    /// The `data:` lines, joined by newlines.
+    pub data: String,
+    pub id: Option<String>,
+    /// The `retry:` field, in milliseconds.
+    pub retry: Option<u64>,
+}
+
+/// Splits an event stream into events chunk by chunk, following the parsing
+/// rules of the HTML standard. A chunk may end anywhere, even inside a
+/// UTF-8 sequence.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    /// Bytes of the line not yet ended.
+    line: Vec<u8>,
+    /// The last line ended in `\r`, so a `\n` right after it belongs to it.
+    after_cr: bool,
+    /// Fields of the event being read.
+    pending: SseEvent,
+}
+
+impl SseParser {
+    /// Feed the next chunk of the stream and return the events it completed.
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+        for &byte in bytes {
+            match byte {
+                b'\n' if self.after_cr => self.after_cr = false,
+                b'\r' | b'\n' => {
+                    self.after_cr = byte == b'\r';
+                    let line = std::mem::take(&mut self.line);
+                    events.extend(self.line(&String::from_utf8_lossy(&line)));
+                }
+                _ => {
+                    self.after_cr = false;
+                    self.line.push(byte);
+                }
+            }
+        }
+        events
+    }
+
+    fn line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        // comments, often sent to keep the connection open
+        if line.starts_with(':') {
+            return None;
+        }
+        let (name, value) = match line.split_once(':') {
+            Some((name, value)) => (name, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match name {
+            "event" => self.pending.event = Some(value.to_string()),
+            "data" => {
+                self.pending.data.push_str(value);
+                self.pending.data.push('\n');
+            }
+            "id" if !value.contains('\0') => self.pending.id = Some(value.to_string()),
+            "retry" => self.pending.retry = value.parse().ok().or(self.pending.retry),
+            _ => {}
+        }
+        None
+    }
+
+    /// End the pending event at a blank line. Events without data aren't
+    /// dispatched.
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let mut event = std::mem::take(&mut self.pending);
+        if event.data.is_empty() {
+            return None;
+        }
+        event.data.pop();
+        Some(event)
+    }
+}
+
+/// Every event of a whole stream, including a last one the stream ended
+/// without a blank line after.
+pub fn parse(body: &str) -> Vec<SseEvent> {
+    let mut parser = SseParser::default();
+    let mut events = parser.push(body.as_bytes());
+    events.extend(parser.push(b"\n\n"));
+    events
+}
+
+/// Events one below the other, numbered, with JSON data pretty-printed.
+pub fn format_events(theme: &Theme, events: &[SseEvent]) -> Text<'static> {
+    let mut lines = Vec::new();
+    for (n, event) in events.iter().enumerate() {
+        let mut head = vec![
+            Span::styled(format!("#{} ", n + 1), theme.fg(theme.muted)),
+            Span::styled(event.event.clone().unwrap_or_else(|| "message".to_string()), theme.fg(theme.title)),
+        ];
+        if let Some(id) = &event.id {
+            head.push(Span::styled(format!("  id: {}", id), theme.fg(theme.accent)));
+        }
+        if let Some(retry) = event.retry {
+            head.push(Span::styled(format!("  retry: {} ms", retry), theme.fg(theme.accent)));
+        }
+        lines.push(Line::from(head));
+        lines.extend(format_body(theme, None, &event.data).lines);
+        lines.push(Line::from(""));
+    }
+    lines.pop();
+    Text::from(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn event(event: Option<&str>, data: &str, id: Option<&str>) -> SseEvent {
+        SseEvent {
+            event: event.map(str::to_string),
+            data: data.to_string(),
+            id: id.map(str::to_string),
+            retry: None,
+        }
+    }
+
+    #[test]
+    fn test_events_split_across_chunks() {
+        let mut parser = SseParser::default();
+        assert_eq!(parser.push(b": keepalive\n\nevent: tick\r"), Vec::new());
+        assert_eq!(parser.push(b"\ndata: {\"n\":1}\r\nid: 7\r\n"), Vec::new());
+        assert_eq!(parser.push(b"\r\ndata:first\ndata: caf\xc3"), vec![event(Some("tick"), "{\"n\":1}", Some("7"))]);
+        assert_eq!(parser.push(b"\xa9\n\nretry: 500\n\n"), vec![event(None, "first\ncafé", None)]);
+        assert_eq!(parser.push(b"data\n\n"), vec![event(None, "", None)]);
+    }
+
+    #[test]
+    fn test_parse_whole_stream() {
+        let events = parse("retry: 3000\ndata: a\n\nevent: done\ndata: b");
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].retry, Some(3000));
+        assert_eq!(events[1], event(Some("done"), "b", None));
+        assert!(is_event_stream(Some("text/event-stream; charset=utf-8")));
+        assert!(!is_event_stream(Some("text/plain")));
+    }
+}
@@ -0,0 +1,265 @@
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use chrono::Utc;
+use ratatui::{prelude::*, widgets::*};
+use tracing::info;
+
+use super::{
+    Component,
+    host_tree::host_of,
+    proxy::{HttpLog, LogReceiver},
+};
+use crate::{
+    config::Config,
+    connections::{ConnectionStats, HostConnections},
+    framework::{Action, Updater},
+    theme::Theme,
+};
+
+/// Width of the requests-per-second window, in seconds.
+const RATE_WINDOW: usize = 60;
+
+/// Aggregate metrics over the captured requests: request rate, status code
+/// distribution, busiest hosts and average latency, and the upstream
+/// connections behind them.
+pub struct Stats {
+    logs: LogReceiver,
+    connections: ConnectionStats,
+    visible: bool,
+    task_handle: Option<tokio::task::JoinHandle<()>>,
+    theme: Theme,
+}
+
+impl Stats {
+    pub fn new(logs: LogReceiver, connections: ConnectionStats) -> Self {
+        Self {
+            logs,
+            connections,
+            visible: false,
+            task_handle: None,
+            theme: Theme::default(),
+        }
+    }
+}
+
+/// Sent once a second by the ticker task.
+struct Tick;
+
+#[derive(Default)]
+struct Summary {
+    rate: Vec<u64>,
+    statuses: BTreeMap<String, u64>,
+    hosts: Vec<(String, u64)>,
+    avg_latency: Option<Duration>,
+    total: usize,
+    /// Responses breaking an assertion rule.
+    failed: usize,
+}
+
+fn summarize(logs: &[Arc<HttpLog>]) -> Summary {
+    let now = Utc::now();
+    let mut rate = vec![0u64; RATE_WINDOW];
+    let mut statuses = BTreeMap::new();
+    let mut hosts: BTreeMap<String, u64> = BTreeMap::new();
+    let mut latency_sum = Duration::ZERO;
+    let mut latency_count = 0u32;
+
+    for log in logs {
+        let age = (now - log.timestamp).num_seconds();
+        if (0..RATE_WINDOW as i64).contains(&age) {
+            rate[RATE_WINDOW - 1 - age as usize] += 1;
+        }
+        let class = match log.status {
+            Some(status) => format!("{}xx", status / 100),
+            None => "pending".to_string(),
+        };
+        *statuses.entry(class).or_default() += 1;
+        *hosts.entry(host_of(&log.uri)).or_default() += 1;
+        if let Some(duration) = log.duration {
+            latency_sum += duration;
+            latency_count += 1;
+        }
+    }
+
+    let mut hosts: Vec<_> = hosts.into_iter().collect();
+    hosts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    Summary {
+        rate,
+        statuses,
+        hosts,
+        avg_latency: (latency_count > 0).then(|| latency_sum / latency_count),
+        total: logs.len(),
+        failed: logs.iter().filter(|log| !log.failed_assertions.is_empty()).count(),
+    }
+}
+
+/// `512 B`, `1.5 KiB`, `3.2 MiB`.
+pub fn bytes(count: u64) -> String {
+    match count {
+        0..1024 => format!("{} B", count),
+        1024..1_048_576 => format!("{:.1} KiB", count as f64 / 1024.0),
+        _ => format!("{:.1} MiB", count as f64 / 1_048_576.0),
+    }
+}
+
+/// A host's connections; many requests per connection means they are
+/// reused, about one means every request dials again.
+fn connection_item(theme: &Theme, host: &str, counters: &HostConnections, requests: u64) -> ListItem<'static> {
+    let per_connection = match counters.opened {
+        0 => "-".to_string(),
+        opened => format!("{:.1}", requests as f64 / opened as f64),
+    };
+    let details = format!(
+        "  {} open · {} closed · {} req/conn · ↑{} ↓{}",
+        counters.open(),
+        counters.closed,
+        per_connection,
+        bytes(counters.sent),
+        bytes(counters.received)
+    );
+    ListItem::new(vec![
+        Line::from(vec![
+            Span::raw(format!("{}  ", host)),
+            Span::styled(counters.tls_versions(), theme.fg(theme.accent)),
+        ]),
+        Line::styled(details, theme.fg(theme.muted)),
+    ])
+}
+
+impl Component for Stats {
+    fn component_will_mount(&mut self, config: Config) -> color_eyre::Result<()> {
+        self.theme = config.theme;
+        Ok(())
+    }
+
+    fn component_did_mount(
+        &mut self,
+        _area: ratatui::layout::Size,
+        updater: Updater,
+    ) -> color_eyre::Result<()> {
+        info!("Stats::component_did_mount");
+        // keep the rate sparkline moving while the panel is visible
+        let ticker = updater.clone();
+        self.task_handle = Some(updater.spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_secs(1)) => ticker.send(Tick),
+                    _ = ticker.cancelled() => break,
+                }
+            }
+        }));
+        Ok(())
+    }
+
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        match action {
+            Action::ConfigReloaded(reloaded) => {
+                self.component_will_mount(reloaded.config())?;
+                Ok(None)
+            }
+            Action::ToggleStats => {
+                self.visible = !self.visible;
+                Ok(Some(Action::Render))
+            }
+            Action::Message(message) if message.is::<Tick>() && self.visible => Ok(Some(Action::Render)),
+            _ => Ok(None),
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect) -> color_eyre::Result<()> {
+        let logs_snapshot = self.logs.borrow_and_update().clone();
+        let summary = summarize(&logs_snapshot);
+
+        let block = Block::default()
+            .title("Statistics")
+            .borders(Borders::ALL)
+            .border_style(self.theme.fg(self.theme.accent));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let [rate_area, status_area, hosts_area, connections_area, latency_area] = Layout::vertical([
+            Constraint::Length(5),
+            Constraint::Length(7),
+            Constraint::Min(3),
+            Constraint::Min(4),
+            Constraint::Length(2),
+        ])
+        .areas(inner);
+
+        let current = summary.rate.last().copied().unwrap_or_default();
+        let sparkline = Sparkline::default()
+            .block(Block::default().title(format!("Requests/s (last {}s, now {})", RATE_WINDOW, current)))
+            .data(&summary.rate)
+            .style(self.theme.fg(self.theme.success));
+        frame.render_widget(sparkline, rate_area);
+
+        let bars: Vec<Bar> = summary
+            .statuses
+            .iter()
+            .map(|(class, count)| {
+                Bar::default()
+                    .label(Line::from(class.as_str()))
+                    .value(*count)
+                    .style(self.theme.fg(self.theme.status_class(class)))
+            })
+            .collect();
+        let chart = BarChart::default()
+            .block(Block::default().title("Status codes"))
+            .data(BarGroup::default().bars(&bars))
+            .bar_width(7)
+            .bar_gap(1);
+        frame.render_widget(chart, status_area);
+
+        let host_items: Vec<ListItem> = summary
+            .hosts
+            .iter()
+            .take(hosts_area.height.saturating_sub(1) as usize)
+            .map(|(host, count)| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{:>6} ", count), self.theme.fg(self.theme.warning)),
+                    Span::raw(host.as_str()),
+                ]))
+            })
+            .collect();
+        frame.render_widget(List::new(host_items).block(Block::default().title("Top hosts")), hosts_area);
+
+        let requests: BTreeMap<_, _> = summary.hosts.iter().cloned().collect();
+        let mut connections: Vec<_> = self.connections.hosts().into_iter().collect();
+        connections.sort_by(|a, b| b.1.opened.cmp(&a.1.opened).then_with(|| a.0.cmp(&b.0)));
+        let connection_items: Vec<ListItem> = connections
+            .iter()
+            .take(connections_area.height.saturating_sub(1) as usize / 2)
+            .map(|(host, counters)| {
+                let requests = requests.get(host).copied().unwrap_or_default();
+                connection_item(&self.theme, host, counters, requests)
+            })
+            .collect();
+        frame.render_widget(
+            List::new(connection_items).block(Block::default().title("Upstream connections")),
+            connections_area,
+        );
+
+        let latency = match summary.avg_latency {
+            Some(latency) => format!("{} ms", latency.as_millis()),
+            None => "-".to_string(),
+        };
+        let mut totals = Line::raw(format!("Total: {}  Avg latency: {}", summary.total, latency));
+        if summary.failed > 0 {
+            totals.push_span(Span::styled(
+                format!("  Failed assertions: {}", summary.failed),
+                self.theme.fg(self.theme.error).add_modifier(Modifier::BOLD),
+            ));
+        }
+        frame.render_widget(Paragraph::new(totals), latency_area);
+        Ok(())
+    }
+}
+
+impl Drop for Stats {
+    fn drop(&mut self) {
+        if let Some(handle) = self.task_handle.take() {
+            handle.abort();
+        }
+    }
+}
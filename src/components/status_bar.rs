@@ -0,0 +1,164 @@
+use std::path::PathBuf;
+
+use ratatui::{prelude::*, widgets::*};
+use tokio::{sync::watch, time::Instant};
+
+use super::{Component, proxy::LogReceiver};
+use crate::{
+    app::Mode,
+    ca,
+    config::Config,
+    framework::{Action, Updater},
+    state::{AppState, SharedState},
+    theme::Theme,
+};
+
+/// One-line bar at the bottom of the screen: mode, listen address, capture
+/// state, request count, whether the root CA is trusted, the active filter
+/// and transient messages.
+pub struct StatusBar {
+    state: SharedState,
+    data_dir: PathBuf,
+    app: watch::Receiver<AppState>,
+    logs: LogReceiver,
+    filter: watch::Receiver<String>,
+    theme: Theme,
+}
+
+impl StatusBar {
+    pub fn new(state: SharedState, logs: LogReceiver, filter: watch::Receiver<String>) -> Self {
+        Self {
+            app: state.subscribe(),
+            state,
+            data_dir: PathBuf::new(),
+            logs,
+            filter,
+            theme: Theme::default(),
+        }
+    }
+}
+
+fn mode_label(theme: &Theme, mode: Mode) -> (&'static str, Color) {
+    match mode {
+        Mode::Home | Mode::List => ("NORMAL", theme.secondary),
+        Mode::Filter => ("FILTER", theme.success),
+        Mode::Detail => ("DETAIL", theme.special),
+        Mode::Diff => ("DIFF", theme.special),
+        Mode::Confirm => ("CONFIRM", theme.error),
+        Mode::Command => ("COMMAND", theme.warning),
+        Mode::Timeline => ("TIMELINE", theme.accent),
+        Mode::Panel => ("PANEL", theme.accent),
+        Mode::Composer => ("COMPOSE", theme.warning),
+    }
+}
+
+impl Component for StatusBar {
+    fn component_will_mount(&mut self, config: Config) -> color_eyre::Result<()> {
+        self.theme = config.theme;
+        self.data_dir = config.config.data_dir;
+        Ok(())
+    }
+
+    fn component_did_mount(
+        &mut self,
+        _area: ratatui::layout::Size,
+        updater: Updater,
+    ) -> color_eyre::Result<()> {
+        // the trust stores are files or tools outside the process, so asked once
+        let (state, data_dir) = (self.state.clone(), self.data_dir.clone());
+        updater.spawn(async move {
+            let trusted = tokio::task::spawn_blocking(move || match ca::load(&data_dir) {
+                Ok(Some(cert)) => Some(ca::trusted(&data_dir, &cert)),
+                Ok(None) | Err(_) => None,
+            });
+            if let Ok(trusted) = trusted.await {
+                state.set_ca_trusted(trusted);
+            }
+        });
+        // redraw on every state change, and once more when a notice expires
+        let mut app = self.state.subscribe();
+        let watcher = updater.clone();
+        updater.spawn(async move {
+            loop {
+                let expires = app
+                    .borrow_and_update()
+                    .notice_at(Instant::now())
+                    .map(|notice| notice.expires);
+                tokio::select! {
+                    changed = app.changed() => {
+                        if changed.is_err() {
+                            break;
+                        }
+                    }
+                    _ = tokio::time::sleep_until(expires.unwrap_or_else(Instant::now)), if expires.is_some() => {}
+                    _ = watcher.cancelled() => break,
+                }
+                watcher.update();
+            }
+        });
+        Ok(())
+    }
+
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        match action {
+            Action::Error(error) => self.state.error(error),
+            Action::ConfigReloaded(reloaded) => {
+                self.component_will_mount(reloaded.config())?;
+                self.state.notify("Config reloaded");
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect) -> color_eyre::Result<()> {
+        let state = self.app.borrow().clone();
+        let count = self.logs.borrow().len();
+        let filter = self.filter.borrow().clone();
+
+        let theme = &self.theme;
+        let (mode, color) = mode_label(theme, state.mode);
+        let separator = Span::styled(" │ ", theme.fg(theme.border));
+        let mut spans = vec![
+            Span::styled(format!(" {} ", mode), theme.fg(theme.badge).bg(color).bold()),
+            Span::raw(" "),
+            Span::raw(match state.listen.as_slice() {
+                [] => "not listening".to_string(),
+                [listen] => format!("listening on {}", listen),
+                [listen, more @ ..] => format!("listening on {} (+{})", listen, more.len()),
+            }),
+            separator.clone(),
+            if state.capturing {
+                Span::styled("capture on", theme.fg(theme.success))
+            } else {
+                Span::styled("capture off", theme.fg(theme.error))
+            },
+            separator.clone(),
+            Span::raw(format!("{} requests", count)),
+        ];
+        if let Some(trusted) = state.ca_trusted {
+            spans.push(separator.clone());
+            spans.push(if trusted {
+                Span::styled("CA trusted", theme.fg(theme.success))
+            } else {
+                Span::styled("CA not trusted", theme.fg(theme.warning))
+            });
+        }
+        if let Some(label) = state.recording() {
+            spans.push(separator.clone());
+            spans.push(Span::styled(format!("● {}", label), theme.fg(theme.error)));
+        }
+        if !filter.is_empty() {
+            spans.push(separator);
+            spans.push(Span::styled(format!("filter: {}", filter), theme.fg(theme.warning)));
+        }
+        frame.render_widget(Paragraph::new(Line::from(spans)), area);
+
+        if let Some(notice) = state.notice_at(Instant::now()) {
+            let style = theme.fg(if notice.error { theme.error } else { theme.accent });
+            let notice = Line::styled(format!("{} ", notice.text), style).right_aligned();
+            frame.render_widget(Paragraph::new(notice), area);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,327 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use crossterm::event::{KeyEvent, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::{prelude::*, widgets::*};
+use tokio::sync::watch;
+
+use super::{
+    Component,
+    proxy::{HttpLog, LogReceiver},
+};
+use crate::{
+    app::Mode,
+    config::{Config, KeyBindings},
+    framework::{Action, Updater},
+    state::{AppState, SharedState, TimeWindow},
+    theme::Theme,
+};
+
+/// Bar heights in eighths of a row.
+const BARS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// The requests of `logs`, which are in timestamp order, that arrived within `window`.
+pub fn in_window<'a>(logs: &'a [Arc<HttpLog>], window: &TimeWindow) -> &'a [Arc<HttpLog>] {
+    let start = logs.partition_point(|log| log.timestamp < window.from);
+    let end = logs.partition_point(|log| log.timestamp < window.to).max(start);
+    &logs[start..end]
+}
+
+/// Requests per column of the timeline, from the first request to the last.
+#[derive(Debug, PartialEq)]
+struct Buckets {
+    start: DateTime<Utc>,
+    /// Time covered by a column, whole seconds.
+    width: TimeDelta,
+    counts: Vec<u64>,
+}
+
+impl Buckets {
+    fn new(logs: &[Arc<HttpLog>], columns: usize) -> Option<Self> {
+        let (first, last) = (logs.first()?.timestamp, logs.last()?.timestamp);
+        let columns = columns.max(1);
+        let span = (last - first).num_seconds() + 1;
+        let width = TimeDelta::seconds((span + columns as i64 - 1) / columns as i64);
+        // columns start on whole seconds, so their times read well
+        let start = first - TimeDelta::nanoseconds(first.timestamp_subsec_nanos() as i64);
+        let mut counts = vec![0; columns];
+        for log in logs {
+            let column = ((log.timestamp - start).num_milliseconds() / width.num_milliseconds()) as usize;
+            counts[column.min(columns - 1)] += 1;
+        }
+        Some(Self { start, width, counts })
+    }
+
+    fn time(&self, column: usize) -> DateTime<Utc> {
+        self.start + self.width * column as i32
+    }
+
+    /// The time of the columns `a` to `b`, either way round.
+    fn window(&self, a: usize, b: usize) -> TimeWindow {
+        TimeWindow {
+            from: self.time(a.min(b)),
+            to: self.time(a.max(b) + 1),
+        }
+    }
+
+    /// The columns `window` covers at least in part.
+    fn covers(&self, column: usize, window: &TimeWindow) -> bool {
+        self.time(column) < window.to && self.time(column + 1) > window.from
+    }
+}
+
+/// Strip above the filter box charting requests over time. A span picked
+/// on it, with the keys or by dragging, limits the request list to it.
+pub struct Timeline {
+    logs: LogReceiver,
+    state: SharedState,
+    app: watch::Receiver<AppState>,
+    /// Column the keys move.
+    cursor: usize,
+    /// Other end of the span being selected.
+    anchor: Option<usize>,
+    /// Columns as last drawn.
+    buckets: Option<Buckets>,
+    keybindings: KeyBindings,
+    theme: Theme,
+    focused: bool,
+    /// Where the bars were last drawn, to map clicks to columns.
+    area: Rect,
+    updater: Option<Updater>,
+}
+
+impl Timeline {
+    pub fn new(logs: LogReceiver, state: SharedState) -> Self {
+        Self {
+            logs,
+            app: state.subscribe(),
+            state,
+            cursor: usize::MAX,
+            anchor: None,
+            buckets: None,
+            keybindings: KeyBindings::default(),
+            theme: Theme::default(),
+            focused: false,
+            area: Rect::default(),
+            updater: None,
+        }
+    }
+
+    fn render_update(&self) {
+        if let Some(updater) = &self.updater {
+            updater.update();
+        }
+    }
+
+    fn last_column(&self) -> usize {
+        self.buckets.as_ref().map_or(0, |buckets| buckets.counts.len() - 1)
+    }
+
+    fn apply(&mut self) {
+        if let Some(buckets) = &self.buckets {
+            let window = buckets.window(self.anchor.unwrap_or(self.cursor), self.cursor);
+            self.state.set_time_window(Some(window));
+        }
+        self.anchor = None;
+    }
+
+    /// The column under `column` of the screen, if it is on the bars.
+    fn column_at(&self, column: u16, row: u16) -> Option<usize> {
+        self.area
+            .contains(Position::new(column, row))
+            .then(|| ((column - self.area.x) as usize).min(self.last_column()))
+    }
+
+    fn bars(&self, buckets: &Buckets, height: u16, window: Option<&TimeWindow>) -> Vec<Line<'static>> {
+        let theme = &self.theme;
+        let max = buckets.counts.iter().copied().max().unwrap_or_default().max(1);
+        let selected = self.anchor.map(|anchor| (anchor.min(self.cursor), anchor.max(self.cursor)));
+        let eighths = height as u64 * 8;
+        (0..height as u64)
+            .map(|row| {
+                let floor = (height as u64 - 1 - row) * 8;
+                let spans = buckets.counts.iter().enumerate().map(|(column, &count)| {
+                    // any request shows at least a sliver
+                    let level = (count * eighths).div_ceil(max);
+                    let bar = BARS[level.saturating_sub(floor).min(8) as usize];
+                    let color = match selected {
+                        Some((from, to)) if (from..=to).contains(&column) => theme.warning,
+                        _ if window.is_some_and(|window| buckets.covers(column, window)) => theme.accent,
+                        _ => theme.success,
+                    };
+                    let mut style = theme.fg(color);
+                    if self.focused && column == self.cursor {
+                        style = style.bg(theme.selection);
+                    }
+                    Span::styled(bar.to_string(), style)
+                });
+                Line::from(spans.collect::<Vec<_>>())
+            })
+            .collect()
+    }
+}
+
+impl Component for Timeline {
+    fn component_will_mount(&mut self, config: Config) -> color_eyre::Result<()> {
+        self.keybindings = config.keybindings;
+        self.theme = config.theme;
+        Ok(())
+    }
+
+    fn component_did_mount(&mut self, _area: Size, updater: Updater) -> color_eyre::Result<()> {
+        self.updater = Some(updater);
+        Ok(())
+    }
+
+    fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+        if focused {
+            self.state.set_mode(Mode::Timeline);
+        }
+    }
+
+    fn captures_key(&self, key: KeyEvent) -> bool {
+        self.focused && self.keybindings.action_for(Mode::Timeline, key).is_some()
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) -> color_eyre::Result<Option<Action>> {
+        let Some(action) = self.keybindings.action_for(Mode::Timeline, key).cloned() else {
+            return Ok(None);
+        };
+        let last = self.last_column();
+        match action {
+            Action::CursorLeft => self.cursor = self.cursor.min(last).saturating_sub(1),
+            Action::CursorRight => self.cursor = self.cursor.saturating_add(1).min(last),
+            Action::CursorStart => self.cursor = 0,
+            Action::CursorEnd => self.cursor = last,
+            Action::MarkRange => {
+                self.cursor = self.cursor.min(last);
+                self.anchor = match self.anchor {
+                    Some(_) => None,
+                    None => Some(self.cursor),
+                };
+            }
+            Action::ApplyRange => self.apply(),
+            Action::ClearRange => {
+                self.anchor = None;
+                self.state.set_time_window(None);
+            }
+            _ => return Ok(None),
+        }
+        self.render_update();
+        Ok(None)
+    }
+
+    fn handle_mouse_event(&mut self, mouse: MouseEvent) -> color_eyre::Result<Option<Action>> {
+        // dragging across the bars selects a span, a click picks one column
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let Some(column) = self.column_at(mouse.column, mouse.row) else {
+                    return Ok(None);
+                };
+                self.cursor = column;
+                self.anchor = Some(column);
+            }
+            MouseEventKind::Drag(MouseButton::Left) if self.anchor.is_some() => {
+                let column = (mouse.column.saturating_sub(self.area.x) as usize).min(self.last_column());
+                self.cursor = column;
+            }
+            MouseEventKind::Up(MouseButton::Left) if self.anchor.is_some() => self.apply(),
+            _ => return Ok(None),
+        }
+        self.render_update();
+        Ok(None)
+    }
+
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        if let Action::ConfigReloaded(reloaded) = &action {
+            self.component_will_mount(reloaded.config())?;
+        }
+        Ok(None)
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect) -> color_eyre::Result<()> {
+        let logs = self.logs.borrow().clone();
+        let window = self.app.borrow_and_update().time_window;
+        let block = Block::default().borders(Borders::ALL).border_style(self.theme.pane(self.focused));
+        let inner = block.inner(area);
+        let [bars_area, axis_area] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(inner);
+        self.area = bars_area;
+
+        self.buckets = Buckets::new(&logs, inner.width as usize);
+        let Some(buckets) = &self.buckets else {
+            self.cursor = usize::MAX;
+            let empty = Paragraph::new(Line::styled("No requests yet", self.theme.fg(self.theme.muted)));
+            frame.render_widget(empty.block(block.title("Timeline")), area);
+            return Ok(());
+        };
+        // the cursor starts on, and sticks to, the newest column
+        self.cursor = self.cursor.min(buckets.counts.len() - 1);
+
+        let local = |time: DateTime<Utc>| time.with_timezone(&Local).format("%H:%M:%S").to_string();
+        let title = format!("Timeline ({}s per column)", buckets.width.num_seconds());
+        let shown = match window {
+            Some(window) => Line::styled(
+                format!(" showing {}–{} (esc to clear) ", local(window.from), local(window.to)),
+                self.theme.fg(self.theme.accent),
+            ),
+            None => Line::default(),
+        };
+        let axis = Line::from(vec![
+            Span::styled(local(buckets.start), self.theme.fg(self.theme.muted)),
+            Span::styled(
+                format!(
+                    "{:>width$}",
+                    logs.last().map(|log| local(log.timestamp)).unwrap_or_default(),
+                    width = (axis_area.width as usize).saturating_sub(8)
+                ),
+                self.theme.fg(self.theme.muted),
+            ),
+        ]);
+        let focused = self.focused.then(|| {
+            let cursor = buckets.window(self.cursor, self.cursor);
+            let count = buckets.counts[self.cursor];
+            Line::styled(format!(" {} · {} requests ", local(cursor.from), count), self.theme.fg(self.theme.text))
+                .right_aligned()
+        });
+        let bars = self.bars(buckets, bars_area.height, window.as_ref());
+        frame.render_widget(block.title(title).title(shown).title(focused.unwrap_or_default()), area);
+        frame.render_widget(Paragraph::new(bars), bars_area);
+        frame.render_widget(Paragraph::new(axis), axis_area);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_buckets_and_windows() {
+        let start = DateTime::parse_from_rfc3339("2024-05-01T10:00:00Z").unwrap().with_timezone(&Utc);
+        let log = |millis: i64| {
+            Arc::new(HttpLog {
+                timestamp: start + TimeDelta::milliseconds(millis),
+                ..Default::default()
+            })
+        };
+        let logs = [log(250), log(900), log(4_100), log(9_999)];
+        let buckets = Buckets::new(&logs, 4).unwrap();
+        assert_eq!(buckets.start, start);
+        assert_eq!(buckets.width, TimeDelta::seconds(3));
+        assert_eq!(buckets.counts, vec![2, 1, 0, 1]);
+
+        let window = buckets.window(1, 0);
+        assert_eq!(window.to, start + TimeDelta::seconds(6));
+        assert_eq!(in_window(&logs, &window).len(), 3);
+        assert!(buckets.covers(1, &window) && !buckets.covers(2, &window));
+        let window = buckets.window(2, 2);
+        assert!(in_window(&logs, &window).is_empty());
+
+        // a single request still gets a column
+        assert_eq!(Buckets::new(&logs[..1], 80).unwrap().counts.iter().sum::<u64>(), 1);
+        assert_eq!(Buckets::new(&[], 80), None);
+    }
+}
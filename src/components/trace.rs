@@ -0,0 +1,140 @@
+//! Requests of one distributed trace: the id services pass along in their
+//! headers, and the waterfall of the requests that carried it.
+
+use hyper::HeaderMap;
+use ratatui::prelude::*;
+
+use super::proxy::HttpLog;
+use crate::theme::Theme;
+
+/// Headers carrying an id shared by every request of one flow, after
+/// `traceparent`, in order of preference.
+const CORRELATION_HEADERS: [&str; 3] = ["x-request-id", "x-correlation-id", "request-id"];
+
+/// Columns of the waterfall bars.
+const BAR_WIDTH: usize = 40;
+
+/// The trace a request belongs to: the trace id of a W3C `traceparent`
+/// header, else the value of a correlation header like `X-Request-Id`.
+pub fn trace_id(headers: &HeaderMap) -> Option<String> {
+    if let Some((trace_id, _)) = traceparent(headers) {
+        return Some(trace_id);
+    }
+    CORRELATION_HEADERS
+        .iter()
+        .filter_map(|name| headers.get(*name).and_then(|v| v.to_str().ok()).map(str::trim))
+        .find(|id| !id.is_empty())
+        .map(str::to_string)
+}
+
+/// Trace id and parent span id of a valid `traceparent` header, lowercase.
+pub fn traceparent(headers: &HeaderMap) -> Option<(String, String)> {
+    let value = headers.get("traceparent")?.to_str().ok()?.trim();
+    // version-traceid-parentid-flags, all-zero ids are invalid
+    let mut fields = value.split('-');
+    let (_version, trace_id, parent_id) = (fields.next()?, fields.next()?, fields.next()?);
+    let valid = |id: &str, len: usize| {
+        id.len() == len && id.bytes().all(|b| b.is_ascii_hexdigit()) && id.bytes().any(|b| b != b'0')
+    };
+    (valid(trace_id, 32) && valid(parent_id, 16)).then(|| (trace_id.to_lowercase(), parent_id.to_lowercase()))
+}
+
+/// Columns from the start, and the width, of the bar of a request running
+/// from `start` to `end` ms into a trace lasting `total` ms; at least one
+/// column wide so instant requests still show.
+fn bar(start: u64, end: u64, total: u64, width: usize) -> (usize, usize) {
+    let total = total.max(1);
+    let column = |ms: u64| (ms.min(total) as usize * width / total as usize).min(width);
+    let from = column(start).min(width - 1);
+    (from, column(end).max(from + 1) - from)
+}
+
+/// One line per request of `chain`, ordered by when they arrived, with a
+/// bar for the time each took; `selected` is highlighted.
+pub fn waterfall(theme: &Theme, chain: &[&HttpLog], selected: u64) -> Vec<Line<'static>> {
+    let Some(first) = chain.iter().map(|log| log.timestamp).min() else {
+        return Vec::new();
+    };
+    let offset = |log: &HttpLog| (log.timestamp - first).num_milliseconds().max(0) as u64;
+    let end = |log: &HttpLog| offset(log) + log.duration.map(|d| d.as_millis() as u64).unwrap_or(0);
+    let total = chain.iter().map(|log| end(log)).max().unwrap_or(0);
+    let path_width = chain.iter().map(|log| short_uri(&log.uri).chars().count()).max().unwrap_or(0).min(40);
+
+    chain
+        .iter()
+        .map(|log| {
+            let (from, width) = bar(offset(log), end(log), total, BAR_WIDTH);
+            let status = match log.status {
+                Some(status) => Span::styled(
+                    format!("{:>3}", status),
+                    theme.fg(theme.status_class(&format!("{}xx", status / 100))),
+                ),
+                None => Span::styled("...", theme.fg(theme.muted)),
+            };
+            let took = match log.duration {
+                Some(duration) => format!(" +{}ms {}ms", offset(log), duration.as_millis()),
+                None => format!(" +{}ms", offset(log)),
+            };
+            let line = Line::from(vec![
+                Span::raw(if log.id == selected { "▶ " } else { "  " }),
+                status,
+                Span::styled(format!(" {:<7}", log.method), theme.fg(theme.method(&log.method))),
+                Span::raw(format!("{:<width$.width$} ", short_uri(&log.uri), width = path_width)),
+                Span::styled(" ".repeat(from), theme.fg(theme.border)),
+                Span::styled(
+                    "█".repeat(width),
+                    theme.fg(if log.status.is_some() { theme.accent } else { theme.muted }),
+                ),
+                Span::raw(" ".repeat(BAR_WIDTH - from - width)),
+                Span::styled(took, theme.fg(theme.muted)),
+            ]);
+            if log.id == selected {
+                line.add_modifier(Modifier::BOLD)
+            } else {
+                line
+            }
+        })
+        .collect()
+}
+
+/// Host and path of `uri`, without the scheme and query.
+fn short_uri(uri: &str) -> String {
+    match url::Url::parse(uri) {
+        Ok(url) => format!("{}{}", url.host_str().unwrap_or_default(), url.path()),
+        Err(_) => uri.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_trace_id_and_bars() {
+        let headers = |pairs: &[(&'static str, &str)]| {
+            let mut headers = HeaderMap::new();
+            for (name, value) in pairs {
+                headers.insert(*name, value.parse().unwrap());
+            }
+            headers
+        };
+        let parent = "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01";
+        assert_eq!(
+            trace_id(&headers(&[("traceparent", parent), ("x-request-id", "abc")])).as_deref(),
+            Some("4bf92f3577b34da6a3ce929d0e0e4736")
+        );
+        let invalid = "00-00000000000000000000000000000000-00f067aa0ba902b7-01";
+        assert_eq!(trace_id(&headers(&[("traceparent", invalid), ("x-request-id", " abc ")])).as_deref(), Some("abc"));
+        assert_eq!(trace_id(&headers(&[("x-correlation-id", "42")])).as_deref(), Some("42"));
+        assert_eq!(trace_id(&headers(&[("x-request-id", "")])), None);
+
+        assert_eq!(bar(0, 100, 100, 40), (0, 40));
+        assert_eq!(bar(50, 75, 100, 40), (20, 10));
+        // an instant request, and one at the very end, still get a column
+        assert_eq!(bar(10, 10, 100, 40), (4, 1));
+        assert_eq!(bar(100, 100, 100, 40), (39, 1));
+        assert_eq!(bar(0, 0, 0, 40), (0, 1));
+    }
+}
@@ -0,0 +1,182 @@
+use std::time::{Duration, Instant};
+
+use ratatui::{prelude::*, widgets::*};
+use tracing::info;
+
+use super::{Component, stats::bytes};
+use crate::{
+    config::Config,
+    framework::{Action, Updater},
+    theme::Theme,
+    transfers::{Transfer, Transfers},
+};
+
+/// Transfers listed at once; more are left out until earlier ones finish.
+pub const MAX_ROWS: usize = 5;
+/// Width of a transfer's progress bar.
+const BAR_WIDTH: usize = 20;
+/// How often the panel redraws while something is listed.
+const REFRESH: Duration = Duration::from_millis(500);
+
+/// A row per large download going through the proxy, with its progress, rate
+/// and time left. Shown only while there is one.
+pub struct TransferList {
+    transfers: Transfers,
+    hidden: bool,
+    /// Something was listed at the last tick, so the panel needs one more
+    /// redraw to go away once nothing is.
+    listed: bool,
+    task_handle: Option<tokio::task::JoinHandle<()>>,
+    theme: Theme,
+}
+
+impl TransferList {
+    pub fn new(transfers: Transfers) -> Self {
+        Self {
+            transfers,
+            hidden: false,
+            listed: false,
+            task_handle: None,
+            theme: Theme::default(),
+        }
+    }
+}
+
+/// Sent by the ticker task.
+struct Tick;
+
+/// `1:05`, or `1:02:05` past an hour.
+fn clock(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs / 3600 {
+        0 => format!("{}:{:02}", secs / 60, secs % 60),
+        hours => format!("{}:{:02}:{:02}", hours, secs / 60 % 60, secs % 60),
+    }
+}
+
+/// `[██████░░░░]`, or a moving block when the size is unknown.
+fn bar(transfer: &Transfer, now: Instant) -> String {
+    let cells = match transfer.fraction() {
+        Some(fraction) => {
+            let filled = (fraction * BAR_WIDTH as f64).round() as usize;
+            format!("{}{}", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled))
+        }
+        None if transfer.finished.is_some() => "█".repeat(BAR_WIDTH),
+        None => {
+            let at = (now.duration_since(transfer.started).as_millis() / REFRESH.as_millis()) as usize % BAR_WIDTH;
+            (0..BAR_WIDTH).map(|cell| if cell == at { '█' } else { '░' }).collect()
+        }
+    };
+    format!("[{}]", cells)
+}
+
+fn transfer_line(theme: &Theme, transfer: &Transfer, now: Instant) -> Line<'static> {
+    let size = match transfer.total {
+        Some(total) => format!("{} / {}", bytes(transfer.done), bytes(total)),
+        None => bytes(transfer.done),
+    };
+    let rate = format!("{}/s", bytes(transfer.rate(now) as u64));
+    let state = match (transfer.finished, transfer.eta(now)) {
+        (Some(finished), _) => {
+            let took = clock(finished.duration_since(transfer.started));
+            Span::styled(format!("done in {}", took), theme.fg(theme.success))
+        }
+        (None, Some(eta)) => Span::raw(format!("ETA {}", clock(eta))),
+        (None, None) => {
+            let elapsed = clock(now.duration_since(transfer.started));
+            Span::styled(format!("{} so far", elapsed), theme.fg(theme.muted))
+        }
+    };
+    Line::from(vec![
+        Span::styled(bar(transfer, now), theme.fg(theme.accent)),
+        Span::raw(format!(" {:>21}  ", size)),
+        Span::styled(format!("{:>12}  ", rate), theme.fg(theme.warning)),
+        state,
+        Span::styled(format!("  {} {}", transfer.method, transfer.uri), theme.fg(theme.text)),
+    ])
+}
+
+impl Component for TransferList {
+    fn component_will_mount(&mut self, config: Config) -> color_eyre::Result<()> {
+        self.theme = config.theme;
+        Ok(())
+    }
+
+    fn component_did_mount(
+        &mut self,
+        _area: ratatui::layout::Size,
+        updater: Updater,
+    ) -> color_eyre::Result<()> {
+        info!("TransferList::component_did_mount");
+        let ticker = updater.clone();
+        self.task_handle = Some(updater.spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(REFRESH) => ticker.send(Tick),
+                    _ = ticker.cancelled() => break,
+                }
+            }
+        }));
+        Ok(())
+    }
+
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        match action {
+            Action::ConfigReloaded(reloaded) => {
+                self.component_will_mount(reloaded.config())?;
+                Ok(None)
+            }
+            Action::ToggleTransfers => {
+                self.hidden = !self.hidden;
+                Ok(Some(Action::Render))
+            }
+            // keeps the bars moving, and the panel going away once all are done
+            Action::Message(message) if message.is::<Tick>() && !self.hidden => {
+                let listed = !self.transfers.shown(Instant::now()).is_empty();
+                let redraw = listed || self.listed;
+                self.listed = listed;
+                Ok(redraw.then_some(Action::Render))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect) -> color_eyre::Result<()> {
+        let now = Instant::now();
+        let transfers = self.transfers.shown(now);
+        let more = transfers.len().saturating_sub(MAX_ROWS);
+        let title = match more {
+            0 => "Transfers".to_string(),
+            more => format!("Transfers ({} more)", more),
+        };
+        let lines: Vec<Line> =
+            transfers.iter().take(MAX_ROWS).map(|transfer| transfer_line(&self.theme, transfer, now)).collect();
+        let block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(self.theme.fg(self.theme.accent));
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+        Ok(())
+    }
+}
+
+impl Drop for TransferList {
+    fn drop(&mut self) {
+        if let Some(handle) = self.task_handle.take() {
+            handle.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_clock() {
+        assert_eq!(clock(Duration::from_secs(65)), "1:05");
+        assert_eq!(clock(Duration::from_secs(3725)), "1:02:05");
+    }
+}
@@ -0,0 +1,102 @@
+//! Body files stored with zstd when `capture.compression` is set. Compressed
+//! files start with a magic header, so files written before, or with
+//! compression off, read back as they are.
+
+use std::{io, path::Path, pin::Pin};
+
+use async_compression::{
+    Level,
+    tokio::{bufread::ZstdDecoder, write::ZstdEncoder},
+};
+use tokio::{
+    fs::File,
+    io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+};
+
+/// What a compressed body file starts with, ahead of the zstd frame.
+const MAGIC: &[u8] = b"YAPZSTD\x01";
+
+/// A body file being read, decompressed on the way.
+pub type BodyReader = Pin<Box<dyn AsyncRead + Send + Sync>>;
+
+/// A body file being written; it is only complete once shut down.
+pub type BodyWriter = Pin<Box<dyn AsyncWrite + Send + Sync>>;
+
+/// Create the body file at `path`, compressed at `level` when set.
+pub async fn create(path: &Path, level: Option<i32>) -> io::Result<BodyWriter> {
+    let mut file = File::create(path).await?;
+    Ok(match level {
+        Some(level) => {
+            file.write_all(MAGIC).await?;
+            Box::pin(ZstdEncoder::with_quality(file, Level::Precise(level)))
+        }
+        None => Box::pin(file),
+    })
+}
+
+/// Write `bytes` to the body file at `path`, compressed at `level` when set.
+pub async fn write(path: &Path, bytes: &[u8], level: Option<i32>) -> io::Result<()> {
+    let mut writer = create(path, level).await?;
+    writer.write_all(bytes).await?;
+    writer.shutdown().await
+}
+
+/// Read the body file at `path`, whether or not it was stored compressed.
+pub async fn open(path: &Path) -> io::Result<BodyReader> {
+    let mut reader = BufReader::new(File::open(path).await?);
+    if reader.fill_buf().await?.starts_with(MAGIC) {
+        reader.consume(MAGIC.len());
+        Ok(Box::pin(ZstdDecoder::new(reader)))
+    } else {
+        Ok(Box::pin(reader))
+    }
+}
+
+/// The whole body in the file at `path`.
+pub async fn read(path: &Path) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    open(path).await?.read_to_end(&mut bytes).await?;
+    Ok(bytes)
+}
+
+/// Up to `limit` bytes from the start of the body in the file at `path`.
+pub async fn read_prefix(path: &Path, limit: u64) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    open(path).await?.take(limit).read_to_end(&mut bytes).await?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use tokio::fs;
+
+    use super::*;
+    use crate::framework::testing::TempDir;
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let dir = TempDir::new();
+        let dir = dir.path();
+        let body = "a body that repeats itself, ".repeat(200).into_bytes();
+
+        let compressed = dir.join("compressed.bin");
+        write(&compressed, &body, Some(3)).await.unwrap();
+        let stored = fs::read(&compressed).await.unwrap();
+        assert!(stored.starts_with(MAGIC));
+        assert!(stored.len() < body.len() / 10);
+        assert_eq!(read(&compressed).await.unwrap(), body);
+        assert_eq!(read_prefix(&compressed, 5).await.unwrap(), b"a bod");
+
+        // files written without compression, or before it existed, read as they are
+        let plain = dir.join("plain.bin");
+        write(&plain, &body, None).await.unwrap();
+        assert_eq!(fs::read(&plain).await.unwrap(), body);
+        assert_eq!(read(&plain).await.unwrap(), body);
+
+        let empty = dir.join("empty.bin");
+        write(&empty, b"", Some(3)).await.unwrap();
+        assert_eq!(read(&empty).await.unwrap(), b"");
+    }
+}
@@ -0,0 +1,298 @@
+//! Validation beyond what the types enforce, and `yap config check`.
+//!
+//! Every struct of the config denies unknown fields, so a misspelled key fails
+//! to load instead of being ignored; [`describe`] turns that failure into a
+//! message naming the key and the closest name that would have worked.
+
+use std::{collections::HashSet, path::Path};
+
+use config::ConfigError;
+
+use super::{Config, config_files, get_config_dir};
+
+/// Candidates less similar than this are not worth suggesting.
+const SIMILARITY: f64 = 0.7;
+
+/// Explain a config that failed to deserialize, by the key it failed at.
+pub fn describe(error: serde_path_to_error::Error<serde_json::Error>) -> ConfigError {
+    let key = error.path().to_string();
+    let message = error.into_inner().to_string();
+    let message = match suggest(&message) {
+        // the suggestion says more than a long list of what was expected
+        Some(suggestion) => {
+            let unknown = message.split(", expected").next().unwrap_or(&message);
+            format!("{}, did you mean `{}`?", unknown, suggestion)
+        }
+        None => message,
+    };
+    ConfigError::Message(if key == "." { message } else { format!("`{}`: {}", key, message) })
+}
+
+/// The expected name closest to the unknown one in a serde message like
+/// "unknown field `listn`, expected one of `listen`, `reverse`".
+fn suggest(message: &str) -> Option<&str> {
+    let (_, rest) = message.split_once("unknown ")?;
+    let mut names = rest.split('`').skip(1).step_by(2);
+    let unknown = names.next()?;
+    names
+        .map(|name| (strsim::jaro(unknown, name), name))
+        .filter(|(similarity, _)| *similarity > SIMILARITY)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, name)| name)
+}
+
+/// A setting that loads but can't work, by its key.
+#[derive(Debug, PartialEq)]
+pub struct Problem {
+    pub key: String,
+    pub message: String,
+}
+
+impl Problem {
+    fn new(key: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// What is wrong with a config that loaded: missing files, unusable URLs,
+/// out of range numbers.
+pub fn problems(config: &Config) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let mut missing = |key: String, path: &Path| {
+        if !path.exists() {
+            problems.push(Problem::new(key, format!("{} does not exist", path.display())));
+        }
+    };
+    let tls = &config.proxy.upstream.tls;
+    for (i, path) in tls.ca_certs.iter().enumerate() {
+        missing(format!("proxy.upstream.tls.ca_certs[{}]", i), path);
+    }
+    if let Some(path) = &tls.client_cert {
+        missing("proxy.upstream.tls.client_cert".to_string(), path);
+    }
+    if let Some(path) = &tls.client_key {
+        missing("proxy.upstream.tls.client_key".to_string(), path);
+    }
+    if let Some(path) = &config.grpc.descriptor_set {
+        missing("grpc.descriptor_set".to_string(), path);
+    }
+    if let Some(path) = &config.replay.from {
+        missing("replay.from".to_string(), path);
+    }
+    if let Some(path) = &config.scripts.dir {
+        missing("scripts.dir".to_string(), path);
+    }
+
+    if tls.client_cert.is_some() != tls.client_key.is_some() {
+        problems.push(Problem::new(
+            "proxy.upstream.tls",
+            "`client_cert` and `client_key` have to be set together",
+        ));
+    }
+    if config.capture.compression.is_some_and(|level| !(1..=22).contains(&level)) {
+        problems.push(Problem::new("capture.compression", "has to be a zstd level from 1 to 22"));
+    }
+    let retention = &config.capture.retention;
+    if retention.enabled() && !config.capture.per_session {
+        problems.push(Problem::new(
+            "capture.retention",
+            "removes whole sessions, which needs `capture.per_session`",
+        ));
+    }
+    if retention.interval_secs == 0 {
+        problems.push(Problem::new("capture.retention.interval_secs", "has to be at least 1"));
+    }
+    if config.render.max_fps == 0 {
+        problems.push(Problem::new("render.max_fps", "has to be at least 1"));
+    }
+
+    let reverse = std::iter::once(("proxy.reverse".to_string(), &config.proxy.reverse)).chain(
+        (config.proxy.listeners.iter().enumerate())
+            .map(|(i, listener)| (format!("proxy.listeners[{}].reverse", i), &listener.reverse)),
+    );
+    for (key, url) in reverse {
+        if let Some(url) = url
+            && let Err(e) = url::Url::parse(url)
+        {
+            problems.push(Problem::new(key, format!("`{}` is not a URL: {}", url, e)));
+        }
+    }
+    for (i, helper) in config.tokens.iter().enumerate() {
+        if let Err(e) = url::Url::parse(&helper.token_url) {
+            problems.push(Problem::new(
+                format!("tokens[{}].token_url", i),
+                format!("`{}` is not a URL: {}", helper.token_url, e),
+            ));
+        }
+    }
+    if config.retry.max_attempts == 0 {
+        problems.push(Problem::new("retry.max_attempts", "must be at least 1, a retry makes one attempt anyway"));
+    }
+    if let Some(endpoint) = &config.otlp.endpoint
+        && let Err(e) = url::Url::parse(endpoint)
+    {
+        problems.push(Problem::new("otlp.endpoint", format!("`{}` is not a URL: {}", endpoint, e)));
+    }
+    let mut addresses = HashSet::from([config.proxy.listen]);
+    for (i, listener) in config.proxy.listeners.iter().enumerate() {
+        if !addresses.insert(listener.listen) {
+            problems.push(Problem::new(
+                format!("proxy.listeners[{}].listen", i),
+                format!("{} is already listened on", listener.listen),
+            ));
+        }
+    }
+
+    for (i, rule) in config.proxy.faults.iter().enumerate() {
+        if !(0.0..=1.0).contains(&rule.probability) {
+            problems.push(Problem::new(
+                format!("proxy.faults[{}].probability", i),
+                "has to be between 0 and 1",
+            ));
+        }
+    }
+    for (i, rule) in config.assertions.iter().enumerate() {
+        if let Some(status) = &rule.status
+            && !(status.len() == 3 && status.chars().all(|c| c.is_ascii_digit() || c.eq_ignore_ascii_case(&'x')))
+        {
+            problems.push(Problem::new(
+                format!("assertions[{}].status", i),
+                format!("`{}` is neither a status like 200 nor a class like 2xx", status),
+            ));
+        }
+    }
+    for (i, rule) in config.proxy.limits.iter().enumerate() {
+        if rule.per_second.is_some_and(|rate| rate <= 0.0) {
+            problems.push(Problem::new(format!("proxy.limits[{}].per_second", i), "has to be above 0"));
+        }
+        if rule.max_concurrent == Some(0) {
+            problems.push(Problem::new(
+                format!("proxy.limits[{}].max_concurrent", i),
+                "has to be at least 1",
+            ));
+        }
+    }
+    let clients = &config.clients;
+    for (ip, profile) in &clients.profiles {
+        if let Some(throttle) = &profile.throttle
+            && !clients.throttles.contains_key(throttle)
+        {
+            problems.push(Problem::new(
+                format!("clients.profiles.{}.throttle", ip),
+                format!("`{}` is not in `clients.throttles`", throttle),
+            ));
+        }
+        if let Some(rewrites) = &profile.rewrites
+            && !clients.rewrites.contains_key(rewrites)
+        {
+            problems.push(Problem::new(
+                format!("clients.profiles.{}.rewrites", ip),
+                format!("`{}` is not in `clients.rewrites`", rewrites),
+            ));
+        }
+    }
+    problems
+}
+
+/// `yap config check`: load the config and print what is wrong with it.
+/// Returns whether it is fine.
+pub fn run() -> bool {
+    let dir = get_config_dir();
+    let files = config_files(&dir);
+    if files.is_empty() {
+        println!("No config file in {}, the defaults apply", dir.display());
+    }
+    for file in &files {
+        println!("Checking {}", file.display());
+    }
+
+    let config = match Config::new() {
+        Ok(config) => config,
+        Err(e) => {
+            println!("error: {}", e);
+            return false;
+        }
+    };
+    let problems = problems(&config);
+    for problem in &problems {
+        println!("error: `{}`: {}", problem.key, problem.message);
+    }
+    if problems.is_empty() {
+        println!("The config is valid");
+    }
+    problems.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn load(config: &str) -> Result<Config, String> {
+        let source = config::File::from_str(config, config::FileFormat::Json5);
+        let value: serde_json::Value = config::Config::builder().add_source(source).build().unwrap().try_deserialize().unwrap();
+        serde_path_to_error::deserialize(value).map_err(|e| describe(e).to_string())
+    }
+
+    #[test]
+    fn test_describe_suggests_known_names() {
+        let error = load(r#"{ proxy: { listn: "127.0.0.1:8080" } }"#).unwrap_err();
+        assert!(error.starts_with("`proxy.listn`: unknown field `listn`"), "{}", error);
+        assert!(error.ends_with("did you mean `listen`?"), "{}", error);
+
+        let error = load(r#"{ capture: { eviction: "DropOldst" } }"#).unwrap_err();
+        assert!(error.ends_with("did you mean `DropOldest`?"), "{}", error);
+
+        let error = load(r#"{ keybindings: { List: { "<j>": "SelectNxt" } } }"#).unwrap_err();
+        assert!(error.ends_with("did you mean `SelectNext`?"), "{}", error);
+
+        let error = load(r#"{ thme: "light" }"#).unwrap_err();
+        assert!(error.ends_with("did you mean `theme`?"), "{}", error);
+
+        let error = load(r#"{ render: { max_fps: "fast" } }"#).unwrap_err();
+        assert!(error.starts_with("`render.max_fps`: invalid type"), "{}", error);
+        assert!(!error.contains("did you mean"), "{}", error);
+    }
+
+    #[test]
+    fn test_problems() {
+        let config = load(
+            r#"{
+                proxy: {
+                    listen: "127.0.0.1:8080",
+                    listeners: [{ listen: "127.0.0.1:8080", reverse: "/api" }],
+                    faults: [{ fault: "Reset", probability: 2 }],
+                    upstream: { tls: { client_key: "/nonexistent/key.pem" } },
+                },
+                capture: { per_session: false, compression: 30, retention: { max_age_days: 7 } },
+                assertions: [{ status: "2xx" }, { status: "ok" }],
+                clients: {
+                    labels: { "192.168.1.23": "pixel" },
+                    throttles: { "3g": [{ per_second: 2 }] },
+                    profiles: { "192.168.1.23": { throttle: "3g" }, "fd00::7": { throttle: "2g", rewrites: "staging" } },
+                },
+            }"#,
+        )
+        .unwrap();
+        let keys: Vec<_> = problems(&config).into_iter().map(|problem| problem.key).collect();
+        assert_eq!(
+            keys,
+            vec![
+                "proxy.upstream.tls.client_key",
+                "proxy.upstream.tls",
+                "capture.compression",
+                "capture.retention",
+                "proxy.listeners[0].reverse",
+                "proxy.listeners[0].listen",
+                "proxy.faults[0].probability",
+                "assertions[1].status",
+                "clients.profiles.fd00::7.throttle",
+                "clients.profiles.fd00::7.rewrites",
+            ]
+        );
+    }
+}
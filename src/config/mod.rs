@@ -0,0 +1,1129 @@
+#![allow(dead_code)] // Remove this once you start using the code
+
+pub mod check;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use derive_deref::{Deref, DerefMut};
+use directories::ProjectDirs;
+use lazy_static::lazy_static;
+use ratatui::style::{Color, Modifier, Style};
+use serde::{
+    Deserialize,
+    de::{Deserializer, Error as _},
+};
+use tracing::error;
+
+use crate::{
+    app::Mode, assertions::AssertionRule, auth::ProxyAuth, batch::BatchConfig, clients::ClientsConfig,
+    faults::FaultRule, framework::Action, highlight::HighlightRule, limits::LimitRule, redact::RedactConfig,
+    retry::RetryConfig, rewrite::RewriteRule, theme::Theme, tokens::TokenHelper,
+};
+
+const CONFIG: &str = include_str!("../../config.json5");
+
+/// The files in the config directory a config is read from, merged in order.
+const CONFIG_FILES: [(&str, config::FileFormat); 5] = [
+    ("config.json5", config::FileFormat::Json5),
+    ("config.json", config::FileFormat::Json),
+    ("config.yaml", config::FileFormat::Yaml),
+    ("config.toml", config::FileFormat::Toml),
+    ("config.ini", config::FileFormat::Ini),
+];
+
+#[derive(Clone, Debug, Deserialize, Default)]
+pub struct AppConfig {
+    #[serde(default)]
+    pub data_dir: PathBuf,
+    #[serde(default)]
+    pub config_dir: PathBuf,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Where the config was read from and data is kept, set by [`Config::new`].
+    #[serde(skip)]
+    pub config: AppConfig,
+    #[serde(default)]
+    pub keybindings: KeyBindings,
+    #[serde(default)]
+    pub styles: Styles,
+    #[serde(default)]
+    pub capture: CaptureConfig,
+    #[serde(default)]
+    pub layout: LayoutConfig,
+    #[serde(default)]
+    pub filter: FilterConfig,
+    /// Styles for the request list rows matching each rule.
+    #[serde(default)]
+    pub highlight: Vec<HighlightRule>,
+    /// Expectations on the responses to matching requests.
+    #[serde(default)]
+    pub assertions: Vec<AssertionRule>,
+    /// Colors of the UI: a preset, optionally with some colors replaced.
+    #[serde(default)]
+    pub theme: Theme,
+    #[serde(default)]
+    pub render: RenderConfig,
+    #[serde(default)]
+    pub proxy: ProxyConfig,
+    #[serde(default)]
+    pub api: ApiConfig,
+    /// Names for the devices sending requests.
+    #[serde(default)]
+    pub clients: ClientsConfig,
+    #[serde(default)]
+    pub grpc: GrpcConfig,
+    #[serde(default)]
+    pub replay: ReplayConfig,
+    #[serde(default)]
+    pub scripts: ScriptsConfig,
+    /// OAuth tokens added to composed requests, by host.
+    #[serde(default)]
+    pub tokens: Vec<TokenHelper>,
+    #[serde(default)]
+    pub diff: DiffConfig,
+    /// Retries of a request started from the list.
+    #[serde(default)]
+    pub retry: RetryConfig,
+    /// Replays of the filtered requests.
+    #[serde(default)]
+    pub batch: BatchConfig,
+    #[serde(default)]
+    pub otlp: OtlpConfig,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiffConfig {
+    /// External diff tool for comparisons, e.g. `difft` or `delta`; the two
+    /// body files are appended to it.
+    pub command: Option<String>,
+}
+
+/// OpenTelemetry export of a span per proxied request.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OtlpConfig {
+    /// Base URL of an OTLP/HTTP collector, e.g. `http://localhost:4318`;
+    /// spans are posted to `/v1/traces` under it. Nothing is exported when unset.
+    pub endpoint: Option<String>,
+    /// `service.name` of the exported spans.
+    pub service_name: String,
+    /// Added to every export request, e.g. for authentication.
+    pub headers: BTreeMap<String, String>,
+}
+
+impl Default for OtlpConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: None,
+            service_name: "yap".to_string(),
+            headers: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScriptsConfig {
+    /// Directory of `*.rhai` scripts run on every exchange,
+    /// `<config_dir>/scripts` when unset.
+    pub dir: Option<PathBuf>,
+}
+
+/// What offline mode does with a request it has no capture for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum ReplayMiss {
+    /// Answer `503 Service Unavailable`.
+    #[default]
+    Fail,
+    /// Forward it to the upstream as usual.
+    Forward,
+}
+
+/// Offline mode, off unless `from` is set.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReplayConfig {
+    /// Capture directory, e.g. an earlier session, whose responses are served
+    /// instead of contacting the upstream.
+    pub from: Option<PathBuf>,
+    pub on_miss: ReplayMiss,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GrpcConfig {
+    /// Descriptor set (`protoc --include_imports --descriptor_set_out`) used
+    /// to decode gRPC messages to JSON.
+    pub descriptor_set: Option<PathBuf>,
+}
+
+/// The management API, off unless `listen` is set, and the control socket,
+/// off unless `socket` is.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiConfig {
+    pub listen: Option<SocketAddr>,
+    /// Unix socket taking one command per line.
+    pub socket: Option<PathBuf>,
+}
+
+/// What the proxy does with new requests once the in-memory log is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum EvictionPolicy {
+    /// Drop the oldest entry to make room for the new one.
+    #[default]
+    DropOldest,
+    /// Keep the existing entries and stop recording new ones.
+    StopCapturing,
+    /// Drop the oldest entry after appending it to `spill.jsonl` in the
+    /// capture directory, which `:e` loads back.
+    DiskSpill,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CaptureConfig {
+    /// Maximum number of requests kept in memory.
+    pub capacity: usize,
+    pub eviction: EvictionPolicy,
+    /// Where captured exchanges are written, `<data_dir>/captures` when unset.
+    pub dir: Option<PathBuf>,
+    /// Give every run its own timestamped subdirectory of `dir`.
+    pub per_session: bool,
+    pub access_log: AccessLogConfig,
+    /// Header values masked in capture files.
+    pub redact: RedactConfig,
+    /// Larger response bodies are moved from memory to a file next to the capture.
+    pub max_body_bytes: u64,
+    /// Also write a line per completed request here, `-` for stderr.
+    pub tee: Option<PathBuf>,
+    /// Store body files compressed with zstd at this level, 1 to 22.
+    pub compression: Option<i32>,
+    pub retention: RetentionConfig,
+    /// What to do with the requests of the previous session at startup.
+    pub restore: RestorePolicy,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 10000,
+            eviction: EvictionPolicy::default(),
+            dir: None,
+            per_session: true,
+            access_log: AccessLogConfig::default(),
+            redact: RedactConfig::default(),
+            max_body_bytes: 16 * 1024 * 1024,
+            tee: None,
+            compression: None,
+            retention: RetentionConfig::default(),
+            restore: RestorePolicy::default(),
+        }
+    }
+}
+
+/// The JSON-Lines access log written next to the captures.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AccessLogConfig {
+    pub enabled: bool,
+    /// Start a new file once the current one would grow past this size.
+    pub max_bytes: u64,
+    /// Start a new file when the local date changes.
+    pub rotate_daily: bool,
+}
+
+impl Default for AccessLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_bytes: 10 * 1024 * 1024,
+            rotate_daily: true,
+        }
+    }
+}
+
+/// Whether the requests captured by the previous run are loaded into the
+/// list at startup.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum RestorePolicy {
+    /// Offer to restore them.
+    #[default]
+    Ask,
+    Always,
+    Never,
+}
+
+/// Earlier sessions removed from the capture directory, oldest first, off
+/// unless a limit is set.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetentionConfig {
+    /// Remove sessions until all of them together take no more than this.
+    pub max_bytes: Option<u64>,
+    /// Remove sessions last written to longer ago than this.
+    pub max_age_days: Option<u64>,
+    /// How often to check, in seconds.
+    pub interval_secs: u64,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: None,
+            max_age_days: None,
+            interval_secs: 600,
+        }
+    }
+}
+
+impl RetentionConfig {
+    pub fn enabled(&self) -> bool {
+        self.max_bytes.is_some() || self.max_age_days.is_some()
+    }
+}
+
+/// How the directories of `capture.per_session` are named.
+pub const SESSION_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+impl CaptureConfig {
+    /// The directory this run writes its captures to.
+    pub fn session_dir(&self, data_dir: &Path, session: &str) -> PathBuf {
+        let root = self.dir.clone().unwrap_or_else(|| data_dir.join("captures"));
+        if self.per_session { root.join(session) } else { root }
+    }
+}
+
+/// Which way a split pane places its two panes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum SplitDirection {
+    /// Side by side.
+    #[default]
+    Horizontal,
+    /// Stacked on top of each other.
+    Vertical,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilterConfig {
+    /// Filters offered by the command palette, by name.
+    pub presets: BTreeMap<String, String>,
+    /// How many applied filters are kept for recall with up and down, 0 to keep none.
+    pub history: usize,
+    /// How long typing has to pause before the list is filtered, 0 to filter on every key.
+    pub debounce_ms: u64,
+}
+
+impl Default for FilterConfig {
+    fn default() -> Self {
+        Self {
+            presets: BTreeMap::new(),
+            history: 100,
+            debounce_ms: 150,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutConfig {
+    /// Percentage of the split given to the request list when statistics are shown.
+    pub stats_ratio: u16,
+    pub stats_direction: SplitDirection,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            stats_ratio: 60,
+            stats_direction: SplitDirection::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProxyConfig {
+    /// Address the proxy listens on.
+    pub listen: SocketAddr,
+    /// Upstream base URL. When set, yap runs as a reverse proxy and forwards
+    /// every request there instead of acting as a forward proxy.
+    pub reverse: Option<String>,
+    /// Host patterns (`*` wildcards) the PAC file sends through the proxy; empty means all.
+    pub include: Vec<String>,
+    /// Host patterns the PAC file always sends direct.
+    pub exclude: Vec<String>,
+    /// URL rewrites applied before forwarding; the API can add more at runtime.
+    pub rewrites: Vec<RewriteRule>,
+    /// Concurrency and rate limits toward upstream hosts.
+    pub limits: Vec<LimitRule>,
+    /// Failures injected into matching requests.
+    pub faults: Vec<FaultRule>,
+    /// Connection pool and timeouts of the client requests are forwarded with.
+    pub upstream: UpstreamConfig,
+    /// Host mapping and DNS servers used to reach upstreams.
+    pub dns: DnsConfig,
+    /// Credentials forward-proxy clients must send.
+    pub auth: ProxyAuth,
+    /// More listeners besides `listen`, recording into the same session.
+    pub listeners: Vec<ListenerConfig>,
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        Self {
+            listen: SocketAddr::from(([127, 0, 0, 1], 9999)),
+            reverse: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            rewrites: Vec::new(),
+            limits: Vec::new(),
+            faults: Vec::new(),
+            upstream: UpstreamConfig::default(),
+            dns: DnsConfig::default(),
+            auth: ProxyAuth::default(),
+            listeners: Vec::new(),
+        }
+    }
+}
+
+/// An additional listener; its requests are labelled in the list.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ListenerConfig {
+    pub listen: SocketAddr,
+    /// Shown next to its requests, the address when unset.
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Upstream base URL, making this listener a reverse proxy.
+    #[serde(default)]
+    pub reverse: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DnsConfig {
+    /// Fixed targets by `host` or `host:port`, e.g. `"api.example.com": "127.0.0.1:3000"`.
+    pub hosts: BTreeMap<String, String>,
+    /// DNS servers to query instead of the system resolver.
+    pub servers: Vec<IpAddr>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UpstreamConfig {
+    /// Idle connections kept open per upstream host.
+    pub max_idle_per_host: usize,
+    /// Seconds an idle connection is kept before it is closed.
+    pub idle_timeout_secs: u64,
+    /// Seconds to wait for a connection to be established.
+    pub connect_timeout_secs: u64,
+    /// Seconds to wait for the response headers; unlimited when unset.
+    pub request_timeout_secs: Option<u64>,
+    /// Speak HTTP/2 to upstreams without negotiation (h2c prior knowledge).
+    pub http2_only: bool,
+    pub tls: UpstreamTlsConfig,
+}
+
+impl Default for UpstreamConfig {
+    fn default() -> Self {
+        Self {
+            max_idle_per_host: 32,
+            idle_timeout_secs: 90,
+            connect_timeout_secs: 10,
+            request_timeout_secs: None,
+            http2_only: false,
+            tls: UpstreamTlsConfig::default(),
+        }
+    }
+}
+
+/// Certificate checks on connections to `https` upstreams.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UpstreamTlsConfig {
+    /// PEM files with extra root certificates to trust, e.g. a local dev CA.
+    pub ca_certs: Vec<PathBuf>,
+    /// PEM certificate chain presented to upstreams asking for one (mTLS).
+    pub client_cert: Option<PathBuf>,
+    /// PEM private key of `client_cert`.
+    pub client_key: Option<PathBuf>,
+    /// Accept any upstream certificate, e.g. self-signed ones.
+    pub insecure: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderConfig {
+    /// Upper bound on redraws per second; bursts of updates in between are coalesced.
+    pub max_fps: u16,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        Self { max_fps: 30 }
+    }
+}
+
+lazy_static! {
+    pub static ref PROJECT_NAME: String = env!("CARGO_CRATE_NAME").to_uppercase().to_string();
+    pub static ref DATA_FOLDER: Option<PathBuf> =
+        env::var(format!("{}_DATA", PROJECT_NAME.clone()))
+            .ok()
+            .map(PathBuf::from);
+    pub static ref CONFIG_FOLDER: Option<PathBuf> =
+        env::var(format!("{}_CONFIG", PROJECT_NAME.clone()))
+            .ok()
+            .map(PathBuf::from);
+}
+
+impl Config {
+    /// The config shipped in `config.json5`, without reading any files.
+    pub fn bundled() -> Self {
+        json5::from_str(CONFIG).unwrap()
+    }
+
+    pub fn new() -> color_eyre::Result<Self, config::ConfigError> {
+        let default_config = Self::bundled();
+        let data_dir = get_data_dir();
+        let config_dir = get_config_dir();
+        let mut builder = config::Config::builder();
+
+        for (file, format) in &CONFIG_FILES {
+            let source = config::File::from(config_dir.join(file))
+                .format(*format)
+                .required(false);
+            builder = builder.add_source(source);
+        }
+        if config_files(&config_dir).is_empty() {
+            error!("No configuration file found. Application may not behave as expected");
+        }
+
+        let merged = builder.build()?;
+        // serde_json reports errors with what was expected, unlike the config crate
+        let value: serde_json::Value = merged.clone().try_deserialize()?;
+        let mut cfg: Self = match serde_path_to_error::deserialize(value) {
+            Ok(cfg) => cfg,
+            // INI values are all strings, which only the config crate converts
+            Err(e) => merged.try_deserialize().map_err(|_| check::describe(e))?,
+        };
+        cfg.config = AppConfig { data_dir, config_dir };
+
+        for (mode, default_bindings) in default_config.keybindings.iter() {
+            let user_bindings = cfg.keybindings.entry(*mode).or_default();
+            for (key, cmd) in default_bindings.iter() {
+                user_bindings
+                    .entry(key.clone())
+                    .or_insert_with(|| cmd.clone());
+            }
+        }
+        for (mode, default_styles) in default_config.styles.iter() {
+            let user_styles = cfg.styles.entry(*mode).or_default();
+            for (style_key, style) in default_styles.iter() {
+                user_styles.entry(style_key.clone()).or_insert(*style);
+            }
+        }
+
+        Ok(cfg)
+    }
+}
+
+pub fn get_data_dir() -> PathBuf {
+    if let Some(s) = DATA_FOLDER.clone() {
+        s
+    } else if let Some(proj_dirs) = project_directory() {
+        proj_dirs.data_local_dir().to_path_buf()
+    } else {
+        PathBuf::from(".").join(".data")
+    }
+}
+
+pub fn get_config_dir() -> PathBuf {
+    if let Some(s) = CONFIG_FOLDER.clone() {
+        s
+    } else if let Some(proj_dirs) = project_directory() {
+        proj_dirs.config_local_dir().to_path_buf()
+    } else {
+        PathBuf::from(".").join(".config")
+    }
+}
+
+/// The config files present in `dir`, in the order they are merged.
+pub fn config_files(dir: &Path) -> Vec<PathBuf> {
+    CONFIG_FILES
+        .iter()
+        .map(|(file, _)| dir.join(file))
+        .filter(|path| path.exists())
+        .collect()
+}
+
+fn project_directory() -> Option<ProjectDirs> {
+    ProjectDirs::from("com", "kdheepak", env!("CARGO_PKG_NAME"))
+}
+
+#[derive(Clone, Debug, Default, Deref, DerefMut)]
+pub struct KeyBindings(pub HashMap<Mode, HashMap<Vec<KeyEvent>, Action>>);
+
+impl KeyBindings {
+    /// Look up the action bound to a single key press in `mode`.
+    pub fn action_for(&self, mode: Mode, key: KeyEvent) -> Option<&Action> {
+        self.get(&mode)?.get(&vec![normalize_key(key)])
+    }
+
+    /// How the key presses `keys` match the bindings of `mode`, for bindings
+    /// made of several keys like `<g><g>`.
+    pub fn match_sequence(&self, mode: Mode, keys: &[KeyEvent]) -> SequenceMatch<'_> {
+        let Some(bindings) = self.get(&mode) else {
+            return SequenceMatch::Unbound;
+        };
+        let keys: Vec<KeyEvent> = keys.iter().copied().map(normalize_key).collect();
+        if let Some(action) = bindings.get(&keys) {
+            return SequenceMatch::Bound(action);
+        }
+        if bindings.keys().any(|bound| bound.len() > keys.len() && bound.starts_with(&keys)) {
+            SequenceMatch::Prefix
+        } else {
+            SequenceMatch::Unbound
+        }
+    }
+
+    /// Display name of the first key bound to `action` in `mode`, for hints.
+    pub fn key_for(&self, mode: Mode, action: &Action) -> Option<String> {
+        let mut keys: Vec<String> = self
+            .get(&mode)?
+            .iter()
+            .filter(|(_, a)| *a == action)
+            .map(|(keys, _)| keys.iter().map(key_event_to_string).collect::<Vec<_>>().join(" "))
+            .collect();
+        keys.sort();
+        keys.into_iter().next()
+    }
+
+    /// The key bound to `action` in `mode` for a title hint, or `unbound`.
+    pub fn hint(&self, mode: Mode, action: &Action) -> String {
+        self.key_for(mode, action).unwrap_or_else(|| "unbound".to_string())
+    }
+}
+
+/// Result of [`KeyBindings::match_sequence`].
+#[derive(Debug, PartialEq)]
+pub enum SequenceMatch<'a> {
+    Bound(&'a Action),
+    /// The keys start a longer binding.
+    Prefix,
+    Unbound,
+}
+
+/// Terminals report SHIFT for symbols like `?`, while the bindings spell the
+/// symbol itself, so drop SHIFT for non-alphabetic characters.
+fn normalize_key(mut key: KeyEvent) -> KeyEvent {
+    if let KeyCode::Char(c) = key.code
+        && !c.is_alphabetic()
+    {
+        key.modifiers.remove(KeyModifiers::SHIFT);
+    }
+    key
+}
+
+impl<'de> Deserialize<'de> for KeyBindings {
+    fn deserialize<D>(deserializer: D) -> color_eyre::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let parsed_map = HashMap::<Mode, HashMap<String, Action>>::deserialize(deserializer)?;
+
+        let keybindings = parsed_map
+            .into_iter()
+            .map(|(mode, inner_map)| {
+                let converted_inner_map = inner_map
+                    .into_iter()
+                    .map(|(key_str, cmd)| {
+                        let keys = parse_key_sequence(&key_str)
+                            .map_err(|e| D::Error::custom(format!("invalid key `{}` in {:?}: {}", key_str, mode, e)))?;
+                        Ok((keys.into_iter().map(normalize_key).collect(), cmd))
+                    })
+                    .collect::<Result<_, D::Error>>()?;
+                Ok((mode, converted_inner_map))
+            })
+            .collect::<Result<_, D::Error>>()?;
+
+        Ok(KeyBindings(keybindings))
+    }
+}
+
+fn parse_key_event(raw: &str) -> color_eyre::Result<KeyEvent, String> {
+    let raw_lower = raw.to_ascii_lowercase();
+    let (remaining, modifiers) = extract_modifiers(&raw_lower);
+    parse_key_code_with_modifiers(remaining, modifiers)
+}
+
+fn extract_modifiers(raw: &str) -> (&str, KeyModifiers) {
+    let mut modifiers = KeyModifiers::empty();
+    let mut current = raw;
+
+    loop {
+        match current {
+            rest if rest.starts_with("ctrl-") => {
+                modifiers.insert(KeyModifiers::CONTROL);
+                current = &rest[5..];
+            }
+            rest if rest.starts_with("alt-") => {
+                modifiers.insert(KeyModifiers::ALT);
+                current = &rest[4..];
+            }
+            rest if rest.starts_with("shift-") => {
+                modifiers.insert(KeyModifiers::SHIFT);
+                current = &rest[6..];
+            }
+            _ => break, // break out of the loop if no known prefix is detected
+        };
+    }
+
+    (current, modifiers)
+}
+
+fn parse_key_code_with_modifiers(
+    raw: &str,
+    mut modifiers: KeyModifiers,
+) -> color_eyre::Result<KeyEvent, String> {
+    let c = match raw {
+        "esc" => KeyCode::Esc,
+        "enter" => KeyCode::Enter,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "backtab" => {
+            modifiers.insert(KeyModifiers::SHIFT);
+            KeyCode::BackTab
+        }
+        "backspace" => KeyCode::Backspace,
+        "delete" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "f1" => KeyCode::F(1),
+        "f2" => KeyCode::F(2),
+        "f3" => KeyCode::F(3),
+        "f4" => KeyCode::F(4),
+        "f5" => KeyCode::F(5),
+        "f6" => KeyCode::F(6),
+        "f7" => KeyCode::F(7),
+        "f8" => KeyCode::F(8),
+        "f9" => KeyCode::F(9),
+        "f10" => KeyCode::F(10),
+        "f11" => KeyCode::F(11),
+        "f12" => KeyCode::F(12),
+        "space" => KeyCode::Char(' '),
+        "hyphen" => KeyCode::Char('-'),
+        "minus" => KeyCode::Char('-'),
+        "tab" => KeyCode::Tab,
+        c if c.len() == 1 => {
+            let mut c = c.chars().next().unwrap();
+            if modifiers.contains(KeyModifiers::SHIFT) {
+                c = c.to_ascii_uppercase();
+            }
+            KeyCode::Char(c)
+        }
+        _ => return Err(format!("Unable to parse {raw}")),
+    };
+    Ok(KeyEvent::new(c, modifiers))
+}
+
+pub fn key_event_to_string(key_event: &KeyEvent) -> String {
+    let char;
+    let key_code = match key_event.code {
+        KeyCode::Backspace => "backspace",
+        KeyCode::Enter => "enter",
+        KeyCode::Left => "left",
+        KeyCode::Right => "right",
+        KeyCode::Up => "up",
+        KeyCode::Down => "down",
+        KeyCode::Home => "home",
+        KeyCode::End => "end",
+        KeyCode::PageUp => "pageup",
+        KeyCode::PageDown => "pagedown",
+        KeyCode::Tab => "tab",
+        KeyCode::BackTab => "backtab",
+        KeyCode::Delete => "delete",
+        KeyCode::Insert => "insert",
+        KeyCode::F(c) => {
+            char = format!("f({c})");
+            &char
+        }
+        KeyCode::Char(' ') => "space",
+        KeyCode::Char(c) => {
+            char = c.to_string();
+            &char
+        }
+        KeyCode::Esc => "esc",
+        KeyCode::Null => "",
+        KeyCode::CapsLock => "",
+        KeyCode::Menu => "",
+        KeyCode::ScrollLock => "",
+        KeyCode::Media(_) => "",
+        KeyCode::NumLock => "",
+        KeyCode::PrintScreen => "",
+        KeyCode::Pause => "",
+        KeyCode::KeypadBegin => "",
+        KeyCode::Modifier(_) => "",
+    };
+
+    let mut modifiers = Vec::with_capacity(3);
+
+    if key_event.modifiers.intersects(KeyModifiers::CONTROL) {
+        modifiers.push("ctrl");
+    }
+
+    if key_event.modifiers.intersects(KeyModifiers::SHIFT) {
+        modifiers.push("shift");
+    }
+
+    if key_event.modifiers.intersects(KeyModifiers::ALT) {
+        modifiers.push("alt");
+    }
+
+    let mut key = modifiers.join("-");
+
+    if !key.is_empty() {
+        key.push('-');
+    }
+    key.push_str(key_code);
+
+    key
+}
+
+pub fn parse_key_sequence(raw: &str) -> color_eyre::Result<Vec<KeyEvent>, String> {
+    if raw.chars().filter(|c| *c == '>').count() != raw.chars().filter(|c| *c == '<').count() {
+        return Err(format!("Unable to parse `{}`", raw));
+    }
+    let raw = if !raw.contains("><") {
+        let raw = raw.strip_prefix('<').unwrap_or(raw);
+        raw.strip_prefix('>').unwrap_or(raw)
+    } else {
+        raw
+    };
+    let sequences = raw
+        .split("><")
+        .map(|seq| {
+            if let Some(s) = seq.strip_prefix('<') {
+                s
+            } else if let Some(s) = seq.strip_suffix('>') {
+                s
+            } else {
+                seq
+            }
+        })
+        .collect::<Vec<_>>();
+
+    sequences.into_iter().map(parse_key_event).collect()
+}
+
+#[derive(Clone, Debug, Default, Deref, DerefMut)]
+pub struct Styles(pub HashMap<Mode, HashMap<String, Style>>);
+
+impl<'de> Deserialize<'de> for Styles {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let parsed_map = HashMap::<Mode, HashMap<String, String>>::deserialize(deserializer)?;
+
+        let styles = parsed_map
+            .into_iter()
+            .map(|(mode, inner_map)| {
+                let converted_inner_map = inner_map
+                    .into_iter()
+                    .map(|(str, style)| (str, parse_style(&style)))
+                    .collect();
+                (mode, converted_inner_map)
+            })
+            .collect();
+
+        Ok(Styles(styles))
+    }
+}
+
+pub fn parse_style(line: &str) -> Style {
+    let (foreground, background) =
+        line.split_at(line.to_lowercase().find("on ").unwrap_or(line.len()));
+    let foreground = process_color_string(foreground);
+    let background = process_color_string(&background.replace("on ", ""));
+
+    let mut style = Style::default();
+    if let Some(fg) = parse_color(&foreground.0) {
+        style = style.fg(fg);
+    }
+    if let Some(bg) = parse_color(&background.0) {
+        style = style.bg(bg);
+    }
+    style = style.add_modifier(foreground.1 | background.1);
+    style
+}
+
+fn process_color_string(color_str: &str) -> (String, Modifier) {
+    let color = color_str
+        .replace("grey", "gray")
+        .replace("bright ", "")
+        .replace("bold ", "")
+        .replace("underline ", "")
+        .replace("inverse ", "");
+
+    let mut modifiers = Modifier::empty();
+    if color_str.contains("underline") {
+        modifiers |= Modifier::UNDERLINED;
+    }
+    if color_str.contains("bold") {
+        modifiers |= Modifier::BOLD;
+    }
+    if color_str.contains("inverse") {
+        modifiers |= Modifier::REVERSED;
+    }
+
+    (color, modifiers)
+}
+
+pub fn parse_color(s: &str) -> Option<Color> {
+    let s = s.trim_start();
+    let s = s.trim_end();
+    if s.contains("bright color") {
+        let s = s.trim_start_matches("bright ");
+        let c = s
+            .trim_start_matches("color")
+            .parse::<u8>()
+            .unwrap_or_default();
+        Some(Color::Indexed(c.wrapping_shl(8)))
+    } else if s.contains("color") {
+        let c = s
+            .trim_start_matches("color")
+            .parse::<u8>()
+            .unwrap_or_default();
+        Some(Color::Indexed(c))
+    } else if s.contains("gray") {
+        let c = 232
+            + s.trim_start_matches("gray")
+                .parse::<u8>()
+                .unwrap_or_default();
+        Some(Color::Indexed(c))
+    } else if s.contains("rgb") {
+        let red = (s.as_bytes()[3] as char).to_digit(10).unwrap_or_default() as u8;
+        let green = (s.as_bytes()[4] as char).to_digit(10).unwrap_or_default() as u8;
+        let blue = (s.as_bytes()[5] as char).to_digit(10).unwrap_or_default() as u8;
+        let c = 16 + red * 36 + green * 6 + blue;
+        Some(Color::Indexed(c))
+    } else if s == "bold black" {
+        Some(Color::Indexed(8))
+    } else if s == "bold red" {
+        Some(Color::Indexed(9))
+    } else if s == "bold green" {
+        Some(Color::Indexed(10))
+    } else if s == "bold yellow" {
+        Some(Color::Indexed(11))
+    } else if s == "bold blue" {
+        Some(Color::Indexed(12))
+    } else if s == "bold magenta" {
+        Some(Color::Indexed(13))
+    } else if s == "bold cyan" {
+        Some(Color::Indexed(14))
+    } else if s == "bold white" {
+        Some(Color::Indexed(15))
+    } else if s == "black" {
+        Some(Color::Indexed(0))
+    } else if s == "red" {
+        Some(Color::Indexed(1))
+    } else if s == "green" {
+        Some(Color::Indexed(2))
+    } else if s == "yellow" {
+        Some(Color::Indexed(3))
+    } else if s == "blue" {
+        Some(Color::Indexed(4))
+    } else if s == "magenta" {
+        Some(Color::Indexed(5))
+    } else if s == "cyan" {
+        Some(Color::Indexed(6))
+    } else if s == "white" {
+        Some(Color::Indexed(7))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_style_default() {
+        let style = parse_style("");
+        assert_eq!(style, Style::default());
+    }
+
+    #[test]
+    fn test_parse_style_foreground() {
+        let style = parse_style("red");
+        assert_eq!(style.fg, Some(Color::Indexed(1)));
+    }
+
+    #[test]
+    fn test_parse_style_background() {
+        let style = parse_style("on blue");
+        assert_eq!(style.bg, Some(Color::Indexed(4)));
+    }
+
+    #[test]
+    fn test_parse_style_modifiers() {
+        let style = parse_style("underline red on blue");
+        assert_eq!(style.fg, Some(Color::Indexed(1)));
+        assert_eq!(style.bg, Some(Color::Indexed(4)));
+    }
+
+    #[test]
+    fn test_process_color_string() {
+        let (color, modifiers) = process_color_string("underline bold inverse gray");
+        assert_eq!(color, "gray");
+        assert!(modifiers.contains(Modifier::UNDERLINED));
+        assert!(modifiers.contains(Modifier::BOLD));
+        assert!(modifiers.contains(Modifier::REVERSED));
+    }
+
+    #[test]
+    fn test_parse_color_rgb() {
+        let color = parse_color("rgb123");
+        let expected = 16 + 36 + 2 * 6 + 3;
+        assert_eq!(color, Some(Color::Indexed(expected)));
+    }
+
+    #[test]
+    fn test_parse_color_unknown() {
+        let color = parse_color("unknown");
+        assert_eq!(color, None);
+    }
+
+    #[test]
+    fn test_config() -> color_eyre::Result<()> {
+        let c = Config::new()?;
+        assert_eq!(
+            c.keybindings
+                .get(&Mode::Home)
+                .unwrap()
+                .get(&parse_key_sequence("<q>").unwrap_or_default())
+                .unwrap(),
+            &Action::Quit
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_capture_config_defaults() {
+        let c: Config = json5::from_str(r#"{ capture: { eviction: "DiskSpill" } }"#).unwrap();
+        assert_eq!(c.capture.capacity, 10000);
+        assert_eq!(c.capture.eviction, EvictionPolicy::DiskSpill);
+    }
+
+    #[test]
+    fn test_action_for_ignores_shift_on_symbols() -> color_eyre::Result<()> {
+        let c = Config::new()?;
+        let key = KeyEvent::new(KeyCode::Char('?'), KeyModifiers::SHIFT);
+        assert_eq!(c.keybindings.action_for(Mode::Home, key), Some(&Action::ToggleHelp));
+        let key = KeyEvent::new(KeyCode::Char('j'), KeyModifiers::empty());
+        assert_eq!(c.keybindings.action_for(Mode::List, key), Some(&Action::SelectNext));
+        Ok(())
+    }
+
+    #[test]
+    fn test_simple_keys() {
+        assert_eq!(
+            parse_key_event("a").unwrap(),
+            KeyEvent::new(KeyCode::Char('a'), KeyModifiers::empty())
+        );
+
+        assert_eq!(
+            parse_key_event("enter").unwrap(),
+            KeyEvent::new(KeyCode::Enter, KeyModifiers::empty())
+        );
+
+        assert_eq!(
+            parse_key_event("esc").unwrap(),
+            KeyEvent::new(KeyCode::Esc, KeyModifiers::empty())
+        );
+    }
+
+    #[test]
+    fn test_with_modifiers() {
+        assert_eq!(
+            parse_key_event("ctrl-a").unwrap(),
+            KeyEvent::new(KeyCode::Char('a'), KeyModifiers::CONTROL)
+        );
+
+        assert_eq!(
+            parse_key_event("alt-enter").unwrap(),
+            KeyEvent::new(KeyCode::Enter, KeyModifiers::ALT)
+        );
+
+        assert_eq!(
+            parse_key_event("shift-esc").unwrap(),
+            KeyEvent::new(KeyCode::Esc, KeyModifiers::SHIFT)
+        );
+    }
+
+    #[test]
+    fn test_multiple_modifiers() {
+        assert_eq!(
+            parse_key_event("ctrl-alt-a").unwrap(),
+            KeyEvent::new(
+                KeyCode::Char('a'),
+                KeyModifiers::CONTROL | KeyModifiers::ALT
+            )
+        );
+
+        assert_eq!(
+            parse_key_event("ctrl-shift-enter").unwrap(),
+            KeyEvent::new(KeyCode::Enter, KeyModifiers::CONTROL | KeyModifiers::SHIFT)
+        );
+    }
+
+    #[test]
+    fn test_reverse_multiple_modifiers() {
+        assert_eq!(
+            key_event_to_string(&KeyEvent::new(
+                KeyCode::Char('a'),
+                KeyModifiers::CONTROL | KeyModifiers::ALT
+            )),
+            "ctrl-alt-a".to_string()
+        );
+    }
+
+    #[test]
+    fn test_invalid_keys() {
+        assert!(parse_key_event("invalid-key").is_err());
+        assert!(parse_key_event("ctrl-invalid-key").is_err());
+    }
+
+    #[test]
+    fn test_case_insensitivity() {
+        assert_eq!(
+            parse_key_event("CTRL-a").unwrap(),
+            KeyEvent::new(KeyCode::Char('a'), KeyModifiers::CONTROL)
+        );
+
+        assert_eq!(
+            parse_key_event("AlT-eNtEr").unwrap(),
+            KeyEvent::new(KeyCode::Enter, KeyModifiers::ALT)
+        );
+    }
+}
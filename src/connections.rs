@@ -0,0 +1,242 @@
+//! Counters of the upstream connections per host: how many were opened and
+//! closed, the bytes they carried and the TLS versions they negotiated.
+
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use hyper::Uri;
+use hyper_rustls::MaybeHttpsStream;
+use hyper_util::{
+    client::legacy::connect::{Connected, Connection},
+    rt::TokioIo,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::TcpStream,
+};
+use tower_service::Service;
+
+type BoxFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send>>;
+
+/// What the connections to one host did so far.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HostConnections {
+    pub opened: u64,
+    pub closed: u64,
+    /// Bytes written to the host, TLS records included.
+    pub sent: u64,
+    pub received: u64,
+    /// Connections per negotiated TLS version.
+    pub tls: BTreeMap<String, u64>,
+}
+
+impl HostConnections {
+    pub fn open(&self) -> u64 {
+        self.opened.saturating_sub(self.closed)
+    }
+
+    /// e.g. `TLS 1.3 ×4, TLS 1.2 ×1`, or `plain` for no TLS at all.
+    pub fn tls_versions(&self) -> String {
+        if self.tls.is_empty() {
+            return "plain".to_string();
+        }
+        let mut versions: Vec<_> = self.tls.iter().collect();
+        versions.sort_by(|a, b| b.1.cmp(a.1).then_with(|| b.0.cmp(a.0)));
+        versions
+            .iter()
+            .map(|(version, count)| format!("{} ×{}", version, count))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Counters of every upstream host, shared by the connector and the stats panel.
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionStats(Arc<Mutex<BTreeMap<String, HostConnections>>>);
+
+impl ConnectionStats {
+    pub fn hosts(&self) -> BTreeMap<String, HostConnections> {
+        self.0.lock().unwrap().clone()
+    }
+
+    /// Bytes sent to and received from every host together.
+    pub fn totals(&self) -> (u64, u64) {
+        let hosts = self.0.lock().unwrap();
+        hosts.values().fold((0, 0), |(sent, received), counters| (sent + counters.sent, received + counters.received))
+    }
+
+    fn count(&self, host: &str, f: impl FnOnce(&mut HostConnections)) {
+        let mut hosts = self.0.lock().unwrap();
+        match hosts.get_mut(host) {
+            Some(counters) => f(counters),
+            None => f(hosts.entry(host.to_string()).or_default()),
+        }
+    }
+}
+
+/// A TCP connection to an upstream host that counts what passes through it.
+pub struct Counted {
+    inner: TcpStream,
+    host: String,
+    stats: ConnectionStats,
+}
+
+impl Counted {
+    fn negotiated(&self, version: String) {
+        self.stats.count(&self.host, |counters| *counters.tls.entry(version).or_default() += 1);
+    }
+}
+
+impl Drop for Counted {
+    fn drop(&mut self) {
+        self.stats.count(&self.host, |counters| counters.closed += 1);
+    }
+}
+
+impl AsyncRead for Counted {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        let read = (buf.filled().len() - before) as u64;
+        if read > 0 {
+            self.stats.count(&self.host, |counters| counters.received += read);
+        }
+        poll
+    }
+}
+
+impl AsyncWrite for Counted {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let poll = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(written)) = poll {
+            self.stats.count(&self.host, |counters| counters.sent += written as u64);
+        }
+        poll
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+impl Connection for Counted {
+    fn connected(&self) -> Connected {
+        self.inner.connected()
+    }
+}
+
+/// Wraps the TCP connector so every connection is counted for the host it
+/// was opened for. Sits below TLS, so the bytes are those on the wire.
+#[derive(Clone)]
+pub struct CountingConnector<C> {
+    inner: C,
+    stats: ConnectionStats,
+}
+
+impl<C> CountingConnector<C> {
+    pub fn new(inner: C, stats: ConnectionStats) -> Self {
+        Self { inner, stats }
+    }
+}
+
+impl<C> Service<Uri> for CountingConnector<C>
+where
+    C: Service<Uri, Response = TokioIo<TcpStream>>,
+    C::Future: Send + 'static,
+{
+    type Response = TokioIo<Counted>;
+    type Error = C::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let host = dst.host().unwrap_or_default().to_string();
+        let stats = self.stats.clone();
+        let connecting = self.inner.call(dst);
+        Box::pin(async move {
+            let inner = connecting.await?.into_inner();
+            stats.count(&host, |counters| counters.opened += 1);
+            Ok(TokioIo::new(Counted { inner, host, stats }))
+        })
+    }
+}
+
+/// Wraps the TLS connector to note the version each connection negotiated.
+#[derive(Clone)]
+pub struct TlsVersions<C>(pub C);
+
+impl<C> Service<Uri> for TlsVersions<C>
+where
+    C: Service<Uri, Response = MaybeHttpsStream<TokioIo<Counted>>>,
+    C::Future: Send + 'static,
+{
+    type Response = C::Response;
+    type Error = C::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.poll_ready(cx)
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let connecting = self.0.call(dst);
+        Box::pin(async move {
+            let stream = connecting.await?;
+            if let MaybeHttpsStream::Https(tls) = &stream {
+                let (io, session) = tls.inner().get_ref();
+                if let Some(version) = session.protocol_version() {
+                    io.inner().inner().negotiated(tls_version(version));
+                }
+            }
+            Ok(stream)
+        })
+    }
+}
+
+/// `TLS 1.3` rather than rustls' `TLSv1_3`.
+fn tls_version(version: rustls::ProtocolVersion) -> String {
+    match version {
+        rustls::ProtocolVersion::TLSv1_3 => "TLS 1.3".to_string(),
+        rustls::ProtocolVersion::TLSv1_2 => "TLS 1.2".to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_host_counters() {
+        let stats = ConnectionStats::default();
+        for _ in 0..3 {
+            stats.count("api.test", |counters| counters.opened += 1);
+        }
+        stats.count("api.test", |counters| counters.closed += 1);
+        let version = |version| move |counters: &mut HostConnections| {
+            *counters.tls.entry(tls_version(version)).or_default() += 1
+        };
+        stats.count("api.test", version(rustls::ProtocolVersion::TLSv1_2));
+        stats.count("api.test", version(rustls::ProtocolVersion::TLSv1_3));
+        stats.count("api.test", version(rustls::ProtocolVersion::TLSv1_3));
+        let hosts = stats.hosts();
+        assert_eq!(hosts["api.test"].open(), 2);
+        assert_eq!(hosts["api.test"].tls_versions(), "TLS 1.3 ×2, TLS 1.2 ×1");
+        assert_eq!(HostConnections::default().tls_versions(), "plain");
+    }
+}
@@ -0,0 +1,209 @@
+//! Control socket: a Unix socket taking one command per line, for scripts
+//! and editor plugins that drive a running yap without speaking HTTP.
+//!
+//! | Command                | Effect                                   |
+//! |------------------------|------------------------------------------|
+//! | `pause` / `resume`     | Stop or restart capturing                |
+//! | `clear`                | Clear the session                        |
+//! | `export <path>`        | Save the session to an archive           |
+//! | `add-rule <from> <to>` | Add a rewrite rule                       |
+//! | `status`               | Whether capturing, and how many requests |
+//!
+//! Every command is answered with one line, starting with `ok` or `error`.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, atomic::Ordering},
+};
+
+use tracing::{error, info};
+
+use crate::{
+    api::ApiContext,
+    components::{
+        command_line::expand_home,
+        proxy::{ClearLogs, HttpLog},
+    },
+    rewrite::RewriteRule,
+    session,
+};
+
+#[derive(Debug, PartialEq)]
+enum ControlCommand {
+    Pause,
+    Resume,
+    Clear,
+    Export(PathBuf),
+    AddRule { from: String, to: String },
+    Status,
+    Help,
+}
+
+const HELP: &str = "commands: pause, resume, clear, export <path>, add-rule <from> <to>, status";
+
+fn parse(line: &str) -> Result<ControlCommand, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words.as_slice() {
+        ["pause"] => Ok(ControlCommand::Pause),
+        ["resume"] => Ok(ControlCommand::Resume),
+        ["clear"] => Ok(ControlCommand::Clear),
+        ["export", path] => Ok(ControlCommand::Export(expand_home(path))),
+        ["add-rule", from, to] => Ok(ControlCommand::AddRule {
+            from: from.to_string(),
+            to: to.to_string(),
+        }),
+        ["status"] => Ok(ControlCommand::Status),
+        ["help"] => Ok(ControlCommand::Help),
+        ["export", ..] => Err("usage: export <path>".to_string()),
+        ["add-rule", ..] => Err("usage: add-rule <from> <to>".to_string()),
+        [] => Err(HELP.to_string()),
+        [name, ..] => Err(format!("unknown command `{}`, {}", name, HELP)),
+    }
+}
+
+/// Serve the control socket at `path` until the app shuts down.
+#[cfg(unix)]
+pub async fn serve(path: PathBuf, ctx: ApiContext) {
+    use std::os::unix::fs::PermissionsExt;
+
+    use tokio::net::{UnixListener, UnixStream};
+
+    if UnixStream::connect(&path).await.is_ok() {
+        error!("Control socket {} is in use by another process", path.display());
+        ctx.state.error(format!("Control socket {} is in use", path.display()));
+        return;
+    }
+    // a socket left behind by a yap that didn't shut down would block the bind
+    if tokio::fs::metadata(&path).await.is_ok_and(|meta| !meta.is_dir()) {
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+    let listener = match UnixListener::bind(&path) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to bind control socket {}: {}", path.display(), e);
+            ctx.state.error(format!("Failed to bind control socket {}: {}", path.display(), e));
+            return;
+        }
+    };
+    // only the user running yap may drive it
+    if let Err(e) = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)) {
+        error!("Failed to restrict {}: {}", path.display(), e);
+    }
+    info!("Control socket listening on {}", path.display());
+
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            _ = ctx.updater.cancelled() => break,
+        };
+        match accepted {
+            Ok((stream, _)) => {
+                ctx.updater.spawn(answer(stream, ctx.clone()));
+            }
+            Err(e) => error!("Failed to accept control connection: {}", e),
+        }
+    }
+    let _ = tokio::fs::remove_file(&path).await;
+}
+
+#[cfg(not(unix))]
+pub async fn serve(path: PathBuf, ctx: ApiContext) {
+    tracing::warn!("Control sockets need Unix, not listening on {}", path.display());
+    ctx.state.error("api.socket needs a Unix system, use api.listen instead");
+}
+
+/// Answer the commands of one connection, line by line.
+#[cfg(unix)]
+async fn answer(stream: tokio::net::UnixStream, ctx: ApiContext) {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    loop {
+        let line = tokio::select! {
+            line = lines.next_line() => line,
+            _ = ctx.updater.cancelled() => return,
+        };
+        let Ok(Some(line)) = line else {
+            return;
+        };
+        let reply = match parse(&line) {
+            Ok(command) => run(command, &ctx).await,
+            Err(e) => Err(e),
+        };
+        let reply = match reply {
+            Ok(message) => format!("ok {}\n", message),
+            Err(e) => format!("error {}\n", e),
+        };
+        if writer.write_all(reply.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+async fn run(command: ControlCommand, ctx: &ApiContext) -> Result<String, String> {
+    match command {
+        ControlCommand::Pause | ControlCommand::Resume => {
+            let enabled = command == ControlCommand::Resume;
+            ctx.capturing.store(enabled, Ordering::Relaxed);
+            ctx.state.set_capturing(enabled);
+            ctx.updater.update();
+            Ok(if enabled { "capture resumed" } else { "capture paused" }.to_string())
+        }
+        ControlCommand::Clear => {
+            ctx.updater.send(ClearLogs { delete_captures: false });
+            Ok("session cleared".to_string())
+        }
+        ControlCommand::Export(path) => {
+            let logs = ctx.logs.borrow().clone();
+            export(&path, &logs).await
+        }
+        ControlCommand::AddRule { from, to } => {
+            info!("Adding rewrite rule {} -> {}", from, to);
+            let reply = format!("rule {} -> {} added", from, to);
+            ctx.rules.write().await.push(RewriteRule {
+                from,
+                to,
+                enabled: true,
+                hits: 0,
+            });
+            ctx.updater.update();
+            Ok(reply)
+        }
+        ControlCommand::Status => Ok(format!(
+            "{} {} requests",
+            if ctx.capturing.load(Ordering::Relaxed) { "capturing" } else { "paused" },
+            ctx.logs.borrow().len()
+        )),
+        ControlCommand::Help => Ok(HELP.to_string()),
+    }
+}
+
+async fn export(path: &Path, logs: &[Arc<HttpLog>]) -> Result<String, String> {
+    match session::save(path, logs).await {
+        Ok(count) => Ok(format!("saved {} requests to {}", count, path.display())),
+        Err(e) => Err(format!("failed to save {}: {}", path.display(), e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(" pause "), Ok(ControlCommand::Pause));
+        assert_eq!(parse("export /tmp/s.json"), Ok(ControlCommand::Export(PathBuf::from("/tmp/s.json"))));
+        assert_eq!(
+            parse("add-rule api.test localhost:3000"),
+            Ok(ControlCommand::AddRule {
+                from: "api.test".to_string(),
+                to: "localhost:3000".to_string(),
+            })
+        );
+        assert_eq!(parse("add-rule api.test"), Err("usage: add-rule <from> <to>".to_string()));
+        assert_eq!(parse("stop"), Err(format!("unknown command `stop`, {}", HELP)));
+    }
+}
@@ -0,0 +1,286 @@
+//! `yap demo`: a mock upstream on a local port with a few endpoints to try
+//! every feature on, without reaching the network. The proxy runs as a
+//! reverse proxy in front of it.
+//!
+//! | Route              | Response                                        |
+//! |--------------------|-------------------------------------------------|
+//! | `GET /`            | HTML page linking the other routes              |
+//! | `GET /json`        | A JSON list of users                            |
+//! | `GET /html`        | An HTML page                                    |
+//! | `GET /slow?ms=N`   | JSON after N ms, 2000 by default                |
+//! | `GET /error`       | A 500 with a JSON error                         |
+//! | `GET /status/N`    | An empty response with status N                 |
+//! | `GET /chunked`     | Five lines streamed 200 ms apart, then trailers |
+//! | `GET /events?n=N`  | N server-sent events a second apart, 10 default |
+//! | `* /echo`          | The request's method, headers and body as JSON  |
+
+use std::{convert::Infallible, net::SocketAddr, time::Duration};
+
+use futures::stream;
+use http_body_util::{BodyExt, Full, StreamBody, combinators::BoxBody};
+use hyper::{
+    HeaderMap, Method, Request, Response, StatusCode,
+    body::{Bytes, Frame, Incoming},
+    header::{CACHE_CONTROL, CONTENT_TYPE, HeaderValue, TRAILER},
+    server::conn::http1,
+    service::service_fn,
+};
+use hyper_util::rt::TokioIo;
+use serde_json::{Map, Value, json};
+use tokio::net::TcpListener;
+use tracing::{error, info};
+
+/// `/slow` waits this long unless asked otherwise.
+const SLOW_DEFAULT: Duration = Duration::from_secs(2);
+/// `/slow` waits no longer than this.
+const SLOW_MAX: Duration = Duration::from_secs(30);
+/// Lines `/chunked` sends, and the pause before each.
+const CHUNKS: u64 = 5;
+const CHUNK_INTERVAL: Duration = Duration::from_millis(200);
+/// The trailer `/chunked` ends with.
+const CHUNKS_TRAILER: &str = "x-chunk-count";
+/// Events `/events` sends unless asked otherwise, the most it sends, and the
+/// pause before each.
+const EVENTS_DEFAULT: u64 = 10;
+const EVENTS_MAX: u64 = 1000;
+const EVENT_INTERVAL: Duration = Duration::from_secs(1);
+
+type Body = BoxBody<Bytes, Infallible>;
+
+#[derive(Debug, PartialEq)]
+enum Endpoint {
+    Index,
+    Json,
+    Html,
+    Slow(Duration),
+    Error,
+    Status(StatusCode),
+    Chunked,
+    Events(u64),
+    Echo,
+    NotFound,
+}
+
+/// The query parameter `name` as a number.
+fn query_number(query: Option<&str>, name: &str) -> Option<u64> {
+    url::form_urlencoded::parse(query.unwrap_or_default().as_bytes())
+        .find(|(key, _)| key == name)
+        .and_then(|(_, value)| value.parse().ok())
+}
+
+fn route(method: &Method, path: &str, query: Option<&str>) -> Endpoint {
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    match (method, segments.as_slice()) {
+        (_, ["echo"]) => Endpoint::Echo,
+        (&Method::GET, []) => Endpoint::Index,
+        (&Method::GET, ["json"]) => Endpoint::Json,
+        (&Method::GET, ["html"]) => Endpoint::Html,
+        (&Method::GET, ["slow"]) => {
+            let ms = query_number(query, "ms");
+            Endpoint::Slow(ms.map(Duration::from_millis).unwrap_or(SLOW_DEFAULT).min(SLOW_MAX))
+        }
+        (&Method::GET, ["error"]) => Endpoint::Error,
+        (&Method::GET, ["status", code]) => code
+            .parse()
+            .ok()
+            .and_then(|code| StatusCode::from_u16(code).ok())
+            .map_or(Endpoint::NotFound, Endpoint::Status),
+        (&Method::GET, ["chunked"]) => Endpoint::Chunked,
+        (&Method::GET, ["events"]) => Endpoint::Events(query_number(query, "n").unwrap_or(EVENTS_DEFAULT).min(EVENTS_MAX)),
+        _ => Endpoint::NotFound,
+    }
+}
+
+fn respond(status: StatusCode, content_type: &str, body: impl Into<Bytes>) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, content_type)
+        .body(Full::new(body.into()).boxed())
+        .unwrap()
+}
+
+fn respond_json(status: StatusCode, body: Value) -> Response<Body> {
+    respond(status, "application/json", serde_json::to_string_pretty(&body).unwrap_or_default())
+}
+
+const INDEX: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head><meta charset="utf-8"><title>yap demo</title></head>
+<body>
+<h1>yap demo upstream</h1>
+<ul>
+<li><a href="/json">/json</a>: a JSON list of users</li>
+<li><a href="/html">/html</a>: this kind of page</li>
+<li><a href="/slow?ms=2000">/slow?ms=2000</a>: answers after a while</li>
+<li><a href="/error">/error</a>: fails with a 500</li>
+<li><a href="/status/404">/status/404</a>: any status you like</li>
+<li><a href="/chunked">/chunked</a>: streamed in chunks</li>
+<li><a href="/events">/events</a>: server-sent events, one a second</li>
+<li>/echo: sends back what you sent, for any method</li>
+</ul>
+</body>
+</html>
+"#;
+
+const PAGE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head><meta charset="utf-8"><title>Release notes</title></head>
+<body>
+<article>
+<h1>Release notes</h1>
+<p>Users can now be <em>archived</em> instead of deleted.</p>
+<p>See <a href="/json">the user list</a> for the new <code>archived</code> field.</p>
+</article>
+</body>
+</html>
+"#;
+
+fn users() -> Value {
+    json!({
+        "users": [
+            { "id": 1, "name": "Ada", "email": "ada@example.com", "archived": false },
+            { "id": 2, "name": "Grace", "email": "grace@example.com", "archived": false },
+            { "id": 3, "name": "Linus", "email": "linus@example.com", "archived": true },
+        ],
+        "page": 1,
+        "total": 3,
+    })
+}
+
+/// Send lines a while apart, so the response arrives in several chunks, and
+/// end with a trailer counting them. Clients only get the trailer if they
+/// sent `TE: trailers`.
+fn chunked() -> Response<Body> {
+    let lines = stream::unfold(1, |n| async move {
+        let frame = match n {
+            n if n <= CHUNKS => {
+                tokio::time::sleep(CHUNK_INTERVAL).await;
+                Frame::data(Bytes::from(format!("chunk {} of {}\n", n, CHUNKS)))
+            }
+            n if n == CHUNKS + 1 => {
+                let mut trailers = HeaderMap::new();
+                trailers.insert(CHUNKS_TRAILER, HeaderValue::from(CHUNKS));
+                Frame::trailers(trailers)
+            }
+            _ => return None,
+        };
+        Some((Ok::<_, Infallible>(frame), n + 1))
+    });
+    Response::builder()
+        .header(CONTENT_TYPE, "text/plain; charset=utf-8")
+        .header(TRAILER, CHUNKS_TRAILER)
+        .body(StreamBody::new(lines).boxed())
+        .unwrap()
+}
+
+/// A server-sent event stream of `count` ticks, with a comment first the way
+/// servers keep idle connections open.
+fn events(count: u64) -> Response<Body> {
+    let ticks = stream::unfold(0, move |n| async move {
+        let event = match n {
+            0 => ": stream open\n\n".to_string(),
+            n if n <= count => {
+                tokio::time::sleep(EVENT_INTERVAL).await;
+                let data = json!({ "tick": n, "of": count, "at": chrono::Utc::now().to_rfc3339() });
+                format!("event: tick\nid: {}\ndata: {}\n\n", n, data)
+            }
+            _ => return None,
+        };
+        Some((Ok::<_, Infallible>(Frame::data(Bytes::from(event))), n + 1))
+    });
+    Response::builder()
+        .header(CONTENT_TYPE, "text/event-stream")
+        .header(CACHE_CONTROL, "no-cache")
+        .body(StreamBody::new(ticks).boxed())
+        .unwrap()
+}
+
+async fn echo(req: Request<Incoming>) -> Response<Body> {
+    let (parts, body) = req.into_parts();
+    let headers: Map<String, Value> = parts
+        .headers
+        .iter()
+        .map(|(name, value)| (name.to_string(), json!(String::from_utf8_lossy(value.as_bytes()))))
+        .collect();
+    let body = match body.collect().await {
+        Ok(body) => String::from_utf8_lossy(&body.to_bytes()).into_owned(),
+        Err(e) => return respond_json(StatusCode::BAD_REQUEST, json!({ "error": e.to_string() })),
+    };
+    respond_json(
+        StatusCode::OK,
+        json!({
+            "method": parts.method.as_str(),
+            "uri": parts.uri.to_string(),
+            "headers": headers,
+            "body": body,
+        }),
+    )
+}
+
+async fn handle(req: Request<Incoming>) -> Response<Body> {
+    match route(req.method(), req.uri().path(), req.uri().query()) {
+        Endpoint::Index => respond(StatusCode::OK, "text/html; charset=utf-8", INDEX),
+        Endpoint::Json => respond_json(StatusCode::OK, users()),
+        Endpoint::Html => respond(StatusCode::OK, "text/html; charset=utf-8", PAGE),
+        Endpoint::Slow(delay) => {
+            tokio::time::sleep(delay).await;
+            respond_json(StatusCode::OK, json!({ "waited_ms": delay.as_millis() as u64 }))
+        }
+        Endpoint::Error => respond_json(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            json!({ "error": "database unavailable", "retry_after": 5 }),
+        ),
+        Endpoint::Status(status) => Response::builder().status(status).body(Full::default().boxed()).unwrap(),
+        Endpoint::Chunked => chunked(),
+        Endpoint::Events(count) => events(count),
+        Endpoint::Echo => echo(req).await,
+        Endpoint::NotFound => respond_json(StatusCode::NOT_FOUND, json!({ "error": "no such route" })),
+    }
+}
+
+/// Start the mock upstream on a free local port, serving until the process
+/// exits, and return its address.
+pub async fn spawn() -> std::io::Result<SocketAddr> {
+    let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+    let addr = listener.local_addr()?;
+    info!("Demo upstream listening on {}", addr);
+    tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    error!("Failed to accept demo connection: {}", e);
+                    continue;
+                }
+            };
+            tokio::spawn(async move {
+                let service = service_fn(|req| async move { Ok::<_, Infallible>(handle(req).await) });
+                if let Err(e) = http1::Builder::new().serve_connection(TokioIo::new(stream), service).await {
+                    error!("Error serving demo connection: {:?}", e);
+                }
+            });
+        }
+    });
+    Ok(addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_route() {
+        assert_eq!(route(&Method::GET, "/", None), Endpoint::Index);
+        assert_eq!(route(&Method::GET, "/json/", None), Endpoint::Json);
+        assert_eq!(route(&Method::GET, "/slow", None), Endpoint::Slow(SLOW_DEFAULT));
+        assert_eq!(route(&Method::GET, "/slow", Some("ms=150")), Endpoint::Slow(Duration::from_millis(150)));
+        assert_eq!(route(&Method::GET, "/slow", Some("ms=999999")), Endpoint::Slow(SLOW_MAX));
+        assert_eq!(route(&Method::GET, "/status/418", None), Endpoint::Status(StatusCode::IM_A_TEAPOT));
+        assert_eq!(route(&Method::GET, "/status/42", None), Endpoint::NotFound);
+        assert_eq!(route(&Method::GET, "/events", Some("n=3")), Endpoint::Events(3));
+        assert_eq!(route(&Method::DELETE, "/echo", None), Endpoint::Echo);
+        assert_eq!(route(&Method::POST, "/json", None), Endpoint::NotFound);
+    }
+}
@@ -0,0 +1,174 @@
+//! How upstream hosts are resolved: a fixed host map like `curl --resolve`,
+//! then either the system resolver or the configured DNS servers.
+
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use hickory_resolver::{
+    TokioAsyncResolver,
+    config::{NameServerConfigGroup, ResolverConfig, ResolverOpts},
+};
+use hyper::Uri;
+use hyper::http::uri::Authority;
+use hyper_util::client::legacy::connect::dns::{GaiResolver, Name};
+use tower_service::Service;
+use tracing::{debug, warn};
+
+use crate::config::DnsConfig;
+
+type BoxFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send>>;
+
+/// Resolver handed to the upstream connector.
+#[derive(Clone)]
+pub enum Resolver {
+    System(GaiResolver),
+    Custom(Arc<TokioAsyncResolver>),
+}
+
+impl Resolver {
+    pub fn new(config: &DnsConfig) -> Self {
+        if config.servers.is_empty() {
+            return Resolver::System(GaiResolver::new());
+        }
+        let servers = NameServerConfigGroup::from_ips_clear(&config.servers, 53, true);
+        let resolver_config = ResolverConfig::from_parts(None, Vec::new(), servers);
+        Resolver::Custom(Arc::new(TokioAsyncResolver::tokio(resolver_config, ResolverOpts::default())))
+    }
+}
+
+impl Service<Name> for Resolver {
+    type Response = std::vec::IntoIter<SocketAddr>;
+    type Error = io::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self {
+            Resolver::System(gai) => gai.poll_ready(cx),
+            Resolver::Custom(_) => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        match self {
+            Resolver::System(gai) => {
+                let lookup = gai.call(name);
+                Box::pin(async move { Ok(lookup.await?.collect::<Vec<_>>().into_iter()) })
+            }
+            Resolver::Custom(resolver) => {
+                let resolver = resolver.clone();
+                Box::pin(async move {
+                    let lookup = resolver.lookup_ip(name.as_str()).await.map_err(io::Error::other)?;
+                    // the connector fills in the port
+                    Ok(lookup.iter().map(|ip| SocketAddr::new(ip, 0)).collect::<Vec<_>>().into_iter())
+                })
+            }
+        }
+    }
+}
+
+/// Host map entries: `host` or `host:port` keys, `addr` or `addr:port` targets.
+#[derive(Clone, Debug, Default)]
+pub struct HostMap(BTreeMap<String, Authority>);
+
+impl HostMap {
+    pub fn new(hosts: &BTreeMap<String, String>) -> Self {
+        let map = hosts
+            .iter()
+            .filter_map(|(host, target)| match target.parse::<Authority>() {
+                Ok(target) => Some((host.to_ascii_lowercase(), target)),
+                Err(e) => {
+                    warn!("Ignoring host mapping {} -> {}: {}", host, target, e);
+                    None
+                }
+            })
+            .collect();
+        Self(map)
+    }
+
+    /// Where to connect for `host:port`; an entry with a port wins over one
+    /// without, and a target without a port keeps the original one.
+    fn lookup(&self, host: &str, port: u16) -> Option<String> {
+        let host = host.to_ascii_lowercase();
+        let target = self
+            .0
+            .get(&format!("{}:{}", host, port))
+            .or_else(|| self.0.get(&host))?;
+        Some(format!("{}:{}", target.host(), target.port_u16().unwrap_or(port)))
+    }
+}
+
+/// Wraps a connector so mapped hosts are dialed at their target. Sits below
+/// TLS, so certificates are still checked against the requested name.
+#[derive(Clone)]
+pub struct MappedConnector<C> {
+    inner: C,
+    hosts: Arc<HostMap>,
+}
+
+impl<C> MappedConnector<C> {
+    pub fn new(inner: C, hosts: HostMap) -> Self {
+        Self {
+            inner,
+            hosts: Arc::new(hosts),
+        }
+    }
+}
+
+impl<C: Service<Uri>> Service<Uri> for MappedConnector<C> {
+    type Response = C::Response;
+    type Error = C::Error;
+    type Future = C::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let default_port = if dst.scheme_str() == Some("https") { 443 } else { 80 };
+        let mapped = dst
+            .host()
+            .and_then(|host| self.hosts.lookup(host, dst.port_u16().unwrap_or(default_port)))
+            .and_then(|authority| {
+                Uri::builder()
+                    .scheme(dst.scheme_str().unwrap_or("http"))
+                    .authority(authority)
+                    .path_and_query("/")
+                    .build()
+                    .ok()
+            });
+        match mapped {
+            Some(target) => {
+                debug!("Connecting to {} for {}", target, dst);
+                self.inner.call(target)
+            }
+            None => self.inner.call(dst),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_host_map_lookup() {
+        let hosts = HostMap::new(&BTreeMap::from([
+            ("api.example.com".to_string(), "127.0.0.1:3000".to_string()),
+            ("cdn.example.com".to_string(), "10.0.0.5".to_string()),
+            ("cdn.example.com:8443".to_string(), "[::1]:9443".to_string()),
+        ]));
+        assert_eq!(hosts.lookup("API.example.com", 443), Some("127.0.0.1:3000".to_string()));
+        assert_eq!(hosts.lookup("cdn.example.com", 80), Some("10.0.0.5:80".to_string()));
+        assert_eq!(hosts.lookup("cdn.example.com", 8443), Some("[::1]:9443".to_string()));
+        assert_eq!(hosts.lookup("other.test", 80), None);
+    }
+}
@@ -14,11 +14,8 @@ pub fn init() -> color_eyre::Result<()> {
         .into_hooks();
     eyre_hook.install()?;
     std::panic::set_hook(Box::new(move |panic_info| {
-        if let Ok(mut t) = crate::tui::Tui::new() {
-            if let Err(r) = t.exit() {
-                error!("Unable to exit Terminal: {:?}", r);
-            }
-        }
+        // before anything is printed, or it lands on the alternate screen
+        crate::tui::restore();
 
         #[cfg(not(debug_assertions))]
         {
@@ -0,0 +1,65 @@
+//! Programs that get the terminal until they exit: the user's editor, and
+//! the configured diff tool.
+
+use std::{io, path::Path, process::ExitStatus};
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+/// `$VISUAL`, else `$EDITOR`, else `vi`, as a program and its arguments,
+/// e.g. `code --wait`.
+fn editor_command(visual: Option<String>, editor: Option<String>) -> Vec<String> {
+    let command = [visual, editor]
+        .into_iter()
+        .flatten()
+        .find(|command| !command.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    command.split_whitespace().map(str::to_string).collect()
+}
+
+/// Run `command`, split on whitespace, with `paths` appended, and wait for
+/// it without blocking the proxy.
+async fn run(command: &[String], paths: &[&Path]) -> io::Result<ExitStatus> {
+    let Some((program, args)) = command.split_first() else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "empty command"));
+    };
+    tokio::process::Command::new(program)
+        .args(args)
+        .args(paths)
+        .status()
+        .await
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", program, e)))
+}
+
+/// Open `path` in the editor.
+pub async fn edit(path: &Path) -> io::Result<ExitStatus> {
+    run(&editor_command(std::env::var("VISUAL").ok(), std::env::var("EDITOR").ok()), &[path]).await
+}
+
+/// Run the diff tool `command` on two files. Its output stays on screen
+/// until enter is pressed, for tools that don't page it themselves.
+pub async fn diff(command: &str, baseline: &Path, other: &Path) -> io::Result<ExitStatus> {
+    let command: Vec<String> = command.split_whitespace().map(str::to_string).collect();
+    let status = run(&command, &[baseline, other]).await?;
+    let mut stdout = tokio::io::stdout();
+    stdout.write_all(b"\nPress enter to return to yap").await?;
+    stdout.flush().await?;
+    tokio::io::BufReader::new(tokio::io::stdin()).read_line(&mut String::new()).await?;
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_editor_command() {
+        let editor = |visual: Option<&str>, editor: Option<&str>| {
+            editor_command(visual.map(str::to_string), editor.map(str::to_string))
+        };
+        assert_eq!(editor(Some("code --wait"), Some("nano")), ["code", "--wait"]);
+        assert_eq!(editor(Some(" "), Some("nano")), ["nano"]);
+        assert_eq!(editor(None, None), [if cfg!(windows) { "notepad" } else { "vi" }]);
+    }
+}
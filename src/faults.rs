@@ -0,0 +1,124 @@
+//! Chaos testing: failures injected into a random share of matching requests.
+
+use std::{sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::pac::sh_exp_match;
+
+/// What happens to a request hit by a fault rule.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum Fault {
+    /// Answer with this status without contacting the upstream.
+    Status(u16),
+    /// Forward the request, then close the connection instead of answering.
+    Reset,
+    /// Forward the request, then send only the first half of the body.
+    Truncate,
+    /// Wait this many seconds before forwarding.
+    Delay(f64),
+}
+
+impl Fault {
+    /// Short description for the request list.
+    pub fn label(&self) -> String {
+        match self {
+            Fault::Status(status) => status.to_string(),
+            Fault::Reset => "reset".to_string(),
+            Fault::Truncate => "truncated".to_string(),
+            Fault::Delay(seconds) => format!("delay {}s", seconds),
+        }
+    }
+
+    pub fn delay(&self) -> Option<Duration> {
+        match self {
+            Fault::Delay(seconds) => Duration::try_from_secs_f64(*seconds).ok(),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FaultRule {
+    /// Host pattern with `*` wildcards.
+    #[serde(default = "any_host")]
+    pub host: String,
+    /// Only requests whose path starts with this.
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Chance from 0 to 1 that a matching request is hit.
+    #[serde(default = "always")]
+    pub probability: f64,
+    pub fault: Fault,
+    /// Cleared from the rules panel to keep a rule without applying it.
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+    /// Faults injected by this rule this run.
+    #[serde(skip)]
+    pub hits: u64,
+}
+
+/// Rules shared between the proxy and the rules panel, which can toggle them.
+pub type SharedFaults = Arc<RwLock<Vec<FaultRule>>>;
+
+fn enabled() -> bool {
+    true
+}
+
+fn any_host() -> String {
+    "*".to_string()
+}
+
+fn always() -> f64 {
+    1.0
+}
+
+/// The fault to inject into a request to `host` and `path`, if any: each
+/// enabled matching rule rolls in turn and the first hit wins.
+pub fn pick(rules: &mut [FaultRule], host: &str, path: &str, mut roll: impl FnMut() -> f64) -> Option<Fault> {
+    let rule = rules
+        .iter_mut()
+        .filter(|rule| rule.enabled && sh_exp_match(&rule.host, host))
+        .filter(|rule| rule.path.as_deref().is_none_or(|prefix| path.starts_with(prefix)))
+        .find(|rule| roll() < rule.probability)?;
+    rule.hits += 1;
+    Some(rule.fault.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_pick_first_hit() {
+        let mut rules = vec![
+            FaultRule {
+                host: "api.test".to_string(),
+                path: Some("/slow".to_string()),
+                probability: 0.5,
+                fault: Fault::Delay(2.0),
+                enabled: true,
+                hits: 0,
+            },
+            FaultRule {
+                host: "*".to_string(),
+                path: None,
+                probability: 0.1,
+                fault: Fault::Status(503),
+                enabled: true,
+                hits: 0,
+            },
+        ];
+        assert_eq!(pick(&mut rules, "api.test", "/slow/1", || 0.3), Some(Fault::Delay(2.0)));
+        assert_eq!(pick(&mut rules, "api.test", "/slow/1", || 0.7), None);
+        assert_eq!(pick(&mut rules, "api.test", "/fast", || 0.05), Some(Fault::Status(503)));
+        assert_eq!((rules[0].hits, rules[1].hits), (1, 1));
+        rules[1].enabled = false;
+        assert_eq!(pick(&mut rules, "api.test", "/fast", || 0.05), None);
+        assert_eq!(Fault::Delay(2.0).label(), "delay 2s");
+    }
+}
@@ -1,6 +1,39 @@
+use std::{fmt, path::PathBuf, sync::Arc};
+
 use serde::{Deserialize, Serialize};
 use strum::Display;
 
+use super::Message;
+use crate::config::Config;
+
+/// A configuration re-read from disk, compared by identity like [`Message`].
+#[derive(Clone)]
+pub struct ReloadedConfig(Arc<Config>);
+
+impl ReloadedConfig {
+    pub fn new(config: Config) -> Self {
+        Self(Arc::new(config))
+    }
+
+    pub fn config(&self) -> Config {
+        (*self.0).clone()
+    }
+}
+
+impl fmt::Debug for ReloadedConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ReloadedConfig")
+    }
+}
+
+impl PartialEq for ReloadedConfig {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for ReloadedConfig {}
+
 #[derive(Debug, Clone, PartialEq, Eq, Display, Serialize, Deserialize)]
 pub enum Action {
     Render,
@@ -8,5 +41,173 @@ pub enum Action {
     Suspend,
     Resume,
     Quit,
+    ClearSession,
+    ToggleStats,
+    ToggleHelp,
+    ToggleCookies,
+    /// Show the rewrite and fault rules with their hit counts.
+    ToggleRules,
+    /// Write a new request and send it through the proxy.
+    ToggleComposer,
+    /// Pick the named session the request list shows.
+    ToggleSessions,
+    /// Show the clients with their capture, throttle and rewrite settings.
+    ToggleClients,
+    /// Show request volume over time, to limit the list to a span of it.
+    ToggleTimeline,
+    /// Hide or show the progress of large downloads.
+    ToggleTransfers,
+    /// Chart of the throughput to and from upstream hosts.
+    ToggleBandwidth,
+    ExportOpenApi,
+    OpenCommand,
+    ToggleCapture,
+    /// Replace the filter, e.g. with a preset from the palette.
+    SetFilter(String),
+    FocusNext,
+    FocusPrev,
+    FocusFilter,
+    FocusList,
+    ResizeLeft,
+    ResizeRight,
+    ResizeUp,
+    ResizeDown,
     Error(String),
+    /// A message type owned by a component, see [`Message`]. Never bound to keys.
+    #[serde(skip)]
+    Message(Message),
+    /// The config file changed; components take the settings they keep
+    /// from it. Never bound to keys.
+    #[serde(skip)]
+    ConfigReloaded(ReloadedConfig),
+    /// Hand the terminal to `$EDITOR` on this file until it exits. Never
+    /// bound to keys.
+    #[serde(skip)]
+    Edit(PathBuf),
+    /// Hand the terminal to the `diff.command` on these two files. Never
+    /// bound to keys.
+    #[serde(skip)]
+    ExternalDiff(PathBuf, PathBuf),
+
+    // request list
+    SelectNext,
+    SelectPrev,
+    SelectFirst,
+    SelectLast,
+    HalfPageDown,
+    HalfPageUp,
+    /// Scroll the selected row to the middle of the list.
+    CenterSelection,
+    OpenDetail,
+    ToggleTree,
+    Collapse,
+    Expand,
+    MarkBaseline,
+    Compare,
+    /// Select the earlier identical request that got another status, with
+    /// this one as the baseline to compare it to.
+    JumpToEarlier,
+    /// Select the next request with the same trace id or `X-Request-Id`.
+    NextInTrace,
+    PrevInTrace,
+    /// Filter the list down to the selected request's trace.
+    FilterTrace,
+    TogglePin,
+    ToggleFollow,
+    /// Fold consecutive identical requests into one row.
+    ToggleRepeats,
+    /// Order the list by the next column.
+    CycleSort,
+    ReverseSort,
+    /// Send the selected request again until it succeeds, backing off
+    /// between attempts.
+    RetryRequest,
+    /// Send every request matching the filter again and report which got
+    /// another status.
+    ReplayFiltered,
+
+    // detail and diff popups
+    ScrollDown,
+    ScrollUp,
+    NextHeader,
+    PrevHeader,
+    CopyUrl,
+    CopyHeader,
+    CopyBody,
+    /// Compare the bodies with the `diff.command` instead.
+    OpenExternalDiff,
+    /// Ask for a path and write the response body there, byte for byte.
+    SaveBody,
+    /// Likewise for the request body.
+    SaveRequestBody,
+    /// Open the response body in `$EDITOR`.
+    OpenBody,
+    /// Likewise for the request body.
+    OpenRequestBody,
+    /// Select the next part of a `multipart/form-data` request.
+    NextPart,
+    PrevPart,
+    /// Show or hide the selected part's headers and body.
+    TogglePart,
+    /// Ask for a path and write the selected part's body there.
+    SavePart,
+    /// Show HTML response bodies as their text, or as markup again.
+    ToggleHtmlText,
+    /// Pick values out of a JSON response body with a JSONPath or jq-like path.
+    QueryBody,
+    /// Copy the values the query picked.
+    CopyQuery,
+    Close,
+
+    // clear-session confirmation
+    ConfirmClear,
+    ConfirmClearAndDelete,
+    /// Load the requests of the previous session, when offered at startup.
+    ConfirmRestore,
+
+    // filter input
+    CursorLeft,
+    CursorRight,
+    CursorStart,
+    CursorEnd,
+    WordLeft,
+    WordRight,
+    DeleteBackward,
+    DeleteForward,
+    DeleteWordBackward,
+    /// Recall the previous filter from the history.
+    HistoryPrev,
+    HistoryNext,
+
+    // command line
+    /// Run the command line, or send the composed request from its method
+    /// or URL field.
+    RunCommand,
+
+    // timeline
+    /// Start selecting a span at the cursor, or drop the selection.
+    MarkRange,
+    /// Limit the list to the selected span, or the column under the cursor.
+    ApplyRange,
+    /// Show requests of any time again.
+    ClearRange,
+
+    // rules, sessions, cookies and clients panels
+    /// Show the selected session in the list, or edit the selected rule.
+    OpenItem,
+    /// Edit the pattern of the selected rule.
+    EditItem,
+    /// Turn the selected rule on or off, or capture from the selected client.
+    ToggleItem,
+    /// Write the rules or client settings to the config file, or the
+    /// selected session to its archive.
+    SaveItem,
+    /// Move the selected client on to the next throttle.
+    CycleThrottle,
+    /// Move the selected client on to the next rewrite set.
+    CycleRewrites,
+
+    // composer
+    /// Send the composed request through the proxy.
+    SendRequest,
 }
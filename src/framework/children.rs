@@ -1,6 +1,6 @@
 use ratatui::layout::Size;
 
-use super::{action::Action, components::Component};
+use super::{action::Action, command::Command, components::Component};
 use crate::{config::Config, framework::Updater, tui::Event};
 
 /// `Children` trait provides React-like children functionality for components.
@@ -49,4 +49,43 @@ pub trait Children {
         Ok(actions)
     }
 
+    /// Helper method to propagate events with focus: key events only go to the
+    /// `focused` child, every other event goes to all children.
+    fn propagate_focused_events(
+        &mut self,
+        event: Option<Event>,
+        focused: Option<usize>,
+    ) -> color_eyre::Result<Vec<Action>> {
+        if !matches!(event, Some(Event::Key(_))) {
+            return self.propagate_events(event);
+        }
+        let mut actions = Vec::new();
+        if let Some(child) = focused.and_then(|index| self.children().into_iter().nth(index))
+            && let Some(action) = child.handle_events(event)?
+        {
+            actions.push(action);
+        }
+        Ok(actions)
+    }
+
+    /// Helper method to propagate actions to all children.
+    /// Call this in your update if you want children to receive actions.
+    fn propagate_update(&mut self, action: Action) -> color_eyre::Result<Vec<Action>> {
+        let mut actions = Vec::new();
+        for child in self.children().iter_mut() {
+            if let Some(action) = child.update(action.clone())? {
+                actions.push(action);
+            }
+        }
+        Ok(actions)
+    }
+
+    /// Helper method to collect the queued commands of all children.
+    /// Call this in your take_commands if you have children.
+    fn children_commands(&mut self) -> Vec<Command> {
+        self.children()
+            .iter_mut()
+            .flat_map(|child| child.take_commands())
+            .collect()
+    }
 }
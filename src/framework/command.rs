@@ -0,0 +1,60 @@
+use std::future::Future;
+
+use futures::{FutureExt, future::BoxFuture};
+
+use super::Action;
+
+/// An async side effect requested by a component, similar to React's `useEffect`.
+///
+/// Components queue commands instead of spawning tokio tasks themselves; the
+/// runtime collects them through `Component::take_commands`, runs them, and
+/// dispatches the action each one resolves to. Pending commands are aborted
+/// when the runtime stops.
+pub struct Command(BoxFuture<'static, Option<Action>>);
+
+impl Command {
+    pub fn new<F>(future: F) -> Self
+    where
+        F: Future<Output = Option<Action>> + Send + 'static,
+    {
+        Self(future.boxed())
+    }
+
+    /// Run `future` for its side effect only, re-rendering once it is done.
+    pub fn then_render<F>(future: F) -> Self
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        Self::new(async move {
+            future.await;
+            Some(Action::Render)
+        })
+    }
+
+    /// Drive the command to completion, returning the action it produced.
+    pub async fn run(self) -> Option<Action> {
+        self.0.await
+    }
+}
+
+impl std::fmt::Debug for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Command")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_command_resolves_to_action() {
+        let command = Command::new(async { Some(Action::ToggleStats) });
+        assert_eq!(command.run().await, Some(Action::ToggleStats));
+
+        let command = Command::then_render(async {});
+        assert_eq!(command.run().await, Some(Action::Render));
+    }
+}
@@ -4,7 +4,7 @@ use ratatui::{
     layout::{Rect, Size},
 };
 
-use super::action::Action;
+use super::{action::Action, command::Command};
 use crate::{config::Config, framework::Updater, tui::Event};
 
 /// `Component` is a trait that represents a visual and interactive element of the user interface.
@@ -37,7 +37,7 @@ pub trait Component {
     ///
     /// * `area` - Rectangular area the component is mounted within.
     /// * `updater` - An updater that can be used to trigger re-renders, the component can only be
-    /// update after mounting, so this is provided here.
+    ///   update after mounting, so this is provided here.
     ///
     /// # Returns
     ///
@@ -93,6 +93,77 @@ pub trait Component {
         Ok(None)
     }
 
+    /// Called by the parent when the component gains or loses focus.
+    /// Focused components receive key events and should highlight themselves.
+    ///
+    /// # Arguments
+    ///
+    /// * `focused` - Whether the component is now focused.
+    fn set_focused(&mut self, focused: bool) {
+        let _ = focused; // to appease clippy
+    }
+
+    /// Whether the component wants `key` for itself in its current state.
+    ///
+    /// When any component captures a key the runtime skips the global (`Home`)
+    /// keybindings for it, so that e.g. typing `q` into an input doesn't quit.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key event about to be dispatched.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the key should not trigger global keybindings.
+    fn captures_key(&self, key: KeyEvent) -> bool {
+        let _ = key; // to appease clippy
+        false
+    }
+
+    /// Whether the component wants `mouse` for itself in its current state,
+    /// e.g. a modal overlay that shouldn't let clicks through to the panes below.
+    ///
+    /// # Arguments
+    ///
+    /// * `mouse` - The mouse event about to be dispatched.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if no other component should receive the event.
+    fn captures_mouse(&self, mouse: MouseEvent) -> bool {
+        let _ = mouse; // to appease clippy
+        false
+    }
+
+    /// Update the state of the component based on a received action.
+    ///
+    /// Actions come from keybindings, other components or async tasks and are
+    /// dispatched to every component by the runtime.
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - An action that may modify the state of the component.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<Action>>` - An action to be processed or none.
+    fn update(&mut self, action: Action) -> color_eyre::Result<Option<Action>> {
+        let _ = action; // to appease clippy
+        Ok(None)
+    }
+
+    /// Hand the commands queued since the last call over to the runtime.
+    ///
+    /// The runtime calls this after every event and action it delivers, runs
+    /// the returned commands and dispatches the actions they resolve to.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Command>` - Commands to run, usually drained from a field.
+    fn take_commands(&mut self) -> Vec<Command> {
+        Vec::new()
+    }
+
     /// Render the component on the screen. (REQUIRED)
     /// Similar to React's render method.
     ///
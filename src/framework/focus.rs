@@ -0,0 +1,72 @@
+/// `FocusManager` tracks which child of a container currently receives key events.
+///
+/// Only the children listed in the focus order can be focused; `next` and `prev`
+/// cycle through them like Tab/Shift-Tab in a browser.
+#[derive(Clone, Debug, Default)]
+pub struct FocusManager {
+    order: Vec<usize>,
+    current: usize,
+}
+
+impl FocusManager {
+    /// Create a manager cycling through the given child indices, focusing the first.
+    pub fn new(order: Vec<usize>) -> Self {
+        Self { order, current: 0 }
+    }
+
+    /// Index of the focused child, if any child is focusable.
+    pub fn focused(&self) -> Option<usize> {
+        self.order.get(self.current).copied()
+    }
+
+    pub fn is_focused(&self, child: usize) -> bool {
+        self.focused() == Some(child)
+    }
+
+    /// Move focus to the next child, wrapping around.
+    pub fn next(&mut self) {
+        if !self.order.is_empty() {
+            self.current = (self.current + 1) % self.order.len();
+        }
+    }
+
+    /// Move focus to the previous child, wrapping around.
+    pub fn prev(&mut self) {
+        if !self.order.is_empty() {
+            self.current = (self.current + self.order.len() - 1) % self.order.len();
+        }
+    }
+
+    /// Focus a specific child. Returns false if it isn't focusable.
+    pub fn focus(&mut self, child: usize) -> bool {
+        match self.order.iter().position(|&c| c == child) {
+            Some(position) => {
+                self.current = position;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_focus_cycles() {
+        let mut focus = FocusManager::new(vec![1, 2]);
+        assert_eq!(focus.focused(), Some(1));
+        focus.next();
+        assert_eq!(focus.focused(), Some(2));
+        focus.next();
+        assert_eq!(focus.focused(), Some(1));
+        focus.prev();
+        assert_eq!(focus.focused(), Some(2));
+        assert!(!focus.focus(3));
+        assert!(focus.focus(1));
+        assert!(focus.is_focused(1));
+    }
+}
@@ -0,0 +1,73 @@
+use std::{
+    any::{Any, type_name},
+    fmt,
+    sync::Arc,
+};
+
+/// A component-defined message carried through the runtime by `Action::Message`.
+///
+/// Components declare their own message types and downcast them back in
+/// `update`, so adding a component doesn't require a new `Action` variant.
+/// Every component still sees every message, like any other action.
+#[derive(Clone)]
+pub struct Message {
+    payload: Arc<dyn Any + Send + Sync>,
+    type_name: &'static str,
+}
+
+#[allow(dead_code)]
+impl Message {
+    pub fn new<T: Any + Send + Sync>(payload: T) -> Self {
+        Self {
+            payload: Arc::new(payload),
+            type_name: type_name::<T>(),
+        }
+    }
+
+    /// The payload, if it is a `T`.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.payload.downcast_ref()
+    }
+
+    pub fn is<T: Any>(&self) -> bool {
+        self.payload.is::<T>()
+    }
+}
+
+impl fmt::Debug for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Message({})", self.type_name)
+    }
+}
+
+// messages compare by identity: a clone of a message equals the original
+impl PartialEq for Message {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.payload, &other.payload)
+    }
+}
+
+impl Eq for Message {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Ping(u32);
+
+    #[test]
+    fn test_downcast() {
+        let message = Message::new(Ping(7));
+        assert!(message.is::<Ping>());
+        assert_eq!(message.downcast_ref::<Ping>().map(|ping| ping.0), Some(7));
+        assert!(message.downcast_ref::<String>().is_none());
+        assert_eq!(format!("{:?}", message), format!("Message({})", type_name::<Ping>()));
+    }
+
+    #[test]
+    fn test_eq_is_identity() {
+        let message = Message::new(Ping(1));
+        assert_eq!(message, message.clone());
+        assert_ne!(message, Message::new(Ping(1)));
+    }
+}
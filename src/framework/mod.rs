@@ -1,12 +1,21 @@
 pub mod action;
 pub mod children;
+pub mod command;
 pub mod components;
+pub mod focus;
+pub mod message;
 pub mod runtime;
+pub mod scheduler;
+#[cfg(test)]
+pub mod testing;
 pub mod updater;
 
 // Re-export commonly used items
-pub use action::Action;
+pub use action::{Action, ReloadedConfig};
 pub use children::Children;
+pub use command::Command;
 pub use components::Component;
+pub use focus::FocusManager;
+pub use message::Message;
 pub use runtime::Runtime;
 pub use updater::Updater;
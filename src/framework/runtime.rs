@@ -1,17 +1,81 @@
 use color_eyre::eyre::Ok;
 use crossterm::event::KeyEvent;
-use ratatui::prelude::Rect;
-use tokio::sync::mpsc;
-use tracing::{debug, info};
+use ratatui::{backend::Backend, prelude::Rect};
+use std::time::Duration;
 
-use super::{action::Action, components::Component};
+use tokio::{sync::mpsc, task::JoinSet};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+use tracing::{debug, info, warn};
+
+use super::{action::Action, command::Command, components::Component, scheduler::RenderScheduler};
 use crate::{
     app::Mode,
     config::Config,
     framework::Updater,
+    external, reload,
     tui::{Event, Tui},
 };
 
+/// How long background tasks get to wind down on quit before the terminal is restored anyway.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Turn the signals sent to yap into actions. SIGTERM and SIGINT, which raw
+/// mode keeps Ctrl-C from sending but `kill` still does, quit as `q` does; if
+/// another comes before the shutdown is over, the terminal is restored and the
+/// process exits right away. SIGTSTP stops yap on the spot, as the runtime may
+/// be waiting on an editor that suspended its whole process group, and SIGCONT
+/// takes the terminal back however yap was stopped.
+#[cfg(unix)]
+async fn forward_signals(action_tx: mpsc::UnboundedSender<Action>) -> std::io::Result<()> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut suspend = signal(SignalKind::from_raw(libc::SIGTSTP))?;
+    let mut resume = signal(SignalKind::from_raw(libc::SIGCONT))?;
+    let mut quitting = false;
+    loop {
+        let number = tokio::select! {
+            _ = terminate.recv() => libc::SIGTERM,
+            _ = interrupt.recv() => libc::SIGINT,
+            _ = suspend.recv() => {
+                info!("Got SIGTSTP, stopping");
+                crate::tui::stop();
+                continue;
+            }
+            _ = resume.recv() => {
+                info!("Got SIGCONT, resuming");
+                let _ = action_tx.send(Action::Resume);
+                continue;
+            }
+        };
+        if quitting {
+            crate::tui::restore();
+            std::process::exit(128 + number);
+        }
+        info!("Got signal {}, quitting", number);
+        quitting = true;
+        let _ = action_tx.send(Action::Quit);
+    }
+}
+
+/// Quit as `q` does when the console window is closed or gets Ctrl-Break,
+/// which raw mode doesn't turn into keys the way it does Ctrl-C.
+#[cfg(windows)]
+async fn forward_signals(action_tx: mpsc::UnboundedSender<Action>) -> std::io::Result<()> {
+    use tokio::signal::windows::{ctrl_break, ctrl_close};
+
+    let mut close = ctrl_close()?;
+    let mut interrupt = ctrl_break()?;
+    tokio::select! {
+        _ = close.recv() => {}
+        _ = interrupt.recv() => {}
+    }
+    info!("The console is closing, quitting");
+    let _ = action_tx.send(Action::Quit);
+    Ok(())
+}
+
 /// Runtime manages the execution of components and handles the application lifecycle.
 ///
 /// This is similar to the React runtime that manages the component tree and handles
@@ -22,12 +86,29 @@ pub struct Runtime {
     action_rx: mpsc::UnboundedReceiver<Action>,
     config: Config,
     mode: Mode,
+    /// Commands queued by components that haven't finished yet.
+    commands: JoinSet<Option<Action>>,
+    /// Cancelled on quit to stop the proxy and background tasks.
+    shutdown: CancellationToken,
+    /// Background tasks spawned through `Updater::spawn`.
+    tasks: TaskTracker,
+    scheduler: RenderScheduler,
+    /// Program to hand the terminal to once the current batch of actions is
+    /// done, an [`Action::Edit`] or [`Action::ExternalDiff`].
+    hand_off: Option<Action>,
+    /// Played instead of reading the terminal, see [`Runtime::scripted`].
+    #[cfg(test)]
+    script: Option<super::testing::Script>,
+    /// The last frame drawn while playing a script.
+    #[cfg(test)]
+    screen: Option<ratatui::buffer::Buffer>,
 }
 
 impl Runtime {
     /// Create a new Runtime with the given components and configuration.
     pub fn new(components: Vec<Box<dyn Component>>, config: Config, mode: Mode) -> Self {
         let (action_tx, action_rx) = mpsc::unbounded_channel();
+        let scheduler = RenderScheduler::new(config.render.max_fps);
 
         Self {
             components,
@@ -35,18 +116,67 @@ impl Runtime {
             action_rx,
             config,
             mode,
+            commands: JoinSet::new(),
+            shutdown: CancellationToken::new(),
+            tasks: TaskTracker::new(),
+            scheduler,
+            hand_off: None,
+            #[cfg(test)]
+            script: None,
+            #[cfg(test)]
+            screen: None,
+        }
+    }
+
+    /// A runtime that takes its events from `script` and draws in memory,
+    /// for end-to-end tests; it quits once the script is over.
+    #[cfg(test)]
+    pub fn scripted(
+        components: Vec<Box<dyn Component>>,
+        config: Config,
+        mode: Mode,
+        script: super::testing::Script,
+    ) -> Self {
+        Self {
+            script: Some(script),
+            ..Self::new(components, config, mode)
         }
     }
 
+    /// The screen as it was last drawn while playing a script.
+    #[cfg(test)]
+    pub fn screen(&self) -> String {
+        self.screen.as_ref().map(super::testing::screen_text).unwrap_or_default()
+    }
+
     /// Run the runtime loop.
     ///
     /// This method handles the full lifecycle:
     /// 1. Initialize TUI
     /// 2. Mount components (component_will_mount, component_did_mount)
     /// 3. Run event loop (handle events, process actions, render)
+    /// 4. Shut down background tasks
     /// 5. Cleanup TUI
     pub async fn run(&mut self) -> color_eyre::Result<()> {
-        let mut tui = Tui::new()?;
+        #[cfg(test)]
+        if let Some(script) = self.script.take() {
+            let mut tui = Tui::scripted(script)?;
+            self.run_on(&mut tui).await?;
+            // what the last actions changed, without waiting for the next frame
+            self.render(&mut tui)?;
+            self.screen = Some(tui.backend().buffer().clone());
+            return Ok(());
+        }
+        let mut tui = Tui::new()?.mouse(true);
+        #[cfg(any(unix, windows))]
+        let signals = tokio::spawn(forward_signals(self.action_tx.clone()));
+        let result = self.run_on(&mut tui).await;
+        #[cfg(any(unix, windows))]
+        signals.abort();
+        result
+    }
+
+    async fn run_on<B: Backend>(&mut self, tui: &mut Tui<B>) -> color_eyre::Result<()> {
         tui.enter()?;
 
         info!("Initializing components (constructor phase)");
@@ -56,31 +186,60 @@ impl Runtime {
 
         // Initial render
         self.action_tx.send(Action::Render)?;
-        let updater = Updater::new(self.action_tx.clone());
+        let updater = Updater::new(self.action_tx.clone(), self.shutdown.clone(), self.tasks.clone());
 
         info!("Mounting components (componentDidMount phase)");
         let size = tui.size()?;
         for component in self.components.iter_mut() {
             component.component_did_mount(size, updater.clone())?;
         }
+        updater.spawn(reload::watch(self.config.config.config_dir.clone(), updater.clone()));
 
         // a tickless event loop
         loop {
+            let deadline = self.scheduler.deadline();
             let stop = tokio::select! {
                 // Wait for input events from TUI
                 Some(event) = tui.next_event() => {
                     // Handle the event
                     self.process_event(event)?;
-                    Ok(false)
+                    // and what it set off before the next one, so that keys typed
+                    // quickly land where the previous ones moved the focus
+                    match self.action_rx.try_recv() {
+                        Result::Ok(action) => self.batch_actions(tui, action),
+                        Err(_) => Ok(false),
+                    }
                 }
 
                 // Also check for actions that may come from async tasks
                 Some(action) = self.action_rx.recv() => {
                     // Put the action back and process all pending actions
-                    let stop = self.batch_actions(&mut tui, action)?;
+                    let stop = self.batch_actions(tui, action)?;
                     Ok(stop)
                 }
+
+                // Route the results of finished commands back as actions
+                Some(result) = self.commands.join_next(), if !self.commands.is_empty() => {
+                    match result {
+                        Result::Ok(Some(action)) => self.action_tx.send(action)?,
+                        Result::Ok(None) => {}
+                        Err(e) => self.action_tx.send(Action::Error(format!("Command failed: {}", e)))?,
+                    }
+                    Ok(false)
+                }
+
+                // Draw once the next frame is due, if anything changed
+                _ = tokio::time::sleep_until(deadline.unwrap_or_else(tokio::time::Instant::now)),
+                    if deadline.is_some() =>
+                {
+                    self.render(tui)?;
+                    Ok(false)
+                }
             }?;
+            self.spawn_commands();
+            if let Some(action) = self.hand_off.take() {
+                self.hand_off(tui, action).await?;
+            }
 
             tracing::info!("Event loop");
 
@@ -89,10 +248,61 @@ impl Runtime {
             }
         }
 
+        // don't leave effects running behind the terminal
+        self.commands.abort_all();
+        self.shutdown().await;
         tui.exit()?;
         Ok(())
     }
 
+    /// Cancel background tasks and wait for them, up to `SHUTDOWN_TIMEOUT`.
+    async fn shutdown(&mut self) {
+        info!("Shutting down {} background tasks", self.tasks.len());
+        self.shutdown.cancel();
+        self.tasks.close();
+        if tokio::time::timeout(SHUTDOWN_TIMEOUT, self.tasks.wait()).await.is_err() {
+            warn!("{} background tasks did not stop in time", self.tasks.len());
+        }
+    }
+
+    /// Leave the terminal to the editor or diff tool, then resume. The proxy
+    /// keeps running meanwhile, and the actions queued are handled after.
+    async fn hand_off<B: Backend>(&mut self, tui: &mut Tui<B>, action: Action) -> color_eyre::Result<()> {
+        tui.exit()?;
+        let error = match action {
+            Action::Edit(path) => match external::edit(&path).await {
+                Result::Ok(status) if !status.success() => Some(format!("The editor exited with {}", status)),
+                Result::Ok(_) => None,
+                Err(e) => Some(format!("Failed to start the editor: {}", e)),
+            },
+            // diff tools exit with 1 when the files differ
+            Action::ExternalDiff(baseline, other) => {
+                let command = self.config.diff.command.clone().unwrap_or_default();
+                external::diff(&command, &baseline, &other)
+                    .await
+                    .err()
+                    .map(|e| format!("Failed to start the diff tool: {}", e))
+            }
+            _ => None,
+        };
+        if let Some(error) = error {
+            self.action_tx.send(Action::Error(error))?;
+        }
+        self.action_tx.send(Action::Resume)?;
+        Ok(())
+    }
+
+    fn spawn_commands(&mut self) {
+        let commands: Vec<Command> = self
+            .components
+            .iter_mut()
+            .flat_map(|component| component.take_commands())
+            .collect();
+        for command in commands {
+            self.commands.spawn(command.run());
+        }
+    }
+
     fn process_event(&mut self, event: Event) -> color_eyre::Result<()> {
         let action_tx = self.action_tx.clone();
 
@@ -114,10 +324,10 @@ impl Runtime {
 
     fn handle_key_event(&mut self, key: KeyEvent) -> color_eyre::Result<()> {
         let action_tx = self.action_tx.clone();
-        let Some(keymap) = self.config.keybindings.get(&self.mode) else {
+        if self.components.iter().any(|component| component.captures_key(key)) {
             return Ok(());
-        };
-        if let Some(action) = keymap.get(&vec![key]) {
+        }
+        if let Some(action) = self.config.keybindings.action_for(self.mode, key) {
             info!("Got action: {action:?}");
             action_tx.send(action.clone())?;
         }
@@ -125,7 +335,7 @@ impl Runtime {
     }
 
     // if the batch result is to stopped rendering and exit, return true
-    fn batch_actions(&mut self, tui: &mut Tui, action: Action) -> color_eyre::Result<bool> {
+    fn batch_actions<B: Backend>(&mut self, tui: &mut Tui<B>, action: Action) -> color_eyre::Result<bool> {
         let mut resize: Option<(u16, u16)> = match action {
             Action::Resize(w, h) => Some((w, h)),
             _ => None,
@@ -134,6 +344,10 @@ impl Runtime {
         let quit = action == Action::Quit;
         let mut suspend = action == Action::Suspend;
         let mut resume = action == Action::Resume;
+        if matches!(action, Action::Edit(_) | Action::ExternalDiff(..)) {
+            self.hand_off = Some(action.clone());
+        }
+        self.dispatch(&action)?;
 
         while let Result::Ok(action) = self.action_rx.try_recv() {
             if action != Action::Render {
@@ -150,15 +364,19 @@ impl Runtime {
                 Action::Resume => {
                     resume = true;
                 }
+                Action::Edit(_) | Action::ExternalDiff(..) => {
+                    self.hand_off = Some(action.clone());
+                }
                 Action::Resize(w, h) => {
                     resize = Some((w, h));
                 }
                 Action::Render => {
-                    // Render action is explicit, so render immediately
+                    // coalesced with other requests until the next frame
                     need_render = true;
                 }
                 _ => {}
             }
+            self.dispatch(&action)?;
         }
 
         if quit {
@@ -169,10 +387,10 @@ impl Runtime {
             self.handle_resize(tui, w, h)?;
         }
 
-        if suspend {
+        // there's no shell job to go back to in the Windows console, and on
+        // unix SIGCONT brings yap back, see `forward_signals`
+        if suspend && cfg!(unix) {
             tui.suspend()?;
-            // wait until resume
-            self.action_tx.send(Action::Resume)?;
         }
 
         if resume {
@@ -182,19 +400,37 @@ impl Runtime {
         }
 
         if need_render {
-            self.render(tui)?;
+            self.scheduler.request();
         }
 
         Ok(false)
     }
 
-    fn handle_resize(&mut self, tui: &mut Tui, w: u16, h: u16) -> color_eyre::Result<()> {
+    // hand the action to every component, forwarding whatever they produce
+    fn dispatch(&mut self, action: &Action) -> color_eyre::Result<()> {
+        if *action == Action::Render {
+            return Ok(());
+        }
+        // global keybindings are looked up here
+        if let Action::ConfigReloaded(reloaded) = action {
+            self.config = reloaded.config();
+        }
+        for component in self.components.iter_mut() {
+            if let Some(action) = component.update(action.clone())? {
+                self.action_tx.send(action)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_resize<B: Backend>(&mut self, tui: &mut Tui<B>, w: u16, h: u16) -> color_eyre::Result<()> {
         tui.resize(Rect::new(0, 0, w, h))?;
         self.render(tui)?;
         Ok(())
     }
 
-    fn render(&mut self, tui: &mut Tui) -> color_eyre::Result<()> {
+    fn render<B: Backend>(&mut self, tui: &mut Tui<B>) -> color_eyre::Result<()> {
+        self.scheduler.rendered(tokio::time::Instant::now());
         tui.draw(|frame| {
             for component in self.components.iter_mut() {
                 if let Err(err) = component.render(frame, frame.area()) {
@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Coalesces render requests and caps how often the runtime redraws.
+///
+/// Requests only set a dirty flag; the runtime asks for the `deadline` of the
+/// next frame and draws once it passes, so a burst of `Updater::update` calls
+/// between two frames costs a single redraw and clean frames are skipped.
+#[derive(Clone, Debug)]
+pub struct RenderScheduler {
+    interval: Duration,
+    last_frame: Option<Instant>,
+    dirty: bool,
+}
+
+impl RenderScheduler {
+    /// A scheduler drawing at most `max_fps` frames per second.
+    pub fn new(max_fps: u16) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / u32::from(max_fps.max(1)),
+            last_frame: None,
+            dirty: false,
+        }
+    }
+
+    /// Mark the screen as needing a redraw.
+    pub fn request(&mut self) {
+        self.dirty = true;
+    }
+
+    /// When the next frame should be drawn, or `None` if nothing changed.
+    pub fn deadline(&self) -> Option<Instant> {
+        if !self.dirty {
+            return None;
+        }
+        Some(match self.last_frame {
+            Some(last_frame) => last_frame + self.interval,
+            None => Instant::now(),
+        })
+    }
+
+    /// Record that a frame was drawn at `now`.
+    pub fn rendered(&mut self, now: Instant) {
+        self.dirty = false;
+        self.last_frame = Some(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_clean_frames_are_skipped() {
+        let scheduler = RenderScheduler::new(30);
+        assert_eq!(scheduler.deadline(), None);
+    }
+
+    #[test]
+    fn test_requests_are_coalesced_and_capped() {
+        let mut scheduler = RenderScheduler::new(10);
+        let now = Instant::now();
+        scheduler.rendered(now);
+
+        scheduler.request();
+        scheduler.request();
+        assert_eq!(scheduler.deadline(), Some(now + Duration::from_millis(100)));
+
+        scheduler.rendered(now + Duration::from_millis(100));
+        assert_eq!(scheduler.deadline(), None);
+    }
+}
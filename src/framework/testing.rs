@@ -0,0 +1,382 @@
+//! Headless harness for component tests: key events go in the way the
+//! runtime delivers them, and the frame comes out of ratatui's `TestBackend`
+//! as text to compare against a snapshot file.
+//!
+//! [`Script`] plays timed events into [`super::Runtime::scripted`] for tests
+//! of the whole app, proxy included. [`TempDir`] and [`capture`] set up the
+//! files that tests of captures read back.
+//!
+//! Snapshots live in `src/snapshots/<name>.snap`. A missing one is written
+//! and fails the test so it gets reviewed; `UPDATE_SNAPSHOTS=1 cargo test`
+//! rewrites the ones that changed.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use crossterm::event::KeyEvent;
+use ratatui::{Terminal, backend::TestBackend, buffer::Buffer, layout::Size};
+use tokio::{sync::mpsc, time::Instant};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+use super::{Action, Component, Updater};
+use crate::{
+    app::Mode,
+    config::{self, Config},
+    tui::Event,
+};
+
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+/// A path under the system temp directory that no other test uses.
+fn unique_dir() -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "yap-test-{}-{}",
+        std::process::id(),
+        NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// The bundled config, with data, config and capture directories of its own.
+pub fn config() -> Config {
+    let mut config = Config::bundled();
+    let dir = unique_dir();
+    config.config.data_dir = dir.join("data");
+    config.config.config_dir = dir.join("config");
+    config.capture.dir = Some(dir.join("captures"));
+    config
+}
+
+/// A directory of its own under the system temp directory, removed when
+/// dropped, even by a failing test.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new() -> Self {
+        let dir = unique_dir();
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        Self(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A capture file the way the proxy writes it, built up for a test.
+pub struct Capture {
+    method: String,
+    uri: String,
+    status: u16,
+    timestamp: Option<DateTime<Utc>>,
+    request_headers: Vec<(String, String)>,
+    request_body: Option<Vec<u8>>,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+/// A capture of `method uri` answered with `status`, without headers or bodies.
+pub fn capture(method: &str, uri: &str, status: u16) -> Capture {
+    Capture {
+        method: method.to_string(),
+        uri: uri.to_string(),
+        status,
+        timestamp: None,
+        request_headers: Vec::new(),
+        request_body: None,
+        headers: Vec::new(),
+        body: String::new(),
+    }
+}
+
+impl Capture {
+    /// `timestamp` in RFC 3339, e.g. `2026-10-17T09:00:00+00:00`.
+    pub fn timestamp(mut self, timestamp: &str) -> Self {
+        self.timestamp = Some(DateTime::parse_from_rfc3339(timestamp).expect("timestamp").with_timezone(&Utc));
+        self
+    }
+
+    pub fn request_header(mut self, name: &str, value: &str) -> Self {
+        self.request_headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Stored beside the capture in a `.req` file, as the proxy does.
+    pub fn request_body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.request_body = Some(body.into());
+        self
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn body(mut self, body: &str) -> Self {
+        self.body = body.to_string();
+        self
+    }
+
+    /// Write the capture to `path`, and the request body beside it, creating
+    /// the directories on the way.
+    pub fn write(&self, path: &Path) {
+        std::fs::create_dir_all(path.parent().expect("parent")).expect("capture dir");
+        let mut content = String::from("=== HTTP Response ===\n");
+        if let Some(timestamp) = self.timestamp {
+            content.push_str(&format!("Timestamp: {}\n", timestamp.to_rfc3339()));
+        }
+        content.push_str(&format!("Method: {}\nURI: {}\nStatus: {}\n", self.method, self.uri, self.status));
+        if let Some(body) = &self.request_body {
+            let body_path = path.with_extension("req");
+            std::fs::write(&body_path, body).expect("request body");
+            content.push_str(&format!("[Request body stored in: {}]\n", body_path.display()));
+        }
+        content.push_str(&format!(
+            "\nRequest Headers:\n{}\nResponse Headers:\n{}\nResponse Body:\n{}",
+            Headers(&self.request_headers),
+            Headers(&self.headers),
+            if self.body.is_empty() { "[Empty]\n".to_string() } else { format!("{}\n", self.body) }
+        ));
+        std::fs::write(path, content).expect("capture");
+    }
+}
+
+struct Headers<'a>(&'a [(String, String)]);
+
+impl fmt::Display for Headers<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|(name, value)| writeln!(f, "  {}: {}", name, value))
+    }
+}
+
+/// Keys written like the keybindings, between plain characters: `10j<enter>`.
+pub fn parse_keys(keys: &str) -> Vec<KeyEvent> {
+    let mut parsed = Vec::new();
+    let mut rest = keys;
+    while let Some(c) = rest.chars().next() {
+        let len = match rest.find('>') {
+            Some(end) if c == '<' && end > 1 => end + 1,
+            _ => c.len_utf8(),
+        };
+        let name = &rest[..len];
+        let name = name.strip_prefix('<').and_then(|name| name.strip_suffix('>')).unwrap_or(name);
+        parsed.push(config::parse_key_sequence(&format!("<{}>", name)).expect("key")[0]);
+        rest = &rest[len..];
+    }
+    parsed
+}
+
+/// `buffer` as text, one line per row without trailing spaces.
+pub fn screen_text(buffer: &Buffer) -> String {
+    let width = buffer.area.width as usize;
+    let symbols: Vec<&str> = buffer.content.iter().map(|cell| cell.symbol()).collect();
+    symbols
+        .chunks(width)
+        .map(|row| row.concat().trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A mounted component, drawn on a terminal of a fixed size.
+pub struct Harness<C: Component> {
+    pub component: C,
+    terminal: Terminal<TestBackend>,
+    config: Config,
+    actions: mpsc::UnboundedReceiver<Action>,
+    /// Every action the component produced or sent through its updater,
+    /// in order, renders left out.
+    pub emitted: Vec<Action>,
+    /// Kept so background tasks see the app as running.
+    _shutdown: CancellationToken,
+}
+
+impl<C: Component> Harness<C> {
+    /// Mount `component` with the bundled config and a data directory of its own.
+    pub fn new(component: C, width: u16, height: u16) -> Self {
+        Self::with_config(component, config(), width, height)
+    }
+
+    pub fn with_config(mut component: C, config: Config, width: u16, height: u16) -> Self {
+        let (tx, actions) = mpsc::unbounded_channel();
+        let shutdown = CancellationToken::new();
+        let updater = Updater::new(tx, shutdown.clone(), TaskTracker::new());
+        component.component_will_mount(config.clone()).expect("component_will_mount");
+        component
+            .component_did_mount(Size::new(width, height), updater)
+            .expect("component_did_mount");
+        Self {
+            component,
+            terminal: Terminal::new(TestBackend::new(width, height)).expect("test terminal"),
+            config,
+            actions,
+            emitted: Vec::new(),
+            _shutdown: shutdown,
+        }
+    }
+
+    /// Where the component keeps its data, e.g. to put capture files in.
+    pub fn data_dir(&self) -> PathBuf {
+        self.config.config.data_dir.clone()
+    }
+
+    /// Type `keys`: characters, and keys written like the keybindings, e.g.
+    /// `10j<enter>`. Global bindings apply unless the component captures the key.
+    pub fn keys(&mut self, keys: &str) {
+        for key in parse_keys(keys) {
+            if !self.component.captures_key(key)
+                && let Some(action) = self.config.keybindings.action_for(Mode::Home, key).cloned()
+            {
+                self.dispatch(action);
+            }
+            let action = self.component.handle_events(Some(Event::Key(key))).expect("handle_events");
+            if let Some(action) = action {
+                self.dispatch(action);
+            }
+        }
+    }
+
+    /// Hand `action` to the component's `update`, and whatever it answers
+    /// after it, like the runtime does.
+    pub fn dispatch(&mut self, action: Action) {
+        let mut next = Some(action);
+        while let Some(action) = next.take() {
+            if action == Action::Render {
+                continue;
+            }
+            self.emitted.push(action.clone());
+            next = self.component.update(action).expect("update");
+        }
+    }
+
+    /// Change the terminal to `width` by `height` and tell the component, as
+    /// the runtime does when the window is resized.
+    pub fn resize(&mut self, width: u16, height: u16) {
+        self.terminal.backend_mut().resize(width, height);
+        self.dispatch(Action::Resize(width, height));
+    }
+
+    /// Run the commands the component queued and deliver what they and the
+    /// updater sent, until nothing more happens.
+    pub async fn settle(&mut self) {
+        loop {
+            let mut idle = true;
+            while let Ok(action) = self.actions.try_recv() {
+                idle = false;
+                self.dispatch(action);
+            }
+            for command in self.component.take_commands() {
+                idle = false;
+                if let Some(action) = command.run().await {
+                    self.dispatch(action);
+                }
+            }
+            if idle {
+                return;
+            }
+        }
+    }
+
+    /// Draw the component over the whole terminal and return the screen,
+    /// one line per row without trailing spaces.
+    pub fn render(&mut self) -> String {
+        let component = &mut self.component;
+        self.terminal
+            .draw(|frame| component.render(frame, frame.area()).expect("render"))
+            .expect("draw");
+        screen_text(self.terminal.backend().buffer())
+    }
+
+    /// Render and compare the screen with the snapshot `name`.
+    pub fn assert_snapshot(&mut self, name: &str) {
+        let screen = self.render();
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/snapshots").join(format!("{}.snap", name));
+        let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+        match std::fs::read_to_string(&path) {
+            Ok(expected) if !update => pretty_assertions::assert_eq!(
+                expected.trim_end_matches('\n'),
+                screen,
+                "snapshot {} changed, rerun with UPDATE_SNAPSHOTS=1 to accept it",
+                name
+            ),
+            found => {
+                std::fs::create_dir_all(path.parent().unwrap()).expect("snapshot directory");
+                std::fs::write(&path, format!("{}\n", screen)).expect("write snapshot");
+                assert!(found.is_ok(), "new snapshot {} written, review it and run again", path.display());
+            }
+        }
+    }
+}
+
+/// Terminal events for [`super::Runtime::scripted`], each due some time after
+/// the app starts. The app quits after the last one, or when [`Script::quit`] says.
+#[derive(Clone, Debug)]
+pub struct Script {
+    width: u16,
+    height: u16,
+    steps: Vec<(Duration, Event)>,
+    quit: Duration,
+}
+
+impl Script {
+    /// An empty script for a terminal of `width` by `height`.
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            steps: Vec::new(),
+            quit: Duration::ZERO,
+        }
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+
+    /// Press `keys`, written as for [`Harness::keys`], `at` after the start.
+    pub fn keys(mut self, at: Duration, keys: &str) -> Self {
+        self.steps.extend(parse_keys(keys).into_iter().map(|key| (at, Event::Key(key))));
+        self
+    }
+
+    /// Deliver `event`, e.g. a paste or focus change, `at` after the start.
+    pub fn event(mut self, at: Duration, event: Event) -> Self {
+        self.steps.push((at, event));
+        self
+    }
+
+    /// Quit no sooner than `at` after the start, e.g. to give requests
+    /// time to finish after the last key.
+    pub fn quit(mut self, at: Duration) -> Self {
+        self.quit = at;
+        self
+    }
+
+    /// Send the events in time order, ones due together in the order they
+    /// were added, then [`Event::Quit`].
+    pub async fn play(mut self, events: mpsc::UnboundedSender<Event>, cancel: CancellationToken) {
+        let start = Instant::now();
+        self.steps.sort_by_key(|(at, _)| *at);
+        let steps = std::iter::once((Duration::ZERO, Event::Init))
+            .chain(self.steps)
+            .chain(std::iter::once((self.quit, Event::Quit)));
+        for (at, event) in steps {
+            tokio::select! {
+                _ = tokio::time::sleep_until(start + at) => {}
+                _ = cancel.cancelled() => return,
+            }
+            if events.send(event).is_err() {
+                return;
+            }
+        }
+    }
+}
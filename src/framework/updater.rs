@@ -1,21 +1,56 @@
-use std::fmt::Display;
+use std::{fmt::Display, future::Future};
 
 use tokio::sync::mpsc::UnboundedSender;
+use tokio_util::{
+    sync::{CancellationToken, WaitForCancellationFutureOwned},
+    task::TaskTracker,
+};
 
 #[derive(Clone, Debug)]
 pub struct Updater {
     tx: UnboundedSender<super::action::Action>,
+    shutdown: CancellationToken,
+    tasks: TaskTracker,
 }
 
 impl Updater {
     
-    pub fn new(tx: UnboundedSender<super::action::Action>) -> Self {
-        Self { tx }
+    pub fn new(
+        tx: UnboundedSender<super::action::Action>,
+        shutdown: CancellationToken,
+        tasks: TaskTracker,
+    ) -> Self {
+        Self { tx, shutdown, tasks }
     }
     
     pub fn update(&self) {
         let _ = self.tx.send(super::Action::Render);
     }
+
+    /// Send an action to every component's `update`, as if a key was bound to it.
+    pub fn dispatch(&self, action: super::Action) {
+        let _ = self.tx.send(action);
+    }
+
+    /// Send a component-defined message to every component's `update`.
+    pub fn send<T: std::any::Any + Send + Sync>(&self, message: T) {
+        let _ = self.tx.send(super::Action::Message(super::Message::new(message)));
+    }
+
+    /// Spawn a background task the runtime waits for before restoring the
+    /// terminal on quit. Long-running tasks must stop once `cancelled` resolves.
+    pub fn spawn<F>(&self, future: F) -> tokio::task::JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.tasks.spawn(future)
+    }
+
+    /// Resolves when the application is shutting down.
+    pub fn cancelled(&self) -> WaitForCancellationFutureOwned {
+        self.shutdown.clone().cancelled_owned()
+    }
 }
 
 impl Display for Updater {
@@ -30,4 +65,4 @@ impl PartialEq for Updater {
     }
 }
 
-impl Eq for Updater {}
\ No newline at end of file
+impl Eq for Updater {}
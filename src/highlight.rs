@@ -0,0 +1,93 @@
+//! Row styles for requests matching user rules, e.g. every 401 in bold red.
+
+use ratatui::style::Style;
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    components::{host_tree::host_of, proxy::HttpLog},
+    config::parse_style,
+    pac::sh_exp_match,
+};
+
+/// A style for the requests matching every condition that is set.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HighlightRule {
+    /// Host pattern with `*` wildcards.
+    #[serde(default)]
+    pub host: Option<String>,
+    /// Only requests whose path starts with this.
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub method: Option<String>,
+    /// A status code, or a class like `4xx`.
+    #[serde(default)]
+    pub status: Option<String>,
+    /// Like `bold red` or `black on yellow`.
+    #[serde(deserialize_with = "deserialize_style")]
+    pub style: Style,
+}
+
+fn deserialize_style<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Style, D::Error> {
+    Ok(parse_style(&String::deserialize(deserializer)?))
+}
+
+impl HighlightRule {
+    fn matches(&self, log: &HttpLog) -> bool {
+        let url = url::Url::parse(&log.uri).ok();
+        self.host.as_deref().is_none_or(|pattern| sh_exp_match(pattern, &host_of(&log.uri)))
+            && self.path.as_deref().is_none_or(|prefix| {
+                url.as_ref().is_some_and(|url| url.path().starts_with(prefix))
+            })
+            && self.method.as_deref().is_none_or(|method| method.eq_ignore_ascii_case(&log.method))
+            && self.status.as_deref().is_none_or(|pattern| log.status.is_some_and(|s| status_matches(pattern, s)))
+    }
+}
+
+/// Whether `status` is `pattern`, where an `x` stands for any digit.
+pub fn status_matches(pattern: &str, status: u16) -> bool {
+    let status = status.to_string();
+    pattern.len() == status.len()
+        && pattern
+            .chars()
+            .zip(status.chars())
+            .all(|(p, s)| p.eq_ignore_ascii_case(&'x') || p == s)
+}
+
+/// The style of the rules matching `log` combined in order, so later rules
+/// win where they set the same attribute.
+pub fn style_for(rules: &[HighlightRule], log: &HttpLog) -> Option<Style> {
+    rules
+        .iter()
+        .filter(|rule| rule.matches(log))
+        .map(|rule| rule.style)
+        .reduce(Style::patch)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_style_for_combines_matching_rules() {
+        let rules: Vec<HighlightRule> = json5::from_str(
+            r#"[
+                { host: "auth.*", style: "magenta" },
+                { status: "4xx", path: "/login", style: "bold red" },
+            ]"#,
+        )
+        .unwrap();
+        let log = |uri: &str, status| HttpLog {
+            method: "POST".to_string(),
+            uri: uri.to_string(),
+            status,
+            ..Default::default()
+        };
+        assert_eq!(style_for(&rules, &log("http://auth.test/token", Some(200))), Some(parse_style("magenta")));
+        assert_eq!(style_for(&rules, &log("http://auth.test/login", Some(401))), Some(parse_style("bold red")));
+        assert_eq!(style_for(&rules, &log("http://api.test/login", None)), None);
+    }
+}
@@ -0,0 +1,225 @@
+//! JSONPath expressions like `$.items[0].id`, picking values out of a JSON
+//! body. The jq spellings of the same paths, like `.items[0].id` or
+//! `.items[].id`, work too.
+
+use std::fmt;
+
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Step {
+    /// `.name` or `['name']`.
+    Key(String),
+    /// `[2]`, or `[-1]` for the last element.
+    Index(i64),
+    /// `.*`, `[*]` or `[]`: every member or element.
+    Wildcard,
+    /// `[1:3]`: the elements from the first index up to the second.
+    Slice(Option<i64>, Option<i64>),
+    /// `..`: the value and everything nested in it, for the next step to pick from.
+    Descendants,
+}
+
+/// A parsed expression, kept with its source for messages.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonPath {
+    source: String,
+    steps: Vec<Step>,
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonPath {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        JsonPath::parse(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
+
+impl JsonPath {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let source = source.trim();
+        // jq's identity
+        let mut rest = if source == "." { "" } else { source.strip_prefix('$').unwrap_or(source) };
+        let mut steps = Vec::new();
+        while let Some(c) = rest.chars().next() {
+            let at = source.len() - rest.len();
+            match c {
+                '.' => {
+                    rest = &rest[1..];
+                    if let Some(after) = rest.strip_prefix('.') {
+                        steps.push(Step::Descendants);
+                        rest = after;
+                    }
+                    // `.[0]` in jq
+                    if rest.starts_with('[') {
+                        continue;
+                    }
+                    let end = rest.find(['.', '[']).unwrap_or(rest.len());
+                    let key = &rest[..end];
+                    steps.push(match key {
+                        "" => return Err(format!("a name has to follow the `.` at {}", at)),
+                        "*" => Step::Wildcard,
+                        key => Step::Key(key.to_string()),
+                    });
+                    rest = &rest[end..];
+                }
+                '[' => {
+                    let end = bracket_end(rest).ok_or_else(|| format!("the `[` at {} is never closed", at))?;
+                    let inside = rest[1..end].trim();
+                    steps.push(if inside == "*" || inside.is_empty() {
+                        Step::Wildcard
+                    } else if let Some(key) = quoted(inside) {
+                        Step::Key(key.to_string())
+                    } else if let Some((start, end)) = inside.split_once(':') {
+                        let bound = |bound: &str| match bound.trim() {
+                            "" => Ok(None),
+                            bound => index(bound, at).map(Some),
+                        };
+                        Step::Slice(bound(start)?, bound(end)?)
+                    } else {
+                        Step::Index(index(inside, at)?)
+                    });
+                    rest = &rest[end + 1..];
+                }
+                c => return Err(format!("expected `.` or `[` at {}, found `{}`", at, c)),
+            }
+        }
+        Ok(Self {
+            source: source.to_string(),
+            steps,
+        })
+    }
+
+    /// The values at this path in `root`, in document order.
+    pub fn select<'a>(&self, root: &'a Value) -> Vec<&'a Value> {
+        let mut values = vec![root];
+        for step in &self.steps {
+            values = values
+                .into_iter()
+                .flat_map(|value| -> Vec<&Value> {
+                    match (step, value) {
+                        (Step::Key(key), Value::Object(map)) => map.get(key).into_iter().collect(),
+                        (Step::Index(index), Value::Array(items)) => {
+                            let index = position(*index, items.len());
+                            (index < items.len()).then(|| &items[index]).into_iter().collect()
+                        }
+                        (Step::Slice(start, end), Value::Array(items)) => {
+                            let start = start.map_or(0, |start| position(start, items.len()).min(items.len()));
+                            let end = end.map_or(items.len(), |end| position(end, items.len()).min(items.len()));
+                            items.get(start..end.max(start)).unwrap_or_default().iter().collect()
+                        }
+                        (Step::Wildcard, Value::Object(map)) => map.values().collect(),
+                        (Step::Wildcard, Value::Array(items)) => items.iter().collect(),
+                        (Step::Descendants, value) => {
+                            let mut all = Vec::new();
+                            descendants(value, &mut all);
+                            all
+                        }
+                        _ => Vec::new(),
+                    }
+                })
+                .collect();
+        }
+        values
+    }
+}
+
+fn index(text: &str, at: usize) -> Result<i64, String> {
+    text.parse().map_err(|_| format!("`{}` at {} is not an index", text, at))
+}
+
+/// `index` into `len` elements, counting back from the end when negative;
+/// `len` or beyond when out of range.
+fn position(index: i64, len: usize) -> usize {
+    if index < 0 {
+        usize::try_from(len as i64 + index).unwrap_or(len)
+    } else {
+        usize::try_from(index).unwrap_or(usize::MAX)
+    }
+}
+
+/// `value` and everything nested in it, in document order.
+fn descendants<'a>(value: &'a Value, all: &mut Vec<&'a Value>) {
+    all.push(value);
+    match value {
+        Value::Object(map) => map.values().for_each(|value| descendants(value, all)),
+        Value::Array(items) => items.iter().for_each(|value| descendants(value, all)),
+        _ => {}
+    }
+}
+
+/// Selected values as text to paste elsewhere: a string as it is, anything
+/// else as JSON, and several values as a JSON array.
+pub fn to_text(values: &[&Value]) -> String {
+    match values {
+        [Value::String(text)] => text.clone(),
+        [value] => serde_json::to_string_pretty(value).unwrap_or_default(),
+        values => serde_json::to_string_pretty(values).unwrap_or_default(),
+    }
+}
+
+/// Where the bracket opening `text` closes, skipping quoted names.
+fn bracket_end(text: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in text.char_indices().skip(1) {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, ']') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn quoted(text: &str) -> Option<&str> {
+    ['\'', '"'].iter().find_map(|&quote| text.strip_prefix(quote)?.strip_suffix(quote))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_select() {
+        let body = json!({
+            "data": { "id": 42, "tags": ["a", "b", "c"], "a.b": true },
+            "items": [{ "id": 1 }, { "id": 2 }, { "name": "x" }],
+        });
+        let select = |path: &str| JsonPath::parse(path).unwrap().select(&body).into_iter().cloned().collect::<Vec<_>>();
+        assert_eq!(select("$.data.id"), vec![json!(42)]);
+        assert_eq!(select("$.data.tags[1]"), vec![json!("b")]);
+        assert_eq!(select("$.data.tags[-1]"), vec![json!("c")]);
+        assert_eq!(select("$.data['a.b']"), vec![json!(true)]);
+        assert_eq!(select("$.items[*].id"), vec![json!(1), json!(2)]);
+        assert_eq!(select("$.data.*").len(), 3);
+        assert_eq!(select("$"), vec![body.clone()]);
+        assert_eq!(select("$.missing.id"), Vec::<Value>::new());
+        assert_eq!(select("$.data.tags[9]"), Vec::<Value>::new());
+        assert_eq!(select("$.data.tags[1:]"), vec![json!("b"), json!("c")]);
+        assert_eq!(select("$.data.tags[:-1]"), vec![json!("a"), json!("b")]);
+        assert_eq!(select("$..id"), vec![json!(42), json!(1), json!(2)]);
+        assert_eq!(select("$..[0]"), vec![json!("a"), json!({ "id": 1 })]);
+        // jq spellings
+        assert_eq!(select(".items[].id"), vec![json!(1), json!(2)]);
+        assert_eq!(select(".data.tags.[0]"), vec![json!("a")]);
+        assert_eq!(select("."), vec![body.clone()]);
+
+        let tags = body["data"]["tags"].as_array().unwrap();
+        assert_eq!(to_text(&[&tags[0]]), "a");
+        assert_eq!(to_text(&[&body["data"]["id"], &tags[1]]), "[\n  42,\n  \"b\"\n]");
+
+        assert_eq!(JsonPath::parse("$.data.").unwrap_err(), "a name has to follow the `.` at 6");
+        assert_eq!(JsonPath::parse("$.items[0").unwrap_err(), "the `[` at 7 is never closed");
+        assert_eq!(JsonPath::parse("$.items[x]").unwrap_err(), "`x` at 7 is not an index");
+        assert_eq!(JsonPath::parse("$data").unwrap_err(), "expected `.` or `[` at 1, found `d`");
+    }
+}
@@ -0,0 +1,232 @@
+//! Per-host concurrency and rate limits on requests forwarded upstream, to
+//! see how clients cope with a throttled API.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::Instant,
+};
+use tracing::info;
+
+use crate::pac::sh_exp_match;
+
+/// Limits for every host matching `host`; each matching host gets its own
+/// budget. The first matching rule applies.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitRule {
+    /// Host pattern with `*` wildcards.
+    pub host: String,
+    /// Requests in flight at once.
+    pub max_concurrent: Option<usize>,
+    /// Requests started per second, with bursts of up to one second's worth.
+    pub per_second: Option<f64>,
+    /// Answer `429 Too Many Requests` over the limit instead of waiting.
+    pub reject: bool,
+    /// Send `Retry-After` with those answers.
+    pub retry_after: bool,
+    /// Cleared from the rules panel to keep a rule without applying it.
+    pub enabled: bool,
+    /// Requests this rule applied to this run.
+    #[serde(skip)]
+    pub hits: u64,
+}
+
+impl Default for LimitRule {
+    fn default() -> Self {
+        Self {
+            host: "*".to_string(),
+            max_concurrent: None,
+            per_second: None,
+            reject: false,
+            retry_after: true,
+            enabled: true,
+            hits: 0,
+        }
+    }
+}
+
+/// Token bucket refilled at `rate` tokens per second up to `capacity`.
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(capacity: f64, now: Instant) -> Self {
+        Self {
+            tokens: capacity,
+            updated: now,
+        }
+    }
+
+    /// Take a token, or say how long until one is available.
+    fn take(&mut self, now: Instant, rate: f64, capacity: f64) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(capacity);
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / rate))
+        }
+    }
+}
+
+struct HostBudget {
+    semaphore: Option<Arc<Semaphore>>,
+    bucket: Mutex<Bucket>,
+}
+
+/// Held while a request is forwarded; frees its concurrency slots when dropped.
+pub struct Permit {
+    _slots: Vec<OwnedSemaphorePermit>,
+}
+
+impl Permit {
+    /// Hold the slots of `other` too, for a request under two sets of limits.
+    pub fn join(mut self, other: Permit) -> Permit {
+        self._slots.extend(other._slots);
+        self
+    }
+}
+
+/// A request over a `reject` limit.
+#[derive(Debug, PartialEq)]
+pub struct Rejected {
+    /// Suggested wait, if the rule sends `Retry-After`.
+    pub retry_after: Option<Duration>,
+}
+
+#[derive(Default)]
+pub struct Limiter {
+    rules: Mutex<Vec<LimitRule>>,
+    /// Budgets by rule index and host.
+    budgets: Mutex<HashMap<(usize, String), Arc<HostBudget>>>,
+}
+
+impl Limiter {
+    pub fn new(rules: Vec<LimitRule>) -> Self {
+        Self {
+            rules: Mutex::new(rules),
+            budgets: Mutex::default(),
+        }
+    }
+
+    /// Replace the rules, starting every host over with a fresh budget.
+    pub fn set_rules(&self, rules: Vec<LimitRule>) {
+        *self.rules() = rules;
+        self.budgets.lock().unwrap().clear();
+    }
+
+    /// The rules, for the rules panel to show and change.
+    pub fn rules(&self) -> MutexGuard<'_, Vec<LimitRule>> {
+        self.rules.lock().unwrap()
+    }
+
+    /// Wait until a request to `host` may be forwarded, or reject it.
+    pub async fn acquire(&self, host: &str) -> Result<Permit, Rejected> {
+        let Some((index, rule)) = self
+            .rules()
+            .iter_mut()
+            .enumerate()
+            .find(|(_, rule)| rule.enabled && sh_exp_match(&rule.host, host))
+            .map(|(index, rule)| {
+                rule.hits += 1;
+                (index, rule.clone())
+            })
+        else {
+            return Ok(Permit { _slots: Vec::new() });
+        };
+        let rule = &rule;
+        let budget = self
+            .budgets
+            .lock()
+            .unwrap()
+            .entry((index, host.to_string()))
+            .or_insert_with(|| {
+                Arc::new(HostBudget {
+                    semaphore: rule.max_concurrent.map(|max| Arc::new(Semaphore::new(max.max(1)))),
+                    bucket: Mutex::new(Bucket::new(capacity(rule), Instant::now())),
+                })
+            })
+            .clone();
+        let reject = |wait: Duration| Rejected {
+            retry_after: rule.retry_after.then_some(wait),
+        };
+
+        if let Some(rate) = rule.per_second.filter(|rate| *rate > 0.0) {
+            loop {
+                let taken = budget.bucket.lock().unwrap().take(Instant::now(), rate, capacity(rule));
+                match taken {
+                    Ok(()) => break,
+                    Err(wait) if rule.reject => {
+                        info!("Rate limit reached for {}, rejecting", host);
+                        return Err(reject(wait));
+                    }
+                    Err(wait) => tokio::time::sleep(wait).await,
+                }
+            }
+        }
+
+        let Some(semaphore) = &budget.semaphore else {
+            return Ok(Permit { _slots: Vec::new() });
+        };
+        let permit = if rule.reject {
+            semaphore.clone().try_acquire_owned().map_err(|_| {
+                info!("Concurrency limit reached for {}, rejecting", host);
+                reject(Duration::from_secs(1))
+            })?
+        } else {
+            // the semaphore is never closed
+            semaphore.clone().acquire_owned().await.expect("limit semaphore closed")
+        };
+        Ok(Permit { _slots: vec![permit] })
+    }
+}
+
+fn capacity(rule: &LimitRule) -> f64 {
+    rule.per_second.unwrap_or(1.0).max(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_bucket_refills_at_rate() {
+        let start = Instant::now();
+        let mut bucket = Bucket::new(2.0, start);
+        assert_eq!(bucket.take(start, 2.0, 2.0), Ok(()));
+        assert_eq!(bucket.take(start, 2.0, 2.0), Ok(()));
+        assert_eq!(bucket.take(start, 2.0, 2.0), Err(Duration::from_millis(500)));
+        assert_eq!(bucket.take(start + Duration::from_millis(500), 2.0, 2.0), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_reject_over_concurrency_limit() {
+        let limiter = Limiter::new(vec![LimitRule {
+            host: "*.example.com".to_string(),
+            max_concurrent: Some(1),
+            reject: true,
+            ..Default::default()
+        }]);
+        let held = limiter.acquire("api.example.com").await.unwrap();
+        assert!(limiter.acquire("api.example.com").await.is_err());
+        // other hosts have their own budget, unmatched hosts none at all
+        assert!(limiter.acquire("www.example.com").await.is_ok());
+        assert!(limiter.acquire("other.test").await.is_ok());
+        drop(held);
+        assert!(limiter.acquire("api.example.com").await.is_ok());
+    }
+}
@@ -0,0 +1,46 @@
+//! Listening sockets for the proxy and the management API.
+
+use std::{io, net::SocketAddr};
+
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::net::TcpListener;
+
+/// Connections queued before they are accepted, as `TcpListener::bind` has it.
+const BACKLOG: i32 = 1024;
+
+/// Listen on `addr`, any address of one interface or all of them. `[::]`
+/// takes IPv4 clients too, also on the systems that keep the two apart by
+/// default, so it covers everything `0.0.0.0` does and IPv6 besides.
+pub fn bind(addr: SocketAddr) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    if addr.is_ipv6() && addr.ip().is_unspecified() {
+        socket.set_only_v6(false)?;
+    }
+    // as tokio does, so a restarted yap gets its port back right away
+    #[cfg(unix)]
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(BACKLOG)?;
+    TcpListener::from_std(socket.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use tokio::net::TcpStream;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_unspecified_ipv6_takes_both_families() {
+        let listener = bind("[::]:0".parse().unwrap()).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        for client in ["127.0.0.1", "::1"] {
+            let stream = TcpStream::connect((client, port)).await.unwrap();
+            let (_, peer) = listener.accept().await.unwrap();
+            assert_eq!(peer.port(), stream.local_addr().unwrap().port());
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! `yap load`: a saved session sent again as a lightweight load test, at the
+//! pace it was captured at, faster or slower, or at a fixed rate, with
+//! latency percentiles and error rates at the end. Requests go straight to
+//! the upstreams, not through a running proxy.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use http_body_util::BodyExt;
+use hyper::body::Bytes;
+
+use crate::{
+    components::proxy::{BodyPart, CapturedExchange, HttpLog, UpstreamClient, upstream_client},
+    config::Config,
+    connections::ConnectionStats,
+    redact::RedactConfig,
+    retry::Resend,
+    session,
+};
+
+/// How long a single request may take.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// When the requests are sent.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pace {
+    /// As captured, `2.0` being twice as fast.
+    Speed(f64),
+    /// This many requests per second, evenly spaced.
+    Rate(f64),
+}
+
+/// When each request goes out, from the start of the run, for requests
+/// captured at `timestamps` in that order.
+fn schedule(timestamps: &[DateTime<Utc>], pace: Pace) -> Vec<Duration> {
+    match pace {
+        Pace::Speed(speed) => {
+            let Some(&first) = timestamps.first() else {
+                return Vec::new();
+            };
+            timestamps
+                .iter()
+                .map(|timestamp| {
+                    let offset = (*timestamp - first).to_std().unwrap_or_default();
+                    offset.div_f64(speed)
+                })
+                .collect()
+        }
+        Pace::Rate(rate) => (0..timestamps.len()).map(|index| Duration::from_secs_f64(index as f64 / rate)).collect(),
+    }
+}
+
+/// How one request went.
+#[derive(Clone, Debug, PartialEq)]
+struct Sample {
+    /// Until the whole response was received.
+    latency: Duration,
+    /// The status, or why there is none.
+    status: Result<u16, String>,
+}
+
+/// The samples of a run.
+#[derive(Debug, Default, PartialEq)]
+struct Summary {
+    samples: Vec<Sample>,
+    elapsed: Duration,
+}
+
+/// The nearest-rank `percent` percentile of `sorted`.
+fn percentile(sorted: &[Duration], percent: f64) -> Duration {
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted.get(rank.saturating_sub(1)).copied().unwrap_or_default()
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+}
+
+impl Summary {
+    /// Server errors and requests without a response.
+    fn errors(&self) -> usize {
+        self.samples.iter().filter(|sample| !matches!(sample.status, Ok(status) if status < 500)).count()
+    }
+
+    /// The report printed at the end of a run.
+    fn render(&self) -> String {
+        let count = self.samples.len();
+        let seconds = self.elapsed.as_secs_f64();
+        let rate = if seconds > 0.0 { count as f64 / seconds } else { 0.0 };
+        let mut text = format!("Requests: {} in {:.1} s ({:.1}/s)\n", count, seconds, rate);
+
+        let mut latencies: Vec<Duration> =
+            self.samples.iter().filter(|sample| sample.status.is_ok()).map(|sample| sample.latency).collect();
+        latencies.sort();
+        if let Some(&max) = latencies.last() {
+            let percentiles: Vec<String> = [50.0, 90.0, 95.0, 99.0]
+                .iter()
+                .map(|&percent| format!("p{} {}", percent, millis(percentile(&latencies, percent))))
+                .collect();
+            let _ = writeln!(text, "Latency: {}, max {}", percentiles.join(", "), millis(max));
+        }
+
+        let mut statuses = BTreeMap::new();
+        let mut failures = BTreeMap::new();
+        for sample in &self.samples {
+            match &sample.status {
+                Ok(status) => *statuses.entry(*status).or_insert(0) += 1,
+                Err(e) => *failures.entry(e.as_str()).or_insert(0) += 1,
+            }
+        }
+        if !statuses.is_empty() {
+            let statuses: Vec<String> = statuses.iter().map(|(status, n)| format!("{} ×{}", status, n)).collect();
+            let _ = writeln!(text, "Status: {}", statuses.join(", "));
+        }
+        let errors = self.errors();
+        let share = if count > 0 { errors as f64 * 100.0 / count as f64 } else { 0.0 };
+        let failed: usize = failures.values().sum();
+        let _ = writeln!(text, "Errors: {} ({:.1}%), {} 5xx, {} failed", errors, share, errors - failed, failed);
+        for (e, n) in failures {
+            let _ = writeln!(text, "  {} ×{}", e, n);
+        }
+        text
+    }
+}
+
+/// Send `resend` straight to its upstream and time it.
+async fn send(resend: Resend, client: UpstreamClient) -> Sample {
+    let started = Instant::now();
+    let request = resend.request(None).map(|body| body.boxed());
+    let sent = async {
+        let response = client.request(request).await.map_err(|e| e.to_string())?;
+        let status = response.status().as_u16();
+        response.into_body().collect().await.map_err(|e| e.to_string())?;
+        Ok(status)
+    };
+    let status = match tokio::time::timeout(TIMEOUT, sent).await {
+        Ok(status) => status,
+        Err(_) => Err("timed out".to_string()),
+    };
+    Sample {
+        latency: started.elapsed(),
+        status,
+    }
+}
+
+/// Rebuild the requests of `logs`, leaving out HTTPS tunnels.
+async fn rebuild(logs: &[HttpLog], redact: &RedactConfig) -> color_eyre::Result<Vec<(DateTime<Utc>, Resend)>> {
+    let mut requests = Vec::with_capacity(logs.len());
+    for log in logs.iter().filter(|log| log.method != "CONNECT") {
+        let exchange = CapturedExchange::load(&log.path).await?;
+        let body = CapturedExchange::load_body(&log.path, BodyPart::Request).await?;
+        let resend = Resend::new(&log.method, &log.uri, &exchange.request_headers, Bytes::from(body), redact)
+            .map_err(|e| color_eyre::eyre::eyre!(e))?;
+        requests.push((log.timestamp, resend));
+    }
+    requests.sort_by_key(|(timestamp, _)| *timestamp);
+    Ok(requests)
+}
+
+/// Replay the session archive at `archive` at `pace` and print how it went.
+pub async fn run(archive: &Path, pace: Pace, config: Config) -> color_eyre::Result<()> {
+    // unpacked where an earlier run that died before cleaning up can't interfere
+    let dir = std::env::temp_dir().join(format!("yap-load-{}-{:08x}", std::process::id(), fastrand::u32(..)));
+    let logs = session::load(archive, &dir).await;
+    let requests = match logs {
+        Ok(logs) => rebuild(&logs, &config.capture.redact).await,
+        Err(e) => Err(e.into()),
+    };
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+    let requests = requests?;
+    if requests.is_empty() {
+        println!("No requests in {}", archive.display());
+        return Ok(());
+    }
+
+    let mut masked: Vec<&str> =
+        requests.iter().flat_map(|(_, resend)| resend.masked.iter().map(String::as_str)).collect();
+    masked.sort_unstable_by_key(|name| name.to_ascii_lowercase());
+    masked.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+    if !masked.is_empty() {
+        println!("Leaving out headers masked in the captures: {}", masked.join(", "));
+    }
+
+    let timestamps: Vec<DateTime<Utc>> = requests.iter().map(|(timestamp, _)| *timestamp).collect();
+    let offsets = schedule(&timestamps, pace);
+    let span = offsets.last().copied().unwrap_or_default();
+    println!("Sending {} requests from {} over {:.1} s", requests.len(), archive.display(), span.as_secs_f64());
+
+    let client = upstream_client(&config.proxy.upstream, &config.proxy.dns, ConnectionStats::default())?;
+    let started = tokio::time::Instant::now();
+    // open loop: each request leaves on time whether or not earlier ones were answered
+    let tasks: Vec<_> = requests
+        .into_iter()
+        .zip(offsets)
+        .map(|((_, resend), offset)| {
+            let client = client.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep_until(started + offset).await;
+                send(resend, client).await
+            })
+        })
+        .collect();
+    let mut summary = Summary::default();
+    for task in tasks {
+        summary.samples.push(task.await?);
+    }
+    summary.elapsed = started.elapsed();
+    print!("{}", summary.render());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn sample(millis: u64, status: Result<u16, &str>) -> Sample {
+        Sample {
+            latency: Duration::from_millis(millis),
+            status: status.map_err(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_schedule_and_summary() {
+        let first = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let timestamps = [first, first + chrono::Duration::seconds(1), first + chrono::Duration::seconds(4)];
+        let millis = |offsets: Vec<Duration>| offsets.iter().map(Duration::as_millis).collect::<Vec<_>>();
+        assert_eq!(millis(schedule(&timestamps, Pace::Speed(2.0))), vec![0, 500, 2000]);
+        assert_eq!(millis(schedule(&timestamps, Pace::Rate(4.0))), vec![0, 250, 500]);
+
+        let sorted: Vec<Duration> = (1..=10).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&sorted, 50.0), Duration::from_millis(5));
+        assert_eq!(percentile(&sorted, 99.0), Duration::from_millis(10));
+        assert_eq!(percentile(&[], 50.0), Duration::ZERO);
+
+        let summary = Summary {
+            samples: vec![
+                sample(10, Ok(200)),
+                sample(20, Ok(200)),
+                sample(30, Ok(503)),
+                sample(60_000, Err("timed out")),
+            ],
+            elapsed: Duration::from_secs(2),
+        };
+        assert_eq!(
+            summary.render(),
+            "Requests: 4 in 2.0 s (2.0/s)\n\
+             Latency: p50 20.0 ms, p90 30.0 ms, p95 30.0 ms, p99 30.0 ms, max 30.0 ms\n\
+             Status: 200 ×2, 503 ×1\n\
+             Errors: 2 (50.0%), 1 5xx, 1 failed\n\
+             \x20 timed out ×1\n"
+        );
+    }
+}
@@ -1,15 +1,54 @@
 use clap::Parser;
-use cli::Cli;
+use cli::{Cli, Commands, ConfigCommands};
 
 use crate::app::App;
 
+mod access_log;
+mod api;
 mod app;
+mod assertions;
+mod auth;
+mod batch;
+mod ca;
 mod cli;
+mod client;
+mod clients;
+mod clipboard;
 mod components;
+mod compression;
 mod config;
+mod connections;
+mod control;
+mod demo;
+mod dns;
 mod errors;
+mod external;
+mod faults;
 mod framework;
+mod highlight;
+mod json_path;
+mod limits;
+mod listener;
+mod load;
 mod logging;
+mod openapi;
+mod otlp;
+mod pac;
+mod redact;
+mod recovery;
+mod reload;
+mod replay;
+mod retention;
+mod retry;
+mod rewrite;
+mod scripts;
+mod session;
+mod state;
+mod tee;
+mod theme;
+mod tls;
+mod tokens;
+mod transfers;
 mod tui;
 
 #[tokio::main(flavor = "current_thread")]
@@ -17,8 +56,30 @@ async fn main() -> color_eyre::Result<()> {
     crate::errors::init()?;
     crate::logging::init()?;
 
-    let _args = Cli::parse();
-    let mut app = App::new()?;
+    let mut args = Cli::parse();
+    match args.command {
+        Some(Commands::Config { command: ConfigCommands::Check }) => {
+            std::process::exit(if config::check::run() { 0 } else { 1 });
+        }
+        Some(Commands::Cert { command }) => return ca::run_command(command, &config::get_data_dir()),
+        // the demo works without a config, so it skips the setup
+        Some(Commands::Demo) => {
+            let upstream = demo::spawn().await?;
+            args.reverse = Some(format!("http://{}", upstream));
+        }
+        Some(Commands::Load { ref archive, speed, rps }) => {
+            let pace = rps.map_or(load::Pace::Speed(speed), load::Pace::Rate);
+            let mut config = config::Config::new()?;
+            config.proxy.upstream.tls.insecure |= args.insecure;
+            return load::run(archive, pace, config).await;
+        }
+        None => {
+            if !App::first_run().await? {
+                return Ok(());
+            }
+        }
+    }
+    let mut app = App::new(args)?;
     app.run().await?;
     Ok(())
 }
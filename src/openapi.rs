@@ -0,0 +1,356 @@
+//! Draft OpenAPI 3 documents inferred from captured traffic.
+//!
+//! Requests are grouped by host and path pattern: path segments that look
+//! like identifiers (numbers, UUIDs, long hex strings) become path
+//! parameters, query keys become query parameters, and JSON request and
+//! response bodies are turned into schemas merged across every sample of an
+//! operation.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::{Map, Value, json};
+
+/// One captured exchange, as far as the analyzer is concerned.
+#[derive(Clone, Debug, Default)]
+pub struct Sample {
+    pub method: String,
+    pub url: String,
+    pub status: Option<u16>,
+    pub content_type: Option<String>,
+    pub body: Option<String>,
+    pub request_content_type: Option<String>,
+    pub request_body: Option<String>,
+}
+
+#[derive(Default)]
+struct Operation {
+    path_params: Vec<String>,
+    query: BTreeSet<String>,
+    /// Request body schema per content type.
+    request_body: BTreeMap<String, Option<Value>>,
+    /// Schema per status code and content type.
+    responses: BTreeMap<String, BTreeMap<String, Option<Value>>>,
+}
+
+/// Operations by path pattern, then lowercase method.
+type Paths = BTreeMap<String, BTreeMap<String, Operation>>;
+
+/// One document per host, keyed by host.
+pub fn generate(samples: &[Sample]) -> BTreeMap<String, Value> {
+    // host -> (scheme, paths)
+    let mut hosts: BTreeMap<String, (String, Paths)> = BTreeMap::new();
+    for sample in samples {
+        let Ok(url) = url::Url::parse(&sample.url) else {
+            continue;
+        };
+        let Some(host) = url.host_str() else {
+            continue;
+        };
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        };
+        let (pattern, path_params) = path_pattern(url.path());
+        let (_, paths) = hosts
+            .entry(host)
+            .or_insert_with(|| (url.scheme().to_string(), BTreeMap::new()));
+        let operation = paths
+            .entry(pattern)
+            .or_default()
+            .entry(sample.method.to_lowercase())
+            .or_default();
+        operation.path_params = path_params;
+        operation.query.extend(url.query_pairs().map(|(key, _)| key.into_owned()));
+        if let Some(content_type) = media_type(&sample.request_content_type)
+            && sample.request_body.as_deref().is_some_and(|body| !body.is_empty())
+        {
+            let schema = body_schema(&content_type, &sample.request_body);
+            add_schema(operation.request_body.entry(content_type).or_default(), schema);
+        }
+
+        let Some(status) = sample.status else {
+            continue;
+        };
+        let Some(content_type) = media_type(&sample.content_type) else {
+            operation.responses.entry(status.to_string()).or_default();
+            continue;
+        };
+        let schema = body_schema(&content_type, &sample.body);
+        let slot = operation
+            .responses
+            .entry(status.to_string())
+            .or_default()
+            .entry(content_type)
+            .or_default();
+        add_schema(slot, schema);
+    }
+
+    hosts
+        .into_iter()
+        .map(|(host, (scheme, paths))| {
+            let document = document(&host, &scheme, paths);
+            (host, document)
+        })
+        .collect()
+}
+
+/// The lowercase media type of a `Content-Type` value, without parameters.
+fn media_type(content_type: &Option<String>) -> Option<String> {
+    content_type
+        .as_deref()
+        .and_then(|ct| ct.split(';').next())
+        .map(|ct| ct.trim().to_lowercase())
+}
+
+/// The schema of a JSON body, if it is one.
+fn body_schema(content_type: &str, body: &Option<String>) -> Option<Value> {
+    body.as_deref()
+        .filter(|_| content_type.contains("json"))
+        .and_then(|body| serde_json::from_str::<Value>(body).ok())
+        .map(|body| infer_schema(&body))
+}
+
+/// Merge `schema` into what earlier samples left in `slot`.
+fn add_schema(slot: &mut Option<Value>, schema: Option<Value>) {
+    *slot = match (slot.take(), schema) {
+        (Some(existing), Some(schema)) => Some(merge(existing, schema)),
+        (existing, schema) => existing.or(schema),
+    };
+}
+
+fn document(host: &str, scheme: &str, paths: Paths) -> Value {
+    let paths: Map<String, Value> = paths
+        .into_iter()
+        .map(|(pattern, methods)| {
+            let methods: Map<String, Value> = methods
+                .into_iter()
+                .map(|(method, operation)| (method, operation_json(operation)))
+                .collect();
+            (pattern, Value::Object(methods))
+        })
+        .collect();
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": format!("{} (draft inferred by yap)", host),
+            "version": "0.0.0",
+        },
+        "servers": [{ "url": format!("{}://{}", scheme, host) }],
+        "paths": paths,
+    })
+}
+
+fn operation_json(operation: Operation) -> Value {
+    let mut parameters: Vec<Value> = operation
+        .path_params
+        .iter()
+        .map(|name| json!({ "name": name, "in": "path", "required": true, "schema": { "type": "string" } }))
+        .collect();
+    parameters.extend(
+        operation
+            .query
+            .iter()
+            .map(|name| json!({ "name": name, "in": "query", "schema": { "type": "string" } })),
+    );
+    let responses: Map<String, Value> = operation
+        .responses
+        .into_iter()
+        .map(|(status, contents)| {
+            let mut response = json!({ "description": format!("Observed {} response", status) });
+            if !contents.is_empty() {
+                response["content"] = content_json(contents);
+            }
+            (status, response)
+        })
+        .collect();
+    let mut json = json!({ "responses": responses });
+    if !parameters.is_empty() {
+        json["parameters"] = Value::Array(parameters);
+    }
+    if !operation.request_body.is_empty() {
+        json["requestBody"] = json!({ "content": content_json(operation.request_body) });
+    }
+    json
+}
+
+fn content_json(contents: BTreeMap<String, Option<Value>>) -> Value {
+    let content: Map<String, Value> = contents
+        .into_iter()
+        .map(|(content_type, schema)| (content_type, json!({ "schema": schema.unwrap_or(json!({})) })))
+        .collect();
+    Value::Object(content)
+}
+
+/// Whether a path segment looks like an identifier rather than a fixed name.
+fn is_identifier(segment: &str) -> bool {
+    let hex = segment.len() >= 16 && segment.chars().all(|c| c.is_ascii_hexdigit());
+    let uuid = segment.len() == 36
+        && segment
+            .chars()
+            .enumerate()
+            .all(|(i, c)| if [8, 13, 18, 23].contains(&i) { c == '-' } else { c.is_ascii_hexdigit() });
+    let number = !segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit());
+    hex || uuid || number
+}
+
+/// The path with identifier segments replaced by named parameters, e.g.
+/// `/users/42/posts` becomes `/users/{userId}/posts`, and the parameter names.
+fn path_pattern(path: &str) -> (String, Vec<String>) {
+    let mut params = Vec::new();
+    let mut previous = "";
+    let mut pattern = String::new();
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        pattern.push('/');
+        if is_identifier(segment) {
+            let base = previous.strip_suffix('s').unwrap_or(previous);
+            let mut name = if !base.is_empty() && base.chars().all(|c| c.is_ascii_alphabetic()) {
+                format!("{}Id", base)
+            } else {
+                "id".to_string()
+            };
+            if params.contains(&name) {
+                name = format!("{}{}", name, params.len() + 1);
+            }
+            pattern.push_str(&format!("{{{}}}", name));
+            params.push(name);
+        } else {
+            pattern.push_str(segment);
+        }
+        previous = segment;
+    }
+    if pattern.is_empty() {
+        pattern.push('/');
+    }
+    (pattern, params)
+}
+
+fn infer_schema(value: &Value) -> Value {
+    match value {
+        Value::Null => json!({ "nullable": true }),
+        Value::Bool(_) => json!({ "type": "boolean" }),
+        Value::Number(n) if n.is_i64() || n.is_u64() => json!({ "type": "integer" }),
+        Value::Number(_) => json!({ "type": "number" }),
+        Value::String(_) => json!({ "type": "string" }),
+        Value::Array(items) => {
+            let items = items.iter().map(infer_schema).reduce(merge).unwrap_or(json!({}));
+            json!({ "type": "array", "items": items })
+        }
+        Value::Object(fields) => {
+            let properties: Map<String, Value> = fields
+                .iter()
+                .map(|(name, value)| (name.clone(), infer_schema(value)))
+                .collect();
+            json!({ "type": "object", "properties": properties })
+        }
+    }
+}
+
+/// Combine two schemas of the same place: object properties are united,
+/// integers widen to numbers, and a `null` seen anywhere makes it nullable.
+fn merge(a: Value, b: Value) -> Value {
+    let nullable_only = |v: &Value| v.get("type").is_none() && v.get("nullable").is_some();
+    if nullable_only(&a) {
+        return with_nullable(b);
+    }
+    if nullable_only(&b) {
+        return with_nullable(a);
+    }
+    match (a.get("type").and_then(Value::as_str), b.get("type").and_then(Value::as_str)) {
+        (Some("object"), Some("object")) => {
+            let mut merged = a.clone();
+            let nullable = b.get("nullable").is_some();
+            let mut properties = a["properties"].as_object().cloned().unwrap_or_default();
+            for (name, schema) in b["properties"].as_object().cloned().unwrap_or_default() {
+                let schema = match properties.remove(&name) {
+                    Some(existing) => merge(existing, schema),
+                    None => schema,
+                };
+                properties.insert(name, schema);
+            }
+            merged["properties"] = Value::Object(properties);
+            if nullable { with_nullable(merged) } else { merged }
+        }
+        (Some("array"), Some("array")) => {
+            let mut merged = a.clone();
+            merged["items"] = merge(a["items"].clone(), b["items"].clone());
+            merged
+        }
+        (Some("integer"), Some("number")) => b,
+        _ => a,
+    }
+}
+
+fn with_nullable(mut schema: Value) -> Value {
+    if let Some(object) = schema.as_object_mut() {
+        object.insert("nullable".to_string(), Value::Bool(true));
+    }
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_path_pattern_names_identifiers() {
+        assert_eq!(
+            path_pattern("/users/42/posts/7"),
+            ("/users/{userId}/posts/{postId}".to_string(), vec!["userId".to_string(), "postId".to_string()])
+        );
+        assert_eq!(
+            path_pattern("/v1/0b8f6e0c-4b1e-4f4e-9a57-2f1d3c4b5a69"),
+            ("/v1/{id}".to_string(), vec!["id".to_string()])
+        );
+        assert_eq!(path_pattern("/"), ("/".to_string(), vec![]));
+    }
+
+    #[test]
+    fn test_schemas_merge_across_samples() {
+        let sample = |url: &str, body: &str| Sample {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            status: Some(200),
+            content_type: Some("application/json; charset=utf-8".to_string()),
+            body: Some(body.to_string()),
+            ..Default::default()
+        };
+        let documents = generate(&[
+            sample("http://api.test/users/1?fields=name", r#"{"id":1,"name":"a"}"#),
+            sample("http://api.test/users/2", r#"{"id":2,"email":null,"score":1.5}"#),
+        ]);
+        let document = &documents["api.test"];
+        let operation = &document["paths"]["/users/{userId}"]["get"];
+        assert_eq!(operation["parameters"][1]["name"], "fields");
+        let schema = &operation["responses"]["200"]["content"]["application/json"]["schema"];
+        assert_eq!(
+            schema["properties"],
+            json!({
+                "id": { "type": "integer" },
+                "name": { "type": "string" },
+                "email": { "nullable": true },
+                "score": { "type": "number" },
+            })
+        );
+    }
+
+    #[test]
+    fn test_request_bodies_get_a_schema() {
+        let sample = |body: &str| Sample {
+            method: "POST".to_string(),
+            url: "http://api.test/users".to_string(),
+            status: Some(201),
+            request_content_type: Some("application/json".to_string()),
+            request_body: Some(body.to_string()),
+            ..Default::default()
+        };
+        let documents = generate(&[sample(r#"{"name":"a"}"#), sample(r#"{"name":"b","admin":true}"#)]);
+        let operation = &documents["api.test"]["paths"]["/users"]["post"];
+        assert_eq!(
+            operation["requestBody"]["content"]["application/json"]["schema"]["properties"],
+            json!({ "name": { "type": "string" }, "admin": { "type": "boolean" } })
+        );
+        assert_eq!(operation["responses"]["201"], json!({ "description": "Observed 201 response" }));
+    }
+}
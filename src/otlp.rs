@@ -0,0 +1,291 @@
+//! OpenTelemetry export: a span per proxied request, posted as OTLP/HTTP
+//! JSON to a collector so captures land next to the services' own traces.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use http_body_util::Full;
+use hyper::{HeaderMap, Request, Uri, body::Bytes, header::CONTENT_TYPE};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use hyper_util::{
+    client::legacy::{Client, connect::HttpConnector},
+    rt::TokioExecutor,
+};
+use serde_json::{Value, json};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+use crate::{
+    components::trace,
+    config::OtlpConfig,
+    framework::Updater,
+    state::SharedState,
+    tls,
+};
+
+/// Spans waiting to be posted; more are dropped rather than queued without end.
+const QUEUE: usize = 4096;
+/// Spans posted in one request at most.
+const MAX_BATCH: usize = 512;
+/// How long a span waits for others to share its request.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+const POST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// `SPAN_KIND_CLIENT`: yap makes the request to the upstream for its client.
+const KIND_CLIENT: u8 = 3;
+const STATUS_ERROR: u8 = 2;
+
+/// What is known of a span when its request arrives.
+#[derive(Clone, Debug)]
+pub struct SpanStart {
+    trace_id: String,
+    /// The span of the client that sent the request, from its `traceparent`.
+    parent_span_id: Option<String>,
+    method: String,
+    uri: Uri,
+    timestamp: DateTime<Utc>,
+}
+
+impl SpanStart {
+    /// Joins the trace of the request's `traceparent`, or starts a new one.
+    pub fn new(method: &str, uri: &Uri, headers: &HeaderMap, timestamp: DateTime<Utc>) -> Self {
+        let (trace_id, parent_span_id) = match trace::traceparent(headers) {
+            Some((trace_id, parent)) => (trace_id, Some(parent)),
+            None => (format!("{:032x}", fastrand::u128(1..)), None),
+        };
+        Self {
+            trace_id,
+            parent_span_id,
+            method: method.to_string(),
+            uri: uri.clone(),
+            timestamp,
+        }
+    }
+
+    /// The span in OTLP JSON, once the response took `duration`.
+    pub fn finish(&self, status: u16, bytes: u64, duration: Duration) -> Value {
+        let start = self.timestamp.timestamp_nanos_opt().unwrap_or_default() as u128;
+        let end = start + duration.as_nanos();
+        let mut attributes = vec![
+            attribute("http.request.method", json!({ "stringValue": self.method })),
+            attribute("url.full", json!({ "stringValue": self.uri.to_string() })),
+            attribute("http.response.status_code", json!({ "intValue": status.to_string() })),
+            attribute("http.response.body.size", json!({ "intValue": bytes.to_string() })),
+        ];
+        if let Some(host) = self.uri.host() {
+            attributes.push(attribute("server.address", json!({ "stringValue": host })));
+        }
+        if let Some(port) = self.uri.port_u16() {
+            attributes.push(attribute("server.port", json!({ "intValue": port.to_string() })));
+        }
+        let mut span = json!({
+            "traceId": self.trace_id,
+            "spanId": format!("{:016x}", fastrand::u64(1..)),
+            "name": format!("{} {}", self.method, self.uri.path()),
+            "kind": KIND_CLIENT,
+            "startTimeUnixNano": start.to_string(),
+            "endTimeUnixNano": end.to_string(),
+            "attributes": attributes,
+        });
+        if let Some(parent) = &self.parent_span_id {
+            span["parentSpanId"] = json!(parent);
+        }
+        // client spans count 4xx as failed, like the OpenTelemetry conventions
+        if status >= 400 {
+            span["status"] = json!({ "code": STATUS_ERROR });
+        }
+        span
+    }
+}
+
+fn attribute(key: &str, value: Value) -> Value {
+    json!({ "key": key, "value": value })
+}
+
+/// The export request for `spans`.
+fn export_body(service_name: &str, spans: &[Value]) -> Value {
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [attribute("service.name", json!({ "stringValue": service_name }))],
+            },
+            "scopeSpans": [{
+                "scope": { "name": "yap", "version": env!("CARGO_PKG_VERSION") },
+                "spans": spans,
+            }],
+        }],
+    })
+}
+
+/// Hands finished spans to the task posting them to the collector.
+#[derive(Clone, Debug)]
+pub struct Exporter {
+    spans: mpsc::Sender<Value>,
+}
+
+impl Exporter {
+    /// Start exporting to `config.endpoint`; `None` when it is unset.
+    pub fn spawn(config: &OtlpConfig, state: SharedState, updater: &Updater) -> color_eyre::Result<Option<Self>> {
+        let Some(endpoint) = &config.endpoint else {
+            return Ok(None);
+        };
+        let endpoint = endpoint.trim_end_matches('/');
+        let url: Uri = if endpoint.ends_with("/v1/traces") {
+            endpoint.parse()?
+        } else {
+            format!("{}/v1/traces", endpoint).parse()?
+        };
+        info!("Exporting spans to {}", url);
+        let (spans, queue) = mpsc::channel(QUEUE);
+        let collector = Collector {
+            client: Client::builder(TokioExecutor::new()).build(
+                HttpsConnectorBuilder::new()
+                    .with_tls_config(tls::client_config(&Default::default())?)
+                    .https_or_http()
+                    .enable_http1()
+                    .build(),
+            ),
+            url,
+            headers: config.headers.clone().into_iter().collect(),
+            service_name: config.service_name.clone(),
+            state,
+            failing: false,
+        };
+        updater.spawn(collector.run(queue, updater.clone()));
+        Ok(Some(Self { spans }))
+    }
+
+    pub fn export(&self, span: Value) {
+        if self.spans.try_send(span).is_err() {
+            warn!("Span export queue is full, dropping a span");
+        }
+    }
+}
+
+struct Collector {
+    client: Client<HttpsConnector<HttpConnector>, Full<Bytes>>,
+    url: Uri,
+    headers: Vec<(String, String)>,
+    service_name: String,
+    state: SharedState,
+    /// Whether the last post failed, so a dead collector is reported once.
+    failing: bool,
+}
+
+impl Collector {
+    /// Post spans in batches until shutdown, then post what is left.
+    async fn run(mut self, mut queue: mpsc::Receiver<Value>, updater: Updater) {
+        let mut batch = Vec::new();
+        loop {
+            tokio::select! {
+                span = queue.recv() => match span {
+                    Some(span) => batch.push(span),
+                    None => return,
+                },
+                _ = updater.cancelled() => {
+                    while let Ok(span) = queue.try_recv() {
+                        batch.push(span);
+                    }
+                    for spans in batch.chunks(MAX_BATCH) {
+                        self.post(spans).await;
+                    }
+                    return;
+                }
+            }
+            // whatever arrives meanwhile goes along in the same request
+            let flush = tokio::time::sleep(FLUSH_INTERVAL);
+            tokio::pin!(flush);
+            while batch.len() < MAX_BATCH {
+                tokio::select! {
+                    span = queue.recv() => match span {
+                        Some(span) => batch.push(span),
+                        None => break,
+                    },
+                    _ = &mut flush => break,
+                }
+            }
+            self.post(&batch).await;
+            batch.clear();
+        }
+    }
+
+    async fn post(&mut self, spans: &[Value]) {
+        let result = match tokio::time::timeout(POST_TIMEOUT, self.send(spans)).await {
+            Ok(result) => result,
+            Err(_) => Err("timed out".to_string()),
+        };
+        match result {
+            Ok(()) if self.failing => {
+                self.failing = false;
+                self.state.notify(format!("Exporting spans to {} again", self.url));
+            }
+            Ok(()) => {}
+            Err(e) => {
+                error!("Failed to export {} spans to {}: {}", spans.len(), self.url, e);
+                if !self.failing {
+                    self.failing = true;
+                    self.state.error(format!("Failed to export spans to {}: {}", self.url, e));
+                }
+            }
+        }
+    }
+
+    async fn send(&self, spans: &[Value]) -> Result<(), String> {
+        let body = serde_json::to_vec(&export_body(&self.service_name, spans)).map_err(|e| e.to_string())?;
+        let mut request = Request::post(&self.url).header(CONTENT_TYPE, "application/json");
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        let request = request.body(Full::new(Bytes::from(body))).map_err(|e| e.to_string())?;
+        let response = self.client.request(request).await.map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("collector answered {}", response.status()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_span_joins_the_client_trace() {
+        let mut headers = HeaderMap::new();
+        headers.insert("traceparent", "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".parse().unwrap());
+        let uri: Uri = "http://api.test:8080/users?page=2".parse().unwrap();
+        let timestamp = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let span = SpanStart::new("GET", &uri, &headers, timestamp).finish(404, 12, Duration::from_millis(250));
+        assert_eq!(span["traceId"], "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(span["parentSpanId"], "00f067aa0ba902b7");
+        assert_eq!(span["name"], "GET /users");
+        assert_eq!(span["startTimeUnixNano"], "1700000000000000000");
+        assert_eq!(span["endTimeUnixNano"], "1700000000250000000");
+        assert_eq!(span["status"]["code"], STATUS_ERROR);
+        let attributes: Vec<String> = span["attributes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|attribute| format!("{}={}", attribute["key"].as_str().unwrap(), attribute["value"]))
+            .collect();
+        assert_eq!(
+            attributes,
+            [
+                r#"http.request.method={"stringValue":"GET"}"#,
+                r#"url.full={"stringValue":"http://api.test:8080/users?page=2"}"#,
+                r#"http.response.status_code={"intValue":"404"}"#,
+                r#"http.response.body.size={"intValue":"12"}"#,
+                r#"server.address={"stringValue":"api.test"}"#,
+                r#"server.port={"intValue":"8080"}"#,
+            ]
+        );
+
+        // without a traceparent it starts a trace of its own
+        let span = SpanStart::new("GET", &uri, &HeaderMap::new(), timestamp).finish(200, 0, Duration::ZERO);
+        assert_eq!(span["traceId"].as_str().unwrap().len(), 32);
+        assert_eq!(span.get("parentSpanId"), None);
+        assert_eq!(span.get("status"), None);
+    }
+}
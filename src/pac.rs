@@ -0,0 +1,100 @@
+//! Proxy auto-config (PAC) file served by the proxy at `/proxy.pac`.
+
+use crate::config::ProxyConfig;
+
+/// Path the PAC file is served at, on the proxy's own listener.
+pub const PAC_PATH: &str = "/proxy.pac";
+
+/// Content type browsers expect for PAC files.
+pub const PAC_CONTENT_TYPE: &str = "application/x-ns-proxy-autoconfig";
+
+/// Match `host` against a `*`/`?` wildcard pattern like PAC's `shExpMatch`,
+/// for host rules applied by the proxy itself. Case-insensitive.
+pub fn sh_exp_match(pattern: &str, host: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let host: Vec<char> = host.to_lowercase().chars().collect();
+    // position in the pattern after the last `*`, and where in the host it resumed
+    let (mut p, mut h, mut star) = (0, 0, None);
+    while h < host.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == host[h]) {
+            p += 1;
+            h += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p + 1, h));
+            p += 1;
+        } else if let Some((after, from)) = star {
+            p = after;
+            h = from + 1;
+            star = Some((after, from + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn sh_exp_matches(patterns: &[String]) -> String {
+    patterns
+        .iter()
+        .map(|pattern| format!("shExpMatch(host, {:?})", pattern))
+        .collect::<Vec<_>>()
+        .join(" || ")
+}
+
+/// Build a PAC script sending hosts matching `config.include` (or every host,
+/// if it is empty) through `proxy_addr`, except those matching `config.exclude`.
+pub fn generate(config: &ProxyConfig, proxy_addr: &str) -> String {
+    let mut script = String::from("function FindProxyForURL(url, host) {\n");
+    if !config.exclude.is_empty() {
+        script.push_str(&format!("  if ({}) return \"DIRECT\";\n", sh_exp_matches(&config.exclude)));
+    }
+    let proxy = format!("PROXY {}", proxy_addr);
+    if config.include.is_empty() {
+        script.push_str(&format!("  return {:?};\n", proxy));
+    } else {
+        script.push_str(&format!("  if ({}) return {:?};\n", sh_exp_matches(&config.include), proxy));
+        script.push_str("  return \"DIRECT\";\n");
+    }
+    script.push_str("}\n");
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_sh_exp_match() {
+        assert!(sh_exp_match("*.example.com", "api.example.com"));
+        assert!(sh_exp_match("*", "localhost"));
+        assert!(sh_exp_match("api-?.test", "API-1.test"));
+        assert!(!sh_exp_match("*.example.com", "example.com"));
+        assert!(!sh_exp_match("example.*", "other.com"));
+    }
+
+    #[test]
+    fn test_generate_everything() {
+        let pac = generate(&ProxyConfig::default(), "127.0.0.1:9999");
+        assert_eq!(pac, "function FindProxyForURL(url, host) {\n  return \"PROXY 127.0.0.1:9999\";\n}\n");
+    }
+
+    #[test]
+    fn test_generate_include_exclude() {
+        let config = ProxyConfig {
+            include: vec!["*.example.com".to_string(), "api.test".to_string()],
+            exclude: vec!["cdn.example.com".to_string()],
+            ..Default::default()
+        };
+        let pac = generate(&config, "localhost:9999");
+        assert_eq!(
+            pac,
+            "function FindProxyForURL(url, host) {\n\
+             \x20 if (shExpMatch(host, \"cdn.example.com\")) return \"DIRECT\";\n\
+             \x20 if (shExpMatch(host, \"*.example.com\") || shExpMatch(host, \"api.test\")) return \"PROXY localhost:9999\";\n\
+             \x20 return \"DIRECT\";\n\
+             }\n"
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! Restoring the previous session: the capture files a crashed or restarted
+//! yap left behind, read back into the list.
+
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::NaiveDateTime;
+use tokio::fs;
+
+use crate::{
+    access_log::AccessRecord,
+    components::proxy::{CapturedExchange, HttpLog},
+    config::SESSION_FORMAT,
+};
+
+/// The capture files under `dir`, requests restored from an archive included.
+async fn capture_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let mut entries = fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if entry.file_type().await?.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|extension| extension == "yap") {
+                files.push(path);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// The latest session under `root` other than `current` that captured
+/// anything, with how many capture files it holds.
+pub async fn last_session(root: &Path, current: &Path) -> io::Result<Option<(PathBuf, usize)>> {
+    let mut sessions = Vec::new();
+    let mut entries = fs::read_dir(root).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name();
+        let Some(started) = name.to_str().and_then(|name| NaiveDateTime::parse_from_str(name, SESSION_FORMAT).ok())
+        else {
+            continue;
+        };
+        if entry.path() != current && entry.file_type().await?.is_dir() {
+            sessions.push((started, entry.path()));
+        }
+    }
+    sessions.sort();
+    for (_, dir) in sessions.into_iter().rev() {
+        let count = capture_files(&dir).await?.len();
+        if count > 0 {
+            return Ok(Some((dir, count)));
+        }
+    }
+    Ok(None)
+}
+
+/// The access log records of the session in `dir`, rotated files included,
+/// by the time their request arrived.
+async fn access_records(dir: &Path) -> io::Result<HashMap<String, AccessRecord>> {
+    let mut records = HashMap::new();
+    let mut entries = fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if !(name.starts_with("access") && name.ends_with(".jsonl")) {
+            continue;
+        }
+        for line in fs::read_to_string(entry.path()).await?.lines() {
+            if let Ok(record) = serde_json::from_str::<AccessRecord>(line) {
+                records.insert(record.timestamp.clone(), record);
+            }
+        }
+    }
+    Ok(records)
+}
+
+/// The list entry for a capture file, timed by its access log record when
+/// there is one. `id` is left for the proxy to assign.
+fn to_log(path: PathBuf, exchange: CapturedExchange, records: &HashMap<String, AccessRecord>) -> Option<HttpLog> {
+    let timestamp = exchange.timestamp?;
+    let record = records.get(&timestamp.to_rfc3339());
+    Some(HttpLog {
+        method: exchange.method,
+        uri: exchange.uri,
+        timestamp,
+        client: exchange.client,
+        path,
+        status: exchange.status.parse().ok(),
+        duration: record.map(|record| Duration::from_millis(record.duration_ms)),
+        size: record.map(|record| record.bytes),
+        ..Default::default()
+    })
+}
+
+/// The requests captured in the session directory `dir`, oldest first.
+/// Repeated requests to a URL each come back, as each has its own capture.
+pub async fn restore(dir: &Path) -> io::Result<Vec<HttpLog>> {
+    let records = access_records(dir).await?;
+    let mut logs = Vec::new();
+    for path in capture_files(dir).await? {
+        let exchange = CapturedExchange::load(&path).await?;
+        logs.extend(to_log(path, exchange, &records));
+    }
+    logs.sort_by_key(|log| log.timestamp);
+    Ok(logs)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::framework::testing::{TempDir, capture};
+
+    #[tokio::test]
+    async fn test_last_session_and_restore() {
+        let root = TempDir::new();
+        let root = root.path();
+        let older = root.join("20260101-090000");
+        let last = root.join("20260102-090000");
+        let current = root.join("20260103-090000");
+        for dir in [&current, &root.join("notes")] {
+            fs::create_dir_all(dir).await.unwrap();
+        }
+        capture("GET", "http://a/", 200).timestamp("2026-01-01T09:00:01+00:00").write(&older.join("a.yap"));
+        capture("DELETE", "http://api.test/users/7", 204)
+            .timestamp("2026-01-02T09:00:05+00:00")
+            .write(&last.join("api.test/users/7.yap"));
+        capture("GET", "http://api.test/users", 200)
+            .timestamp("2026-01-02T09:00:01+00:00")
+            .write(&last.join("api.test/users.1.yap"));
+        capture("GET", "http://api.test/users", 500)
+            .timestamp("2026-01-02T09:00:03+00:00")
+            .write(&last.join("api.test/users.4.yap"));
+        fs::write(
+            last.join("access.jsonl"),
+            r#"{"timestamp":"2026-01-02T09:00:01+00:00","method":"GET","host":"api.test","path":"/users","status":200,"bytes":512,"duration_ms":42}"#,
+        )
+        .await
+        .unwrap();
+
+        // the running session doesn't count, even once it captured something
+        assert_eq!(last_session(root, &current).await.unwrap(), Some((last.clone(), 3)));
+        let logs = restore(&last).await.unwrap();
+        let summary: Vec<_> = logs
+            .iter()
+            .map(|log| (log.method.as_str(), log.uri.as_str(), log.status, log.duration, log.size))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("GET", "http://api.test/users", Some(200), Some(Duration::from_millis(42)), Some(512)),
+                ("GET", "http://api.test/users", Some(500), None, None),
+                ("DELETE", "http://api.test/users/7", Some(204), None, None),
+            ]
+        );
+        assert_eq!(logs[0].path, last.join("api.test/users.1.yap"));
+    }
+}
@@ -0,0 +1,100 @@
+//! Masking of sensitive header values in capture files. Requests are still
+//! forwarded with the real values.
+
+use std::borrow::Cow;
+
+use serde::Deserialize;
+
+/// What replaces a secret.
+const MASK: &str = "****";
+
+/// Headers masked even when not configured.
+const ALWAYS: [&str; 4] = ["authorization", "proxy-authorization", "cookie", "set-cookie"];
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RedactConfig {
+    pub enabled: bool,
+    /// More header names to mask, e.g. `x-api-key`.
+    pub headers: Vec<String>,
+}
+
+impl Default for RedactConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            headers: Vec::new(),
+        }
+    }
+}
+
+impl RedactConfig {
+    /// Whether values of header `name` are masked.
+    pub fn masks(&self, name: &str) -> bool {
+        self.enabled
+            && (ALWAYS.iter().any(|h| h.eq_ignore_ascii_case(name)) || self.headers.iter().any(|h| h.eq_ignore_ascii_case(name)))
+    }
+
+    /// `value` as it may be written to disk. Authorization schemes and cookie
+    /// names and attributes are kept so the capture stays readable.
+    pub fn redact<'a>(&self, name: &str, value: &'a str) -> Cow<'a, str> {
+        if !self.masks(name) {
+            return Cow::Borrowed(value);
+        }
+        let name = name.to_ascii_lowercase();
+        let masked = match name.as_str() {
+            "authorization" | "proxy-authorization" => match value.split_once(' ') {
+                Some((scheme, _)) => format!("{} {}", scheme, MASK),
+                None => MASK.to_string(),
+            },
+            "cookie" => value
+                .split(';')
+                .map(|pair| mask_cookie(pair.trim()))
+                .collect::<Vec<_>>()
+                .join("; "),
+            "set-cookie" => {
+                let (cookie, attributes) = value.split_once(';').unwrap_or((value, ""));
+                if attributes.is_empty() {
+                    mask_cookie(cookie.trim())
+                } else {
+                    format!("{};{}", mask_cookie(cookie.trim()), attributes)
+                }
+            }
+            _ => MASK.to_string(),
+        };
+        Cow::Owned(masked)
+    }
+}
+
+fn mask_cookie(pair: &str) -> String {
+    match pair.split_once('=') {
+        Some((name, _)) => format!("{}={}", name, MASK),
+        None => MASK.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_redact_keeps_structure() {
+        let config = RedactConfig {
+            headers: vec!["X-Api-Key".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(config.redact("Authorization", "Bearer abc.def"), "Bearer ****");
+        assert_eq!(config.redact("cookie", "sid=1; theme=dark"), "sid=****; theme=****");
+        assert_eq!(config.redact("set-cookie", "sid=1; Path=/; HttpOnly"), "sid=****; Path=/; HttpOnly");
+        assert_eq!(config.redact("x-api-key", "secret"), "****");
+        assert_eq!(config.redact("accept", "*/*"), "*/*");
+
+        let disabled = RedactConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        assert_eq!(disabled.redact("authorization", "Bearer abc"), "Bearer abc");
+    }
+}
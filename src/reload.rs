@@ -0,0 +1,82 @@
+//! Re-reads the configuration when its file changes, so that most settings
+//! apply without a restart.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use notify::{RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+use tracing::{error, info};
+
+use crate::{
+    config::Config,
+    framework::{Action, ReloadedConfig, Updater},
+};
+
+/// Editors save in several steps; wait for them to settle before reading.
+const SETTLE: Duration = Duration::from_millis(200);
+
+/// Whether `path` is one of the files `Config::new` reads, like `config.json5`.
+fn is_config_file(path: &Path) -> bool {
+    path.file_stem().is_some_and(|stem| stem == "config") && path.extension().is_some()
+}
+
+/// Watch the config directory and dispatch [`Action::ConfigReloaded`] after
+/// every change, or an error if the new config doesn't load.
+pub async fn watch(dir: PathBuf, updater: Updater) {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event
+            && event.paths.iter().any(|path| is_config_file(path))
+        {
+            let _ = tx.send(());
+        }
+    });
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            error!("Failed to watch the config: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+        // there is nothing to watch until a config directory is created
+        info!("Not watching {} for config changes: {}", dir.display(), e);
+        return;
+    }
+
+    loop {
+        tokio::select! {
+            changed = rx.recv() => if changed.is_none() { return },
+            _ = updater.cancelled() => return,
+        }
+        tokio::time::sleep(SETTLE).await;
+        while rx.try_recv().is_ok() {}
+
+        match Config::new() {
+            Ok(config) => {
+                info!("Reloaded the config from {}", dir.display());
+                updater.dispatch(Action::ConfigReloaded(ReloadedConfig::new(config)));
+            }
+            Err(e) => {
+                error!("Failed to reload the config: {}", e);
+                updater.dispatch(Action::Error(format!("Failed to reload the config: {}", e)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_config_file() {
+        assert!(is_config_file(Path::new("/home/me/.config/yap/config.json5")));
+        assert!(is_config_file(Path::new("config.yaml")));
+        assert!(!is_config_file(Path::new("/home/me/.config/yap/scripts")));
+        assert!(!is_config_file(Path::new("filter_history")));
+    }
+}
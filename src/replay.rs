@@ -0,0 +1,129 @@
+//! Offline mode: answer requests from earlier captures instead of the upstream.
+
+use std::path::{Path, PathBuf};
+
+use hyper::{Response, StatusCode, body::Bytes, header};
+use tokio::fs;
+use tracing::{error, info};
+
+use crate::{
+    components::proxy::{CapturedExchange, Proxy},
+    compression,
+};
+
+/// Body the capture format writes for an empty response.
+const EMPTY_BODY: &str = "[Empty]";
+
+/// The capture files that may hold `uri` under `dir`: one per exchange, and
+/// the one all exchanges with the URL shared in captures of older versions.
+async fn candidates(dir: &Path, uri: &str) -> Vec<PathBuf> {
+    let path = Proxy::uri_to_file_path(dir, uri);
+    let (Some(parent), Some(stem)) = (path.parent(), path.file_stem().and_then(|stem| stem.to_str())) else {
+        return Vec::new();
+    };
+    let Ok(mut entries) = fs::read_dir(parent).await else {
+        return Vec::new();
+    };
+    let mut found = Vec::new();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let name = entry.file_name();
+        let Some(id) = name.to_str().and_then(|name| name.strip_prefix(stem)?.strip_suffix(".yap")) else {
+            continue;
+        };
+        let per_exchange = id.strip_prefix('.').is_some_and(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()));
+        if id.is_empty() || per_exchange {
+            found.push(entry.path());
+        }
+    }
+    found
+}
+
+/// The captured response to `method uri` under `dir`, the latest if there
+/// are several.
+pub async fn lookup(dir: &Path, method: &str, uri: &str) -> Option<Response<Bytes>> {
+    let mut latest: Option<(PathBuf, CapturedExchange)> = None;
+    for path in candidates(dir, uri).await {
+        let Ok(exchange) = CapturedExchange::load(&path).await else {
+            continue;
+        };
+        // other URLs can share a file name once made safe
+        if !exchange.method.eq_ignore_ascii_case(method) || exchange.uri != uri {
+            continue;
+        }
+        if latest.as_ref().is_none_or(|(_, latest)| exchange.timestamp > latest.timestamp) {
+            latest = Some((path, exchange));
+        }
+    }
+    let (path, exchange) = latest?;
+    let body = match &exchange.body_file {
+        // the capture may have moved since, but the body sits next to it
+        Some(_) => match compression::read(&path.with_extension("bin")).await {
+            Ok(body) => Bytes::from(body),
+            Err(e) => {
+                error!("Failed to read replayed body for {}: {}", uri, e);
+                return None;
+            }
+        },
+        None if exchange.body == EMPTY_BODY => Bytes::new(),
+        None => Bytes::from(exchange.body.clone()),
+    };
+    info!("Replaying {} {} from {}", method, uri, path.display());
+    to_response(&exchange, body)
+}
+
+/// Rebuild a response from a capture. Framing headers are dropped since the
+/// body is sent in one piece, and text bodies were trimmed when captured.
+fn to_response(exchange: &CapturedExchange, body: Bytes) -> Option<Response<Bytes>> {
+    let status: StatusCode = exchange.status.parse().ok()?;
+    let mut response = Response::builder().status(status);
+    for (name, value) in &exchange.headers {
+        if [header::CONTENT_LENGTH, header::TRANSFER_ENCODING, header::CONNECTION]
+            .iter()
+            .any(|skip| skip.as_str().eq_ignore_ascii_case(name))
+        {
+            continue;
+        }
+        response = response.header(name, value);
+    }
+    response.body(body).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::framework::testing::{TempDir, capture};
+
+    #[tokio::test]
+    async fn test_lookup_serves_the_latest_capture() {
+        let dir = TempDir::new();
+        let dir = dir.path();
+        let uri = "http://api.test/users";
+        for (id, timestamp, uri, body) in [
+            (None, "2026-10-17T09:00:00+00:00", uri, "older version"),
+            (Some(3), "2026-10-17T09:00:02+00:00", uri, "latest"),
+            (Some(9), "2026-10-17T09:00:01+00:00", uri, "earlier"),
+            (Some(12), "2026-10-17T09:00:03+00:00", "http://api.test/users?", "another URL"),
+        ] {
+            let path = Proxy::capture_path(dir, uri, id);
+            capture("GET", uri, 200).timestamp(timestamp).body(body).write(&path);
+        }
+        let response = lookup(dir, "GET", uri).await.unwrap();
+        assert_eq!(response.body(), &Bytes::from("latest"));
+        assert!(lookup(dir, "POST", uri).await.is_none());
+    }
+
+    #[test]
+    fn test_to_response_drops_framing_headers() {
+        let exchange = CapturedExchange::parse(
+            "=== HTTP Response ===\nMethod: GET\nURI: http://example.com/\nStatus: 404\n\nResponse Headers:\n  content-type: text/plain\n  content-length: 99\n\nResponse Body:\nmissing\n",
+        );
+        assert_eq!(exchange.method, "GET");
+        let response = to_response(&exchange, Bytes::from(exchange.body.clone())).unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/plain");
+        assert!(!response.headers().contains_key(header::CONTENT_LENGTH));
+        assert_eq!(response.body(), &Bytes::from("missing"));
+    }
+}
@@ -0,0 +1,207 @@
+//! The capture janitor: removes earlier sessions from the capture directory,
+//! oldest first, once they are older or take more disk than
+//! `capture.retention` allows.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use chrono::NaiveDateTime;
+use tokio::fs;
+use tracing::{error, info};
+
+use crate::{
+    components::stats,
+    config::{RetentionConfig, SESSION_FORMAT},
+    framework::Updater,
+    state::SharedState,
+};
+
+/// A session directory under the capture root.
+#[derive(Clone, Debug, PartialEq)]
+struct Session {
+    path: PathBuf,
+    started: NaiveDateTime,
+    /// When its newest file was written.
+    written: SystemTime,
+    bytes: u64,
+}
+
+/// The sessions to remove, oldest first: those last written to longer than
+/// `max_age_days` ago, then as many more as it takes for the rest to fit in
+/// `max_bytes`. Sessions holding a path of `keep`, like the current one,
+/// count towards the size but stay.
+fn expired(mut sessions: Vec<Session>, keep: &[PathBuf], config: &RetentionConfig, now: SystemTime) -> Vec<Session> {
+    sessions.sort_by_key(|session| session.started);
+    let mut total: u64 = sessions.iter().map(|session| session.bytes).sum();
+    let max_age = config.max_age_days.map(|days| Duration::from_secs(days * 24 * 60 * 60));
+    let mut expired = Vec::new();
+    let kept = |session: &Session| keep.iter().any(|path| path.starts_with(&session.path));
+    for session in sessions.into_iter().filter(|session| !kept(session)) {
+        let age = now.duration_since(session.written).unwrap_or_default();
+        if max_age.is_some_and(|max| age > max) || config.max_bytes.is_some_and(|max| total > max) {
+            total -= session.bytes;
+            expired.push(session);
+        }
+    }
+    expired
+}
+
+/// Total size of the files under `dir`, and when the newest was written.
+async fn usage(dir: &Path) -> io::Result<(u64, SystemTime)> {
+    let mut bytes = 0;
+    let mut written = SystemTime::UNIX_EPOCH;
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let mut entries = fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            if metadata.is_dir() {
+                dirs.push(entry.path());
+            } else {
+                bytes += metadata.len();
+            }
+            written = written.max(metadata.modified()?);
+        }
+    }
+    Ok((bytes, written))
+}
+
+/// The session directories under `root`; anything else is left alone.
+async fn sessions(root: &Path) -> io::Result<Vec<Session>> {
+    let mut sessions = Vec::new();
+    let mut entries = fs::read_dir(root).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name();
+        let Some(started) = name.to_str().and_then(|name| NaiveDateTime::parse_from_str(name, SESSION_FORMAT).ok())
+        else {
+            continue;
+        };
+        if !entry.file_type().await?.is_dir() {
+            continue;
+        }
+        let (bytes, written) = usage(&entry.path()).await?;
+        sessions.push(Session {
+            path: entry.path(),
+            started,
+            written,
+            bytes,
+        });
+    }
+    Ok(sessions)
+}
+
+/// Remove the sessions under `root` that `config` doesn't keep, returning
+/// how many went and the space they took.
+async fn sweep(root: &Path, keep: &[PathBuf], config: &RetentionConfig) -> io::Result<(usize, u64)> {
+    let expired = expired(sessions(root).await?, keep, config, SystemTime::now());
+    let (mut removed, mut reclaimed) = (0, 0);
+    for session in expired {
+        match fs::remove_dir_all(&session.path).await {
+            Ok(()) => {
+                info!("Removed capture session {} ({} bytes)", session.path.display(), session.bytes);
+                removed += 1;
+                reclaimed += session.bytes;
+            }
+            Err(e) => error!("Failed to remove capture session {}: {}", session.path.display(), e),
+        }
+    }
+    Ok((removed, reclaimed))
+}
+
+/// Sweep the sessions under `root` every `interval_secs` until the app
+/// shuts down, starting right away, and keeping the sessions that hold a
+/// path of `keep`.
+pub async fn run(root: PathBuf, keep: Vec<PathBuf>, config: RetentionConfig, state: SharedState, updater: Updater) {
+    // compared as the paths found under the root
+    let root = fs::canonicalize(&root).await.unwrap_or(root);
+    let mut kept = Vec::with_capacity(keep.len());
+    for path in keep {
+        kept.push(fs::canonicalize(&path).await.unwrap_or(path));
+    }
+    loop {
+        match sweep(&root, &kept, &config).await {
+            Ok((0, _)) => {}
+            Ok((1, reclaimed)) => {
+                state.notify(format!("Removed 1 old capture session, {} reclaimed", stats::bytes(reclaimed)))
+            }
+            Ok((removed, reclaimed)) => state.notify(format!(
+                "Removed {} old capture sessions, {} reclaimed",
+                removed,
+                stats::bytes(reclaimed)
+            )),
+            Err(e) => {
+                error!("Failed to clean up {}: {}", root.display(), e);
+                state.error(format!("Failed to clean up {}: {}", root.display(), e));
+            }
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(config.interval_secs)) => {}
+            _ = updater.cancelled() => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn session(name: &str, days_ago: u32, bytes: u64, now: SystemTime) -> Session {
+        Session {
+            path: PathBuf::from(name),
+            started: NaiveDateTime::parse_from_str(name, SESSION_FORMAT).unwrap(),
+            written: now - DAY * days_ago,
+            bytes,
+        }
+    }
+
+    #[test]
+    fn test_expired() {
+        let now = SystemTime::now();
+        let sessions = vec![
+            session("20260103-090000", 8, 300, now),
+            session("20260101-090000", 10, 100, now),
+            session("20260105-090000", 2, 500, now),
+            session("20260110-090000", 0, 400, now),
+        ];
+        let keep = [PathBuf::from("20260110-090000")];
+        let names = |config: &RetentionConfig| -> Vec<String> {
+            expired(sessions.clone(), &keep, config, now)
+                .into_iter()
+                .map(|session| session.path.display().to_string())
+                .collect()
+        };
+
+        let by_age = RetentionConfig {
+            max_age_days: Some(7),
+            ..RetentionConfig::default()
+        };
+        assert_eq!(names(&by_age), vec!["20260101-090000", "20260103-090000"]);
+
+        // the current session counts, so 1300 bytes have to come down to 1000
+        let by_size = RetentionConfig {
+            max_bytes: Some(1000),
+            ..RetentionConfig::default()
+        };
+        assert_eq!(names(&by_size), vec!["20260101-090000", "20260103-090000"]);
+
+        // the current session is never removed, even when it alone is too large
+        let tiny = RetentionConfig {
+            max_bytes: Some(10),
+            ..RetentionConfig::default()
+        };
+        assert_eq!(names(&tiny), vec!["20260101-090000", "20260103-090000", "20260105-090000"]);
+        assert_eq!(names(&RetentionConfig::default()), Vec::<String>::new());
+
+        // nor is one replayed from
+        let keep = [PathBuf::from("20260110-090000"), PathBuf::from("20260101-090000/example.com")];
+        let expired = expired(sessions.clone(), &keep, &by_age, now);
+        assert_eq!(expired.iter().map(|session| &session.path).collect::<Vec<_>>(), vec![Path::new("20260103-090000")]);
+    }
+}
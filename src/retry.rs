@@ -0,0 +1,221 @@
+//! Sending a captured request again until it succeeds, with a pause between
+//! attempts that doubles every time, for endpoints that fail now and then.
+//! Attempts go through the proxy and are captured like any request.
+
+use std::time::Duration;
+
+use http_body_util::Full;
+use hyper::{
+    HeaderMap, Method, Request, Uri,
+    body::Bytes,
+    header::{HeaderName, HeaderValue},
+};
+use serde::Deserialize;
+
+use crate::{client::ProxyClient, redact::RedactConfig, state::SharedState};
+
+/// Marks the requests of a retry, so that the list shows them below the
+/// request they retry. The proxy removes it before forwarding.
+pub const ATTEMPT_HEADER: &str = "x-yap-attempt";
+
+/// How long a single attempt may take.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Headers not sent again: the body goes out in one piece, so its framing
+/// is redone, and the connection is yap's own.
+const FRAMING: [&str; 8] = [
+    "content-length",
+    "transfer-encoding",
+    "connection",
+    "proxy-connection",
+    "keep-alive",
+    "te",
+    "trailer",
+    "upgrade",
+];
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryConfig {
+    /// Attempts at most before giving up.
+    pub max_attempts: u32,
+    /// Pause after the first failed attempt, doubled after each further one.
+    pub delay_ms: u64,
+    /// Longest pause between two attempts.
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            delay_ms: 500,
+            max_delay_ms: 30_000,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// The pause after failed attempt `number`, counted from 1.
+    pub fn delay(&self, number: u32) -> Duration {
+        let factor = 1u64.checked_shl(number.saturating_sub(1)).unwrap_or(u64::MAX);
+        Duration::from_millis(self.delay_ms.saturating_mul(factor).min(self.max_delay_ms))
+    }
+}
+
+/// Which attempt of a retry a request is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Attempt {
+    /// Id of the request retried.
+    pub of: u64,
+    pub number: u32,
+    pub max: u32,
+}
+
+impl Attempt {
+    /// The value of [`ATTEMPT_HEADER`], e.g. `42 2/5`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let (of, count) = value.trim().split_once(' ')?;
+        let (number, max) = count.split_once('/')?;
+        Some(Self {
+            of: of.parse().ok()?,
+            number: number.parse().ok()?,
+            max: max.parse().ok()?,
+        })
+    }
+
+    fn header(&self) -> String {
+        format!("{} {}/{}", self.of, self.number, self.max)
+    }
+}
+
+/// A captured request, rebuilt to be sent again.
+#[derive(Clone, Debug)]
+pub struct Resend {
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+    /// Headers the capture masked, which are left out rather than sent as `****`.
+    pub masked: Vec<String>,
+}
+
+impl Resend {
+    pub fn new(
+        method: &str,
+        uri: &str,
+        headers: &[(String, String)],
+        body: Bytes,
+        redact: &RedactConfig,
+    ) -> Result<Self, String> {
+        let method = Method::from_bytes(method.as_bytes()).map_err(|e| format!("Invalid method {}: {}", method, e))?;
+        let uri: Uri = uri.parse().map_err(|e| format!("Invalid URL {}: {}", uri, e))?;
+        let mut resend = Self {
+            method,
+            uri,
+            headers: HeaderMap::new(),
+            body,
+            masked: Vec::new(),
+        };
+        for (name, value) in headers {
+            if FRAMING.iter().any(|framing| framing.eq_ignore_ascii_case(name)) {
+                continue;
+            }
+            if redact.masks(name) {
+                resend.masked.push(name.clone());
+                continue;
+            }
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|e| format!("Invalid header {}: {}", name, e))?;
+            let value = HeaderValue::from_str(value).map_err(|e| format!("Invalid value of {}: {}", name, e))?;
+            resend.headers.append(name, value);
+        }
+        Ok(resend)
+    }
+
+    /// `GET http://host/path`, for notices.
+    pub fn target(&self) -> String {
+        format!("{} {}", self.method, self.uri)
+    }
+
+    /// The request to send, marked as `attempt` of a retry if it is one.
+    pub fn request(&self, attempt: Option<Attempt>) -> Request<Full<Bytes>> {
+        let mut request = Request::new(Full::new(self.body.clone()));
+        *request.method_mut() = self.method.clone();
+        *request.uri_mut() = self.uri.clone();
+        *request.headers_mut() = self.headers.clone();
+        if let Some(value) = attempt.and_then(|attempt| HeaderValue::from_str(&attempt.header()).ok()) {
+            request.headers_mut().insert(ATTEMPT_HEADER, value);
+        }
+        request
+    }
+}
+
+/// Send `resend` until it gets a 2xx or 3xx response or `config.max_attempts`
+/// are used up, telling how each attempt went in the status bar. The request
+/// retried has id `of`.
+pub async fn run(resend: Resend, of: u64, config: RetryConfig, client: ProxyClient, state: SharedState) {
+    let max = config.max_attempts.max(1);
+    let target = if resend.masked.is_empty() {
+        resend.target()
+    } else {
+        format!("{} (without {})", resend.target(), resend.masked.join(", "))
+    };
+    for number in 1..=max {
+        let attempt = Attempt { of, number, max };
+        let outcome = match tokio::time::timeout(TIMEOUT, client.send(resend.request(Some(attempt)))).await {
+            Ok(Ok(response)) if response.status().is_success() || response.status().is_redirection() => {
+                state.notify(format!("{} → {} on attempt {}/{}", target, response.status(), number, max));
+                return;
+            }
+            Ok(Ok(response)) => response.status().to_string(),
+            Ok(Err(e)) => e.to_string(),
+            Err(_) => "timed out".to_string(),
+        };
+        if number == max {
+            state.error(format!("{} still failing after {} attempts: {}", target, max, outcome));
+            return;
+        }
+        let delay = config.delay(number);
+        state.notify(format!(
+            "{} → {} on attempt {}/{}, again in {:.1} s",
+            target,
+            outcome,
+            number,
+            max,
+            delay.as_secs_f64()
+        ));
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_backoff_and_resend() {
+        let config = RetryConfig::default();
+        let delays: Vec<u64> = (1..=8).map(|n| config.delay(n).as_millis() as u64).collect();
+        assert_eq!(delays, vec![500, 1000, 2000, 4000, 8000, 16000, 30000, 30000]);
+        assert_eq!(config.delay(200), Duration::from_millis(30_000));
+
+        let attempt = Attempt { of: 42, number: 2, max: 5 };
+        assert_eq!(Attempt::parse(&attempt.header()), Some(attempt));
+        assert_eq!(Attempt::parse("42"), None);
+
+        let headers = [
+            ("Content-Type".to_string(), "application/json".to_string()),
+            ("Content-Length".to_string(), "2".to_string()),
+            ("Authorization".to_string(), "Bearer ****".to_string()),
+        ];
+        let resend =
+            Resend::new("POST", "http://api.test/jobs", &headers, Bytes::from("{}"), &RedactConfig::default()).unwrap();
+        assert_eq!(resend.masked, vec!["Authorization".to_string()]);
+        let request = resend.request(Some(attempt));
+        assert_eq!(request.headers().len(), 2);
+        assert_eq!(request.headers()[ATTEMPT_HEADER], "42 2/5");
+        assert_eq!(resend.target(), "POST http://api.test/jobs");
+    }
+}
@@ -0,0 +1,74 @@
+//! URL rewrite rules applied to requests before they are forwarded.
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+/// Replace `from` with `to` in the URL of every request containing it,
+/// e.g. to send `api.example.com` traffic to a local server.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RewriteRule {
+    pub from: String,
+    pub to: String,
+    /// Cleared from the rules panel to keep a rule without applying it.
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+    /// Requests rewritten by this rule since it was added.
+    #[serde(default, skip_deserializing)]
+    pub hits: u64,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// Rules shared between the proxy and the management API, which can add to them.
+pub type SharedRules = Arc<RwLock<Vec<RewriteRule>>>;
+
+/// The URL after the first enabled rule matching `url`, if any, counting
+/// the hit on that rule.
+pub fn apply(rules: &mut [RewriteRule], url: &str) -> Option<String> {
+    let rule = rules
+        .iter_mut()
+        .find(|rule| rule.enabled && !rule.from.is_empty() && url.contains(&rule.from))?;
+    rule.hits += 1;
+    Some(url.replace(&rule.from, &rule.to))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn rule(from: &str, to: &str) -> RewriteRule {
+        RewriteRule {
+            from: from.to_string(),
+            to: to.to_string(),
+            enabled: true,
+            hits: 0,
+        }
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let mut rules = vec![
+            rule("api.example.com", "localhost:3000"),
+            rule("example.com", "example.org"),
+        ];
+        assert_eq!(
+            apply(&mut rules, "http://api.example.com/users").as_deref(),
+            Some("http://localhost:3000/users")
+        );
+        assert_eq!(apply(&mut rules, "http://example.com/").as_deref(), Some("http://example.org/"));
+        assert_eq!(apply(&mut rules, "http://other.net/"), None);
+        assert_eq!((rules[0].hits, rules[1].hits), (1, 1));
+        rules[0].enabled = false;
+        assert_eq!(
+            apply(&mut rules, "http://api.example.com/users").as_deref(),
+            Some("http://api.example.org/users")
+        );
+    }
+}
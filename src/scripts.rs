@@ -0,0 +1,376 @@
+//! User scripts run on every exchange, loaded from the `*.rhai` files of a
+//! directory and reloaded when they change.
+//!
+//! A script defines either hook or both:
+//!
+//! ```rhai
+//! // Return the request, changed or not, or a map with a `status` to answer
+//! // it without contacting the upstream.
+//! fn on_request(req) {
+//!     req.headers["x-debug"] = "1";
+//!     req
+//! }
+//!
+//! // Return the response, changed or not. A `tag` is shown in the request list.
+//! fn on_response(req, res) {
+//!     if res.status >= 500 { res.tag = "flaky"; }
+//!     res
+//! }
+//! ```
+//!
+//! Requests and responses are maps with `method`, `url`, `status`, `headers`
+//! (lowercase names) and `body`, which is `()` for bodies that aren't UTF-8.
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use hyper::{
+    HeaderMap, Method, Response, StatusCode, Uri,
+    body::Bytes,
+    header::{self, HeaderName, HeaderValue},
+    http::request::Parts,
+};
+use rhai::{AST, Dynamic, Engine, Map, Scope};
+use tracing::{error, info};
+
+use crate::{framework::Updater, state::SharedState};
+
+/// How often the script directory is checked for changes.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Upper bound on the work one hook call may do, so a runaway loop can't
+/// hang the proxy.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+struct Script {
+    path: PathBuf,
+    ast: AST,
+}
+
+/// The loaded scripts, shared by every connection.
+pub struct Scripts {
+    engine: Engine,
+    dir: PathBuf,
+    scripts: RwLock<Vec<Script>>,
+    /// Files and modification times seen by the last reload.
+    seen: RwLock<Vec<(PathBuf, Option<SystemTime>)>>,
+}
+
+pub type SharedScripts = Arc<Scripts>;
+
+impl Scripts {
+    pub fn new(dir: PathBuf) -> Self {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        Self {
+            engine,
+            dir,
+            scripts: RwLock::default(),
+            seen: RwLock::default(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scripts.read().unwrap().is_empty()
+    }
+
+    /// `*.rhai` files of the directory in name order, with modification times.
+    fn list(&self) -> Vec<(PathBuf, Option<SystemTime>)> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut files: Vec<_> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+            .map(|path| {
+                let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+                (path, modified)
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    /// Recompile the scripts if any was added, changed or removed. Returns
+    /// `None` when nothing changed, else the errors of scripts that failed to
+    /// load; those are left out until fixed.
+    pub fn reload(&self) -> Option<Vec<String>> {
+        let files = self.list();
+        if *self.seen.read().unwrap() == files {
+            return None;
+        }
+        let mut errors = Vec::new();
+        let mut scripts = Vec::new();
+        for (path, _) in &files {
+            let compiled = std::fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|source| self.engine.compile(source).map_err(|e| e.to_string()));
+            match compiled {
+                Ok(ast) => scripts.push(Script { path: path.clone(), ast }),
+                Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+        info!("Loaded {} script(s) from {}", scripts.len(), self.dir.display());
+        *self.scripts.write().unwrap() = scripts;
+        *self.seen.write().unwrap() = files;
+        Some(errors)
+    }
+
+    /// Call `hook` of every script defining it, threading the value through.
+    /// `stop` ends the chain early, e.g. once a request has been answered.
+    fn run(&self, hook: &str, request: Option<&Map>, mut value: Map, stop: impl Fn(&Map) -> bool) -> Map {
+        let arity = if request.is_some() { 2 } else { 1 };
+        for script in self.scripts.read().unwrap().iter() {
+            if !script
+                .ast
+                .iter_functions()
+                .any(|f| f.name == hook && f.params.len() == arity)
+            {
+                continue;
+            }
+            let arg = Dynamic::from_map(value.clone());
+            let result = match request {
+                Some(request) => self.engine.call_fn::<Dynamic>(
+                    &mut Scope::new(),
+                    &script.ast,
+                    hook,
+                    (Dynamic::from_map(request.clone()), arg),
+                ),
+                None => self.engine.call_fn::<Dynamic>(&mut Scope::new(), &script.ast, hook, (arg,)),
+            };
+            match result {
+                Ok(result) if result.is_unit() => {}
+                Ok(result) => match result.try_cast::<Map>() {
+                    Some(map) => value = map,
+                    None => error!("{} in {} must return a map", hook, script.path.display()),
+                },
+                Err(e) => error!("{} in {} failed: {}", hook, script.path.display(), e),
+            }
+            if stop(&value) {
+                break;
+            }
+        }
+        value
+    }
+
+    /// Run the `on_request` hooks on a request about to be forwarded, and
+    /// return the response if one of them answered it.
+    pub fn on_request(&self, parts: &mut Parts, body: &mut Bytes) -> Option<Response<Bytes>> {
+        let before = request_map(&parts.method, &parts.uri, &parts.headers, Some(body));
+        let after = self.run("on_request", None, before.clone(), |map| map.contains_key("status"));
+        if after.contains_key("status") {
+            return response_from_map(&after);
+        }
+
+        if let Some(method) = string(&after, "method")
+            && let Ok(method) = Method::from_bytes(method.as_bytes())
+        {
+            parts.method = method;
+        }
+        if let Some(url) = string(&after, "url")
+            && url != parts.uri.to_string()
+        {
+            match url.parse::<Uri>() {
+                Ok(uri) => {
+                    if let Some(authority) = uri.authority()
+                        && let Ok(host) = HeaderValue::from_str(authority.as_str())
+                    {
+                        parts.headers.insert(header::HOST, host);
+                    }
+                    parts.uri = uri;
+                }
+                Err(e) => error!("on_request set an invalid url {}: {}", url, e),
+            }
+        }
+        apply_headers(&mut parts.headers, &before, &after);
+        if let Some(new_body) = string(&after, "body")
+            && new_body.as_bytes() != body.as_ref()
+        {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            *body = Bytes::from(new_body);
+        }
+        None
+    }
+
+    /// Run the `on_response` hooks on a response about to be returned, and
+    /// return the tag they set, if any.
+    pub fn on_response(
+        &self,
+        method: &Method,
+        uri: &Uri,
+        req_headers: &HeaderMap,
+        response: &mut Response<Bytes>,
+    ) -> Option<String> {
+        let request = request_map(method, uri, req_headers, None);
+        let mut before = Map::new();
+        before.insert("status".into(), (response.status().as_u16() as i64).into());
+        before.insert("headers".into(), Dynamic::from_map(header_map(response.headers())));
+        before.insert("body".into(), body_value(response.body()));
+        let after = self.run("on_response", Some(&request), before.clone(), |_| false);
+
+        if let Some(status) = after.get("status").and_then(|s| s.as_int().ok())
+            && let Ok(status) = StatusCode::from_u16(status as u16)
+        {
+            *response.status_mut() = status;
+        }
+        apply_headers(response.headers_mut(), &before, &after);
+        if let Some(body) = string(&after, "body")
+            && body.as_bytes() != response.body().as_ref()
+        {
+            response.headers_mut().remove(header::CONTENT_LENGTH);
+            *response.body_mut() = Bytes::from(body);
+        }
+        string(&after, "tag")
+    }
+}
+
+/// Reload the scripts whenever their files change, until the app shuts down.
+pub async fn watch(scripts: SharedScripts, state: SharedState, updater: Updater) {
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(RELOAD_INTERVAL) => {}
+            _ = updater.cancelled() => return,
+        }
+        match scripts.reload() {
+            None => {}
+            Some(errors) if errors.is_empty() => state.notify("Scripts reloaded"),
+            Some(errors) => {
+                for e in &errors {
+                    error!("Failed to load script {}", e);
+                }
+                state.error(format!("Failed to load script {}", errors[0]));
+            }
+        }
+    }
+}
+
+fn string(map: &Map, key: &str) -> Option<String> {
+    map.get(key).and_then(|value| value.clone().into_string().ok())
+}
+
+fn body_value(body: &Bytes) -> Dynamic {
+    std::str::from_utf8(body).map_or(Dynamic::UNIT, |body| body.to_string().into())
+}
+
+/// Headers as a map; repeated headers are joined with `, `.
+fn header_map(headers: &HeaderMap) -> Map {
+    let mut map = Map::new();
+    for (name, value) in headers {
+        let Ok(value) = value.to_str() else {
+            continue;
+        };
+        map.entry(name.as_str().into())
+            .and_modify(|joined: &mut Dynamic| *joined = format!("{}, {}", joined, value).into())
+            .or_insert_with(|| value.to_string().into());
+    }
+    map
+}
+
+fn request_map(method: &Method, uri: &Uri, headers: &HeaderMap, body: Option<&Bytes>) -> Map {
+    let mut map = Map::new();
+    map.insert("method".into(), method.to_string().into());
+    map.insert("url".into(), uri.to_string().into());
+    map.insert("headers".into(), Dynamic::from_map(header_map(headers)));
+    map.insert("body".into(), body.map_or(Dynamic::UNIT, body_value));
+    map
+}
+
+/// Apply the header changes between `before` and `after`. Headers a script
+/// left alone are kept as they were, including repeated ones.
+fn apply_headers(headers: &mut HeaderMap, before: &Map, after: &Map) {
+    let headers_of = |map: &Map| {
+        map.get("headers")
+            .and_then(|h| h.clone().try_cast::<Map>())
+            .unwrap_or_default()
+    };
+    let (before, after) = (headers_of(before), headers_of(after));
+    for name in before.keys().filter(|name| !after.contains_key(*name)) {
+        if let Ok(name) = HeaderName::from_bytes(name.as_bytes()) {
+            headers.remove(name);
+        }
+    }
+    for (name, value) in &after {
+        if before.get(name).map(|v| v.to_string()) == Some(value.to_string()) {
+            continue;
+        }
+        match (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(&value.to_string())) {
+            (Ok(name), Ok(value)) => {
+                headers.insert(name, value);
+            }
+            _ => error!("Script set an invalid header {}: {}", name, value),
+        }
+    }
+}
+
+/// A response built by an `on_request` hook.
+fn response_from_map(map: &Map) -> Option<Response<Bytes>> {
+    let status = map.get("status")?.as_int().ok()?;
+    let mut response = Response::builder().status(status as u16);
+    if let Some(headers) = map.get("headers").and_then(|h| h.clone().try_cast::<Map>()) {
+        for (name, value) in headers {
+            response = response.header(name.as_str(), value.to_string());
+        }
+    }
+    let body = string(map, "body").unwrap_or_default();
+    response.body(Bytes::from(body)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn scripts(source: &str) -> Scripts {
+        let scripts = Scripts::new(PathBuf::new());
+        *scripts.scripts.write().unwrap() = vec![Script {
+            path: PathBuf::from("test.rhai"),
+            ast: scripts.engine.compile(source).unwrap(),
+        }];
+        scripts
+    }
+
+    fn request(uri: &str) -> Parts {
+        let (mut parts, _) = hyper::Request::builder().uri(uri).body(()).unwrap().into_parts();
+        parts.headers.append(header::ACCEPT, HeaderValue::from_static("a"));
+        parts.headers.append(header::ACCEPT, HeaderValue::from_static("b"));
+        parts
+    }
+
+    #[test]
+    fn test_on_request_changes_headers_and_url() {
+        let scripts = scripts(
+            r#"fn on_request(req) { req.url = "http://localhost:3000/"; req.headers["x-debug"] = "1"; req }"#,
+        );
+        let mut parts = request("http://example.com/");
+        let mut body = Bytes::new();
+        assert!(scripts.on_request(&mut parts, &mut body).is_none());
+        assert_eq!(parts.uri.to_string(), "http://localhost:3000/");
+        assert_eq!(parts.headers[header::HOST], "localhost:3000");
+        assert_eq!(parts.headers["x-debug"], "1");
+        // untouched repeated headers survive
+        assert_eq!(parts.headers.get_all(header::ACCEPT).iter().count(), 2);
+    }
+
+    #[test]
+    fn test_on_request_short_circuits() {
+        let scripts = scripts(r#"fn on_request(req) { #{ status: 418, body: "teapot" } }"#);
+        let response = scripts
+            .on_request(&mut request("http://example.com/"), &mut Bytes::new())
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::IM_A_TEAPOT);
+        assert_eq!(response.body(), &Bytes::from("teapot"));
+    }
+
+    #[test]
+    fn test_on_response_sets_tag() {
+        let scripts = scripts(r#"fn on_response(req, res) { if res.status >= 500 { res.tag = req.method; } res }"#);
+        let mut response = Response::builder().status(502).body(Bytes::new()).unwrap();
+        let tag = scripts.on_response(&Method::GET, &Uri::from_static("/"), &HeaderMap::new(), &mut response);
+        assert_eq!(tag.as_deref(), Some("GET"));
+    }
+}
@@ -0,0 +1,306 @@
+//! Session archives: the captured requests and their capture files in one
+//! JSON file, written with `:w` and read back with `:e`. Requests evicted
+//! under `capture.eviction: "DiskSpill"` are appended to a spill file of
+//! JSON lines instead, which `:e` reads back the same way.
+
+use std::{
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::{fs, io::AsyncWriteExt};
+
+use crate::{
+    components::{graphql::GraphqlOperation, proxy::HttpLog},
+    compression,
+};
+
+const VERSION: u32 = 1;
+
+/// Name of the spill file in the capture directory.
+pub const SPILL_FILE: &str = "spill.jsonl";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Archive {
+    version: u32,
+    saved: String,
+    exchanges: Vec<ArchivedExchange>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct ArchivedExchange {
+    method: String,
+    uri: String,
+    /// RFC 3339.
+    timestamp: String,
+    status: Option<u16>,
+    duration_ms: Option<u64>,
+    size: Option<u64>,
+    pinned: bool,
+    graphql: Option<GraphqlOperation>,
+    cookie: Option<String>,
+    set_cookies: Vec<String>,
+    tag: Option<String>,
+    fault: Option<String>,
+    listener: Option<String>,
+    client: Option<SocketAddr>,
+    client_name: Option<String>,
+    session: Option<String>,
+    trace_id: Option<String>,
+    /// Text of the capture file, unless it was never written or is gone.
+    capture: Option<String>,
+    /// Path the capture file pointed at for its body, if it had a separate one.
+    body_path: Option<String>,
+    /// That body, base64.
+    body: Option<String>,
+    /// Path the capture file pointed at for the request body.
+    request_body_path: Option<String>,
+    /// The request body, base64.
+    request_body: Option<String>,
+}
+
+impl ArchivedExchange {
+    fn new(log: &HttpLog) -> Self {
+        Self {
+            method: log.method.clone(),
+            uri: log.uri.clone(),
+            timestamp: log.timestamp.to_rfc3339(),
+            status: log.status,
+            duration_ms: log.duration.map(|d| d.as_millis() as u64),
+            size: log.size,
+            pinned: log.pinned,
+            graphql: log.graphql.as_deref().cloned(),
+            cookie: log.cookie.clone(),
+            set_cookies: log.set_cookies.clone(),
+            tag: log.tag.clone(),
+            fault: log.fault.clone(),
+            listener: log.listener.clone(),
+            client: log.client,
+            client_name: log.client_name.clone(),
+            session: log.session.clone(),
+            trace_id: log.trace_id.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// The request as a log entry whose capture file is `path`; `id` is left
+    /// for the proxy to assign.
+    fn into_log(self, path: PathBuf) -> HttpLog {
+        HttpLog {
+            method: self.method,
+            uri: self.uri,
+            timestamp: DateTime::parse_from_rfc3339(&self.timestamp)
+                .map(|t| t.with_timezone(&Utc))
+                .unwrap_or_default(),
+            path,
+            status: self.status,
+            duration: self.duration_ms.map(Duration::from_millis),
+            size: self.size,
+            pinned: self.pinned,
+            graphql: self.graphql.map(Arc::new),
+            cookie: self.cookie,
+            set_cookies: self.set_cookies,
+            tag: self.tag,
+            fault: self.fault,
+            listener: self.listener,
+            client: self.client,
+            client_name: self.client_name,
+            session: self.session,
+            trace_id: self.trace_id,
+            ..Default::default()
+        }
+    }
+}
+
+/// An evicted request in the spill file, still pointing at its capture file.
+#[derive(Debug, Serialize, Deserialize)]
+struct SpilledExchange {
+    path: PathBuf,
+    #[serde(flatten)]
+    exchange: ArchivedExchange,
+}
+
+/// Append `log` to the spill file at `path`, leaving its capture file where it is.
+pub async fn spill(path: &Path, log: &HttpLog) -> io::Result<()> {
+    let spilled = SpilledExchange {
+        path: log.path.clone(),
+        exchange: ArchivedExchange::new(log),
+    };
+    let mut line = serde_json::to_vec(&spilled)?;
+    line.push(b'\n');
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(path).await?;
+    file.write_all(&line).await?;
+    file.flush().await
+}
+
+/// Whether `path` is a spill file rather than an archive.
+fn is_spill_file(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "jsonl")
+}
+
+/// Read the requests of a spill file, oldest first.
+async fn load_spilled(path: &Path) -> io::Result<Vec<HttpLog>> {
+    let content = fs::read_to_string(path).await?;
+    // a line cut short by a crash is skipped rather than failing the rest
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<SpilledExchange>(line).ok())
+        .map(|spilled| spilled.exchange.into_log(spilled.path))
+        .collect())
+}
+
+/// Write `logs` and their capture files to `path`; returns how many were saved.
+pub async fn save(path: &Path, logs: &[Arc<HttpLog>]) -> io::Result<usize> {
+    let mut exchanges = Vec::with_capacity(logs.len());
+    for log in logs {
+        let mut exchange = ArchivedExchange::new(log);
+        exchange.capture = fs::read_to_string(&log.path).await.ok();
+        // binary and spilled bodies, and request bodies, live next to the capture file
+        if let Some((path, body)) = read_body(&exchange.capture, &log.path.with_extension("bin")).await {
+            exchange.body_path = Some(path);
+            exchange.body = Some(body);
+        }
+        if let Some((path, body)) = read_body(&exchange.capture, &log.path.with_extension("req")).await {
+            exchange.request_body_path = Some(path);
+            exchange.request_body = Some(body);
+        }
+        exchanges.push(exchange);
+    }
+    let archive = Archive {
+        version: VERSION,
+        saved: Utc::now().to_rfc3339(),
+        exchanges,
+    };
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).await?;
+    }
+    fs::write(path, serde_json::to_vec(&archive)?).await?;
+    Ok(archive.exchanges.len())
+}
+
+/// The body in the file at `path` base64, decompressed if it was stored
+/// compressed, if `capture` points at it.
+async fn read_body(capture: &Option<String>, path: &Path) -> Option<(String, String)> {
+    let path_text = path.display().to_string();
+    if !capture.as_ref()?.contains(&path_text) {
+        return None;
+    }
+    let body = compression::read(path).await.ok()?;
+    Some((path_text, base64::engine::general_purpose::STANDARD.encode(body)))
+}
+
+/// Write a body saved by [`read_body`] to `path`, pointing `capture` at it.
+async fn restore_body(capture: &mut String, old: Option<String>, body: Option<String>, path: &Path) -> io::Result<()> {
+    let (Some(old), Some(body)) = (old, body) else {
+        return Ok(());
+    };
+    let body = base64::engine::general_purpose::STANDARD
+        .decode(body)
+        .map_err(io::Error::other)?;
+    fs::write(path, body).await?;
+    *capture = capture.replace(&old, &path.display().to_string());
+    Ok(())
+}
+
+/// Read the archive at `path`, restoring its capture files into `dir`, or
+/// the spill file at `path`, whose capture files are still in place.
+pub async fn load(path: &Path, dir: &Path) -> io::Result<Vec<HttpLog>> {
+    if is_spill_file(path) {
+        return load_spilled(path).await;
+    }
+    let archive: Archive = serde_json::from_slice(&fs::read(path).await?)?;
+    if archive.version > VERSION {
+        return Err(io::Error::other(format!("unsupported archive version {}", archive.version)));
+    }
+    fs::create_dir_all(dir).await?;
+    let mut logs = Vec::with_capacity(archive.exchanges.len());
+    for (index, mut exchange) in archive.exchanges.into_iter().enumerate() {
+        let capture_path = dir.join(format!("{:05}.yap", index));
+        if let Some(mut capture) = exchange.capture.take() {
+            let (old, body) = (exchange.body_path.take(), exchange.body.take());
+            restore_body(&mut capture, old, body, &capture_path.with_extension("bin")).await?;
+            let (old, body) = (exchange.request_body_path.take(), exchange.request_body.take());
+            restore_body(&mut capture, old, body, &capture_path.with_extension("req")).await?;
+            fs::write(&capture_path, capture).await?;
+        }
+        logs.push(exchange.into_log(capture_path));
+    }
+    Ok(logs)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_exchange_round_trip() {
+        let log = HttpLog {
+            id: 7,
+            method: "POST".to_string(),
+            uri: "http://api.test/graphql".to_string(),
+            timestamp: DateTime::parse_from_rfc3339("2024-05-01T10:00:00Z").unwrap().with_timezone(&Utc),
+            path: PathBuf::from("/old/api.test/graphql.yap"),
+            status: Some(200),
+            duration: Some(Duration::from_millis(42)),
+            pinned: true,
+            tag: Some("login".to_string()),
+            listener: Some("lan".to_string()),
+            client: Some("192.168.1.23:50412".parse().unwrap()),
+            client_name: Some("pixel".to_string()),
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&ArchivedExchange::new(&log)).unwrap();
+        let restored = serde_json::from_str::<ArchivedExchange>(&json)
+            .unwrap()
+            .into_log(PathBuf::from("/new/00000.yap"));
+        assert_eq!(restored.timestamp, log.timestamp);
+        assert_eq!(restored.duration, log.duration);
+        assert_eq!((restored.pinned, restored.tag, restored.listener), (true, log.tag, log.listener));
+        assert_eq!((restored.client, restored.client_name), (log.client, log.client_name));
+        assert_eq!(restored.path, PathBuf::from("/new/00000.yap"));
+    }
+
+    #[tokio::test]
+    async fn test_spilled_requests_load_back() {
+        use crate::framework::testing::TempDir;
+
+        let dir = TempDir::new();
+        let path = dir.path().join(SPILL_FILE);
+        let log = |id, uri: &str| HttpLog {
+            id,
+            method: "GET".to_string(),
+            uri: uri.to_string(),
+            path: dir.path().join(format!("api.test/{}.yap", id)),
+            status: Some(200),
+            duration: Some(Duration::from_millis(12)),
+            size: Some(512),
+            ..Default::default()
+        };
+        spill(&path, &log(1, "http://api.test/a")).await.unwrap();
+        spill(&path, &log(2, "http://api.test/b")).await.unwrap();
+        let mut content = fs::read_to_string(&path).await.unwrap();
+        content.push_str("{\"path\":");
+        fs::write(&path, content).await.unwrap();
+
+        let loaded = load(&path, &dir.path().join("loaded")).await.unwrap();
+        let summary: Vec<_> = loaded
+            .iter()
+            .map(|log| (log.uri.as_str(), log.path.clone(), log.status, log.duration, log.size))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("http://api.test/a", dir.path().join("api.test/1.yap"), Some(200), Some(Duration::from_millis(12)), Some(512)),
+                ("http://api.test/b", dir.path().join("api.test/2.yap"), Some(200), Some(Duration::from_millis(12)), Some(512)),
+            ]
+        );
+    }
+}
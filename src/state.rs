@@ -0,0 +1,210 @@
+//! Application state that isn't owned by a single component.
+//!
+//! Components publish to it through a [`SharedState`] handle and the status
+//! bar subscribes to it, the same way the filter and the logs are shared.
+
+use std::{net::SocketAddr, time::Duration};
+
+use chrono::{DateTime, Local, Utc};
+use tokio::{sync::watch, time::Instant};
+
+use crate::app::Mode;
+
+/// How long a transient message stays in the status bar.
+pub const NOTICE_TTL: Duration = Duration::from_secs(4);
+
+/// A transient message, e.g. the result of an export or an error.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Notice {
+    pub text: String,
+    pub error: bool,
+    pub expires: Instant,
+}
+
+/// A stretch of recording started with `:start <label>`; requests arriving
+/// meanwhile are tagged with its label.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NamedSession {
+    pub label: String,
+    pub started: DateTime<Local>,
+    /// `None` while it is recording.
+    pub stopped: Option<DateTime<Local>>,
+}
+
+/// The span of time the request list is limited to, picked on the timeline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeWindow {
+    pub from: DateTime<Utc>,
+    /// Exclusive.
+    pub to: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AppState {
+    /// Addresses the proxy accepts connections on, in the order they were bound.
+    pub listen: Vec<SocketAddr>,
+    /// Whether new requests are being recorded.
+    pub capturing: bool,
+    /// Keybinding context of the focused pane.
+    pub mode: Mode,
+    pub notice: Option<Notice>,
+    /// Named sessions in the order they were started.
+    pub sessions: Vec<NamedSession>,
+    /// The named session the request list is limited to.
+    pub shown_session: Option<String>,
+    pub time_window: Option<TimeWindow>,
+    /// Whether the system trusts the root CA, `None` without one.
+    pub ca_trusted: Option<bool>,
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self {
+            listen: Vec::new(),
+            capturing: true,
+            mode: Mode::List,
+            notice: None,
+            sessions: Vec::new(),
+            shown_session: None,
+            time_window: None,
+            ca_trusted: None,
+        }
+    }
+}
+
+impl AppState {
+    /// The notice, unless it has expired by `now`.
+    pub fn notice_at(&self, now: Instant) -> Option<&Notice> {
+        self.notice.as_ref().filter(|notice| notice.expires > now)
+    }
+
+    /// Label of the named session being recorded, if any.
+    pub fn recording(&self) -> Option<&str> {
+        (self.sessions.last())
+            .filter(|session| session.stopped.is_none())
+            .map(|session| session.label.as_str())
+    }
+}
+
+/// Handle for publishing to the shared [`AppState`]. Clones publish to the
+/// same state; subscribers are only woken when a value actually changes.
+#[derive(Clone, Debug)]
+pub struct SharedState(watch::Sender<AppState>);
+
+impl Default for SharedState {
+    fn default() -> Self {
+        Self(watch::Sender::new(AppState::default()))
+    }
+}
+
+impl SharedState {
+    pub fn subscribe(&self) -> watch::Receiver<AppState> {
+        self.0.subscribe()
+    }
+
+    pub fn add_listen(&self, listen: SocketAddr) {
+        self.modify(|state| state.listen.push(listen));
+    }
+
+    pub fn set_capturing(&self, capturing: bool) {
+        self.modify(|state| state.capturing = capturing);
+    }
+
+    pub fn set_mode(&self, mode: Mode) {
+        self.modify(|state| state.mode = mode);
+    }
+
+    /// Label new requests are tagged with.
+    pub fn recording(&self) -> Option<String> {
+        self.0.borrow().recording().map(str::to_string)
+    }
+
+    /// Record into a new named session, stopping the one being recorded.
+    pub fn start_session(&self, label: &str) -> Result<(), String> {
+        if self.0.borrow().sessions.iter().any(|session| session.label == label) {
+            return Err(format!("There already is a session named {}", label));
+        }
+        let now = Local::now();
+        self.modify(|state| {
+            if let Some(running) = state.sessions.last_mut().filter(|session| session.stopped.is_none()) {
+                running.stopped = Some(now);
+            }
+            state.sessions.push(NamedSession {
+                label: label.to_string(),
+                started: now,
+                stopped: None,
+            });
+        });
+        Ok(())
+    }
+
+    /// Stop recording the named session; returns its label.
+    pub fn stop_session(&self) -> Option<String> {
+        let label = self.recording()?;
+        self.modify(|state| {
+            if let Some(running) = state.sessions.last_mut() {
+                running.stopped = Some(Local::now());
+            }
+        });
+        Some(label)
+    }
+
+    /// Limit the request list to a named session, or show every request.
+    pub fn show_session(&self, label: Option<String>) {
+        self.modify(|state| state.shown_session = label);
+    }
+
+    /// Limit the request list to the requests that arrived within `window`.
+    pub fn set_time_window(&self, window: Option<TimeWindow>) {
+        self.modify(|state| state.time_window = window);
+    }
+
+    pub fn set_ca_trusted(&self, trusted: Option<bool>) {
+        self.modify(|state| state.ca_trusted = trusted);
+    }
+
+    /// Show `text` in the status bar for [`NOTICE_TTL`].
+    pub fn notify(&self, text: impl Into<String>) {
+        self.post(text.into(), false);
+    }
+
+    /// Like [`SharedState::notify`], highlighted as an error.
+    pub fn error(&self, text: impl Into<String>) {
+        self.post(text.into(), true);
+    }
+
+    fn post(&self, text: String, error: bool) {
+        let expires = Instant::now() + NOTICE_TTL;
+        self.modify(|state| state.notice = Some(Notice { text, error, expires }));
+    }
+
+    fn modify(&self, f: impl FnOnce(&mut AppState)) {
+        self.0.send_if_modified(|state| {
+            let before = state.clone();
+            f(state);
+            *state != before
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn notices_expire_and_unchanged_values_do_not_wake_subscribers() {
+        let state = SharedState::default();
+        let mut rx = state.subscribe();
+
+        state.set_capturing(true);
+        assert_eq!(rx.has_changed().unwrap(), false);
+
+        state.notify("Exported 3 requests");
+        assert_eq!(rx.has_changed().unwrap(), true);
+        let now = Instant::now();
+        let current = rx.borrow_and_update().clone();
+        assert_eq!(current.notice_at(now).map(|n| n.text.as_str()), Some("Exported 3 requests"));
+        assert_eq!(current.notice_at(now + NOTICE_TTL), None);
+    }
+}
@@ -0,0 +1,169 @@
+//! `--tee`: a one-line summary of every completed request, written to
+//! stderr or a file or FIFO while the interface runs, for grep and awk.
+
+use std::{
+    io::IsTerminal,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local};
+use tokio::{
+    fs::OpenOptions,
+    io::{AsyncWrite, AsyncWriteExt},
+    sync::mpsc,
+};
+use tracing::{error, info, warn};
+
+use crate::{access_log::AccessRecord, framework::Updater, state::SharedState};
+
+/// Lines waiting for a slow reader; more are dropped so the proxy never waits.
+const QUEUE: usize = 1024;
+
+/// Target of `--tee` meaning stderr.
+pub const STDERR: &str = "-";
+
+const RESET: &str = "\x1b[0m";
+
+/// `09:06:11.042 GET     404     7ms      335 api.test/users?page=2`, with
+/// the method and status colored unless `color` is off.
+pub fn summary(record: &AccessRecord, color: bool) -> String {
+    let time = DateTime::parse_from_rfc3339(&record.timestamp)
+        .map(|t| t.with_timezone(&Local).format("%H:%M:%S%.3f").to_string())
+        .unwrap_or_else(|_| record.timestamp.clone());
+    let paint = |code: &str, text: String| if color { format!("\x1b[{}m{}{}", code, text, RESET) } else { text };
+    let method = match record.method.as_str() {
+        "GET" => "32",
+        "POST" => "34",
+        "CONNECT" => "35",
+        _ => "33",
+    };
+    let status = match record.status {
+        200..300 => "32",
+        300..400 => "36",
+        400..500 => "33",
+        _ => "31",
+    };
+    format!(
+        "{} {} {} {:>5}ms {:>8} {}{}",
+        time,
+        paint(method, format!("{:<7}", record.method)),
+        paint(status, record.status.to_string()),
+        record.duration_ms,
+        record.bytes,
+        record.host,
+        record.path
+    )
+}
+
+/// Hands summaries to the task writing them out.
+#[derive(Clone, Debug)]
+pub struct Tee {
+    lines: mpsc::Sender<String>,
+    color: bool,
+}
+
+impl Tee {
+    /// Start writing to `target`, [`STDERR`] or a path. Stderr is refused
+    /// while it is the terminal the interface is drawn on.
+    pub fn spawn(target: &Path, state: SharedState, updater: &Updater) -> Option<Self> {
+        let to_stderr = target == Path::new(STDERR);
+        if to_stderr && std::io::stderr().is_terminal() {
+            warn!("Not teeing to stderr, it is the terminal");
+            state.error("--tee: stderr is the terminal, redirect it, e.g. 2>>traffic.log");
+            return None;
+        }
+        info!("Teeing requests to {}", if to_stderr { "stderr".into() } else { target.to_string_lossy() });
+        let (lines, queue) = mpsc::channel(QUEUE);
+        let target = (!to_stderr).then(|| target.to_path_buf());
+        updater.spawn(write_lines(target, queue, state, updater.clone()));
+        Some(Self {
+            lines,
+            // NO_COLOR is the convention for asking for plain text, e.g. for awk
+            color: std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()),
+        })
+    }
+
+    pub fn write(&self, record: &AccessRecord) {
+        let _ = self.lines.try_send(summary(record, self.color));
+    }
+}
+
+/// Write lines to `path`, or stderr, until shutdown. A file is opened when
+/// the first line is due and again after a failed write, so a FIFO waits
+/// for its reader and finds the next one once it goes away.
+async fn write_lines(path: Option<PathBuf>, mut queue: mpsc::Receiver<String>, state: SharedState, updater: Updater) {
+    let mut output: Option<Box<dyn AsyncWrite + Send + Unpin>> = None;
+    let mut reported = false;
+    loop {
+        let line = tokio::select! {
+            line = queue.recv() => match line {
+                Some(line) => line,
+                None => return,
+            },
+            _ = updater.cancelled() => return,
+        };
+        if output.is_none() {
+            output = match &path {
+                None => Some(Box::new(tokio::io::stderr())),
+                Some(path) => {
+                    // opening a FIFO waits for a reader
+                    let mut options = OpenOptions::new();
+                    options.create(true).append(true);
+                    let opened = tokio::select! {
+                        opened = options.open(path) => opened,
+                        _ = updater.cancelled() => return,
+                    };
+                    match opened {
+                        Ok(file) => Some(Box::new(file)),
+                        Err(e) => {
+                            error!("Failed to open {}: {}", path.display(), e);
+                            if !reported {
+                                reported = true;
+                                state.error(format!("--tee: failed to open {}: {}", path.display(), e));
+                            }
+                            continue;
+                        }
+                    }
+                }
+            };
+        }
+        let Some(writer) = output.as_mut() else {
+            continue;
+        };
+        let written = async {
+            writer.write_all(line.as_bytes()).await?;
+            writer.write_all(b"\n").await?;
+            writer.flush().await
+        };
+        match written.await {
+            Ok(()) => reported = false,
+            Err(e) => {
+                // e.g. the reader of a FIFO went away
+                warn!("Failed to tee a request: {}", e);
+                output = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let timestamp = DateTime::parse_from_rfc3339("2026-10-17T08:00:00.042Z").unwrap().with_timezone(&chrono::Utc);
+        let uri = "http://api.test/users?page=2".parse().unwrap();
+        let record = AccessRecord::new(timestamp, "GET", &uri, 404, 335, Duration::from_millis(7));
+        let time = timestamp.with_timezone(&Local).format("%H:%M:%S%.3f");
+        assert_eq!(summary(&record, false), format!("{} GET     404     7ms      335 api.test/users?page=2", time));
+        assert_eq!(
+            summary(&record, true),
+            format!("{} \x1b[32mGET    \x1b[0m \x1b[33m404\x1b[0m     7ms      335 api.test/users?page=2", time)
+        );
+    }
+}
@@ -0,0 +1,219 @@
+//! Colors of the UI by role, so that components don't hard-code them and a
+//! light terminal or a high-contrast preset only needs a different [`Theme`].
+
+use std::collections::BTreeMap;
+
+use ratatui::style::{Color, Style};
+use serde::{Deserialize, Deserializer, de::Error};
+
+use crate::config::parse_color;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Theme {
+    /// Regular text.
+    pub text: Color,
+    /// Secondary text like timestamps, counts and placeholders.
+    pub muted: Color,
+    /// Borders of unfocused panes and separators.
+    pub border: Color,
+    /// Background of the selected row.
+    pub selection: Color,
+    /// Border of the focused pane.
+    pub focus: Color,
+    /// Borders of popups and section headings.
+    pub title: Color,
+    /// Keys, header names and notices; JSON keys.
+    pub accent: Color,
+    /// GET, 2xx and additions; strings.
+    pub success: Color,
+    /// 4xx and the active filter; numbers.
+    pub warning: Color,
+    /// Errors, faults, 5xx and removals.
+    pub error: Color,
+    /// Pins, GraphQL operations, CONNECT; literals.
+    pub special: Color,
+    /// Listener labels and POST; XML tags.
+    pub secondary: Color,
+    /// Text on colored badges like the mode in the status bar.
+    pub badge: Color,
+}
+
+impl Theme {
+    pub const DARK: Theme = Theme {
+        text: Color::White,
+        muted: Color::Gray,
+        border: Color::DarkGray,
+        selection: Color::DarkGray,
+        focus: Color::Cyan,
+        title: Color::Yellow,
+        accent: Color::Cyan,
+        success: Color::Green,
+        warning: Color::Yellow,
+        error: Color::Red,
+        special: Color::Magenta,
+        secondary: Color::Blue,
+        badge: Color::Black,
+    };
+
+    /// For terminals with a light background.
+    pub const LIGHT: Theme = Theme {
+        text: Color::Black,
+        muted: Color::DarkGray,
+        border: Color::Gray,
+        selection: Color::Indexed(252),
+        focus: Color::Blue,
+        title: Color::Indexed(130),
+        accent: Color::Indexed(30),
+        success: Color::Indexed(28),
+        warning: Color::Indexed(130),
+        error: Color::Indexed(160),
+        special: Color::Indexed(127),
+        secondary: Color::Indexed(25),
+        badge: Color::White,
+    };
+
+    pub const HIGH_CONTRAST: Theme = Theme {
+        text: Color::White,
+        muted: Color::White,
+        border: Color::White,
+        selection: Color::Blue,
+        focus: Color::LightCyan,
+        title: Color::LightYellow,
+        accent: Color::LightCyan,
+        success: Color::LightGreen,
+        warning: Color::LightYellow,
+        error: Color::LightRed,
+        special: Color::LightMagenta,
+        secondary: Color::LightBlue,
+        badge: Color::Black,
+    };
+
+    pub fn fg(&self, color: Color) -> Style {
+        Style::default().fg(color)
+    }
+
+    /// Background of the row at `selected`, for lists.
+    pub fn row(&self, selected: bool) -> Style {
+        if selected {
+            Style::default().bg(self.selection)
+        } else {
+            Style::default()
+        }
+    }
+
+    /// Border of a pane, by whether it has the focus.
+    pub fn pane(&self, focused: bool) -> Style {
+        self.fg(if focused { self.focus } else { self.border })
+    }
+
+    pub fn method(&self, method: &str) -> Color {
+        match method {
+            "GET" => self.success,
+            "POST" => self.secondary,
+            "CONNECT" => self.special,
+            _ => self.warning,
+        }
+    }
+
+    /// Color of a status class like `4xx`.
+    pub fn status_class(&self, class: &str) -> Color {
+        match class {
+            "2xx" => self.success,
+            "3xx" => self.accent,
+            "4xx" => self.warning,
+            "5xx" => self.error,
+            _ => self.muted,
+        }
+    }
+
+    fn set(&mut self, role: &str, color: Color) -> Result<(), String> {
+        let slot = match role {
+            "text" => &mut self.text,
+            "muted" => &mut self.muted,
+            "border" => &mut self.border,
+            "selection" => &mut self.selection,
+            "focus" => &mut self.focus,
+            "title" => &mut self.title,
+            "accent" => &mut self.accent,
+            "success" => &mut self.success,
+            "warning" => &mut self.warning,
+            "error" => &mut self.error,
+            "special" => &mut self.special,
+            "secondary" => &mut self.secondary,
+            "badge" => &mut self.badge,
+            _ => {
+                return Err(format!(
+                    "unknown theme color `{}`, expected one of `text`, `muted`, `border`, `selection`, `focus`, \
+                     `title`, `accent`, `success`, `warning`, `error`, `special`, `secondary`, `badge`",
+                    role
+                ));
+            }
+        };
+        *slot = color;
+        Ok(())
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::DARK
+    }
+}
+
+fn preset(name: &str) -> Result<Theme, String> {
+    match name {
+        "dark" => Ok(Theme::DARK),
+        "light" => Ok(Theme::LIGHT),
+        "high-contrast" => Ok(Theme::HIGH_CONTRAST),
+        _ => Err(format!("unknown theme `{}`, expected one of `dark`, `light`, `high-contrast`", name)),
+    }
+}
+
+/// A preset name, or a preset with some of its colors replaced.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ThemeConfig {
+    Preset(String),
+    Custom {
+        #[serde(default = "dark")]
+        preset: String,
+        #[serde(flatten)]
+        colors: BTreeMap<String, String>,
+    },
+}
+
+fn dark() -> String {
+    "dark".to_string()
+}
+
+impl<'de> Deserialize<'de> for Theme {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (name, colors) = match ThemeConfig::deserialize(deserializer)? {
+            ThemeConfig::Preset(name) => (name, BTreeMap::new()),
+            ThemeConfig::Custom { preset, colors } => (preset, colors),
+        };
+        let mut theme = preset(&name).map_err(D::Error::custom)?;
+        for (role, color) in colors {
+            let color = parse_color(&color).ok_or_else(|| D::Error::custom(format!("unknown color `{}`", color)))?;
+            theme.set(&role, color).map_err(D::Error::custom)?;
+        }
+        Ok(theme)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_theme_presets_and_overrides() {
+        assert_eq!(json5::from_str::<Theme>(r#""light""#).unwrap(), Theme::LIGHT);
+        let custom: Theme = json5::from_str(r#"{ preset: "high-contrast", focus: "color208" }"#).unwrap();
+        assert_eq!(custom, Theme { focus: Color::Indexed(208), ..Theme::HIGH_CONTRAST });
+        assert_eq!(json5::from_str::<Theme>(r#"{ error: "red" }"#).unwrap().error, Color::Indexed(1));
+        assert!(json5::from_str::<Theme>(r#""solarized""#).is_err());
+        assert!(json5::from_str::<Theme>(r#"{ foreground: "red" }"#).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+//! TLS settings for connections to `https` upstreams, i.e. reverse-proxy
+//! targets and rewrite destinations.
+
+use std::{path::Path, sync::Arc};
+
+use color_eyre::eyre::{WrapErr, eyre};
+use rustls::{
+    ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{CryptoProvider, verify_tls12_signature, verify_tls13_signature},
+};
+use rustls_pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime, pem::PemObject};
+use tracing::warn;
+
+use crate::config::UpstreamTlsConfig;
+
+/// Client configuration trusting the bundled web roots plus `config.ca_certs`.
+pub fn client_config(config: &UpstreamTlsConfig) -> color_eyre::Result<ClientConfig> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = ClientConfig::builder_with_provider(provider.clone()).with_safe_default_protocol_versions()?;
+
+    let builder = if config.insecure {
+        warn!("Upstream certificates are not verified");
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AcceptAny(provider)))
+    } else {
+        let mut roots = RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        for path in &config.ca_certs {
+            for cert in load_certs(path)? {
+                roots
+                    .add(cert)
+                    .wrap_err_with(|| format!("Invalid CA certificate in {}", path.display()))?;
+            }
+        }
+        builder.with_root_certificates(roots)
+    };
+
+    let client_config = match (&config.client_cert, &config.client_key) {
+        (Some(cert), Some(key)) => {
+            let key = PrivateKeyDer::from_pem_file(key)
+                .map_err(|e| eyre!("Failed to read private key {}: {}", key.display(), e))?;
+            builder
+                .with_client_auth_cert(load_certs(cert)?, key)
+                .wrap_err("Invalid client certificate")?
+        }
+        (None, None) => builder.with_no_client_auth(),
+        _ => return Err(eyre!("client_cert and client_key must be set together")),
+    };
+    // ALPN is set up by the connector
+    Ok(client_config)
+}
+
+fn load_certs(path: &Path) -> color_eyre::Result<Vec<CertificateDer<'static>>> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| eyre!("Failed to read certificates from {}: {}", path.display(), e))?;
+    if certs.is_empty() {
+        return Err(eyre!("No certificates found in {}", path.display()));
+    }
+    Ok(certs)
+}
+
+/// Accepts any server certificate, but still checks handshake signatures so
+/// the connection itself stays well-formed.
+#[derive(Debug)]
+struct AcceptAny(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AcceptAny {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_client_config_validates_settings() {
+        assert!(client_config(&UpstreamTlsConfig::default()).is_ok());
+        let insecure = UpstreamTlsConfig {
+            insecure: true,
+            ..Default::default()
+        };
+        assert!(client_config(&insecure).is_ok());
+        let key_only = UpstreamTlsConfig {
+            client_key: Some(PathBuf::from("key.pem")),
+            ..Default::default()
+        };
+        assert!(client_config(&key_only).is_err());
+    }
+}
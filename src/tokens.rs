@@ -0,0 +1,231 @@
+//! OAuth access tokens for composed requests: fetched with the client
+//! credentials grant, added as `Authorization: Bearer`, and fetched again
+//! once they expire or the upstream answers `401`.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime},
+};
+
+use base64::Engine;
+use color_eyre::eyre::eyre;
+use http_body_util::Full;
+use hyper::{
+    Method, Request, Response, StatusCode,
+    body::Bytes,
+    header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE, HOST, HeaderValue},
+};
+use serde::Deserialize;
+use tokio::sync::Mutex;
+
+use crate::{client::ProxyClient, pac::sh_exp_match};
+
+/// A token this close to its expiry is fetched again rather than sent.
+const MARGIN: Duration = Duration::from_secs(30);
+
+/// Where to get a token for the requests to `host`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TokenHelper {
+    /// Host pattern with `*` wildcards.
+    pub host: String,
+    /// The OAuth token endpoint.
+    pub token_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+impl TokenHelper {
+    /// The client credentials grant, form-encoded.
+    fn form(&self) -> String {
+        let mut form = url::form_urlencoded::Serializer::new(String::new());
+        form.append_pair("grant_type", "client_credentials")
+            .append_pair("client_id", &self.client_id)
+            .append_pair("client_secret", &self.client_secret);
+        if let Some(scope) = &self.scope {
+            form.append_pair("scope", scope);
+        }
+        form.finish()
+    }
+
+    fn token_request(&self) -> color_eyre::Result<Request<Full<Bytes>>> {
+        let url = url::Url::parse(&self.token_url)?;
+        let host = url.host_str().unwrap_or_default();
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        };
+        Ok(Request::builder()
+            .method(Method::POST)
+            .uri(url.as_str())
+            .header(HOST, host)
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .header(ACCEPT, "application/json")
+            .body(Full::new(Bytes::from(self.form())))?)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Token {
+    value: String,
+    /// Unknown when the endpoint gives no lifetime and the token is no JWT;
+    /// then only a `401` replaces it.
+    expires: Option<SystemTime>,
+}
+
+impl Token {
+    fn fresh(&self, now: SystemTime) -> bool {
+        self.expires.is_none_or(|expires| now + MARGIN < expires)
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+}
+
+/// Read a token endpoint's answer, expiring after `expires_in` or else at
+/// the token's own `exp` claim.
+fn parse_token(body: &[u8], now: SystemTime) -> Result<Token, String> {
+    let response: TokenResponse =
+        serde_json::from_slice(body).map_err(|e| format!("Unexpected token response: {}", e))?;
+    let expires = match response.expires_in {
+        Some(seconds) => Some(now + Duration::from_secs(seconds)),
+        None => jwt_expiry(&response.access_token),
+    };
+    Ok(Token {
+        value: response.access_token,
+        expires,
+    })
+}
+
+/// The `exp` claim of a JWT.
+fn jwt_expiry(token: &str) -> Option<SystemTime> {
+    let payload = token.split('.').nth(1)?;
+    let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .ok()?;
+    let exp = serde_json::from_slice::<serde_json::Value>(&payload)
+        .ok()?
+        .get("exp")?
+        .as_u64()?;
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(exp))
+}
+
+/// The configured helpers with the tokens they fetched so far.
+#[derive(Debug, Default)]
+pub struct Tokens {
+    helpers: Vec<TokenHelper>,
+    /// By index into `helpers`.
+    cache: Mutex<HashMap<usize, Token>>,
+}
+
+impl Tokens {
+    pub fn new(helpers: Vec<TokenHelper>) -> Self {
+        Self {
+            helpers,
+            cache: Mutex::default(),
+        }
+    }
+
+    /// The helper for `request`, unless it brings its own `Authorization`.
+    fn helper_for(&self, request: &Request<Full<Bytes>>) -> Option<usize> {
+        if request.headers().contains_key(AUTHORIZATION) {
+            return None;
+        }
+        let host = request.uri().host()?;
+        self.helpers.iter().position(|helper| sh_exp_match(&helper.host, host))
+    }
+
+    /// The cached token of helper `index` while it is fresh, otherwise or
+    /// with `renew` a new one.
+    async fn token(&self, index: usize, client: &ProxyClient, renew: bool) -> color_eyre::Result<String> {
+        // held while fetching, so that concurrent requests share one new token
+        let mut cache = self.cache.lock().await;
+        if !renew
+            && let Some(token) = cache.get(&index)
+            && token.fresh(SystemTime::now())
+        {
+            return Ok(token.value.clone());
+        }
+        let helper = &self.helpers[index];
+        let response = client.send(helper.token_request()?).await?;
+        if !response.status().is_success() {
+            return Err(eyre!(
+                "Token endpoint {} answered {}",
+                helper.token_url,
+                response.status()
+            ));
+        }
+        let token = parse_token(response.body(), SystemTime::now()).map_err(|e| eyre!(e))?;
+        let value = token.value.clone();
+        cache.insert(index, token);
+        Ok(value)
+    }
+
+    /// Send `request` with a token when a helper matches it, retrying once
+    /// with a new token if it is refused.
+    pub async fn send(
+        &self,
+        client: &ProxyClient,
+        request: Request<Full<Bytes>>,
+    ) -> color_eyre::Result<Response<Bytes>> {
+        let Some(index) = self.helper_for(&request) else {
+            return client.send(request).await;
+        };
+        let with_token = |mut request: Request<Full<Bytes>>, token: &str| -> color_eyre::Result<_> {
+            let value = HeaderValue::try_from(format!("Bearer {}", token))?;
+            request.headers_mut().insert(AUTHORIZATION, value);
+            Ok(request)
+        };
+        let token = self.token(index, client, false).await?;
+        let response = client.send(with_token(request.clone(), &token)?).await?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+        // revoked or expired earlier than announced
+        let token = self.token(index, client, true).await?;
+        client.send(with_token(request, &token)?).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_token_expiry() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let token = parse_token(br#"{"access_token":"abc","token_type":"Bearer","expires_in":60}"#, now).unwrap();
+        assert_eq!(token.expires, Some(now + Duration::from_secs(60)));
+        assert!(token.fresh(now));
+        assert!(!token.fresh(now + Duration::from_secs(40)));
+
+        // {"alg":"none"} . {"sub":"yap","exp":1000100}
+        let jwt = "eyJhbGciOiJub25lIn0.eyJzdWIiOiJ5YXAiLCJleHAiOjEwMDAxMDB9.";
+        let token = parse_token(format!(r#"{{"access_token":"{}"}}"#, jwt).as_bytes(), now).unwrap();
+        assert_eq!(token.expires, Some(now + Duration::from_secs(100)));
+
+        let token = parse_token(br#"{"access_token":"opaque"}"#, now).unwrap();
+        assert!(token.fresh(now + Duration::from_secs(86400)));
+        assert!(parse_token(b"<html>", now).is_err());
+
+        let helper = TokenHelper {
+            host: "api.test".to_string(),
+            token_url: "https://auth.test:8443/token".to_string(),
+            client_id: "yap".to_string(),
+            client_secret: "s&cret".to_string(),
+            scope: Some("read write".to_string()),
+        };
+        assert_eq!(
+            helper.form(),
+            "grant_type=client_credentials&client_id=yap&client_secret=s%26cret&scope=read+write"
+        );
+        assert_eq!(helper.token_request().unwrap().headers()[HOST], "auth.test:8443");
+    }
+}
@@ -0,0 +1,186 @@
+//! Response bodies on their way through the proxy, with how far along they
+//! are, for the transfers panel. Counted as the body is recorded, so the
+//! figures are those of the payload rather than the wire.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Transfers are listed once they have run this long, so quick responses
+/// never show up.
+const SHOW_AFTER: Duration = Duration::from_millis(500);
+/// How long a finished transfer stays listed.
+const LINGER: Duration = Duration::from_secs(3);
+/// The rate is taken over about this much of the recent past.
+const RATE_WINDOW: Duration = Duration::from_secs(3);
+/// Samples of the rate window are at least this far apart.
+const SAMPLE_EVERY: Duration = Duration::from_millis(250);
+
+/// One response body being received.
+#[derive(Clone, Debug)]
+pub struct Transfer {
+    pub method: String,
+    pub uri: String,
+    /// From `Content-Length`, if the upstream sent one.
+    pub total: Option<u64>,
+    pub done: u64,
+    pub started: Instant,
+    pub finished: Option<Instant>,
+    /// Recent `(when, done)` samples the rate is taken from.
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl Transfer {
+    /// Bytes per second over the last few seconds, or over the whole
+    /// transfer once it has finished.
+    pub fn rate(&self, now: Instant) -> f64 {
+        let (since, from) = match self.finished {
+            Some(finished) => (finished.duration_since(self.started), 0),
+            None => match self.samples.front() {
+                Some(&(at, done)) => (now.duration_since(at), done),
+                None => return 0.0,
+            },
+        };
+        match since.as_secs_f64() {
+            0.0 => 0.0,
+            secs => (self.done - from) as f64 / secs,
+        }
+    }
+
+    /// Time left at the current rate, when the size is known.
+    pub fn eta(&self, now: Instant) -> Option<Duration> {
+        let left = self.total?.saturating_sub(self.done);
+        let rate = self.rate(now);
+        (self.finished.is_none() && rate > 0.0).then(|| Duration::from_secs_f64(left as f64 / rate))
+    }
+
+    /// Done as a fraction of the total, when it is known.
+    pub fn fraction(&self) -> Option<f64> {
+        match self.total? {
+            0 => Some(1.0),
+            total => Some((self.done as f64 / total as f64).min(1.0)),
+        }
+    }
+
+    fn record(&mut self, bytes: u64, now: Instant) {
+        self.done += bytes;
+        if self.samples.back().is_none_or(|&(at, _)| now.duration_since(at) >= SAMPLE_EVERY) {
+            self.samples.push_back((now, self.done));
+        }
+        while self.samples.len() > 1
+            && self.samples.front().is_some_and(|&(at, _)| now.duration_since(at) > RATE_WINDOW)
+        {
+            self.samples.pop_front();
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    next: u64,
+    transfers: BTreeMap<u64, Transfer>,
+}
+
+/// Every transfer in progress, shared by the proxy and the transfers panel.
+#[derive(Clone, Debug, Default)]
+pub struct Transfers(Arc<Mutex<Registry>>);
+
+impl Transfers {
+    /// Start counting a body. It counts as finished once the handle is dropped.
+    pub fn start(&self, method: &str, uri: &str, total: Option<u64>) -> Progress {
+        let now = Instant::now();
+        let mut registry = self.0.lock().unwrap();
+        let id = registry.next;
+        registry.next += 1;
+        let transfer = Transfer {
+            method: method.to_string(),
+            uri: uri.to_string(),
+            total,
+            done: 0,
+            started: now,
+            finished: None,
+            samples: VecDeque::from([(now, 0)]),
+        };
+        registry.transfers.insert(id, transfer);
+        Progress {
+            transfers: self.clone(),
+            id,
+        }
+    }
+
+    /// The transfers worth showing at `now`, oldest first: those that ran
+    /// long enough, until a while after they finished. Older ones are dropped.
+    pub fn shown(&self, now: Instant) -> Vec<Transfer> {
+        let mut registry = self.0.lock().unwrap();
+        registry
+            .transfers
+            .retain(|_, transfer| transfer.finished.is_none_or(|finished| now.duration_since(finished) < LINGER));
+        registry
+            .transfers
+            .values()
+            .filter(|transfer| transfer.finished.unwrap_or(now).duration_since(transfer.started) >= SHOW_AFTER)
+            .cloned()
+            .collect()
+    }
+
+    fn update(&self, id: u64, f: impl FnOnce(&mut Transfer)) {
+        if let Some(transfer) = self.0.lock().unwrap().transfers.get_mut(&id) {
+            f(transfer);
+        }
+    }
+}
+
+/// Handle of one transfer, for the side receiving the body.
+#[derive(Debug)]
+pub struct Progress {
+    transfers: Transfers,
+    id: u64,
+}
+
+impl Progress {
+    pub fn add(&self, bytes: u64) {
+        self.transfers.update(self.id, |transfer| transfer.record(bytes, Instant::now()));
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.transfers.update(self.id, |transfer| transfer.finished = Some(Instant::now()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_progress_rate_and_eta() {
+        let transfers = Transfers::default();
+        let progress = transfers.start("GET", "http://cdn.test/big.iso", Some(1000));
+        let start = transfers.shown(Instant::now() + SHOW_AFTER)[0].started;
+        transfers.update(progress.id, |transfer| {
+            transfer.record(100, start + Duration::from_secs(1));
+            transfer.record(100, start + Duration::from_secs(2));
+        });
+
+        let now = start + Duration::from_secs(2);
+        let transfer = &transfers.shown(now)[0];
+        assert_eq!(transfer.done, 200);
+        assert_eq!(transfer.rate(now), 100.0);
+        assert_eq!(transfer.eta(now), Some(Duration::from_secs(8)));
+        assert_eq!(transfer.fraction(), Some(0.2));
+        // quick ones aren't listed yet
+        assert!(transfers.shown(start).is_empty());
+
+        let finished = start + Duration::from_secs(4);
+        transfers.update(progress.id, |transfer| transfer.finished = Some(finished));
+        let transfer = &transfers.shown(finished)[0];
+        assert_eq!(transfer.rate(finished), 50.0);
+        assert_eq!(transfer.eta(finished), None);
+        assert!(transfers.shown(finished + LINGER).is_empty());
+    }
+}
@@ -15,7 +15,7 @@ use crossterm::{
     terminal::{EnterAlternateScreen, LeaveAlternateScreen},
 };
 use futures::{FutureExt, StreamExt};
-use ratatui::backend::CrosstermBackend as Backend;
+use ratatui::backend::{Backend, CrosstermBackend};
 use serde::{Deserialize, Serialize};
 use tokio::{
     sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
@@ -38,30 +38,58 @@ pub enum Event {
     Resize(u16, u16),
 }
 
-pub struct Tui {
-    pub terminal: ratatui::Terminal<Backend<Stdout>>,
+pub struct Tui<B: Backend = CrosstermBackend<Stdout>> {
+    pub terminal: ratatui::Terminal<B>,
     pub task: JoinHandle<()>,
     pub cancellation_token: CancellationToken,
     pub event_rx: UnboundedReceiver<Event>,
     pub event_tx: UnboundedSender<Event>,
     pub mouse: bool,
     pub paste: bool,
+    /// Events come from a script and the screen is in memory, so the real
+    /// terminal is left alone.
+    #[cfg(test)]
+    headless: bool,
 }
 
 impl Tui {
     pub fn new() -> color_eyre::Result<Self> {
         let (event_tx, event_rx) = mpsc::unbounded_channel();
         Ok(Self {
-            terminal: ratatui::Terminal::new(Backend::new(stdout()))?,
+            terminal: ratatui::Terminal::new(CrosstermBackend::new(stdout()))?,
             task: tokio::spawn(async {}),
             cancellation_token: CancellationToken::new(),
             event_rx,
             event_tx,
             mouse: false,
             paste: false,
+            #[cfg(test)]
+            headless: false,
         })
     }
+}
 
+#[cfg(test)]
+impl Tui<ratatui::backend::TestBackend> {
+    /// A terminal in memory, sized as `script` says, that plays its events.
+    pub fn scripted(script: crate::framework::testing::Script) -> color_eyre::Result<Self> {
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let (width, height) = script.size();
+        let cancellation_token = CancellationToken::new();
+        Ok(Self {
+            terminal: ratatui::Terminal::new(ratatui::backend::TestBackend::new(width, height))?,
+            task: tokio::spawn(script.play(event_tx.clone(), cancellation_token.clone())),
+            cancellation_token,
+            event_rx,
+            event_tx,
+            mouse: false,
+            paste: false,
+            headless: true,
+        })
+    }
+}
+
+impl<B: Backend> Tui<B> {
     pub fn mouse(mut self, mouse: bool) -> Self {
         self.mouse = mouse;
         self
@@ -75,7 +103,7 @@ impl Tui {
     pub fn start(&mut self) {
         self.cancel(); // Cancel any existing task
         self.cancellation_token = CancellationToken::new();
-        let event_loop = Self::event_loop(
+        let event_loop = event_loop(
             self.event_tx.clone(),
             self.cancellation_token.clone(),
         );
@@ -84,44 +112,6 @@ impl Tui {
         });
     }
 
-    async fn event_loop(
-        event_tx: UnboundedSender<Event>,
-        cancellation_token: CancellationToken,
-    ) {
-        let mut event_stream = EventStream::new();
-
-        // if this fails, then it's likely a bug in the calling code
-        event_tx
-            .send(Event::Init)
-            .expect("failed to send init event");
-        loop {
-            debug!("Waiting for event...");
-            let event = tokio::select! {
-                _ = cancellation_token.cancelled() => {
-                    break;
-                }
-                crossterm_event = event_stream.next().fuse() => match crossterm_event {
-                    Some(Ok(event)) => match event {
-                        CrosstermEvent::Key(key) if key.kind == KeyEventKind::Press => Event::Key(key),
-                        CrosstermEvent::Mouse(mouse) => Event::Mouse(mouse),
-                        CrosstermEvent::Resize(x, y) => Event::Resize(x, y),
-                        CrosstermEvent::FocusLost => Event::FocusLost,
-                        CrosstermEvent::FocusGained => Event::FocusGained,
-                        CrosstermEvent::Paste(s) => Event::Paste(s),
-                        _ => continue, // ignore other events
-                    }
-                    Some(Err(_)) => Event::Error,
-                    None => break, // the event stream has stopped and will not produce any more events
-                },
-            };
-            if event_tx.send(event).is_err() {
-                // the receiver has been dropped, so there's no point in continuing the loop
-                break;
-            }
-        }
-        cancellation_token.cancel();
-    }
-
     pub fn stop(&self) -> color_eyre::Result<()> {
         self.cancel();
         let mut counter = 0;
@@ -140,6 +130,10 @@ impl Tui {
     }
 
     pub fn enter(&mut self) -> color_eyre::Result<()> {
+        #[cfg(test)]
+        if self.headless {
+            return Ok(());
+        }
         crossterm::terminal::enable_raw_mode()?;
         crossterm::execute!(stdout(), EnterAlternateScreen, cursor::Hide)?;
         if self.mouse {
@@ -153,6 +147,10 @@ impl Tui {
     }
 
     pub fn exit(&mut self) -> color_eyre::Result<()> {
+        #[cfg(test)]
+        if self.headless {
+            return Ok(());
+        }
         self.stop()?;
         if crossterm::terminal::is_raw_mode_enabled()? {
             self.flush()?;
@@ -174,8 +172,13 @@ impl Tui {
 
     pub fn suspend(&mut self) -> color_eyre::Result<()> {
         self.exit()?;
-        #[cfg(not(windows))]
-        signal_hook::low_level::raise(signal_hook::consts::signal::SIGTSTP)?;
+        #[cfg(test)]
+        if self.headless {
+            return Ok(());
+        }
+        // SIGTSTP is caught by the runtime, which would only bring us back here
+        #[cfg(unix)]
+        signal_hook::low_level::raise(signal_hook::consts::signal::SIGSTOP)?;
         Ok(())
     }
 
@@ -189,22 +192,82 @@ impl Tui {
     }
 }
 
-impl Deref for Tui {
-    type Target = ratatui::Terminal<Backend<Stdout>>;
+/// Put the terminal back the way the shell expects it, whatever state it was
+/// left in. Errors are ignored, as this runs on the way out of a panic or a
+/// signal where there is nothing better to do.
+pub fn restore() {
+    // one at a time, as the legacy Windows console refuses bracketed paste
+    let _ = crossterm::execute!(stdout(), LeaveAlternateScreen);
+    let _ = crossterm::execute!(stdout(), cursor::Show);
+    let _ = crossterm::execute!(stdout(), DisableMouseCapture);
+    let _ = crossterm::execute!(stdout(), DisableBracketedPaste);
+    let _ = crossterm::terminal::disable_raw_mode();
+}
+
+/// Stop the process as Ctrl+Z does in a shell, with the terminal restored
+/// first, for a SIGTSTP that came from outside.
+#[cfg(unix)]
+pub fn stop() {
+    restore();
+    let _ = signal_hook::low_level::raise(signal_hook::consts::signal::SIGSTOP);
+}
+
+/// Forward the terminal's events until cancelled.
+async fn event_loop(
+    event_tx: UnboundedSender<Event>,
+    cancellation_token: CancellationToken,
+) {
+    let mut event_stream = EventStream::new();
+
+    // if this fails, then it's likely a bug in the calling code
+    event_tx
+        .send(Event::Init)
+        .expect("failed to send init event");
+    loop {
+        debug!("Waiting for event...");
+        let event = tokio::select! {
+            _ = cancellation_token.cancelled() => {
+                break;
+            }
+            crossterm_event = event_stream.next().fuse() => match crossterm_event {
+                Some(Ok(event)) => match event {
+                    CrosstermEvent::Key(key) if key.kind == KeyEventKind::Press => Event::Key(key),
+                    CrosstermEvent::Mouse(mouse) => Event::Mouse(mouse),
+                    CrosstermEvent::Resize(x, y) => Event::Resize(x, y),
+                    CrosstermEvent::FocusLost => Event::FocusLost,
+                    CrosstermEvent::FocusGained => Event::FocusGained,
+                    CrosstermEvent::Paste(s) => Event::Paste(s),
+                    _ => continue, // ignore other events
+                }
+                Some(Err(_)) => Event::Error,
+                None => break, // the event stream has stopped and will not produce any more events
+            },
+        };
+        if event_tx.send(event).is_err() {
+            // the receiver has been dropped, so there's no point in continuing the loop
+            break;
+        }
+    }
+    cancellation_token.cancel();
+}
+
+impl<B: Backend> Deref for Tui<B> {
+    type Target = ratatui::Terminal<B>;
 
     fn deref(&self) -> &Self::Target {
         &self.terminal
     }
 }
 
-impl DerefMut for Tui {
+impl<B: Backend> DerefMut for Tui<B> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.terminal
     }
 }
 
-impl Drop for Tui {
+impl<B: Backend> Drop for Tui<B> {
     fn drop(&mut self) {
+        self.cancel();
         self.exit().unwrap();
     }
 }